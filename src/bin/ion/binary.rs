@@ -0,0 +1,345 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ion_rs::{SymbolTable, SystemEventHandler};
+
+use crate::input::IVM;
+
+// Helpers for working with binary Ion at the byte level, for commands that need to slice up or
+// stitch together streams without decoding every value.
+
+// The number of symbols in the Ion 1.0 system symbol table, including $0.
+const ION_1_0_SYSTEM_TABLE_LENGTH: usize = 10;
+// System symbol IDs used when encoding a local symbol table.
+const ION_SYMBOL_TABLE_SID: usize = 3;
+const SYMBOLS_SID: usize = 7;
+
+// Type codes (the high nibble of a type descriptor) used below.
+pub const NEGATIVE_INT_TYPE_CODE: u8 = 0x3;
+const STRING_TYPE_CODE: u8 = 0x8;
+const LIST_TYPE_CODE: u8 = 0xB;
+const STRUCT_TYPE_CODE: u8 = 0xD;
+const ANNOTATION_WRAPPER_TYPE_CODE: u8 = 0xE;
+// A length nibble of 14 means the length follows as a VarUInt; 15 means the value is null.
+const VAR_UINT_LENGTH: u8 = 14;
+const NULL_LENGTH: u8 = 15;
+
+// Appends `value` to `buffer` as a VarUInt: 7 bits per byte, most significant bits first, with
+// the high bit set on the final byte.
+pub fn encode_var_uint(value: usize, buffer: &mut Vec<u8>) {
+    let mut groups = vec![(value & 0x7F) as u8 | 0x80];
+    let mut remaining = value >> 7;
+    while remaining > 0 {
+        groups.push((remaining & 0x7F) as u8);
+        remaining >>= 7;
+    }
+    buffer.extend(groups.iter().rev());
+}
+
+// Reads a VarUInt from the beginning of `bytes`, returning its value and encoded length.
+pub fn read_var_uint(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut value: usize = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        value = value.checked_mul(128)? | (byte & 0x7F) as usize;
+        if byte & 0x80 != 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+// Reads a VarInt from the beginning of `bytes`, returning its magnitude, whether it's negative,
+// and its encoded length. The sign is returned separately because a negative zero is meaningful
+// in some places, like a timestamp's unknown offset.
+pub fn read_var_int(bytes: &[u8]) -> Option<(usize, bool, usize)> {
+    let first = *bytes.first()?;
+    let negative = first & 0x40 != 0;
+    let mut value = (first & 0x3F) as usize;
+    if first & 0x80 != 0 {
+        return Some((value, negative, 1));
+    }
+    for (index, byte) in bytes.iter().enumerate().skip(1) {
+        value = value.checked_mul(128)? | (byte & 0x7F) as usize;
+        if byte & 0x80 != 0 {
+            return Some((value, negative, index + 1));
+        }
+    }
+    None
+}
+
+// Appends a type descriptor (and VarUInt length, if needed) to `buffer`.
+fn encode_header(type_code: u8, length: usize, buffer: &mut Vec<u8>) {
+    if length < VAR_UINT_LENGTH as usize {
+        buffer.push((type_code << 4) | length as u8);
+    } else {
+        buffer.push((type_code << 4) | VAR_UINT_LENGTH);
+        encode_var_uint(length, buffer);
+    }
+}
+
+// Encodes a local symbol table, `$ion_symbol_table::{symbols: [...]}`, defining the given
+// symbols. Prefixing a slice of a binary stream with an IVM and this table allows it to be read
+// independently of the rest of the stream.
+pub fn encode_symbol_table(symbols: &[String]) -> Vec<u8> {
+    let mut list_body = Vec::new();
+    for symbol in symbols {
+        encode_header(STRING_TYPE_CODE, symbol.len(), &mut list_body);
+        list_body.extend_from_slice(symbol.as_bytes());
+    }
+
+    let mut struct_body = Vec::new();
+    encode_var_uint(SYMBOLS_SID, &mut struct_body);
+    encode_header(LIST_TYPE_CODE, list_body.len(), &mut struct_body);
+    struct_body.extend(list_body);
+
+    let mut wrapper_body = Vec::new();
+    let mut annotations = Vec::new();
+    encode_var_uint(ION_SYMBOL_TABLE_SID, &mut annotations);
+    encode_var_uint(annotations.len(), &mut wrapper_body);
+    wrapper_body.extend(annotations);
+    encode_header(STRUCT_TYPE_CODE, struct_body.len(), &mut wrapper_body);
+    wrapper_body.extend(struct_body);
+
+    let mut encoded = Vec::new();
+    encode_header(ANNOTATION_WRAPPER_TYPE_CODE, wrapper_body.len(), &mut encoded);
+    encoded.extend(wrapper_body);
+    encoded
+}
+
+// Creates the bytes needed to begin a new stream in which the given symbols are defined.
+pub fn stream_preamble(symbols: &[String]) -> Vec<u8> {
+    let mut preamble = IVM.to_vec();
+    if !symbols.is_empty() {
+        preamble.extend(encode_symbol_table(symbols));
+    }
+    preamble
+}
+
+// Returns the total encoded length (type descriptor, length, and body) of the top-level value
+// at the beginning of `bytes`, or `None` if the header is invalid or the value would extend past
+// the end of `bytes`. An IVM is treated as a 4-byte value.
+pub fn encoded_length(bytes: &[u8]) -> Option<usize> {
+    if bytes.starts_with(&IVM) {
+        return Some(IVM.len());
+    }
+    let descriptor = *bytes.first()?;
+    let type_code = descriptor >> 4;
+    let length_code = descriptor & 0x0F;
+    let (body_length, header_length) = match (type_code, length_code) {
+        // Type code 15 is reserved.
+        (0xF, _) => return None,
+        // Booleans store their value in the length nibble, and nulls have no body.
+        (0x1, _) | (_, NULL_LENGTH) => (0, 1),
+        // A struct with length code 1 has sorted fields and a VarUInt length.
+        (STRUCT_TYPE_CODE, 1) | (_, VAR_UINT_LENGTH) => {
+            let (length, var_uint_length) = read_var_uint(&bytes[1..])?;
+            (length, 1 + var_uint_length)
+        }
+        (_, length) => (length as usize, 1),
+    };
+    let total_length = header_length.checked_add(body_length)?;
+    if total_length > bytes.len() {
+        return None;
+    }
+    Some(total_length)
+}
+
+// Whether `bytes` begins with a binary version marker for any version of Ion: 0xE0, the major
+// and minor versions, and 0xEA.
+pub fn is_version_marker(bytes: &[u8]) -> bool {
+    bytes.len() >= IVM.len() && bytes[0] == IVM[0] && bytes[3] == IVM[3]
+}
+
+// Walks the top-level values of a binary stream, returning the offset and version of the first
+// version marker for a version other than Ion 1.0. Stops early, returning `None`, if a value's
+// header is invalid; the reader will report that.
+pub fn unsupported_version_marker(bytes: &[u8]) -> Option<(usize, u8, u8)> {
+    let mut position = 0;
+    while position < bytes.len() {
+        let rest = &bytes[position..];
+        if is_version_marker(rest) && !rest.starts_with(&IVM) {
+            return Some((position, rest[1], rest[2]));
+        }
+        position += encoded_length(rest)?;
+    }
+    None
+}
+
+// If `value` is a top-level annotation wrapper whose first annotation is $ion_symbol_table,
+// returns the bytes of the value it wraps.
+pub fn local_symbol_table(value: &[u8]) -> Option<&[u8]> {
+    if value.starts_with(&IVM) || value.first()? >> 4 != ANNOTATION_WRAPPER_TYPE_CODE {
+        return None;
+    }
+    let mut position = 1;
+    if value[0] & 0x0F == VAR_UINT_LENGTH {
+        position += read_var_uint(&value[position..])?.1;
+    }
+    let (annotations_length, length_bytes) = read_var_uint(&value[position..])?;
+    position += length_bytes;
+    let (first_annotation, _) = read_var_uint(&value[position..])?;
+    if first_annotation != ION_SYMBOL_TABLE_SID {
+        return None;
+    }
+    value.get(position + annotations_length..)
+}
+
+// Whether the top-level value at the beginning of `bytes` is one the reader handles itself rather
+// than returning: a version marker, a local symbol table, or NOP padding.
+pub fn is_system_value(bytes: &[u8]) -> bool {
+    let descriptor = match bytes.first() {
+        Some(descriptor) => *descriptor,
+        None => return false,
+    };
+    let is_nop_pad = descriptor >> 4 == 0 && descriptor & 0x0F != NULL_LENGTH;
+    is_nop_pad || is_version_marker(bytes) || local_symbol_table(bytes).is_some()
+}
+
+// A SystemEventHandler that keeps a copy of the local symbols defined by the stream being read,
+// so that a stream with the same symbols can be re-created using `stream_preamble`.
+pub struct SymbolTableTracker {
+    symbols: Rc<RefCell<Vec<String>>>,
+}
+
+impl SymbolTableTracker {
+    // Returns a tracker and a shared reference to the symbols it will keep up to date.
+    pub fn new() -> (SymbolTableTracker, Rc<RefCell<Vec<String>>>) {
+        let symbols = Rc::new(RefCell::new(Vec::new()));
+        (SymbolTableTracker { symbols: Rc::clone(&symbols) }, symbols)
+    }
+}
+
+impl SystemEventHandler for SymbolTableTracker {
+    fn on_ivm(&mut self, _ion_version: (u8, u8)) {
+        self.symbols.borrow_mut().clear();
+    }
+
+    fn on_symbol_table_append(&mut self, symbol_table: &SymbolTable, starting_id: usize) {
+        self.symbols.borrow_mut().extend_from_slice(symbol_table.symbols_tail(starting_id));
+    }
+
+    fn on_symbol_table_reset(&mut self, symbol_table: &SymbolTable) {
+        let mut symbols = self.symbols.borrow_mut();
+        symbols.clear();
+        if symbol_table.len() > ION_1_0_SYSTEM_TABLE_LENGTH {
+            symbols.extend_from_slice(symbol_table.symbols_tail(ION_1_0_SYSTEM_TABLE_LENGTH));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(value: usize) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        encode_var_uint(value, &mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn var_uint_uses_seven_bits_per_byte() {
+        assert_eq!(encoded(0), [0x80]);
+        assert_eq!(encoded(127), [0xFF]);
+        assert_eq!(encoded(128), [0x01, 0x80]);
+        assert_eq!(encoded(16_383), [0x7F, 0xFF]);
+        assert_eq!(encoded(16_384), [0x01, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn var_uint_round_trips() {
+        for value in [0, 1, 127, 128, 16_383, 16_384, u32::MAX as usize, usize::MAX].iter() {
+            let bytes = encoded(*value);
+            assert_eq!(read_var_uint(&bytes), Some((*value, bytes.len())), "{}", value);
+        }
+    }
+
+    #[test]
+    fn var_uint_stops_at_its_last_byte() {
+        assert_eq!(read_var_uint(&[0x01, 0x80, 0xFF]), Some((128, 2)));
+    }
+
+    #[test]
+    fn var_uint_must_be_terminated() {
+        assert_eq!(read_var_uint(&[]), None);
+        assert_eq!(read_var_uint(&[0x01, 0x02]), None);
+    }
+
+    #[test]
+    fn var_uint_must_fit_in_a_usize() {
+        let mut bytes = vec![0x7F; 10];
+        bytes.push(0x80);
+        assert_eq!(read_var_uint(&bytes), None);
+    }
+
+    #[test]
+    fn var_int_has_a_sign_bit() {
+        assert_eq!(read_var_int(&[0x81]), Some((1, false, 1)));
+        assert_eq!(read_var_int(&[0xC1]), Some((1, true, 1)));
+        assert_eq!(read_var_int(&[0xBF]), Some((63, false, 1)));
+        assert_eq!(read_var_int(&[0x01, 0x80]), Some((128, false, 2)));
+        assert_eq!(read_var_int(&[0x41, 0x80]), Some((128, true, 2)));
+    }
+
+    #[test]
+    fn var_int_keeps_negative_zero() {
+        assert_eq!(read_var_int(&[0xC0]), Some((0, true, 1)));
+        assert_eq!(read_var_int(&[0x80]), Some((0, false, 1)));
+    }
+
+    #[test]
+    fn var_int_must_be_terminated_and_fit() {
+        assert_eq!(read_var_int(&[]), None);
+        assert_eq!(read_var_int(&[0x01]), None);
+        let mut bytes = vec![0x3F];
+        bytes.extend(vec![0x7F; 9]);
+        bytes.push(0x80);
+        assert_eq!(read_var_int(&bytes), None);
+    }
+
+    #[test]
+    fn encoded_length_of_values() {
+        assert_eq!(encoded_length(&IVM), Some(4));
+        // int 0, with no body
+        assert_eq!(encoded_length(&[0x20]), Some(1));
+        // int 5
+        assert_eq!(encoded_length(&[0x21, 0x05, 0x20]), Some(2));
+        // null.int and true store nothing after the descriptor
+        assert_eq!(encoded_length(&[0x2F]), Some(1));
+        assert_eq!(encoded_length(&[0x11]), Some(1));
+        // a 14-byte string, whose length follows as a VarUInt
+        let mut string = vec![0x8E, 0x8E];
+        string.extend(b"fourteen bytes");
+        assert_eq!(encoded_length(&string), Some(16));
+        // a struct with sorted fields
+        assert_eq!(encoded_length(&[0xD1, 0x82, 0x84, 0x20]), Some(4));
+    }
+
+    #[test]
+    fn encoded_length_rejects_invalid_and_truncated_values() {
+        assert_eq!(encoded_length(&[]), None);
+        // type code 15 is reserved
+        assert_eq!(encoded_length(&[0xF0]), None);
+        // the body extends past the end
+        assert_eq!(encoded_length(&[0x22, 0x01]), None);
+        // the VarUInt length is missing or unterminated
+        assert_eq!(encoded_length(&[0x8E]), None);
+        assert_eq!(encoded_length(&[0x8E, 0x01]), None);
+        // a length so large that adding the header overflows
+        let mut huge = vec![0x8E];
+        encode_var_uint(usize::MAX, &mut huge);
+        assert_eq!(encoded_length(&huge), None);
+    }
+
+    #[test]
+    fn system_values_are_version_markers_symbol_tables_and_padding() {
+        assert!(is_system_value(&IVM));
+        assert!(is_system_value(&encode_symbol_table(&["a".to_owned()])));
+        assert!(is_system_value(&[0x00]));
+        assert!(is_system_value(&[0x02, 0x00, 0x00]));
+        // null.null shares padding's type code.
+        assert!(!is_system_value(&[0x0F]));
+        assert!(!is_system_value(&[0x21, 0x01]));
+        assert!(!is_system_value(&[]));
+    }
+}
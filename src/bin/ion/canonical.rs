@@ -0,0 +1,59 @@
+use anyhow::Result;
+
+use crate::value::{Data, FloatStyle, TextFormatter, Value};
+
+// Ion data can be encoded many ways: as text or binary, with different symbol tables, with struct
+// fields in any order, and with varying whitespace. The canonical form of a stream is its compact
+// text Ion with each struct's fields sorted, one top-level value per line, so two streams have the
+// same canonical form exactly when they hold the same data.
+//
+// Nothing in the data model is lost: ints keep every digit, timestamps keep their precision and
+// offset (so `2021T` and `2021-01-01T00:00Z` differ), decimals keep their exponent, and floats
+// are written with the fewest digits that read back as the same float.
+//
+// Fields are sorted by name and then by their values' canonical text. Symbols whose text is
+// unknown can't be put in canonical form, so they are an error.
+pub fn canonical_bytes(values: &[Value]) -> Result<Vec<u8>> {
+    let mut formatter = canonical_formatter();
+    let mut text = String::new();
+    for value in values {
+        let value = canonicalize(value, &mut formatter)?;
+        formatter.format(&value, &mut text)?;
+        text.push('\n');
+    }
+    Ok(text.into_bytes())
+}
+
+fn canonical_formatter() -> TextFormatter {
+    let mut formatter = TextFormatter::new();
+    formatter.set_float_style(FloatStyle::Shortest);
+    formatter.set_exact_decimals(true);
+    formatter
+}
+
+// Returns a copy of `value` with the fields of every struct in it sorted.
+fn canonicalize(value: &Value, formatter: &mut TextFormatter) -> Result<Value> {
+    let data = match &value.data {
+        Data::List(values) => Data::List(canonicalize_all(values, formatter)?),
+        Data::SExpression(values) => Data::SExpression(canonicalize_all(values, formatter)?),
+        Data::Struct(fields) => {
+            let mut sortable = Vec::with_capacity(fields.len());
+            for (name, field_value) in fields {
+                let field_value = canonicalize(field_value, formatter)?;
+                let mut text = String::new();
+                formatter.format(&field_value, &mut text)?;
+                sortable.push((name.clone(), field_value, text));
+            }
+            sortable.sort_by(|(name_a, _, text_a), (name_b, _, text_b)| {
+                name_a.text().cmp(&name_b.text()).then_with(|| text_a.cmp(text_b))
+            });
+            Data::Struct(sortable.into_iter().map(|(name, value, _)| (name, value)).collect())
+        }
+        other => other.clone(),
+    };
+    Ok(Value { annotations: value.annotations.clone(), data })
+}
+
+fn canonicalize_all(values: &[Value], formatter: &mut TextFormatter) -> Result<Vec<Value>> {
+    values.iter().map(|value| canonicalize(value, formatter)).collect()
+}
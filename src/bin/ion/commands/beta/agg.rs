@@ -0,0 +1,485 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Duration, FixedOffset, TimeZone};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::key::KeyExtractor;
+use crate::output::{format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::size::check_max_memory;
+use crate::timestamp::Timestamp;
+use crate::value::{Data, Symbol, UnknownSymbols, Value};
+
+const ABOUT: &str = "Groups values by key and computes counts, sums, minimums, maximums, and averages.";
+
+// The longest window allowed: the span of Ion timestamps, from year 1 through 9999, in seconds.
+// Longer windows would all hold every value, and could overflow when their ends are computed.
+const MAX_WINDOW_SECONDS: i64 = 3_652_059 * 86_400;
+
+pub fn app() -> CommandConfig {
+    App::new("agg")
+        .about(ABOUT)
+        .arg(path_list_arg("group-by", "g", "Group values by the value at this path"))
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .short("c")
+                .help("Count the values in each group"),
+        )
+        .arg(path_list_arg("sum", "s", "Sum the numbers at this path"))
+        .arg(path_list_arg("min", "", "Find the smallest number at this path"))
+        .arg(path_list_arg("max", "", "Find the largest number at this path"))
+        .arg(path_list_arg("avg", "a", "Average the numbers at this path"))
+        .arg(
+            Arg::with_name("window")
+                .long("window")
+                .short("w")
+                .takes_value(true)
+                .requires("on")
+                .help("Also group values into tumbling windows of this length, e.g. 30s, 5m, 1h, 1d"),
+        )
+        .arg(
+            Arg::with_name("on")
+                .long("on")
+                .takes_value(true)
+                .requires("window")
+                .help("Path to the timestamp that determines each value's window"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes one struct per group, in the order the groups first appeared. Each
+struct has a field for each --group-by path and each aggregate, named
+after the path's last field: `--group-by region --sum bytes --count`
+produces structs like {region: \"us-west-2\", count: 12, sum_bytes: 4096}.
+Without --group-by, all values form a single group.
+
+Aggregates skip values that are missing, null, or not numbers. Sums
+of integers are integers; sums involving decimals are decimals; sums
+involving floats, and all averages, are floats.
+
+With --window, each group also covers a single window of time, and its
+struct begins with window_start and window_end fields (in UTC). Windows
+are aligned to the Unix epoch. Input is expected to be roughly in time
+order: a window's groups are written as soon as a value from a later
+window is read. Values without a timestamp at the --on path are skipped.
+
+Every open group is held in memory. With the global --max-memory, agg
+fails if the groups would take more than that."
+        )
+}
+
+// Creates an argument that accepts any number of paths.
+fn path_list_arg(name: &'static str, short: &'static str, help: &'static str) -> Arg<'static, 'static> {
+    let arg = Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help(help);
+    if short.is_empty() {
+        arg
+    } else {
+        arg.short(short)
+    }
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let mut aggregator = Aggregator::new(matches)?;
+    let mut output = IonOutput::from_matches(matches)?;
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    output.set_unknown_symbols(UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()));
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            aggregator.add(&value)
+                .with_context(|| format!("Could not aggregate a value in '{}'", input.name()))?;
+            for group in aggregator.closed_groups() {
+                output.write_value(&group)?;
+            }
+        }
+    }
+    for group in aggregator.results() {
+        output.write_value(&group)?;
+    }
+    if aggregator.values_without_timestamps > 0 {
+        eprintln!("Skipped {} value(s) without a timestamp at the --on path.",
+                  aggregator.values_without_timestamps);
+    }
+    output.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operation {
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl Operation {
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::Sum => "sum",
+            Operation::Min => "min",
+            Operation::Max => "max",
+            Operation::Avg => "avg",
+        }
+    }
+}
+
+// An aggregate requested on the command line, like `--sum bytes`.
+struct Aggregate {
+    operation: Operation,
+    path: Path,
+    // The name of the output field, like `sum_bytes`.
+    field_name: String,
+}
+
+// Tumbling windows of time, selected by a timestamp within each value.
+struct Window {
+    path: Path,
+    length: Duration,
+}
+
+struct Aggregator {
+    window: Option<Window>,
+    // The start of the latest window seen so far, and whether it has changed since the last
+    // call to `closed_groups`.
+    latest_window: Option<DateTime<FixedOffset>>,
+    window_advanced: bool,
+    values_without_timestamps: usize,
+    group_by: Vec<KeyExtractor>,
+    count: bool,
+    aggregates: Vec<Aggregate>,
+    // Groups in the order they first appeared, and the index of each group by its key.
+    groups: Vec<Group>,
+    group_indexes: HashMap<GroupKey, usize>,
+    // The approximate memory used by `groups` and `group_indexes`, in bytes.
+    estimated_size: usize,
+}
+
+// The text of each `group_by` key (or `None` if it was missing), preceded by the group's window
+// start if there is one.
+type GroupKey = Vec<Option<String>>;
+
+struct Group {
+    key: GroupKey,
+    window_start: Option<DateTime<FixedOffset>>,
+    // The value of each `group_by` path for this group, or null if it was missing.
+    keys: Vec<Value>,
+    count: usize,
+    // One accumulator for each of the Aggregator's `aggregates`.
+    accumulators: Vec<Accumulator>,
+    // The approximate memory used by this group and its entry in `group_indexes`, in bytes.
+    estimated_size: usize,
+}
+
+impl Aggregator {
+    fn new(matches: &ArgMatches<'static>) -> Result<Aggregator> {
+        let group_by = paths(matches, "group-by")?
+            .into_iter()
+            .map(KeyExtractor::new)
+            .collect();
+        let mut aggregates = Vec::new();
+        for operation in &[Operation::Sum, Operation::Min, Operation::Max, Operation::Avg] {
+            for path in paths(matches, operation.name())? {
+                let field_name = format!("{}_{}", operation.name(), path_name(&path));
+                aggregates.push(Aggregate { operation: *operation, path, field_name });
+            }
+        }
+        let window = match (matches.value_of("window"), matches.value_of("on")) {
+            (Some(length), Some(path)) => Some(Window {
+                path: Path::from_str(path).with_context(|| "Invalid --on path")?,
+                length: parse_duration(length)?,
+            }),
+            _ => None,
+        };
+        Ok(Aggregator {
+            window,
+            latest_window: None,
+            window_advanced: false,
+            values_without_timestamps: 0,
+            group_by,
+            count: matches.is_present("count"),
+            aggregates,
+            groups: Vec::new(),
+            group_indexes: HashMap::new(),
+            estimated_size: 0,
+        })
+    }
+
+    fn add(&mut self, value: &Value) -> Result<()> {
+        let window_start = match &self.window {
+            Some(window) => match window_start(window, value) {
+                Some(start) => Some(start),
+                None => {
+                    self.values_without_timestamps += 1;
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+        if let Some(start) = window_start {
+            if self.latest_window < Some(start) {
+                self.latest_window = Some(start);
+                self.window_advanced = true;
+            }
+        }
+
+        let mut key: GroupKey = window_start.iter().map(|start| Some(start.to_rfc3339())).collect();
+        for keys in self.group_by.iter_mut() {
+            key.push(keys.key_of(value)?);
+        }
+        let index = match self.group_indexes.get(&key) {
+            Some(index) => *index,
+            None => {
+                let keys = self.group_by
+                    .iter()
+                    .map(|keys| match keys.path().select(value).first() {
+                        Some(key) => (*key).clone(),
+                        None => Value::new(Data::Null(IonType::Null)),
+                    })
+                    .collect();
+                // The key's text is held twice, and the key values take about as much again.
+                let key_size: usize = key.iter().flatten().map(String::len).sum();
+                let estimated_size = size_of::<Group>()
+                    + 3 * key_size
+                    + self.aggregates.len() * size_of::<Accumulator>();
+                self.estimated_size += estimated_size;
+                check_max_memory(
+                    self.estimated_size,
+                    || format!("Aggregating {} groups at once", self.groups.len() + 1),
+                    "try fewer --group-by paths or a shorter --window",
+                )?;
+                self.groups.push(Group {
+                    key: key.clone(),
+                    window_start,
+                    keys,
+                    count: 0,
+                    accumulators: vec![Accumulator::default(); self.aggregates.len()],
+                    estimated_size,
+                });
+                self.group_indexes.insert(key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+
+        let group = &mut self.groups[index];
+        group.count += 1;
+        for (aggregate, accumulator) in self.aggregates.iter().zip(group.accumulators.iter_mut()) {
+            for selected in aggregate.path.select(value) {
+                if let Some(number) = Number::from_value(selected) {
+                    accumulator.add(number, selected);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Removes the groups whose windows ended before the latest window began and returns a
+    // struct describing each of them.
+    fn closed_groups(&mut self) -> Vec<Value> {
+        if !self.window_advanced {
+            return Vec::new();
+        }
+        self.window_advanced = false;
+        let latest_window = self.latest_window;
+        let (closed, open): (Vec<Group>, Vec<Group>) = self.groups
+            .drain(..)
+            .partition(|group| group.window_start < latest_window);
+        self.groups = open;
+        self.estimated_size = self.groups.iter().map(|group| group.estimated_size).sum();
+        self.group_indexes = self.groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (group.key.clone(), index))
+            .collect();
+        closed.into_iter().map(|group| self.describe(group)).collect()
+    }
+
+    // Returns a struct describing each remaining group.
+    fn results(&mut self) -> Vec<Value> {
+        let groups: Vec<Group> = self.groups.drain(..).collect();
+        self.group_indexes.clear();
+        self.estimated_size = 0;
+        groups.into_iter().map(|group| self.describe(group)).collect()
+    }
+
+    fn describe(&self, group: Group) -> Value {
+        let mut fields = Vec::new();
+        if let (Some(window), Some(start)) = (&self.window, group.window_start) {
+            fields.push((Symbol::from("window_start"), Value::new(Data::Timestamp(Timestamp::from_datetime(start)))));
+            fields.push((Symbol::from("window_end"), Value::new(Data::Timestamp(Timestamp::from_datetime(start + window.length)))));
+        }
+        let names = self.group_by.iter().map(|keys| Symbol::from(path_name(keys.path())));
+        fields.extend(names.zip(group.keys));
+        if self.count {
+            fields.push((Symbol::from("count"), Value::new(Data::Integer(group.count as i64))));
+        }
+        for (aggregate, accumulator) in self.aggregates.iter().zip(group.accumulators) {
+            fields.push((Symbol::from(aggregate.field_name.as_str()), accumulator.result(aggregate.operation)));
+        }
+        Value::new(Data::Struct(fields))
+    }
+}
+
+// Returns the start of the window containing the timestamp in `value`, in UTC, or `None` if
+// `value` doesn't have exactly one timestamp at the window's path.
+fn window_start(window: &Window, value: &Value) -> Option<DateTime<FixedOffset>> {
+    let timestamp = match window.path.select(value).as_slice() {
+        [Value { data: Data::Timestamp(timestamp), .. }] => timestamp.instant(),
+        _ => return None,
+    };
+    let length = window.length.num_seconds();
+    let seconds = timestamp.timestamp();
+    let start = seconds - seconds.rem_euclid(length);
+    FixedOffset::east_opt(0)?.timestamp_opt(start, 0).single()
+}
+
+// Parses a window length like `30s`, `5m`, `1h`, or `1d`.
+fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let (count, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let count: i64 = match count.parse() {
+        Ok(count) if count > 0 => count,
+        _ => bail!("Invalid window length '{}'; expected a positive number followed by s, m, h, or d", text),
+    };
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("Invalid window length '{}'; the unit must be s, m, h, or d", text),
+    };
+    match count.checked_mul(unit_seconds) {
+        Some(seconds) if seconds <= MAX_WINDOW_SECONDS => Ok(Duration::seconds(seconds)),
+        _ => bail!("Invalid window length '{}'; windows can be at most {}d long", text, MAX_WINDOW_SECONDS / 86_400),
+    }
+}
+
+// Parses each occurrence of the named argument as a path.
+fn paths(matches: &ArgMatches<'static>, name: &str) -> Result<Vec<Path>> {
+    match matches.values_of(name) {
+        Some(values) => values
+            .map(|text| Path::from_str(text).with_context(|| format!("Invalid --{} path", name)))
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+// The name used for a path's output field: its last field name, or the path itself if it
+// doesn't end with a field name.
+pub fn path_name(path: &Path) -> String {
+    match path.split_last() {
+        Some((_, Step::Field(name))) => name.clone(),
+        _ => path.to_string(),
+    }
+}
+
+// A number that can be aggregated. Integers and decimals are aggregated exactly.
+#[derive(Debug, Clone)]
+enum Number {
+    Exact(BigDecimal),
+    Float(f64),
+}
+
+impl Number {
+    fn from_value(value: &Value) -> Option<Number> {
+        match &value.data {
+            Data::Integer(i) => Some(Number::Exact(BigDecimal::from(*i))),
+            Data::BigInteger(i) => Some(Number::Exact(BigDecimal::new(i.clone(), 0))),
+            Data::Decimal(d) => Some(Number::Exact(d.clone())),
+            Data::Float(f) => Some(Number::Float(*f)),
+            _ => None,
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(d) => d.to_f64().unwrap_or(f64::NAN),
+            Number::Float(f) => *f,
+        }
+    }
+
+    fn add(self, other: Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a + b),
+            (a, b) => Number::Float(a.to_f64() + b.to_f64()),
+        }
+    }
+
+    fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Some(a.cmp(b)),
+            (a, b) => a.to_f64().partial_cmp(&b.to_f64()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    count: usize,
+    sum: Option<Number>,
+    // Whether every number added so far was an integer.
+    all_integers: bool,
+    // The smallest and largest values seen so far, as they appeared in the input.
+    min: Option<(Number, Value)>,
+    max: Option<(Number, Value)>,
+}
+
+impl Accumulator {
+    fn add(&mut self, number: Number, value: &Value) {
+        self.all_integers = (self.count == 0 || self.all_integers) && value.ion_type() == IonType::Integer;
+        self.count += 1;
+        self.sum = Some(match self.sum.take() {
+            Some(sum) => sum.add(number.clone()),
+            None => number.clone(),
+        });
+        let is_new_min = match &self.min {
+            Some((min, _)) => number.compare(min) == Some(Ordering::Less),
+            None => true,
+        };
+        if is_new_min {
+            self.min = Some((number.clone(), value.clone()));
+        }
+        let is_new_max = match &self.max {
+            Some((max, _)) => number.compare(max) == Some(Ordering::Greater),
+            None => true,
+        };
+        if is_new_max {
+            self.max = Some((number, value.clone()));
+        }
+    }
+
+    // Returns the result of an operation, or null if no numbers were added.
+    fn result(self, operation: Operation) -> Value {
+        let data = match (operation, self.sum) {
+            (_, None) => Data::Null(IonType::Null),
+            // A sum of integers is an integer, however large it gets.
+            (Operation::Sum, Some(Number::Exact(sum))) if self.all_integers => {
+                Data::integer(sum.with_scale(0).into_bigint_and_exponent().0)
+            }
+            (Operation::Sum, Some(Number::Exact(sum))) => Data::Decimal(sum),
+            (Operation::Sum, Some(Number::Float(sum))) => Data::Float(sum),
+            (Operation::Avg, Some(sum)) => Data::Float(sum.to_f64() / self.count as f64),
+            // The minimum and maximum are always set once a number has been added.
+            (Operation::Min, Some(_)) => return self.min.map(|(_, value)| value).unwrap(),
+            (Operation::Max, Some(_)) => return self.max.map(|(_, value)| value).unwrap(),
+        };
+        Value::new(data)
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::iter;
+use std::mem;
+use std::path::{Path as FilePath, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput, STDIN_NAME};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Assembles an Ion document by replacing include::\"file.ion\" values with the files' contents.";
+
+pub fn app() -> CommandConfig {
+    App::new("assemble")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("annotation")
+                .long("annotation")
+                .short("a")
+                .takes_value(true)
+                .default_value("include")
+                .help("Annotation that marks a string as the path of a file to include"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "A string annotated with `include` (and nothing else) is replaced by the
+values in the file it names. Relative paths are resolved against the
+directory of the file containing the include, or the current directory
+for STDIN. Included files are assembled too, and an include that leads
+back to a file that is already being assembled is an error.
+
+At the top level and in lists and s-expressions, every value in the
+included file takes the include's place. As a struct field's value, the
+included file must contain exactly one value."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --annotation has a default value, so we can unwrap this safely.
+    let assembler = Assembler { annotation: matches.value_of("annotation").unwrap() };
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let values = if input_name == STDIN_NAME {
+            let input = IonInput::open(input_name)?;
+            assembler.assemble_values(&input, FilePath::new("."), &mut Vec::new())?
+        } else {
+            assembler.assemble_file(FilePath::new(input_name), &mut Vec::new())?
+        };
+        for value in &values {
+            output.write_value(value)?;
+        }
+    }
+    output.finish()
+}
+
+struct Assembler<'a> {
+    annotation: &'a str,
+}
+
+impl<'a> Assembler<'a> {
+    // Reads a file and expands its includes. `files` holds the files currently being assembled,
+    // outermost first, so that cycles can be reported.
+    fn assemble_file(&self, file: &FilePath, files: &mut Vec<PathBuf>) -> Result<Vec<Value>> {
+        let canonical = fs::canonicalize(file)
+            .with_context(|| format!("Could not find '{}'", file.display()))?;
+        if let Some(index) = files.iter().position(|f| *f == canonical) {
+            let cycle: Vec<String> = files[index..]
+                .iter()
+                .chain(iter::once(&canonical))
+                .map(|f| f.display().to_string())
+                .collect();
+            bail!("Found an include cycle: {}", cycle.join(" -> "));
+        }
+        let name = file.to_str()
+            .with_context(|| format!("'{}' is not a valid UTF-8 path.", file.display()))?;
+        let input = IonInput::open(name)?;
+        // `canonical` names a file, so it always has a parent directory.
+        let directory = canonical.parent().unwrap().to_owned();
+        files.push(canonical);
+        let values = self.assemble_values(&input, &directory, files)?;
+        files.pop();
+        Ok(values)
+    }
+
+    fn assemble_values(&self, input: &IonInput, directory: &FilePath, files: &mut Vec<PathBuf>) -> Result<Vec<Value>> {
+        let mut values = Vec::new();
+        for value in input.read_all()? {
+            values.extend(self.expand(value, directory, files)
+                .with_context(|| format!("Could not assemble '{}'", input.name()))?);
+        }
+        Ok(values)
+    }
+
+    // Returns the values that take the place of `value`: the contents of the file it includes,
+    // or the value itself with any includes inside of it expanded.
+    fn expand(&self, mut value: Value, directory: &FilePath, files: &mut Vec<PathBuf>) -> Result<Vec<Value>> {
+        if let Some(file) = self.include_target(&value) {
+            return self.assemble_file(&directory.join(file), files);
+        }
+        match &mut value.data {
+            Data::List(children) | Data::SExpression(children) => {
+                let mut expanded = Vec::with_capacity(children.len());
+                for child in children.drain(..) {
+                    expanded.extend(self.expand(child, directory, files)?);
+                }
+                *children = expanded;
+            }
+            Data::Struct(fields) => {
+                for (name, field) in fields.iter_mut() {
+                    let placeholder = Value::new(Data::Null(IonType::Null));
+                    let mut expanded = self.expand(mem::replace(field, placeholder), directory, files)?;
+                    if expanded.len() != 1 {
+                        bail!(
+                            "The file included by field '{}' contains {} values, but a field can only hold one.",
+                            name.text().unwrap_or("<unknown>"),
+                            expanded.len()
+                        );
+                    }
+                    *field = expanded.pop().unwrap();
+                }
+            }
+            _ => {}
+        }
+        Ok(vec![value])
+    }
+
+    // Returns the path named by an include, or None if `value` isn't an include.
+    fn include_target<'v>(&self, value: &'v Value) -> Option<&'v str> {
+        match (value.annotations.as_slice(), &value.data) {
+            ([annotation], Data::String(file)) if annotation == self.annotation => Some(file),
+            _ => None,
+        }
+    }
+}
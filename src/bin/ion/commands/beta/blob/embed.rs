@@ -0,0 +1,66 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Embeds the contents of files as blob (or clob) fields of Ion structs.";
+
+pub fn app() -> CommandConfig {
+    App::new("embed")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .takes_value(true)
+                .default_value("payload")
+                .help("Name of the field that will hold each file's contents"),
+        )
+        .arg(
+            Arg::with_name("name-field")
+                .long("name-field")
+                .short("n")
+                .takes_value(true)
+                .default_value("name")
+                .help("Name of the field that will hold each file's name"),
+        )
+        .arg(
+            Arg::with_name("clob")
+                .long("clob")
+                .help("Embed the files as clobs instead of blobs"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .multiple(true)
+                .required(true)
+                .help("Files to embed"),
+        )
+        .after_help("Each file becomes a struct like {name: \"file.bin\", payload: {{...}}}.")
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // Both of these have default values, so we can unwrap them safely.
+    let field = matches.value_of("field").unwrap();
+    let name_field = matches.value_of("name-field").unwrap();
+    let as_clob = matches.is_present("clob");
+
+    let mut output = IonOutput::from_matches(matches)?;
+    // `file` is required, so we can unwrap this safely.
+    for file_name in matches.values_of("file").unwrap() {
+        let bytes = fs::read(file_name)
+            .with_context(|| format!("Could not read '{}'", file_name))?;
+        let payload = if as_clob { Data::Clob(bytes) } else { Data::Blob(bytes) };
+        let envelope = Value::new(Data::Struct(vec![
+            (Symbol::from(name_field), Value::new(Data::String(file_name.to_owned()))),
+            (Symbol::from(field), Value::new(payload)),
+        ]));
+        output.write_value(&envelope)?;
+    }
+    output.finish()
+}
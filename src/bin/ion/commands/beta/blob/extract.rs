@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::path::{Path, Step};
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Writes the blob and clob values found at a path to separate files.";
+
+pub fn app() -> CommandConfig {
+    App::new("extract")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the blob/clob values to extract, e.g. '(payload)'"),
+        )
+        .arg(
+            Arg::with_name("name-field")
+                .long("name-field")
+                .short("n")
+                .takes_value(true)
+                .help("Name each file using this field of the struct containing the blob")
+                .long_help(
+                    "When specified, each extracted file will be named using the value of
+this field in the struct that contains the blob. The field must be a
+string, symbol, or integer. If it is missing, empty, '.', or '..', the
+blob's index will be used instead. By default, files are named by index: 0, 1, 2, etc."
+                ),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .short("d")
+                .takes_value(true)
+                .default_value(".")
+                .help("Directory in which to write the extracted files"),
+        )
+        .arg(
+            Arg::with_name("extension")
+                .long("extension")
+                .short("e")
+                .takes_value(true)
+                .default_value("bin")
+                .help("File extension to use for the extracted files"),
+        )
+        .arg(
+            Arg::with_name("overwrite")
+                .long("overwrite")
+                .help("Replace files that already exist in the output directory"),
+        )
+        .arg(input_arg())
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --path is required, so we can unwrap this safely.
+    let path = Path::from_str(matches.value_of("path").unwrap())?;
+    let (parent_path, last_step) = match path.split_last() {
+        Some(split) => split,
+        None => bail!("--path must select values nested within the top-level values."),
+    };
+
+    let mut extractor = Extractor {
+        parent_path,
+        last_step: last_step.clone(),
+        name_field: matches.value_of("name-field"),
+        // Both of these have default values, so we can unwrap them safely.
+        output_dir: PathBuf::from(matches.value_of("output-dir").unwrap()),
+        extension: matches.value_of("extension").unwrap(),
+        overwrite: matches.is_present("overwrite"),
+        index: 0,
+        files_written: HashSet::new(),
+    };
+
+    fs::create_dir_all(&extractor.output_dir).with_context(|| {
+        format!("Could not create output directory '{}'", extractor.output_dir.display())
+    })?;
+
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            extractor.extract_from(&value)?;
+        }
+    }
+    eprintln!("Extracted {} file(s) to '{}'", extractor.index, extractor.output_dir.display());
+    Ok(())
+}
+
+struct Extractor<'a> {
+    // The path to the containers holding the blobs; this is where `name_field` is looked up.
+    parent_path: Path,
+    last_step: Step,
+    name_field: Option<&'a str>,
+    output_dir: PathBuf,
+    extension: &'a str,
+    // Whether files that were already in `output_dir` may be replaced.
+    overwrite: bool,
+    // The number of blobs extracted so far.
+    index: usize,
+    // The files written so far, so that no two values are written to the same one.
+    files_written: HashSet<PathBuf>,
+}
+
+impl<'a> Extractor<'a> {
+    fn extract_from(&mut self, value: &Value) -> Result<()> {
+        for parent in self.parent_path.select(value) {
+            for lob in self.last_step.select(parent) {
+                let bytes = match lob.as_lob() {
+                    Some(bytes) => bytes,
+                    // Nulls and other types at the path are skipped.
+                    None => continue,
+                };
+                let mut name = self.file_name_for(parent);
+                // The extension is added rather than replacing anything after a '.' in the name,
+                // so names like 'v1.2' and 'v1.3' stay distinct.
+                if !self.extension.is_empty() {
+                    name = format!("{}.{}", name, self.extension);
+                }
+                let file_path = self.output_dir.join(&name);
+                if !self.files_written.insert(file_path.clone()) {
+                    bail!("More than one extracted value would be written to '{}'", file_path.display());
+                }
+                if !self.overwrite && file_path.exists() {
+                    bail!("'{}' already exists; pass --overwrite to replace it.", file_path.display());
+                }
+                fs::write(&file_path, bytes)
+                    .with_context(|| format!("Could not write '{}'", file_path.display()))?;
+                self.index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn file_name_for(&self, parent: &Value) -> String {
+        let name = self.name_field
+            .and_then(|field| parent.get(field))
+            .and_then(|field_value| match &field_value.data {
+                Data::Integer(i) => Some(i.to_string()),
+                Data::BigInteger(i) => Some(i.to_string()),
+                _ => field_value.as_text().map(|text| text.to_owned()),
+            });
+        // Don't allow the field's text to send the file outside of the output directory, or name
+        // the directory itself.
+        match name.map(|name| name.replace(&['/', '\\'][..], "_")) {
+            Some(name) if !matches!(name.as_str(), "" | "." | "..") => name,
+            _ => self.index.to_string(),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+pub mod embed;
+pub mod extract;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `blob` subcommands
+pub fn blob_subcommands() -> Vec<CommandConfig> {
+    vec![
+        embed::app(),
+        extract::app(),
+    ]
+}
+
+pub fn runner_for_blob_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "embed" => embed::run,
+        "extract" => extract::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `blob` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_blob_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested blob command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("blob")
+        .about("Moves blob and clob payloads between Ion streams and standalone files.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(blob_subcommands())
+}
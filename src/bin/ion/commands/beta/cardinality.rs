@@ -0,0 +1,157 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::BigDecimal;
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{read_projection, Path};
+use crate::schema::ion_text;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Estimates the number of distinct values at one or more paths.";
+
+pub fn app() -> CommandConfig {
+    App::new("cardinality")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Path to the values to count; can be repeated"),
+        )
+        .arg(
+            Arg::with_name("precision")
+                .long("precision")
+                .takes_value(true)
+                .default_value("14")
+                .help("Use 2^precision registers per path (4-18); higher is more accurate but uses more memory"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes one struct per path, like
+  {path: \"(user_id)\", values: 1048576, estimate: 52213,
+   standard_error: 0.81, low: 51367, high: 53059}
+`values` is how many values were found at the path and `estimate` is
+the estimated number of distinct values among them. Values are compared
+by their Ion text, so `1` and `1.0` are different values, and
+annotations are significant. Missing values aren't counted.
+
+The estimate comes from a HyperLogLog sketch, so each path takes a fixed
+2^--precision bytes however large the input is. `standard_error` is the
+sketch's relative standard error as a percentage, 104 / sqrt(2^precision)
+(0.81% at the default precision), and the true count falls between `low`
+and `high`, two standard errors either side of the estimate, about 95%
+of the time. Small counts are estimated by linear counting, which is
+close to exact."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --path is required and --precision has a default value, so we can unwrap these safely.
+    let precision_arg = matches.value_of("precision").unwrap();
+    let precision = match u32::from_str(precision_arg) {
+        Ok(precision) if (4..=18).contains(&precision) => precision,
+        _ => bail!("--precision must be a whole number from 4 to 18, not '{}'.", precision_arg),
+    };
+    let mut paths = Vec::new();
+    for path_text in matches.values_of("path").unwrap() {
+        paths.push(Path::from_str(path_text).with_context(|| format!("Invalid --path '{}'", path_text))?);
+    }
+    // The number of values found at each path, and a sketch of them.
+    let mut counts: Vec<(usize, HyperLogLog)> = paths.iter().map(|_| (0, HyperLogLog::new(precision))).collect();
+
+    let projection: Vec<&Path> = paths.iter().collect();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = read_projection(&mut reader, &projection)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for (path, (values, sketch)) in paths.iter().zip(counts.iter_mut()) {
+                for selected in path.select(&value) {
+                    *values += 1;
+                    sketch.add(&ion_text(selected));
+                }
+            }
+        }
+    }
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for (path, (values, sketch)) in paths.iter().zip(&counts) {
+        let estimate = sketch.estimate();
+        let standard_error = sketch.standard_error();
+        let margin = 2.0 * standard_error * estimate;
+        let integer = |n: f64| Value::new(Data::Integer(n.round() as i64));
+        // The formatted number is always a valid decimal.
+        let percent = BigDecimal::from_str(&format!("{:.2}", 100.0 * standard_error)).unwrap();
+        output.write_value(&Value::new(Data::Struct(vec![
+            (Symbol::from("path"), Value::new(Data::String(path.to_string()))),
+            (Symbol::from("values"), Value::new(Data::Integer(*values as i64))),
+            (Symbol::from("estimate"), integer(estimate)),
+            (Symbol::from("standard_error"), Value::new(Data::Decimal(percent))),
+            (Symbol::from("low"), integer((estimate - margin).max(0.0))),
+            (Symbol::from("high"), integer(estimate + margin)),
+        ])))?;
+    }
+    output.finish()
+}
+
+// A HyperLogLog sketch: each value's hash picks a register, which keeps the longest run of
+// leading zeros seen in the rest of the hashes that picked it.
+struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new(precision: u32) -> HyperLogLog {
+        HyperLogLog { precision, registers: vec![0; 1 << precision] }
+    }
+
+    fn add(&mut self, text: &str) {
+        // DefaultHasher::new() always uses the same keys, so estimates are reproducible.
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - self.precision)) as usize;
+        // The remaining bits, with a sentinel bit so the count can't run past them.
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|register| 2f64.powi(-(*register as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|register| **register == 0).count();
+        // Small cardinalities are estimated more accurately by counting the empty registers.
+        if raw <= 2.5 * m && empty > 0 {
+            return m * (m / empty as f64).ln();
+        }
+        raw
+    }
+
+    fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use glob::Pattern;
+
+use crate::binary::{encoded_length, local_symbol_table};
+use crate::commands::CommandConfig;
+use crate::input::{reader_for, IonInput, IVM};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::Path;
+use crate::schema::{authority_arg, Authority};
+use crate::timestamp::Timestamp;
+use crate::validation::Validator;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Checks that the files in a directory are consistent with each other.";
+
+pub fn app() -> CommandConfig {
+    App::new("check-partition")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("directory")
+                .index(1)
+                .required(true)
+                .help("Directory holding the partition's files"),
+        )
+        .arg(
+            Arg::with_name("pattern")
+                .long("pattern")
+                .short("p")
+                .takes_value(true)
+                .default_value("*")
+                .help("Only check files whose names match this glob, e.g. '*.10n'"),
+        )
+        .arg(
+            Arg::with_name("timestamp")
+                .long("timestamp")
+                .short("t")
+                .takes_value(true)
+                .help("Path to each value's timestamp; files whose time ranges overlap are reported"),
+        )
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .takes_value(true)
+                .requires("type")
+                .help("ISL schema to validate each value against"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .takes_value(true)
+                .requires("schema")
+                .help("Type in --schema that each value must match"),
+        )
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .after_help(
+            "Reads each file in the directory whose name matches --pattern, in name
+order, and writes one struct per file, like
+  {file: \"part-0003.10n\", values: 51234, imports: [\"events@2\"],
+   fields: 14, start: 2024-01-03T00:00:00Z, end: 2024-01-03T23:59:59Z,
+   anomalies: [\"missing field 'region', which 9 of 10 files have\"]}
+A file's anomalies are:
+  imports    its local symbol tables import different shared symbol
+             tables than most files do. Only binary files carry their
+             imports; text files are compared as ion-c transcodes them.
+  fields     a top-level field that more than half of the files have
+             is missing from all of its values, or it has a field that
+             only half of the files or fewer have
+  schema     with --schema and --type, values that don't match the type;
+             the first violation is shown
+  overlap    with --timestamp, its range of timestamps overlaps another
+             file's. Values without a timestamp at the path are ignored.
+
+Exits with an error after the report if any file has an anomaly."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // `directory` is required and --pattern has a default value, so we can unwrap these safely.
+    let directory = PathBuf::from(matches.value_of("directory").unwrap());
+    let pattern_text = matches.value_of("pattern").unwrap();
+    let pattern = Pattern::new(pattern_text)
+        .with_context(|| format!("Invalid --pattern '{}'", pattern_text))?;
+    let timestamp_path = match matches.value_of("timestamp") {
+        Some(text) => Some(Path::from_str(text).with_context(|| format!("Invalid --timestamp path '{}'", text))?),
+        None => None,
+    };
+    let mut target = match (matches.value_of("schema"), matches.value_of("type")) {
+        (Some(schema_file), Some(type_name)) => {
+            let authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+            let id = authority.id_of(schema_file)?;
+            Some((Validator::new(authority), id, type_name))
+        }
+        _ => None,
+    };
+
+    let mut files = Vec::new();
+    let entries = fs::read_dir(&directory)
+        .with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+        if entry.file_type().is_ok_and(|file_type| file_type.is_file())
+            && pattern.matches(&entry.file_name().to_string_lossy()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    if files.is_empty() {
+        bail!("No files in '{}' match '{}'.", directory.display(), pattern_text);
+    }
+
+    let mut summaries = Vec::new();
+    for file in &files {
+        let file_name = file.to_str()
+            .with_context(|| format!("Input path {:?} is not valid UTF-8", file))?;
+        let input = IonInput::open(file_name)?;
+        let mut summary = FileSummary {
+            name: file.file_name().unwrap().to_string_lossy().into_owned(),
+            values: 0,
+            imports: imports(input.bytes()).with_context(|| format!("Could not read the symbol tables in '{}'", file_name))?,
+            fields: BTreeSet::new(),
+            range: None,
+            anomalies: Vec::new(),
+        };
+        let mut invalid_values = 0;
+        let mut first_violation = None;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", file_name))?;
+            summary.values += 1;
+            if let Data::Struct(fields) = &value.data {
+                summary.fields.extend(fields.iter().filter_map(|(name, _)| name.text().map(str::to_owned)));
+            }
+            if let Some(path) = &timestamp_path {
+                for timestamp in path.select(&value) {
+                    if let Data::Timestamp(timestamp) = &timestamp.data {
+                        // The earliest and latest are kept as they were written.
+                        let instant = timestamp.instant();
+                        summary.range = Some(match summary.range.take() {
+                            Some((start, end)) => (
+                                if instant < start.instant() { timestamp.clone() } else { start },
+                                if instant > end.instant() { timestamp.clone() } else { end },
+                            ),
+                            None => (timestamp.clone(), timestamp.clone()),
+                        });
+                    }
+                }
+            }
+            if let Some((validator, id, type_name)) = &mut target {
+                let violations = validator.validate(&value, id, type_name)?;
+                if let Some(violation) = violations.first() {
+                    invalid_values += 1;
+                    first_violation.get_or_insert_with(|| {
+                        format!("value {}: {}: {}", summary.values, violation.path, violation.message)
+                    });
+                }
+            }
+        }
+        if let (Some(violation), Some((_, _, type_name))) = (first_violation, &target) {
+            summary.anomalies.push(format!(
+                "{} value(s) don't match type '{}', the first being {}", invalid_values, type_name, violation
+            ));
+        }
+        summaries.push(summary);
+    }
+
+    check_imports(&mut summaries);
+    check_fields(&mut summaries);
+    check_overlaps(&mut summaries);
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for summary in &summaries {
+        output.write_value(&summary.to_value())?;
+    }
+    output.finish()?;
+    let anomalous = summaries.iter().filter(|summary| !summary.anomalies.is_empty()).count();
+    if anomalous > 0 {
+        bail!("{} of {} files have anomalies.", anomalous, summaries.len());
+    }
+    Ok(())
+}
+
+struct FileSummary {
+    name: String,
+    values: usize,
+    // The shared symbol tables its local symbol tables import, as name@version.
+    imports: Vec<String>,
+    // The names of the fields in its top-level structs.
+    fields: BTreeSet<String>,
+    // Its earliest and latest timestamps, if --timestamp was given.
+    range: Option<(Timestamp, Timestamp)>,
+    anomalies: Vec<String>,
+}
+
+impl FileSummary {
+    fn to_value(&self) -> Value {
+        let strings = |items: &mut dyn Iterator<Item = &String>| {
+            Value::new(Data::List(items.map(|item| Value::new(Data::String(item.clone()))).collect()))
+        };
+        let mut fields = vec![
+            (Symbol::from("file"), Value::new(Data::String(self.name.clone()))),
+            (Symbol::from("values"), Value::new(Data::Integer(self.values as i64))),
+            (Symbol::from("imports"), strings(&mut self.imports.iter())),
+            (Symbol::from("fields"), Value::new(Data::Integer(self.fields.len() as i64))),
+        ];
+        if let Some((start, end)) = &self.range {
+            fields.push((Symbol::from("start"), Value::new(Data::Timestamp(start.clone()))));
+            fields.push((Symbol::from("end"), Value::new(Data::Timestamp(end.clone()))));
+        }
+        fields.push((Symbol::from("anomalies"), strings(&mut self.anomalies.iter())));
+        Value::new(Data::Struct(fields))
+    }
+}
+
+// Reports the files whose imports differ from the most common imports.
+fn check_imports(summaries: &mut [FileSummary]) {
+    let mut counts: HashMap<&Vec<String>, usize> = HashMap::new();
+    for summary in summaries.iter() {
+        *counts.entry(&summary.imports).or_insert(0) += 1;
+    }
+    // Ties go to the imports of the earliest file. `max_by_key` returns the last of several
+    // maximums, so the files are searched from the end.
+    let most_common = summaries
+        .iter()
+        .rev()
+        .map(|summary| &summary.imports)
+        .max_by_key(|imports| counts[imports])
+        .cloned()
+        .unwrap_or_default();
+    for summary in summaries.iter_mut() {
+        if summary.imports != most_common {
+            summary.anomalies.push(format!(
+                "imports [{}], but most files import [{}]", summary.imports.join(", "), most_common.join(", ")
+            ));
+        }
+    }
+}
+
+// Reports the fields that most files have but a file is missing, and the fields that a file has
+// but most files don't.
+fn check_fields(summaries: &mut [FileSummary]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for summary in summaries.iter() {
+        for field in &summary.fields {
+            *counts.entry(field.clone()).or_insert(0) += 1;
+        }
+    }
+    let total = summaries.len();
+    let mut expected: Vec<&String> = counts.iter().filter(|(_, count)| **count * 2 > total).map(|(field, _)| field).collect();
+    expected.sort();
+    for summary in summaries.iter_mut() {
+        for field in &expected {
+            if !summary.fields.contains(*field) {
+                summary.anomalies.push(format!("missing field '{}', which {} of {} files have", field, counts[*field], total));
+            }
+        }
+        for field in &summary.fields {
+            if counts[field] * 2 <= total {
+                summary.anomalies.push(format!("has field '{}', which only {} of {} files have", field, counts[field], total));
+            }
+        }
+    }
+}
+
+// Reports the files whose timestamp ranges overlap.
+fn check_overlaps(summaries: &mut [FileSummary]) {
+    let mut overlaps = Vec::new();
+    for (i, first) in summaries.iter().enumerate() {
+        for (j, second) in summaries.iter().enumerate().skip(i + 1) {
+            if let (Some((start1, end1)), Some((start2, end2))) = (&first.range, &second.range) {
+                if start1.instant() <= end2.instant() && start2.instant() <= end1.instant() {
+                    overlaps.push((i, j));
+                }
+            }
+        }
+    }
+    for (i, j) in overlaps {
+        let (first, second) = (summaries[i].name.clone(), summaries[j].name.clone());
+        summaries[i].anomalies.push(format!("timestamps overlap with '{}'", second));
+        summaries[j].anomalies.push(format!("timestamps overlap with '{}'", first));
+    }
+}
+
+// Returns the shared symbol tables imported by the local symbol tables in a binary stream, as
+// name@version, in the order they're first imported.
+fn imports(bytes: &[u8]) -> Result<Vec<String>> {
+    let mut imports = Vec::new();
+    let mut position = 0;
+    while let Some(length) = encoded_length(&bytes[position..]) {
+        let symbol_table = local_symbol_table(&bytes[position..position + length]);
+        position += length;
+        let symbol_table = match symbol_table {
+            Some(symbol_table) => symbol_table,
+            None => continue,
+        };
+        // Read the struct on its own, where its system symbols still resolve.
+        let mut stream = IVM.to_vec();
+        stream.extend_from_slice(symbol_table);
+        let mut reader = reader_for(&stream);
+        if reader.next()?.is_none() {
+            continue;
+        }
+        let value = Value::read(&mut reader)?;
+        // `imports: $ion_symbol_table` appends to the current symbols rather than importing any.
+        if let Some(Data::List(tables)) = value.get("imports").map(|imports| &imports.data) {
+            for table in tables {
+                let name = table.get("name").and_then(Value::as_text).unwrap_or("?");
+                let version = match table.get("version").map(|version| &version.data) {
+                    Some(Data::Integer(version)) => *version,
+                    _ => 1,
+                };
+                let import = format!("{}@{}", name, version);
+                if !imports.contains(&import) {
+                    imports.push(import);
+                }
+            }
+        }
+    }
+    Ok(imports)
+}
@@ -0,0 +1,167 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+use tempfile::NamedTempFile;
+
+use crate::commands::CommandConfig;
+use crate::compression::{dictionary, train_dictionary, Codec, Compression};
+use crate::framing::for_each_record;
+use crate::input::{input_arg, input_names, path_str, IonInput};
+use crate::output::{format_arg, output_arg, output_writer, IonOutput};
+use crate::size::parse_size;
+use crate::value::Value;
+
+const ABOUT: &str = "Writes the input as gzip- or zstd-compressed Ion.";
+
+pub fn app() -> CommandConfig {
+    App::new("compress")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("codec")
+                .long("codec")
+                .short("c")
+                .takes_value(true)
+                .default_value("zstd")
+                .possible_values(&["gzip", "zstd"])
+                .help("How to compress the output"),
+        )
+        .arg(
+            Arg::with_name("compress-level")
+                .long("compress-level")
+                .short("l")
+                .takes_value(true)
+                .help("Compression level: 0-9 for gzip [default: 6], 1-22 for zstd [default: 3]"),
+        )
+        .arg(
+            Arg::with_name("long")
+                .long("long")
+                .help("With zstd, match repeated data across the whole window, not just nearby"),
+        )
+        .arg(
+            Arg::with_name("window-log")
+                .long("window-log")
+                .takes_value(true)
+                .help("With zstd, the window size as a power of two, 10-31 [default: 27 with --long]"),
+        )
+        .arg(
+            Arg::with_name("block-size")
+                .long("block-size")
+                .takes_value(true)
+                .help("With zstd, the size to aim for in each compressed block, e.g. 16K [default: no limit]"),
+        )
+        .arg(
+            Arg::with_name("train-dict")
+                .long("train-dict")
+                .takes_value(true)
+                .help("Also write a zstd dictionary trained on the input's values to this file"),
+        )
+        .arg(
+            Arg::with_name("dict-size")
+                .long("dict-size")
+                .takes_value(true)
+                .default_value("112K")
+                .requires("train-dict")
+                .help("The largest dictionary --train-dict may write"),
+        )
+        .arg(format_arg().default_value("binary"))
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes every input's values in --format, compressed as a single gzip or
+zstd stream. The output can be read by any command, which detects the
+compression, or decompressed with gunzip or `zstd -d`.
+
+--long helps with large inputs that repeat themselves far apart, like
+many similar records, at the cost of memory while compressing and
+decompressing. Decompressing windows larger than 27 needs
+`zstd -d --long=N`; this CLI reads them as is. Smaller --block-size
+values let readers start on the data sooner, at a small cost in size.
+
+--train-dict writes a dictionary trained on each top-level value, each
+encoded as a binary Ion stream of its own as framed records are, for
+compressing values one at a time elsewhere; see `beta dict train`.
+Training needs a few hundred varied values at least. With the global
+--dict option, the output is compressed with that dictionary and can
+only be decompressed with it, e.g. `zstd -d -D DICT`."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --codec, --format, and --dict-size have default values, so we can unwrap them safely.
+    let codec = Codec::from_arg(matches.value_of("codec").unwrap());
+    let format = matches.value_of("format").unwrap();
+    let mut compression = Compression::new(codec);
+    if let Some(level) = matches.value_of("compress-level") {
+        compression.level = i32::from_str(level)
+            .with_context(|| format!("Invalid value for '--compress-level': '{}'", level))?;
+    }
+    compression.long = matches.is_present("long");
+    if let Some(window_log) = matches.value_of("window-log") {
+        compression.window_log = Some(u32::from_str(window_log)
+            .with_context(|| format!("Invalid value for '--window-log': '{}'", window_log))?);
+    }
+    if let Some(block_size) = matches.value_of("block-size") {
+        let size = parse_size(block_size)?;
+        compression.block_size = Some(u32::try_from(size)
+            .with_context(|| format!("Block size '{}' is too large", block_size))?);
+    }
+    compression.dictionary = dictionary().map(<[u8]>::to_vec);
+    compression.check()?;
+    let dict_size = parse_size(matches.value_of("dict-size").unwrap())?;
+
+    // Write the values uncompressed first, and a binary copy to train on if a dictionary is
+    // wanted.
+    let temp_file = NamedTempFile::new()
+        .with_context(|| "Failed to create a temporary file for the output.")?;
+    let mut output = IonOutput::new(format, Some(path_str(&temp_file)?))?;
+    let training_file = match matches.value_of("train-dict") {
+        Some(_) => Some(NamedTempFile::new()
+            .with_context(|| "Failed to create a temporary file to train a dictionary.")?),
+        None => None,
+    };
+    let mut training_output = match &training_file {
+        Some(file) => Some(IonOutput::new("binary", Some(path_str(file)?))?),
+        None => None,
+    };
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            output.write_value(&value)?;
+            if let Some(training_output) = &mut training_output {
+                training_output.write_value(&value)?;
+            }
+        }
+    }
+    output.finish()?;
+
+    if let (Some(file_name), Some(training_output), Some(training_file)) =
+        (matches.value_of("train-dict"), training_output, &training_file) {
+        training_output.finish()?;
+        // ion-c may have replaced the file, so it's read by its path.
+        let binary = fs::read(training_file.path())
+            .with_context(|| "Could not read back the values to train a dictionary.")?;
+        let mut samples = Vec::new();
+        for_each_record(&binary, |_, record| {
+            samples.push(record);
+            Ok(())
+        })?;
+        let dictionary = train_dictionary(&samples, dict_size)?;
+        fs::write(file_name, dictionary).with_context(|| format!("Could not write '{}'", file_name))?;
+    }
+
+    let mut compressor = compression.compressor(output_writer(matches.value_of("output"))?)?;
+    let mut uncompressed = File::open(temp_file.path())
+        .with_context(|| "Could not reopen the uncompressed output.")?;
+    io::copy(&mut uncompressed, &mut compressor).with_context(|| "Failed to write to the output.")?;
+    compressor.finish()?.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{FixedOffset, TimeZone, Utc};
+use clap::{App, Arg, ArgMatches};
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{ClientConfig, Message};
+
+use crate::commands::CommandConfig;
+use crate::input::IonInput;
+use crate::output::{format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::timestamp::Timestamp;
+use crate::value::{Data, Symbol, UnknownSymbols, Value};
+
+const ABOUT: &str = "Reads the Ion values in the messages of a Kafka topic.";
+
+pub fn app() -> CommandConfig {
+    App::new("consume")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("broker")
+                .long("broker")
+                .short("b")
+                .takes_value(true)
+                .required(true)
+                .help("Kafka broker to connect to, e.g. 'localhost:9092'; can be a comma-separated list"),
+        )
+        .arg(
+            Arg::with_name("topic")
+                .long("topic")
+                .short("t")
+                .takes_value(true)
+                .required(true)
+                .help("Topic to read"),
+        )
+        .arg(
+            Arg::with_name("group")
+                .long("group")
+                .short("g")
+                .takes_value(true)
+                .default_value("ion-cli")
+                .help("Consumer group to join"),
+        )
+        .arg(
+            Arg::with_name("from-beginning")
+                .long("from-beginning")
+                .help("Start from the oldest message rather than the newest"),
+        )
+        .arg(
+            Arg::with_name("commit")
+                .long("commit")
+                .help("Commit the group's offsets as messages are read"),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .short("n")
+                .takes_value(true)
+                .help("Stop after reading this many messages"),
+        )
+        .arg(
+            Arg::with_name("idle-timeout")
+                .long("idle-timeout")
+                .takes_value(true)
+                .default_value("5")
+                .help("Stop after this many seconds without a message; 0 waits forever"),
+        )
+        .arg(
+            Arg::with_name("with-metadata")
+                .long("with-metadata")
+                .help("Wrap each value in a struct with its message's topic, partition, offset, key, and timestamp"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .short("X")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Set a librdkafka configuration property, written as 'name=value'"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .after_help(
+            "Each message's payload may be binary Ion, text Ion, JSON, or gzipped, and
+may hold any number of values, which are written in order. Messages
+without a payload are skipped.
+
+By default, the group's offsets are never committed, so every run starts
+from the same place: the newest message, or the oldest with
+--from-beginning. Reading stops after --limit messages, after
+--idle-timeout seconds without a message, or when interrupted.
+
+With --with-metadata, each value is written as
+  {topic: \"orders\", partition: 0, offset: 42, key: \"k1\",
+   timestamp: 2021-06-01T12:00:00.000Z, value: ...}
+where `key` is a string if it's UTF-8 text, a blob if it's not, and
+absent if the message has none.
+
+Only available when ion-cli is built with the `kafka` feature."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --broker, --topic, --group, and --idle-timeout are required or have default values, so we
+    // can unwrap these safely.
+    let broker = matches.value_of("broker").unwrap();
+    let topic = matches.value_of("topic").unwrap();
+    let group = matches.value_of("group").unwrap();
+    let limit = match matches.value_of("limit") {
+        Some(text) => Some(text.parse::<usize>()
+            .with_context(|| format!("--limit must be a number of messages, not '{}'", text))?),
+        None => None,
+    };
+    let idle_seconds = matches.value_of("idle-timeout").unwrap();
+    let idle_timeout = match idle_seconds.parse::<u64>() {
+        Ok(0) => None,
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => bail!("--idle-timeout must be a number of seconds, not '{}'.", idle_seconds),
+    };
+    let with_metadata = matches.is_present("with-metadata");
+
+    let mut config = ClientConfig::new();
+    config
+        .set("bootstrap.servers", broker)
+        .set("group.id", group)
+        .set("enable.auto.commit", if matches.is_present("commit") { "true" } else { "false" })
+        .set("auto.offset.reset", if matches.is_present("from-beginning") { "earliest" } else { "latest" });
+    for property in matches.values_of("config").into_iter().flatten() {
+        let (name, value) = parse_property(property)?;
+        config.set(name, value);
+    }
+    let consumer: BaseConsumer = config.create()
+        .with_context(|| format!("Could not create a Kafka consumer for '{}'", broker))?;
+    consumer.subscribe(&[topic])
+        .with_context(|| format!("Could not subscribe to topic '{}'", topic))?;
+
+    let mut output = IonOutput::from_matches(matches)?;
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    output.set_unknown_symbols(UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()));
+    let mut messages_read = 0;
+    let mut last_message = Instant::now();
+    while limit.is_none_or(|limit| messages_read < limit) {
+        let message = match consumer.poll(Duration::from_millis(100)) {
+            Some(message) => message.with_context(|| format!("Could not read from topic '{}'", topic))?,
+            None if idle_timeout.is_some_and(|timeout| last_message.elapsed() >= timeout) => break,
+            None => continue,
+        };
+        messages_read += 1;
+        last_message = Instant::now();
+        let payload = match message.payload() {
+            Some(payload) => payload,
+            None => continue,
+        };
+        let name = format!("{} [{}] @{}", message.topic(), message.partition(), message.offset());
+        let input = IonInput::from_bytes(&name, payload)?;
+        for value in input.read_all()? {
+            if !with_metadata {
+                output.write_value(&value)?;
+                continue;
+            }
+            let mut fields = vec![
+                field("topic", Data::String(message.topic().to_owned())),
+                field("partition", Data::Integer(message.partition() as i64)),
+                field("offset", Data::Integer(message.offset())),
+            ];
+            match message.key().map(std::str::from_utf8) {
+                Some(Ok(key)) => fields.push(field("key", Data::String(key.to_owned()))),
+                Some(Err(_)) => fields.push(field("key", Data::Blob(message.key().unwrap().to_vec()))),
+                None => {}
+            }
+            let timestamp = message.timestamp()
+                .to_millis()
+                .and_then(|millis| Utc.timestamp_millis_opt(millis).single());
+            if let Some(timestamp) = timestamp {
+                let utc = FixedOffset::east_opt(0).unwrap();
+                fields.push(field("timestamp", Data::Timestamp(Timestamp::from_datetime(timestamp.with_timezone(&utc)))));
+            }
+            fields.push((Symbol::from("value"), value));
+            output.write_value(&Value::new(Data::Struct(fields)))?;
+        }
+    }
+    output.finish()
+}
+
+fn field(name: &str, data: Data) -> (Symbol, Value) {
+    (Symbol::from(name), Value::new(data))
+}
+
+// Splits a --config argument into the property's name and value.
+pub fn parse_property(property: &str) -> Result<(&str, &str)> {
+    match property.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name, value)),
+        _ => bail!("--config must be written as 'name=value', not '{}'.", property),
+    }
+}
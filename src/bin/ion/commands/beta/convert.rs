@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path as FilePath, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput, STDIN_NAME};
+use crate::nested::read_document;
+use crate::output::{format_arg, IonOutput};
+use crate::value::{Data, Symbol, TextFormatter, Value};
+
+const ABOUT: &str = "Converts each input file to a file of its own in an output directory.";
+
+pub fn app() -> CommandConfig {
+    App::new("convert")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .short("d")
+                .takes_value(true)
+                .required(true)
+                .help("Directory in which to write each converted file"),
+        )
+        .arg(
+            Arg::with_name("state")
+                .long("state")
+                .short("s")
+                .takes_value(true)
+                .help("File recording which inputs have been converted, so that a re-run skips them"),
+        )
+        .arg(
+            Arg::with_name("on-error")
+                .long("on-error")
+                .takes_value(true)
+                .default_value("abort")
+                .possible_values(&["abort", "skip", "dead-letter"])
+                .help("What to do with an input that can't be converted"),
+        )
+        .arg(
+            Arg::with_name("dead-letter-dir")
+                .long("dead-letter-dir")
+                .takes_value(true)
+                .required_if("on-error", "dead-letter")
+                .help("With --on-error dead-letter, the directory to copy inputs that can't be converted to"),
+        )
+        .arg(format_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each input is read like any other, so it may be binary Ion, text Ion,
+JSON, or compressed, and is written in --format to the same path under
+--output-dir with .10n (binary) or .ion added to its name:
+  ion beta convert -f binary -d out --input-dir data --glob '*.json'
+writes data/2021/01.json to out/data/2021/01.json.10n, so inputs that
+differ only by extension, like 01.json and 01.ion, don't share an
+output. Each file is written
+under a temporary name and renamed when it's complete, so an output
+file is never left half-written.
+
+With --state, each input is recorded in the state file, as a struct
+like {input: \"data/2021/01.json\", output: \"out/data/2021/01.json.10n\"},
+once it's converted. If the state file already exists, the inputs it
+lists are skipped as long as their output files still exist, so a run
+that was interrupted can be resumed by running the same command again.
+Delete the state file to convert everything again.
+
+An input that can't be read, like a malformed record in the middle of
+a file, stops the run unless --on-error says otherwise:
+  abort        stop with an error (the default)
+  skip         report the input on STDERR and carry on
+  dead-letter  also copy the input to the same path under
+               --dead-letter-dir, next to a <name>.error.ion file like
+               {input: \"data/07.json\", error: \"...\", causes: [...]}
+Nothing is written to --output-dir for an input that fails, and it isn't
+recorded in the state file, so a re-run tries it again. Inputs are read
+a whole file at a time, so a file with one bad value fails as a whole."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --output-dir is required and --format has a default value, so we can unwrap these safely.
+    let output_dir = PathBuf::from(matches.value_of("output-dir").unwrap());
+    let format = matches.value_of("format").unwrap();
+    let mut state = match matches.value_of("state") {
+        Some(file_name) => Some(State::open(file_name)?),
+        None => None,
+    };
+    // --on-error has a default value, and --dead-letter-dir is required with dead-letter.
+    let on_error = match matches.value_of("on-error").unwrap() {
+        "skip" => OnError::Skip,
+        "dead-letter" => OnError::DeadLetter(PathBuf::from(matches.value_of("dead-letter-dir").unwrap())),
+        _ => OnError::Abort,
+    };
+
+    let (mut converted, mut skipped, mut failed) = (0, 0, 0);
+    // The input written to each output file, so that two inputs are never written to the same one.
+    let mut outputs: HashMap<PathBuf, &str> = HashMap::new();
+    for input_name in input_names(matches) {
+        if input_name == STDIN_NAME {
+            bail!("convert needs input files; STDIN has no name to give its output.");
+        }
+        let output_file = output_path(&output_dir, input_name, format)?;
+        if let Some(other_input) = outputs.insert(output_file.clone(), input_name) {
+            bail!("'{}' and '{}' would both be written to '{}'.", other_input, input_name, output_file.display());
+        }
+        if state.as_ref().is_some_and(|state| state.is_done(input_name, &output_file)) {
+            skipped += 1;
+            continue;
+        }
+        if let Err(error) = convert(input_name, &output_file, format) {
+            let error = error.context(format!("Could not convert '{}'", input_name));
+            match &on_error {
+                OnError::Abort => return Err(error),
+                OnError::Skip => eprintln!("Skipping '{}': {:#}", input_name, error),
+                OnError::DeadLetter(dead_letter_dir) => {
+                    let copy = dead_letter(dead_letter_dir, input_name, &error)?;
+                    eprintln!("Copied '{}' to '{}': {:#}", input_name, copy.display(), error);
+                }
+            }
+            failed += 1;
+            continue;
+        }
+        if let Some(state) = &mut state {
+            state.record(input_name, &output_file)?;
+        }
+        converted += 1;
+    }
+    eprintln!("Converted {} files; skipped {} that were already converted.", converted, skipped);
+    if failed > 0 {
+        eprintln!("{} files could not be converted.", failed);
+    }
+    Ok(())
+}
+
+// What to do with an input that can't be converted.
+enum OnError {
+    Abort,
+    Skip,
+    // Copy it to this directory along with a description of the error.
+    DeadLetter(PathBuf),
+}
+
+// Returns where the input is written: the same path under `output_dir`, with an extension added.
+// The input's own extension is kept so that inputs like a.json and a.ion have different outputs.
+fn output_path(output_dir: &FilePath, input_name: &str, format: &str) -> Result<PathBuf> {
+    let extension = if format == "binary" { ".10n" } else { ".ion" };
+    let mut output_file = output_dir.join(relative_path(input_name)?).into_os_string();
+    output_file.push(extension);
+    Ok(PathBuf::from(output_file))
+}
+
+// Returns the input's path without anything that would lead outside of a directory it's joined to.
+fn relative_path(input_name: &str) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in FilePath::new(input_name).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => bail!("Can't name an output for '{}', which is outside the current directory; use --input-dir instead.", input_name),
+        }
+    }
+    Ok(relative)
+}
+
+// Copies an input that couldn't be converted to the same path under `dead_letter_dir`, and writes
+// the error beside it. Returns the path of the copy.
+fn dead_letter(dead_letter_dir: &FilePath, input_name: &str, error: &anyhow::Error) -> Result<PathBuf> {
+    let copy = dead_letter_dir.join(relative_path(input_name)?);
+    if let Some(parent) = copy.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create '{}'", parent.display()))?;
+    }
+    fs::copy(input_name, &copy)
+        .with_context(|| format!("Could not copy '{}' to '{}'", input_name, copy.display()))?;
+
+    let causes = error.chain().skip(1).map(|cause| Value::new(Data::String(cause.to_string()))).collect();
+    let description = Value::new(Data::Struct(vec![
+        (Symbol::from("input"), Value::new(Data::String(input_name.to_owned()))),
+        (Symbol::from("error"), Value::new(Data::String(error.to_string()))),
+        (Symbol::from("causes"), Value::new(Data::List(causes))),
+    ]));
+    let mut text = String::new();
+    TextFormatter::new().format(&description, &mut text)?;
+    text.push('\n');
+    let mut error_file = copy.clone().into_os_string();
+    error_file.push(".error.ion");
+    fs::write(&error_file, text)
+        .with_context(|| format!("Could not write '{}'", error_file.to_string_lossy()))?;
+    Ok(copy)
+}
+
+// Converts a file, writing it under a temporary name and renaming it once it's complete.
+fn convert(input_name: &str, output_file: &FilePath, format: &str) -> Result<()> {
+    if let Some(parent) = output_file.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create '{}'", parent.display()))?;
+    }
+    let mut partial_file = output_file.as_os_str().to_owned();
+    partial_file.push(".partial");
+    let partial_file = PathBuf::from(partial_file);
+    let partial_name = partial_file.to_str()
+        .with_context(|| format!("Output path {:?} is not valid UTF-8", partial_file))?;
+
+    let written = write(input_name, partial_name, format).and_then(|_| check_written(&partial_file, format));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial_file);
+    }
+    written?;
+    fs::rename(&partial_file, output_file)
+        .with_context(|| format!("Could not move '{}' to '{}'", partial_file.display(), output_file.display()))
+}
+
+// Makes sure that the converted file was written. A binary file always begins with a version
+// marker, so an empty one means that transcoding failed even if it wasn't reported.
+fn check_written(partial_file: &FilePath, format: &str) -> Result<()> {
+    let length = fs::metadata(partial_file)
+        .with_context(|| format!("Converted file '{}' was not written", partial_file.display()))?
+        .len();
+    if format == "binary" && length == 0 {
+        bail!("Converted file '{}' is empty; transcoding it to binary failed.", partial_file.display());
+    }
+    Ok(())
+}
+
+fn write(input_name: &str, output_name: &str, format: &str) -> Result<()> {
+    let input = IonInput::open(input_name)?;
+    let mut output = IonOutput::new(format, Some(output_name))?;
+    let mut reader = input.reader();
+    let mut index = 0;
+    while reader.next()?.is_some() {
+        index += 1;
+        let value = Value::read(&mut reader)
+            .with_context(|| format!("Could not read value {} from '{}'", index, input.name()))?;
+        output.write_value(&value)?;
+    }
+    output.finish()
+}
+
+// The inputs that have been converted, and the file in which each one is recorded as it's done.
+struct State {
+    file_name: String,
+    file: File,
+    done: HashSet<(String, PathBuf)>,
+    formatter: TextFormatter,
+}
+
+impl State {
+    fn open(file_name: &str) -> Result<State> {
+        let mut done = HashSet::new();
+        let mut complete_length = None;
+        if FilePath::new(file_name).exists() {
+            let (entries, length) = read_state(file_name)?;
+            complete_length = Some(length);
+            for entry in entries {
+                let input = entry.get("input").and_then(Value::as_text);
+                let output = entry.get("output").and_then(Value::as_text);
+                match (input, output) {
+                    (Some(input), Some(output)) => done.insert((input.to_owned(), PathBuf::from(output))),
+                    _ => bail!("'{}' is not a state file written by `convert --state`.", file_name),
+                };
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_name)
+            .with_context(|| format!("Could not open state file '{}'", file_name))?;
+        // New entries must start on a line of their own, so part of a line is removed.
+        if let Some(length) = complete_length {
+            file.set_len(length)
+                .with_context(|| format!("Could not remove an incomplete entry from state file '{}'", file_name))?;
+        }
+        Ok(State { file_name: file_name.to_owned(), file, done, formatter: TextFormatter::new() })
+    }
+
+    // Whether the input was converted by an earlier run and its output is still there.
+    fn is_done(&self, input_name: &str, output_file: &FilePath) -> bool {
+        self.done.contains(&(input_name.to_owned(), output_file.to_owned())) && output_file.is_file()
+    }
+
+    // Records that the input has been converted. Each record is written to the file straight away
+    // so that it survives the process being killed.
+    fn record(&mut self, input_name: &str, output_file: &FilePath) -> Result<()> {
+        let entry = Value::new(Data::Struct(vec![
+            (Symbol::from("input"), Value::new(Data::String(input_name.to_owned()))),
+            (Symbol::from("output"), Value::new(Data::String(output_file.to_string_lossy().into_owned()))),
+        ]));
+        let mut line = String::new();
+        self.formatter.format(&entry, &mut line)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("Could not write to state file '{}'", self.file_name))
+    }
+}
+
+// Reads the entries in a state file, and returns them with the length of the file's complete
+// lines. If the process was killed while an entry was being written, the file ends with part of
+// a line, which is ignored.
+fn read_state(file_name: &str) -> Result<(Vec<Value>, u64)> {
+    let bytes = fs::read(file_name).with_context(|| format!("Could not read state file '{}'", file_name))?;
+    let complete = match bytes.iter().rposition(|byte| *byte == b'\n') {
+        Some(end) => &bytes[..end + 1],
+        None => &[][..],
+    };
+    let entries = read_document(complete)
+        .with_context(|| format!("'{}' is not a state file written by `convert --state`", file_name))?;
+    Ok((entries, complete.len() as u64))
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::encryption::{encrypted_envelope, ValueCipher};
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Decrypts the values encrypted by 'ion beta encrypt-fields'.";
+
+pub fn app() -> CommandConfig {
+    App::new("decrypt-fields")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .required(true)
+                .help("File containing the 32-byte AES key in base64"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help("Every `ion_encrypted` blob in the input is replaced with the value it holds.")
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --key is required, so we can unwrap this safely.
+    let cipher = ValueCipher::from_key_file(matches.value_of("key").unwrap())?;
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let mut value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            decrypt_all(&cipher, &mut value)
+                .with_context(|| format!("Could not decrypt a value in '{}'", input.name()))?;
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
+
+// Replaces every encrypted value within `value` with its plaintext.
+fn decrypt_all(cipher: &ValueCipher, value: &mut Value) -> Result<()> {
+    if let Some(envelope) = encrypted_envelope(value) {
+        *value = cipher.decrypt(envelope)?;
+        return Ok(());
+    }
+    match &mut value.data {
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                decrypt_all(cipher, child)?;
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                decrypt_all(cipher, child)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+pub mod train;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `dict` subcommands
+pub fn dict_subcommands() -> Vec<CommandConfig> {
+    vec![
+        train::app(),
+    ]
+}
+
+pub fn runner_for_dict_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "train" => train::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `dict` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_dict_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested dict command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("dict")
+        .about("Creates zstd dictionaries for compressing Ion values one at a time.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(dict_subcommands())
+}
@@ -0,0 +1,74 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::compression::train_dictionary;
+use crate::framing::for_each_record;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::output_writer;
+use crate::size::parse_size;
+
+const ABOUT: &str = "Trains a zstd dictionary for compressing the input's values one at a time.";
+
+pub fn app() -> CommandConfig {
+    App::new("train")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("max-size")
+                .long("max-size")
+                .short("m")
+                .takes_value(true)
+                .default_value("112K")
+                .help("The largest dictionary to write, e.g. 64K"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Each top-level value in the inputs is a sample, encoded as a binary Ion
+stream of its own, with its own version marker and symbol table, just as
+`dump --framed` writes records. A dictionary trained this way holds what
+the records have in common, like their symbol tables and field names, so
+each record compresses to a fraction of what it would on its own.
+
+Use the dictionary with the global --dict option:
+  ion dump --framed --compress-records --dict values.dict -f binary -o out
+  ion dump --frame length-prefixed --dict values.dict out
+or with `zstd -D values.dict`. Training needs a few hundred varied values
+at least; a sample of about 100 times the dictionary's size works well."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --max-size has a default value, so we can unwrap this safely.
+    let max_size = parse_size(matches.value_of("max-size").unwrap())?;
+    if max_size == 0 {
+        bail!("--max-size must be more than 0.");
+    }
+
+    let mut samples = Vec::new();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        for_each_record(input.bytes(), |_, record| {
+            samples.push(record);
+            Ok(())
+        })
+        .with_context(|| format!("Could not read the values in '{}'", input.name()))?;
+    }
+    if samples.is_empty() {
+        bail!("The input has no values to train a dictionary on.");
+    }
+    let dictionary = train_dictionary(&samples, max_size)?;
+
+    let mut sink = output_writer(matches.value_of("output"))?;
+    sink.write_all(&dictionary).with_context(|| "Failed to write to the output.")?;
+    sink.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
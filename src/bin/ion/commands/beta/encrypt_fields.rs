@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::encryption::ValueCipher;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{deterministic, format_arg, output_arg, IonOutput};
+use crate::path::Path;
+use crate::value::Value;
+
+const ABOUT: &str = "Encrypts the values at the given paths, leaving the rest readable.";
+
+pub fn app() -> CommandConfig {
+    App::new("encrypt-fields")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .required(true)
+                .help("File containing a 32-byte AES key in base64"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Path to the values to encrypt, e.g. '(customer email)'"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each selected value, with its annotations, is encrypted with AES-256-GCM
+and replaced by a blob annotated with `ion_encrypted`. The values can be
+restored with 'ion beta decrypt-fields' and the same key.
+
+A key can be generated with `head -c 32 /dev/urandom | base64`."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    if deterministic() {
+        bail!("encrypt-fields can't be --deterministic; each value is encrypted with a random nonce.");
+    }
+    // --key and --path are required, so we can unwrap them safely.
+    let mut cipher = ValueCipher::from_key_file(matches.value_of("key").unwrap())?;
+    let paths = matches.values_of("path")
+        .unwrap()
+        .map(Path::from_str)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let mut value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for path in &paths {
+                path.for_each_mut(&mut value, &mut |selected| {
+                    *selected = cipher.encrypt(selected)?;
+                    Ok(())
+                })?;
+            }
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
@@ -0,0 +1,415 @@
+use std::convert::TryInto;
+use std::io;
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::binary::read_var_uint;
+use crate::commands::CommandConfig;
+use crate::input::{IonInput, IVM};
+use crate::output::output_writer;
+
+const ABOUT: &str = "Explains, byte by byte, how a small text Ion value is encoded in binary Ion.";
+
+// The Ion 1.0 system symbols, by symbol ID. Symbol ID 0 has no text.
+const SYSTEM_SYMBOLS: &[&str] = &[
+    "$0", "$ion", "$ion_1_0", "$ion_symbol_table", "name", "version", "imports", "symbols", "max_id",
+    "$ion_shared_symbol_table",
+];
+// System symbol IDs used by local symbol tables.
+const ION_SYMBOL_TABLE_SID: usize = 3;
+const IMPORTS_SID: usize = 6;
+const SYMBOLS_SID: usize = 7;
+
+// The name of each type code, the high nibble of a type descriptor.
+const TYPE_NAMES: [&str; 16] = [
+    "null", "bool", "positive int", "negative int", "float", "decimal", "timestamp", "symbol",
+    "string", "clob", "blob", "list", "sexp", "struct", "annotation wrapper", "reserved",
+];
+
+// How many bytes to show on each line of the hex column.
+const BYTES_PER_LINE: usize = 8;
+
+pub fn app() -> CommandConfig {
+    App::new("explain-encoding")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("value")
+                .index(1)
+                .help("Text Ion to encode, e.g. '{name: \"Ada\", age: 36}' [default: read from STDIN]"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .after_help(
+            "Encodes the value as binary Ion, the same way `ion dump --format binary`
+would, and then walks through the result one field at a time. Each line
+shows the offset and bytes of one part of the encoding and explains it:
+the version marker, how each type descriptor's nibbles give the type
+and length, how VarUInts and VarInts are read, how numbers and
+timestamps are stored, and which symbol ID stands for each field name,
+annotation, and symbol. Lines are indented to show nesting.
+
+The local symbol table that ion-c writes before the value is explained
+the same way, noting the ID each symbol is assigned. A summary of the
+symbol table in effect ends the explanation.
+
+Meant for learning the format with small values; for real data, see
+`ion beta inspect`."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let text = match matches.value_of("value") {
+        Some(text) => text.to_owned(),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).with_context(|| "Could not read the value from STDIN")?;
+            text
+        }
+    };
+    if text.as_bytes().starts_with(&IVM) {
+        bail!("explain-encoding expects text Ion; use `ion beta inspect` to look at binary Ion.");
+    }
+    let input = IonInput::from_bytes("the value", text.as_bytes())?;
+    if input.bytes().is_empty() {
+        bail!("There is no value to explain.");
+    }
+
+    let mut explainer = Explainer {
+        bytes: input.bytes(),
+        symbols: SYSTEM_SYMBOLS.iter().map(|symbol| symbol.to_string()).collect(),
+        lines: Vec::new(),
+    };
+    let mut position = 0;
+    while position < explainer.bytes.len() {
+        position = explainer.top_level(position)?;
+    }
+
+    let mut writer = output_writer(matches.value_of("output"))?;
+    for line in &explainer.lines {
+        writeln!(writer, "{}", line)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "Symbol table in effect at the end of the stream:")?;
+    for (sid, symbol) in explainer.symbols.iter().enumerate() {
+        let origin = if sid < SYSTEM_SYMBOLS.len() { "system symbol" } else { "local symbol" };
+        writeln!(writer, "  ${:<4} {:<28} {}", sid, symbol, origin)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// The type descriptor of a value, and where its body is.
+struct Header {
+    type_code: u8,
+    length_code: u8,
+    body_start: usize,
+    body_end: usize,
+}
+
+struct Explainer<'a> {
+    bytes: &'a [u8],
+    // The text of each symbol ID currently defined.
+    symbols: Vec<String>,
+    lines: Vec<String>,
+}
+
+impl<'a> Explainer<'a> {
+    // Adds a line explaining the bytes in `start..end`, continuing the hex onto more lines if
+    // there are too many to fit.
+    fn line(&mut self, start: usize, end: usize, depth: usize, explanation: String) {
+        let indentation = "  ".repeat(depth);
+        let chunks: Vec<&[u8]> = self.bytes[start..end].chunks(BYTES_PER_LINE).collect();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text = if index == 0 { format!("{}{}", indentation, explanation) } else { String::new() };
+            let line = format!("{:>6}: {:<24} {}", start + index * BYTES_PER_LINE, hex.join(" "), text);
+            self.lines.push(line.trim_end().to_owned());
+        }
+    }
+
+    fn byte(&self, position: usize) -> Result<u8> {
+        match self.bytes.get(position) {
+            Some(byte) => Ok(*byte),
+            None => bail!("The encoding ends unexpectedly at offset {}.", position),
+        }
+    }
+
+    fn symbol(&self, sid: usize) -> String {
+        match self.symbols.get(sid) {
+            Some(text) => format!("${} is `{}`", sid, text),
+            None => format!("${} isn't defined", sid),
+        }
+    }
+
+    // Explains a version marker, a local symbol table, or a value at the top level.
+    fn top_level(&mut self, position: usize) -> Result<usize> {
+        if self.bytes[position..].starts_with(&IVM) {
+            self.line(position, position + IVM.len(), 0, "Ion 1.0 version marker: begins the stream and resets the symbol table to the system symbols".to_owned());
+            self.symbols.truncate(SYSTEM_SYMBOLS.len());
+            return Ok(position + IVM.len());
+        }
+        let is_symbol_table = self.byte(position)? >> 4 == 0xE && {
+            let header = self.header(position, 0, false)?;
+            let (_, annotations_length) = self.var_uint(header.body_start)?;
+            let (first, _) = self.var_uint(header.body_start + annotations_length)?;
+            first == ION_SYMBOL_TABLE_SID
+        };
+        if is_symbol_table {
+            self.lines.push(String::new());
+            self.lines.push("Local symbol table: a struct annotated with $ion_symbol_table that defines the symbols used below".to_owned());
+            let mut table = SymbolTableState { append: false, defined: Vec::new(), in_symbols_list: false };
+            let end = self.value(position, 0, Some(&mut table))?;
+            if !table.append {
+                self.symbols.truncate(SYSTEM_SYMBOLS.len());
+            }
+            self.symbols.extend(table.defined);
+            return Ok(end);
+        }
+        self.lines.push(String::new());
+        self.lines.push("Value".to_owned());
+        self.value(position, 0, None)
+    }
+
+    // Reads a type descriptor and the length that follows it, if any, explaining them.
+    fn header(&mut self, position: usize, depth: usize, explain: bool) -> Result<Header> {
+        let descriptor = self.byte(position)?;
+        let type_code = descriptor >> 4;
+        let length_code = descriptor & 0x0F;
+        let type_name = TYPE_NAMES[type_code as usize];
+        let nibbles = format!("type descriptor: high nibble {:x} = {}, low nibble {:x}", type_code, type_name, length_code);
+        let (body_start, body_length, explanation) = match (type_code, length_code) {
+            (0xF, _) => bail!("Offset {} has a reserved type code.", position),
+            (0x0, 0xF) => (position + 1, 0, format!("{} = null.null", nibbles)),
+            (_, 0xF) => (position + 1, 0, format!("{} = null.{}", nibbles, type_name.trim_start_matches("positive ").trim_start_matches("negative "))),
+            (0x1, value) => (position + 1, 0, format!("{}: the low nibble is the value, {}", nibbles, value == 1)),
+            (0xD, 1) | (_, 0xE) => {
+                let (length, length_bytes) = self.var_uint(position + 1)?;
+                if explain {
+                    let why = if type_code == 0xD && length_code == 1 {
+                        "1 means a struct with sorted fields, whose length follows as a VarUInt"
+                    } else {
+                        "14 means the length follows as a VarUInt"
+                    };
+                    self.line(position, position + 1, depth, format!("{}: {}", nibbles, why));
+                    self.line(position + 1, position + 1 + length_bytes, depth, format!("VarUInt length: {} = the next {} bytes", self.describe_var_uint(position + 1, length_bytes), length));
+                }
+                return self.checked_header(type_code, length_code, position + 1 + length_bytes, length);
+            }
+            (0x0, length) => (position + 1, length as usize, format!("{}: NOP padding, {} byte(s) to skip", nibbles, length)),
+            (_, length) => (position + 1, length as usize, format!("{}: the body is the next {} byte(s)", nibbles, length)),
+        };
+        if explain {
+            self.line(position, position + 1, depth, explanation);
+        }
+        self.checked_header(type_code, length_code, body_start, body_length)
+    }
+
+    fn checked_header(&self, type_code: u8, length_code: u8, body_start: usize, body_length: usize) -> Result<Header> {
+        let body_end = body_start + body_length;
+        if body_end > self.bytes.len() {
+            bail!("The value at offset {} claims {} bytes, past the end of the encoding.", body_start, body_length);
+        }
+        Ok(Header { type_code, length_code, body_start, body_end })
+    }
+
+    // Explains a value and returns the offset just past it. `table` is set while explaining a
+    // local symbol table, to collect the symbols it defines.
+    fn value(&mut self, position: usize, depth: usize, table: Option<&mut SymbolTableState>) -> Result<usize> {
+        let header = self.header(position, depth, true)?;
+        let (start, end) = (header.body_start, header.body_end);
+        if header.length_code == 0xF || header.type_code == 0x1 || start == end {
+            if start == end && header.length_code != 0xF && header.type_code != 0x1 && header.type_code != 0x0 {
+                let zero = match header.type_code {
+                    0x2 | 0x3 => "0",
+                    0x4 => "0e0",
+                    0x5 => "0d0",
+                    0x8 => "\"\"",
+                    0x9 => "{{\"\"}}",
+                    0xA => "{{}}",
+                    0xB => "[]",
+                    0xC => "()",
+                    0xD => "{}",
+                    _ => "empty",
+                };
+                self.lines.push(format!("{:>6}  {:<24} {}  (an empty body means {})", "", "", "  ".repeat(depth), zero));
+            }
+            return Ok(end);
+        }
+        match header.type_code {
+            0x0 => self.line(start, end, depth, "padding, ignored by readers".to_owned()),
+            0x2 | 0x3 => {
+                let magnitude = self.uint(start, end)?;
+                let sign = if header.type_code == 0x3 { "-" } else { "" };
+                self.line(start, end, depth, format!("UInt magnitude, big-endian: {}{} (the type code gives the sign)", sign, magnitude));
+            }
+            0x4 => {
+                let body = &self.bytes[start..end];
+                let value = match body.len() {
+                    4 => f32::from_be_bytes(body.try_into().unwrap()) as f64,
+                    8 => f64::from_be_bytes(body.try_into().unwrap()),
+                    length => bail!("The float at offset {} is {} bytes; floats are 0, 4, or 8.", position, length),
+                };
+                self.line(start, end, depth, format!("IEEE-754 {}-bit float, big-endian: {:e}", body.len() * 8, value));
+            }
+            0x5 => {
+                let (exponent, exponent_bytes) = self.var_int(start)?;
+                self.line(start, start + exponent_bytes, depth, format!("VarInt exponent: {}", exponent));
+                let coefficient = self.int(start + exponent_bytes, end)?;
+                self.line(start + exponent_bytes, end, depth, format!("Int coefficient: {}, so the value is {} × 10^{}", coefficient, coefficient, exponent));
+            }
+            0x6 => self.timestamp(start, end, depth)?,
+            0x7 => {
+                let sid = self.uint(start, end)?;
+                let text = self.symbol(sid as usize);
+                self.line(start, end, depth, format!("UInt symbol ID: {}", text));
+            }
+            0x8 => {
+                let text = String::from_utf8_lossy(&self.bytes[start..end]).into_owned();
+                match table {
+                    Some(table) if table.in_symbols_list => {
+                        let sid = if table.append { self.symbols.len() } else { SYSTEM_SYMBOLS.len() } + table.defined.len();
+                        self.line(start, end, depth, format!("UTF-8 text {:?}, which becomes symbol ${}", text, sid));
+                        table.defined.push(text);
+                    }
+                    _ => self.line(start, end, depth, format!("UTF-8 text {:?}", text)),
+                }
+            }
+            0x9 | 0xA => self.line(start, end, depth, format!("{} raw byte(s)", end - start)),
+            0xB | 0xC => {
+                let mut child = start;
+                let mut table = table;
+                while child < end {
+                    child = self.value(child, depth + 1, table.as_deref_mut())?;
+                }
+            }
+            0xD => {
+                let mut field = start;
+                let mut table = table;
+                while field < end {
+                    let (sid, sid_bytes) = self.var_uint(field)?;
+                    let text = self.symbol(sid);
+                    self.line(field, field + sid_bytes, depth + 1, format!("VarUInt field name: {}", text));
+                    if let Some(table) = table.as_deref_mut() {
+                        table.in_symbols_list = sid == SYMBOLS_SID;
+                        if sid == IMPORTS_SID && self.byte(field + sid_bytes)? == 0x71 && self.byte(field + sid_bytes + 1)? as usize == ION_SYMBOL_TABLE_SID {
+                            table.append = true;
+                            self.lines.push(format!("{:>6}  {:<24} {}(imports: $ion_symbol_table adds to the current symbols instead of replacing them)", "", "", "  ".repeat(depth + 1)));
+                        }
+                    }
+                    field = self.value(field + sid_bytes, depth + 1, table.as_deref_mut())?;
+                }
+            }
+            0xE => {
+                let (annotations_length, length_bytes) = self.var_uint(start)?;
+                self.line(start, start + length_bytes, depth, format!("VarUInt annotations length: the next {} byte(s) are annotation symbol IDs", annotations_length));
+                let mut annotation = start + length_bytes;
+                let annotations_end = annotation + annotations_length;
+                while annotation < annotations_end {
+                    let (sid, sid_bytes) = self.var_uint(annotation)?;
+                    let text = self.symbol(sid);
+                    self.line(annotation, annotation + sid_bytes, depth, format!("VarUInt annotation: {}", text));
+                    annotation += sid_bytes;
+                }
+                self.lines.push(format!("{:>6}  {:<24} {}the annotated value:", "", "", "  ".repeat(depth)));
+                self.value(annotations_end, depth + 1, table)?;
+            }
+            _ => {}
+        }
+        Ok(end)
+    }
+
+    fn timestamp(&mut self, start: usize, end: usize, depth: usize) -> Result<()> {
+        let (offset, offset_bytes) = self.var_int(start)?;
+        let offset_text = if self.byte(start)? == 0xC0 {
+            "-0, the unknown offset".to_owned()
+        } else {
+            format!("{} minutes from UTC", offset)
+        };
+        self.line(start, start + offset_bytes, depth, format!("VarInt offset: {}", offset_text));
+        let mut position = start + offset_bytes;
+        for field in &["year", "month", "day", "hour", "minute", "second"] {
+            if position >= end {
+                return Ok(());
+            }
+            let (value, length) = self.var_uint(position)?;
+            self.line(position, position + length, depth, format!("VarUInt {}: {}", field, value));
+            position += length;
+        }
+        if position < end {
+            let (exponent, exponent_bytes) = self.var_int(position)?;
+            self.line(position, position + exponent_bytes, depth, format!("VarInt fractional second exponent: {}", exponent));
+            position += exponent_bytes;
+            if position < end {
+                let coefficient = self.int(position, end)?;
+                self.line(position, end, depth, format!("Int fractional second coefficient: {}, so the fraction is {} × 10^{}", coefficient, coefficient, exponent));
+            }
+        }
+        Ok(())
+    }
+
+    // Reads a VarUInt: 7 bits per byte, most significant first, with the high bit set on the
+    // last byte.
+    fn var_uint(&self, position: usize) -> Result<(usize, usize)> {
+        read_var_uint(self.bytes.get(position..).unwrap_or_default())
+            .with_context(|| format!("The VarUInt at offset {} is incomplete.", position))
+    }
+
+    fn describe_var_uint(&self, position: usize, length: usize) -> String {
+        let bits: Vec<String> = self.bytes[position..position + length]
+            .iter()
+            .map(|byte| format!("{:07b}", byte & 0x7F))
+            .collect();
+        format!("bits {} (the high bit marks the last byte)", bits.join(" "))
+    }
+
+    // Reads a VarInt: like a VarUInt, but the first byte's second-highest bit is the sign.
+    fn var_int(&self, position: usize) -> Result<(i64, usize)> {
+        let first = self.byte(position)?;
+        let negative = first & 0x40 != 0;
+        let mut magnitude = (first & 0x3F) as i64;
+        let mut length = 1;
+        let mut byte = first;
+        while byte & 0x80 == 0 {
+            byte = self.byte(position + length)?;
+            magnitude = magnitude.checked_mul(128).with_context(|| format!("The VarInt at offset {} is too large.", position))?
+                | (byte & 0x7F) as i64;
+            length += 1;
+        }
+        Ok((if negative { -magnitude } else { magnitude }, length))
+    }
+
+    // Reads a UInt: big-endian bytes filling the rest of the body.
+    fn uint(&self, start: usize, end: usize) -> Result<u128> {
+        if end - start > 16 {
+            bail!("The integer at offset {} is too large to explain.", start);
+        }
+        Ok(self.bytes[start..end].iter().fold(0, |value, byte| (value << 8) | *byte as u128))
+    }
+
+    // Reads an Int: like a UInt, but the highest bit of the first byte is the sign.
+    fn int(&self, start: usize, end: usize) -> Result<i128> {
+        if start == end {
+            return Ok(0);
+        }
+        let negative = self.bytes[start] & 0x80 != 0;
+        let magnitude = self.uint(start, end)? & !(0x80 << ((end - start - 1) * 8));
+        Ok(if negative { -(magnitude as i128) } else { magnitude as i128 })
+    }
+}
+
+// What's been learned so far while explaining a local symbol table.
+struct SymbolTableState {
+    // Whether the table adds to the symbols already defined, via `imports: $ion_symbol_table`.
+    append: bool,
+    // The symbols that the table defines, in order.
+    defined: Vec<String>,
+    // Whether the strings being explained are in the table's `symbols` list.
+    in_symbols_list: bool,
+}
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Writes the elements of nested lists as top-level values.";
+
+pub fn app() -> CommandConfig {
+    App::new("explode")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the list within each top-level value, e.g. '(records)'"),
+        )
+        .arg(
+            Arg::with_name("keep")
+                .long("keep")
+                .short("k")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Path to a field of the top-level value to add to each element, e.g. '(batch id)'"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each element of each list or s-expression the path selects is written as
+a top-level value, so
+  {batch: 12, records: [{id: 1}, {id: 2}]}
+with `--path records --keep batch` becomes
+  {batch: 12, id: 1}
+  {batch: 12, id: 2}
+Kept fields are named after the last step of their path and are added
+before the element's own fields; an element's own field with the same
+name takes precedence. Kept paths that select nothing are skipped, and
+--keep requires the elements to be structs."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --path is required, so we can unwrap this safely.
+    let path = Path::from_str(matches.value_of("path").unwrap())?;
+    let mut kept_fields = Vec::new();
+    for text in matches.values_of("keep").into_iter().flatten() {
+        let keep_path = Path::from_str(text)?;
+        let name = match keep_path.split_last() {
+            Some((_, Step::Field(name))) => name.clone(),
+            _ => bail!("--keep path '{}' must end with a field name.", text),
+        };
+        kept_fields.push((name, keep_path));
+    }
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let mut kept = Vec::new();
+            for (name, keep_path) in &kept_fields {
+                match keep_path.select(&value).as_slice() {
+                    [] => {}
+                    [selected] => kept.push((Symbol::from(name.as_str()), (*selected).clone())),
+                    _ => bail!("--keep path {} selected more than one value in '{}'.", keep_path, input.name()),
+                }
+            }
+            for list in path.select(&value) {
+                let elements = match &list.data {
+                    Data::List(elements) | Data::SExpression(elements) => elements,
+                    _ => continue,
+                };
+                for element in elements {
+                    if kept_fields.is_empty() {
+                        output.write_value(element)?;
+                    } else {
+                        output.write_value(&merged(&kept, element, input.name())?)?;
+                    }
+                }
+            }
+        }
+    }
+    output.finish()
+}
+
+// Adds the kept fields to a copy of `element`, unless it already has fields with those names.
+fn merged(kept: &[(Symbol, Value)], element: &Value, input_name: &str) -> Result<Value> {
+    let fields = match &element.data {
+        Data::Struct(fields) => fields,
+        _ => bail!("--keep requires list elements to be structs, but '{}' has an element of type {:?}.", input_name, element.ion_type()),
+    };
+    let mut merged: Vec<(Symbol, Value)> = kept
+        .iter()
+        .filter(|(name, _)| !fields.iter().any(|(field_name, _)| field_name == name))
+        .cloned()
+        .collect();
+    merged.extend(fields.iter().cloned());
+    Ok(Value { annotations: element.annotations.clone(), data: Data::Struct(merged) })
+}
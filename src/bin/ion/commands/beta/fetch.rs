@@ -0,0 +1,167 @@
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::nested::read_document;
+use crate::output::{format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::value::{Data, TextFormatter, UnknownSymbols, Value};
+
+const ABOUT: &str = "Requests Ion from an HTTP endpoint, following pagination tokens.";
+
+pub fn app() -> CommandConfig {
+    App::new("fetch")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("url")
+                .index(1)
+                .required(true)
+                .help("URL of the first page"),
+        )
+        .arg(
+            Arg::with_name("paginate")
+                .long("paginate")
+                .short("p")
+                .takes_value(true)
+                .help("Path to the next page's token in each response, e.g. '(next_token)'"),
+        )
+        .arg(
+            Arg::with_name("token-param")
+                .long("token-param")
+                .takes_value(true)
+                .help("Query parameter that passes the token [default: the --paginate field name]"),
+        )
+        .arg(
+            Arg::with_name("items")
+                .long("items")
+                .short("i")
+                .takes_value(true)
+                .help("Only write the values at this path in each response, e.g. '(results *)'"),
+        )
+        .arg(
+            Arg::with_name("header")
+                .long("header")
+                .short("H")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Add a request header, written as 'Name: value'"),
+        )
+        .arg(
+            Arg::with_name("max-pages")
+                .long("max-pages")
+                .takes_value(true)
+                .help("Stop after requesting this many pages"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .after_help(
+            "Each response may be text or binary Ion. Requests are sent with the
+header `Accept: application/ion` unless another Accept header is given.
+With --paginate, the token found in each response is added to the URL
+as a query parameter to request the next page. Fetching stops when a
+response has no token, or the token is null or repeats."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // `url` is required, so we can unwrap this safely.
+    let url = matches.value_of("url").unwrap();
+    let token_path = match matches.value_of("paginate") {
+        Some(path) => Some(Path::from_str(path).with_context(|| "Invalid --paginate path")?),
+        None => None,
+    };
+    let token_param = match (matches.value_of("token-param"), &token_path) {
+        (Some(param), _) => param.to_owned(),
+        (None, Some(path)) => match path.split_last() {
+            Some((_, Step::Field(name))) => name.clone(),
+            _ => bail!("--token-param is required when --paginate doesn't end with a field name"),
+        },
+        (None, None) => String::new(),
+    };
+    let items_path = match matches.value_of("items") {
+        Some(path) => Some(Path::from_str(path).with_context(|| "Invalid --items path")?),
+        None => None,
+    };
+    let headers = matches.values_of("header")
+        .into_iter()
+        .flatten()
+        .map(|header| match header.find(':') {
+            Some(index) => Ok((header[..index].trim(), header[index + 1..].trim())),
+            None => bail!("Header '{}' must have the form 'Name: value'", header),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let max_pages = match matches.value_of("max-pages") {
+        Some(count) => Some(count.parse::<usize>().with_context(|| format!("Invalid page count '{}'", count))?),
+        None => None,
+    };
+
+    let mut output = IonOutput::from_matches(matches)?;
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    output.set_unknown_symbols(UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()));
+
+    let mut token: Option<String> = None;
+    let mut pages = 0;
+    loop {
+        let mut request = ureq::get(url);
+        if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("accept")) {
+            request = request.set("Accept", "application/ion");
+        }
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        if let Some(token) = &token {
+            request = request.query(&token_param, token);
+        }
+        let mut body = Vec::new();
+        request.call()
+            .with_context(|| format!("Request for page {} failed", pages + 1))?
+            .into_reader()
+            .read_to_end(&mut body)
+            .with_context(|| format!("Could not read the response for page {}", pages + 1))?;
+        let values = read_document(&body)
+            .with_context(|| format!("The response for page {} is not valid Ion", pages + 1))?;
+        pages += 1;
+
+        for value in &values {
+            match &items_path {
+                Some(path) => {
+                    for item in path.select(value) {
+                        output.write_value(item)?;
+                    }
+                }
+                None => output.write_value(value)?,
+            }
+        }
+
+        let next_token = match &token_path {
+            Some(path) => values.iter().flat_map(|value| path.select(value)).next().and_then(token_text),
+            None => None,
+        };
+        if next_token.is_none() || next_token == token || max_pages.is_some_and(|max| pages >= max) {
+            break;
+        }
+        token = next_token;
+    }
+    eprintln!("Fetched {} page(s).", pages);
+    output.finish()
+}
+
+// Returns the text to send for a pagination token, or `None` if it's null.
+fn token_text(token: &Value) -> Option<String> {
+    match &token.data {
+        Data::Null(_) => None,
+        Data::String(_) | Data::Symbol(_) => token.as_text().map(|text| text.to_owned()),
+        Data::Integer(i) => Some(i.to_string()),
+        // Any other token is sent as text Ion.
+        _ => {
+            let mut text = String::new();
+            TextFormatter::new().format(token, &mut text).ok()?;
+            Some(text)
+        }
+    }
+}
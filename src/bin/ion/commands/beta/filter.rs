@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, FixedOffset};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+
+use crate::commands::{exit_status, exit_status_arg, CommandConfig};
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::Path;
+use crate::timestamp::parse_period;
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Writes the values whose timestamp at a path falls within a time range.";
+
+pub fn app() -> CommandConfig {
+    App::new("filter")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the timestamp within each top-level value, e.g. '(event time)'"),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .takes_value(true)
+                .help("Keep values at or after the start of this Ion timestamp, e.g. 2021-06-01T"),
+        )
+        .arg(
+            Arg::with_name("until")
+                .long("until")
+                .takes_value(true)
+                .help("Keep values before the end of this Ion timestamp, e.g. 2021-06-30T"),
+        )
+        .group(
+            ArgGroup::with_name("range")
+                .args(&["since", "until"])
+                .multiple(true)
+                .required(true)
+        )
+        .arg(
+            Arg::with_name("keep-missing")
+                .long("keep-missing")
+                .help("Keep values that don't have a timestamp at the path"),
+        )
+        .arg(exit_status_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Timestamps are compared as instants, so offsets are taken into account:
+2021-06-01T08:00-07:00 and 2021-06-01T15:00Z are equal. The bounds are
+inclusive and cover the whole period their precision describes, so
+`--since 2021-06T --until 2021-06T` keeps every value in June and
+`--until 2021-06-30` keeps values through the end of June 30th. Bounds
+without a time, and those with the unknown offset -00:00, are in UTC.
+
+Values whose path selects no timestamp are dropped unless --keep-missing
+is given. A path that selects more than one value is an error. With
+--exit-status, the exit status is 1 if no values were kept."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let kept = filter(matches);
+    if matches.is_present("exit-status") {
+        return exit_status(kept);
+    }
+    kept.map(|_| ())
+}
+
+// Writes the values in range and returns whether there were any.
+fn filter(matches: &ArgMatches<'static>) -> Result<bool> {
+    // --path is required, so we can unwrap this safely.
+    let path = Path::from_str(matches.value_of("path").unwrap())?;
+    let since = match matches.value_of("since") {
+        Some(text) => Some(parse_period(text).with_context(|| "Invalid --since")?.0),
+        None => None,
+    };
+    let until = match matches.value_of("until") {
+        Some(text) => Some(parse_period(text).with_context(|| "Invalid --until")?.1),
+        None => None,
+    };
+    let keep_missing = matches.is_present("keep-missing");
+
+    let mut output = IonOutput::from_matches(matches)?;
+    let mut kept_any = false;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let keep = match timestamp_at(&path, &value)
+                .with_context(|| format!("Could not filter a value in '{}'", input.name()))? {
+                Some(timestamp) => in_range(timestamp, since, until),
+                None => keep_missing,
+            };
+            if keep {
+                output.write_value(&value)?;
+                kept_any = true;
+            }
+        }
+    }
+    output.finish()?;
+    Ok(kept_any)
+}
+
+// Returns the timestamp at `path`, or None if the path doesn't select a (non-null) timestamp.
+fn timestamp_at(path: &Path, value: &Value) -> Result<Option<DateTime<FixedOffset>>> {
+    match path.select(value).as_slice() {
+        [Value { data: Data::Timestamp(timestamp), .. }] => Ok(Some(timestamp.instant())),
+        [] | [_] => Ok(None),
+        selected => bail!("Path {} selected {} values; expected at most one.", path, selected.len()),
+    }
+}
+
+// `since` is the first instant in the range and `until` is the first instant after it.
+fn in_range(timestamp: DateTime<FixedOffset>, since: Option<DateTime<FixedOffset>>, until: Option<DateTime<FixedOffset>>) -> bool {
+    since.is_none_or(|since| timestamp >= since) && until.is_none_or(|until| timestamp < until)
+}
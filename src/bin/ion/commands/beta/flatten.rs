@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Flattens nested structs into structs with dotted field names.";
+
+// Creates the `separator` argument shared by `flatten` and `unflatten`.
+pub fn separator_arg() -> Arg<'static, 'static> {
+    Arg::with_name("separator")
+        .long("separator")
+        .short("s")
+        .takes_value(true)
+        .default_value(".")
+        .help("Text that separates the parts of a flattened field name")
+}
+
+// Creates the `lists` argument shared by `flatten` and `unflatten`.
+pub fn lists_arg() -> Arg<'static, 'static> {
+    Arg::with_name("lists")
+        .long("lists")
+        .short("l")
+        .takes_value(true)
+        .default_value("index")
+        .possible_values(&["index", "keep"])
+        .help("Whether list elements get their own field names, like `tags.0`, or lists are kept as values")
+}
+
+pub fn app() -> CommandConfig {
+    App::new("flatten")
+        .about(ABOUT)
+        .arg(separator_arg())
+        .arg(lists_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each top-level struct like
+  {id: 7, owner: {name: \"ana\", tags: [a, b]}}
+is written as
+  {id: 7, 'owner.name': \"ana\", 'owner.tags.0': a, 'owner.tags.1': b}
+With `--lists keep`, lists are kept as field values instead.
+S-expressions are always kept as values. Empty structs and lists are kept as values so that they survive
+`ion beta unflatten`. Annotations on nested containers are dropped, and
+top-level values that aren't structs are written unchanged."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --separator and --lists have default values, so we can unwrap them safely.
+    let separator = matches.value_of("separator").unwrap();
+    let index_lists = matches.value_of("lists").unwrap() == "index";
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let value = match value.data {
+                Data::Struct(fields) => {
+                    let mut flattened = Vec::new();
+                    for (name, child) in fields {
+                        flatten(field_name(&name), child, separator, index_lists, &mut flattened);
+                    }
+                    Value { annotations: value.annotations, data: Data::Struct(flattened) }
+                }
+                _ => value,
+            };
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
+
+// Adds the fields for `value`, whose flattened name is `prefix`, to `flattened`.
+fn flatten(prefix: String, value: Value, separator: &str, index_lists: bool, flattened: &mut Vec<(Symbol, Value)>) {
+    match value.data {
+        Data::Struct(fields) if !fields.is_empty() => {
+            for (name, child) in fields {
+                let name = format!("{}{}{}", prefix, separator, field_name(&name));
+                flatten(name, child, separator, index_lists, flattened);
+            }
+        }
+        Data::List(values) if index_lists && !values.is_empty() => {
+            for (index, child) in values.into_iter().enumerate() {
+                flatten(format!("{}{}{}", prefix, separator, index), child, separator, index_lists, flattened);
+            }
+        }
+        _ => flattened.push((Symbol::from(prefix), value)),
+    }
+}
+
+// Symbols without known text are named by their symbol ID.
+fn field_name(name: &Symbol) -> String {
+    match (name.text(), name.sid()) {
+        (Some(text), _) => text.to_owned(),
+        (None, Some(sid)) => format!("${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    }
+}
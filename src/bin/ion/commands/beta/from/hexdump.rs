@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{claim_stdin, display_name, input_arg, input_names, IonInput, STDIN_NAME};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::Value;
+
+const ABOUT: &str = "Converts a hex dump of Ion data, like the output of xxd or od, back to Ion.";
+
+pub fn app() -> CommandConfig {
+    App::new("hexdump")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("dump-format")
+                .long("dump-format")
+                .takes_value(true)
+                .default_value("auto")
+                .possible_values(&["auto", "xxd", "od", "raw"])
+                .help("The layout of the hex dump")
+                .long_help(
+                    "The layout of the hex dump.
+  xxd   lines like `00000010: e001 00ea 2183  ....!.`, as written by xxd
+  od    lines like `000010 e0 01 00 ea 21 83`, as written by
+        `od -A x -t x1` or `hexdump -C`; a `*` line repeats the line
+        before it up to the next offset
+  raw   only hex digits, in pairs or runs, separated by whitespace;
+        `0x` prefixes and trailing commas are ignored
+  auto  decides from the first line"
+                ),
+        )
+        .arg(
+            Arg::with_name("save-bytes")
+                .long("save-bytes")
+                .takes_value(true)
+                .help("Also write the reconstructed bytes to this file, e.g. to run `inspect` on it"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Reads the bytes out of the hex dump and writes the Ion values they hold.
+The bytes may be binary Ion or Ion text. Offsets in xxd and od dumps are
+read as hexadecimal and must match the number of bytes before them, so a
+dump with missing lines, or od's default octal offsets, is reported
+rather than misread; use `od -A x`. ASCII columns, like xxd's and
+`hexdump -C`'s, are ignored."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --dump-format has a default value, so we can unwrap this safely.
+    let dump_format = matches.value_of("dump-format").unwrap();
+    let mut bytes = Vec::new();
+    for input_name in input_names(matches) {
+        let text = if input_name == STDIN_NAME {
+            claim_stdin()?;
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).with_context(|| "Could not read STDIN")?;
+            text
+        } else {
+            fs::read_to_string(input_name).with_context(|| format!("Could not read '{}'", input_name))?
+        };
+        let format = match dump_format {
+            "auto" => detect_format(&text),
+            format => format,
+        };
+        let dump = match format {
+            "xxd" | "od" => parse_offset_dump(&text, format == "xxd"),
+            _ => parse_raw(&text),
+        };
+        bytes.extend(dump.with_context(|| format!("Could not read the hex dump in '{}'", display_name(input_name)))?);
+    }
+
+    if let Some(file_name) = matches.value_of("save-bytes") {
+        fs::write(file_name, &bytes).with_context(|| format!("Could not write '{}'", file_name))?;
+    }
+    let input = IonInput::from_bytes("the hex dump", &bytes)?;
+    let mut reader = input.reader();
+    let mut output = IonOutput::from_matches(matches)?;
+    while reader.next()?.is_some() {
+        let value = Value::read(&mut reader).with_context(|| "Could not read a value from the hex dump")?;
+        output.write_value(&value)?;
+    }
+    output.finish()
+}
+
+// Guesses the layout of a dump from its first line: xxd's offsets end with a colon, and od's are
+// followed by single bytes or pairs of bytes.
+fn detect_format(text: &str) -> &'static str {
+    let mut tokens = match text.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) => line.split_whitespace(),
+        None => return "raw",
+    };
+    let first = tokens.next().unwrap_or_default();
+    if first.ends_with(':') && is_hex(&first[..first.len() - 1]) {
+        return "xxd";
+    }
+    let second_is_byte_group = tokens.next().is_some_and(|token| is_hex(token) && (token.len() == 2 || token.len() == 4));
+    if first.len() >= 6 && is_hex(first) && second_is_byte_group {
+        return "od";
+    }
+    "raw"
+}
+
+// Reads a dump whose lines begin with the offset of their first byte. xxd's hex digits are
+// separated from its ASCII column by two spaces, and `hexdump -C`'s ASCII column is set off by
+// pipes; od writes no ASCII column.
+fn parse_offset_dump(text: &str, xxd: bool) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    // The bytes on the last line, and whether a `*` said to repeat them.
+    let mut last_line: Vec<u8> = Vec::new();
+    let mut repeating = false;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "*" {
+            repeating = true;
+            continue;
+        }
+        let (offset_text, rest) = match line.find(|c: char| c.is_whitespace() || c == ':') {
+            Some(end) => (&line[..end], line[end..].trim_start_matches(':')),
+            None => (line, ""),
+        };
+        let offset = usize::from_str_radix(offset_text, 16)
+            .with_context(|| format!("Line {} doesn't begin with a hex offset: '{}'", line_number, line))?;
+        if repeating {
+            if last_line.is_empty() {
+                bail!("Line {} follows a '*' with no line before it to repeat.", line_number);
+            }
+            while bytes.len() < offset {
+                let needed = (offset - bytes.len()).min(last_line.len());
+                bytes.extend_from_slice(&last_line[..needed]);
+            }
+            repeating = false;
+        }
+        if offset != bytes.len() {
+            bail!(
+                "Line {} has offset {:#x}, but {:#x} bytes came before it; is a line missing, or are the offsets not hex?",
+                line_number,
+                offset,
+                bytes.len()
+            );
+        }
+        let hex = if xxd {
+            rest.trim_start().split("  ").next().unwrap_or_default()
+        } else {
+            rest.split('|').next().unwrap_or_default()
+        };
+        last_line.clear();
+        for token in hex.split_whitespace() {
+            last_line.extend(decode_hex(token).with_context(|| format!("Invalid hex on line {}", line_number))?);
+        }
+        bytes.extend_from_slice(&last_line);
+    }
+    Ok(bytes)
+}
+
+// Reads whitespace-separated runs of hex digits.
+fn parse_raw(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for token in line.split_whitespace() {
+            let token = token.trim_end_matches(',');
+            let token = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+            bytes.extend(decode_hex(token).with_context(|| format!("Invalid hex on line {}", index + 1))?);
+        }
+    }
+    Ok(bytes)
+}
+
+fn decode_hex(token: &str) -> Result<Vec<u8>> {
+    if !is_hex(token) || token.len() % 2 == 1 {
+        bail!("'{}' is not an even number of hex digits.", token);
+    }
+    // The token is all ASCII hex digits, so it can be sliced in pairs and each pair parsed.
+    Ok((0..token.len()).step_by(2).map(|i| u8::from_str_radix(&token[i..i + 2], 16).unwrap()).collect())
+}
+
+fn is_hex(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit())
+}
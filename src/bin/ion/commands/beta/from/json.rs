@@ -0,0 +1,84 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+use serde_json::{Deserializer, Value as JsonValue};
+
+use crate::commands::CommandConfig;
+use crate::input::{claim_stdin, display_name, input_arg, input_names, STDIN_NAME};
+use crate::json::{dialect_arg, from_json, restore_big_numbers, Dialect, NumbersAs};
+use crate::output::{format_arg, output_arg, IonOutput};
+
+const ABOUT: &str = "Converts a stream of JSON values to Ion.";
+
+pub fn app() -> CommandConfig {
+    App::new("json")
+        .about(ABOUT)
+        .arg(dialect_arg())
+        .arg(
+            Arg::with_name("numbers-as")
+                .long("numbers-as")
+                .takes_value(true)
+                .default_value("auto")
+                .possible_values(&["auto", "decimal", "float"])
+                .help("Whether JSON numbers that aren't integers become Ion decimals or floats")
+                .long_help(
+                    "Controls how JSON numbers that aren't integers are converted.
+  auto     numbers with an exponent become floats and others become
+           decimals, the way Ion text reads them
+  decimal  every such number becomes a decimal, keeping its digits exactly
+  float    every such number becomes a float
+Integers always become ints, however large they are."
+                ),
+        )
+        .arg(
+            Arg::with_name("restore-big-numbers")
+                .long("restore-big-numbers")
+                .help("Convert {\"$int\": \"...\"} and {\"$decimal\": \"...\"} objects back to numbers")
+                .long_help(
+                    "Converts the objects written by `to json --big-numbers object`, like
+{\"$int\": \"12345678901234567890\"}, back to the numbers they hold: an int
+for $int and a decimal for $decimal."
+                ),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "The input may contain any number of JSON values, separated by whitespace,
+as in JSON Lines files. By default, numbers are converted the way Ion text
+reads them: integers become ints, numbers with exponents become floats,
+and other numbers become decimals; see --numbers-as."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --dialect has a default value, so we can unwrap this safely.
+    let dialect = Dialect::from_arg(matches.value_of("dialect").unwrap());
+    // --numbers-as has a default value, so we can unwrap this safely.
+    let numbers = NumbersAs::from_arg(matches.value_of("numbers-as").unwrap());
+    let restore = matches.is_present("restore-big-numbers");
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let bytes = if input_name == STDIN_NAME {
+            claim_stdin()?;
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes).with_context(|| "Could not read STDIN")?;
+            bytes
+        } else {
+            fs::read(input_name).with_context(|| format!("Could not read '{}'", input_name))?
+        };
+        for (index, json) in Deserializer::from_slice(&bytes).into_iter::<JsonValue>().enumerate() {
+            let json = json.with_context(|| format!("Invalid JSON in '{}'", display_name(input_name)))?;
+            let mut value = from_json(json, dialect, numbers)
+                .with_context(|| format!("Could not convert value #{} in '{}'", index + 1, display_name(input_name)))?;
+            if restore {
+                restore_big_numbers(&mut value)?;
+            }
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
@@ -0,0 +1,47 @@
+pub mod hexdump;
+pub mod json;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `from` subcommands
+pub fn from_subcommands() -> Vec<CommandConfig> {
+    vec![
+        hexdump::app(),
+        json::app(),
+    ]
+}
+
+pub fn runner_for_from_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "hexdump" => hexdump::run,
+        "json" => json::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `from` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_from_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested from command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("from")
+        .about("Converts other formats to Ion streams.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(from_subcommands())
+}
@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::schema::{authority_arg, Authority};
+
+mod model;
+mod rust;
+mod typescript;
+
+// How each generated file begins, whatever the language.
+pub const GENERATED_HEADER: &str = "// Generated by `ion beta generate`";
+
+const ABOUT: &str = "Generates code for the types in an Ion Schema and the schemas it refers to.";
+
+pub fn app() -> CommandConfig {
+    App::new("generate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .help("ISL schema whose types are generated"),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .short("d")
+                .takes_value(true)
+                .required(true)
+                .help("Directory in which to write the generated files"),
+        )
+        .arg(
+            Arg::with_name("language")
+                .long("language")
+                .short("l")
+                .takes_value(true)
+                .default_value("rust")
+                .possible_values(&["rust", "typescript"])
+                .help("Language to generate"),
+        )
+        .arg(
+            Arg::with_name("with-builders")
+                .long("with-builders")
+                .help("Also generate a builder for each struct (Rust only)"),
+        )
+        .arg(
+            Arg::with_name("with-validation")
+                .long("with-validation")
+                .help("Also generate a validate() method checking the constraints types can't express (Rust only)"),
+        )
+        .arg(
+            Arg::with_name("serde")
+                .long("serde")
+                .help("Also derive serde's Serialize and Deserialize for each type (Rust only)"),
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("Check that --output-dir holds what would be generated, without writing to it"),
+        )
+        .arg(authority_arg())
+        .after_help(
+            "Writes a module for --schema, and one for each schema whose types it
+refers to, directly or not:
+  ion beta generate --schema schemas/orders.isl -d src/model
+writes src/model/orders.rs, a file for each schema that orders.isl
+imports types from, like billing_invoice.rs for billing/invoice.isl,
+and a mod.rs declaring them. Type references are resolved as in the
+`schema` commands, relative to --authority, and a type from another
+schema is referred to through that schema's module.
+
+Each type with `fields` becomes a struct, with a field that may be
+missing as an Option and one that may occur more than once as a Vec.
+A type with `one_of` or `any_of` becomes an enum with a variant for
+each alternative, and any other type becomes an alias of the type it
+has, like `pub type Sku = String;`. A struct or union written inline,
+like the `item` field of type `order`, becomes a type of its own named
+after both, `OrderItem`. Types may refer to themselves or each other;
+a reference that would make a struct contain itself is boxed.
+
+Decimals, timestamps, and types without a more specific representation,
+like `any`, are ion-rs's Decimal, Timestamp, and Element.
+
+--with-builders adds `Order::builder()`, returning an `OrderBuilder`
+with a method to set each field and a `build()` that fails, listing
+them, if required fields weren't set.
+
+--with-validation adds a `validate()` method to each struct and union,
+which checks the constraints on its fields that their Rust types can't
+express: `valid_values`, `regex`, and the length constraints. It returns
+a message for each value that doesn't meet them, like
+  items[2].sku: \"x\" doesn't meet regex: \"^[A-Z]+$\"
+and validates the structs and unions the value holds. With both flags,
+`build()` also validates what it builds. A `regex` is checked with the
+regex crate, which the generated code then depends on. A constraint
+that can't be checked on its field's type is skipped with a warning.
+
+--serde derives serde's Serialize and Deserialize, so the types can be
+read and written with ion-rs's serde support, in its `serde` module,
+and used anywhere else serde is. A
+field whose Rust name differs from its name in the schema is renamed
+back, a missing optional field is read as None and not written, a
+union is written as the alternative it holds, and a blob or clob is
+written as bytes using the serde_bytes crate. The generated code then
+depends on serde, with its derive feature, and serde_bytes if the
+schema has blobs or clobs. Ion's repeated fields have no serde
+equivalent, so a field that may occur more than once is read and
+written as a list, with a warning, and a type holding values of any
+type can't be generated with --serde.
+
+--language typescript writes a .ts file for each module instead, for
+ion-js, with an index.ts re-exporting them and an ion_codec.ts holding
+what they share. A struct becomes an interface, keeping the field names
+in the schema, with a missing optional field left undefined and a
+repeated field as an array. A union becomes a tagged union like
+  { kind: \"Circle\"; value: Circle } | { kind: \"Square\"; value: Square }
+and any other type an alias. Each type also gets functions to decode it
+from an ion-js DOM value and encode it with an ion-js writer:
+  const order = orders.decodeOrder(ion.load(text));
+  orders.encodeOrder(writer, order);
+Decoding a union picks the first alternative the value matches. Ints
+and floats are numbers, decimals and timestamps are ion-js's Decimal
+and Timestamp, blobs and clobs are Uint8Arrays, and types without a
+more specific representation are ion-js DOM values.
+
+--check generates the files without writing them and compares them with
+those in --output-dir, failing if any file is missing or differs, or if
+the directory holds a generated file that would no longer be generated.
+Running it in CI keeps committed code in sync with its schemas:
+  ion beta generate --schema schemas/orders.isl -d src/model --check"
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --schema and --output-dir are required, so we can unwrap them safely.
+    let schema_file = matches.value_of("schema").unwrap();
+    let output_dir = PathBuf::from(matches.value_of("output-dir").unwrap());
+    let mut authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+    let id = authority.id_of(schema_file)?;
+    let modules = model::modules(&mut authority, &id)?;
+    let options = rust::Options {
+        builders: matches.is_present("with-builders"),
+        validation: matches.is_present("with-validation"),
+        serde: matches.is_present("serde"),
+    };
+    let files = match matches.value_of("language") {
+        Some("typescript") => {
+            if options.builders || options.validation || options.serde {
+                bail!("--with-builders, --with-validation, and --serde only apply to Rust.");
+            }
+            typescript::render(&modules)?
+        }
+        _ => rust::render(&modules, options)?,
+    };
+
+    if matches.is_present("check") {
+        return check(&output_dir, &files);
+    }
+    fs::create_dir_all(&output_dir)
+        .with_context(|| format!("Could not create output directory '{}'", output_dir.display()))?;
+    for (file_name, text) in &files {
+        let path = output_dir.join(file_name);
+        fs::write(&path, text).with_context(|| format!("Could not write '{}'", path.display()))?;
+    }
+    eprintln!("Generated {} files in '{}'.", files.len(), output_dir.display());
+    Ok(())
+}
+
+// Compares the files that would be generated with those in `output_dir`, reporting each one that
+// doesn't match.
+fn check(output_dir: &Path, files: &[(String, String)]) -> Result<()> {
+    let mut problems = Vec::new();
+    for (file_name, text) in files {
+        let path = output_dir.join(file_name);
+        match fs::read_to_string(&path) {
+            Ok(existing) if existing == *text => {}
+            Ok(existing) => {
+                let line = existing.lines().zip(text.lines()).take_while(|(old, new)| old == new).count() + 1;
+                problems.push(format!("'{}' differs from what would be generated, from line {}", path.display(), line));
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                problems.push(format!("'{}' is missing", path.display()))
+            }
+            Err(error) => return Err(error).with_context(|| format!("Could not read '{}'", path.display())),
+        }
+    }
+    // Files left over from types or schemas that were removed.
+    if output_dir.is_dir() {
+        let generated: HashSet<&str> = files.iter().map(|(file_name, _)| file_name.as_str()).collect();
+        let entries = fs::read_dir(output_dir)
+            .with_context(|| format!("Could not read output directory '{}'", output_dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if generated.contains(file_name) || !path.is_file() {
+                continue;
+            }
+            let is_generated = fs::read_to_string(&path).is_ok_and(|text| text.starts_with(GENERATED_HEADER));
+            if is_generated {
+                problems.push(format!("'{}' was generated, but no longer would be", path.display()));
+            }
+        }
+    }
+    if problems.is_empty() {
+        eprintln!("All {} generated files in '{}' are up to date.", files.len(), output_dir.display());
+        return Ok(());
+    }
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    bail!(
+        "{} generated files in '{}' are out of date; run the same command without --check to update them.",
+        problems.len(),
+        output_dir.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The expected files are what the generator wrote for tests/generate/schemas/shapes.isl. After
+    // changing what it writes, update them with
+    //   ion beta generate -s tests/generate/schemas/shapes.isl -d tests/generate/rust \
+    //     --with-builders --with-validation --serde
+    //   ion beta generate -s tests/generate/schemas/shapes.isl -d tests/generate/typescript -l typescript
+    // and check that the differences are the ones intended.
+    fn golden_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("generate")
+    }
+
+    fn modules() -> Vec<model::Module> {
+        let mut authority = Authority::new(golden_dir().join("schemas"));
+        model::modules(&mut authority, "shapes.isl").unwrap()
+    }
+
+    #[test]
+    fn rust_matches_golden_files() {
+        let options = rust::Options { builders: true, validation: true, serde: true };
+        let files = rust::render(&modules(), options).unwrap();
+        check(&golden_dir().join("rust"), &files).unwrap();
+    }
+
+    #[test]
+    fn typescript_matches_golden_files() {
+        let files = typescript::render(&modules()).unwrap();
+        check(&golden_dir().join("typescript"), &files).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+
+use anyhow::{bail, Context, Result};
+
+use crate::schema::{as_reference, has_annotation, ion_text, is_built_in, Authority, TypeReference};
+use crate::validation::{occurs, Bound, Range};
+use crate::value::{Data, Value};
+
+// A description of the code to generate for a schema that doesn't depend on the language: one
+// module per schema, holding a type for each of the schema's types, and for each inline struct or
+// union nested within them, which is named after the type and field holding it.
+
+pub struct Module {
+    // The ID of the schema the module is generated from.
+    pub schema_id: String,
+    // In snake_case, from the schema's ID, e.g. `billing_invoice` for `billing/invoice.isl`.
+    pub name: String,
+    pub types: Vec<TypeModel>,
+}
+
+pub struct TypeModel {
+    // In PascalCase, e.g. `OrderItem` for `order_item`, or for the `item` field of `order`.
+    pub name: String,
+    pub kind: Kind,
+    // For an alias, the constraints on its values that its type can't express.
+    pub checks: Vec<Check>,
+}
+
+pub enum Kind {
+    Struct(Vec<Field>),
+    // One of several types, from `one_of` or `any_of`.
+    Union(Vec<FieldType>),
+    // Another name for a type, like a scalar with constraints or a list of some type.
+    Alias(FieldType),
+}
+
+pub struct Field {
+    // The field's name in Ion.
+    pub name: String,
+    pub field_type: FieldType,
+    pub cardinality: Cardinality,
+    // The constraints on each of the field's values that its type can't express.
+    pub checks: Vec<Check>,
+}
+
+// A constraint that a generated type can't express, which a generated `validate` can check.
+pub struct Check {
+    // The constraint as the schema writes it, like `regex: "^[A-Z]+$"`, for messages.
+    pub description: String,
+    pub kind: CheckKind,
+}
+
+pub enum CheckKind {
+    // `valid_values: range::[...]`. Each bound is a value and whether it's inclusive, or `None` if
+    // the range is unbounded on that side.
+    Range { lower: Option<(Value, bool)>, upper: Option<(Value, bool)> },
+    // `valid_values` listing the values allowed.
+    Values(Vec<Value>),
+    // `regex`, with its `i` and `m` flags written into the pattern as `(?i)` and `(?m)`.
+    Regex(String),
+    // `codepoint_length`, `utf8_byte_length`, `byte_length`, or `container_length`, with the fewest
+    // and most allowed.
+    Length { constraint: String, min: usize, max: Option<usize> },
+}
+
+// How many times a field may occur, from its `occurs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cardinality {
+    Required,
+    Optional,
+    Repeated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Scalar(Scalar),
+    // A generated type, in the module named `module`.
+    Named { module: String, name: String },
+    // A list or s-expression whose elements all have this type.
+    Sequence(Box<FieldType>),
+    // A type that also matches null, like `$int` or `nullable::order`.
+    Nullable(Box<FieldType>),
+}
+
+// The built-in types. `Any` stands for the types that don't have a more specific representation,
+// like `any`, `number`, and `struct` without `fields`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    Bool,
+    Int,
+    Float,
+    Decimal,
+    Timestamp,
+    String,
+    Symbol,
+    Blob,
+    Clob,
+    Any,
+}
+
+// Builds a module for the schema `schema_id`, followed by one for each schema whose types it
+// refers to, directly or not. Recursive types, within a schema or across several, refer to each
+// other by name like any others.
+pub fn modules(authority: &mut Authority, schema_id: &str) -> Result<Vec<Module>> {
+    let mut modules: Vec<Module> = Vec::new();
+    let mut queued: HashSet<String> = HashSet::new();
+    let mut pending = VecDeque::new();
+    queued.insert(schema_id.to_owned());
+    pending.push_back(schema_id.to_owned());
+    while let Some(id) = pending.pop_front() {
+        let definitions: Vec<(String, Value)> = authority
+            .load(&id)?
+            .types
+            .iter()
+            .map(|definition| (definition.name.clone(), definition.definition.clone()))
+            .collect();
+        let name = module_name(&id);
+        if let Some(other) = modules.iter().find(|module| module.name == name) {
+            bail!("Schemas '{}' and '{}' would both generate a module named '{}'.", other.schema_id, id, name);
+        }
+        let mut builder = ModuleBuilder { authority, schema_id: &id, module: name.clone(), types: Vec::new(), referenced: Vec::new() };
+        for (type_name, definition) in &definitions {
+            builder.add_type(pascal_case(type_name), definition)
+                .with_context(|| format!("Could not generate type '{}' of schema '{}'", type_name, id))?;
+        }
+        let ModuleBuilder { types, referenced, .. } = builder;
+        let mut names = HashSet::new();
+        if let Some(duplicate) = types.iter().find(|type_model| !names.insert(&type_model.name)) {
+            bail!("Schema '{}' would generate more than one type named '{}'.", id, duplicate.name);
+        }
+        for referenced_id in referenced {
+            if queued.insert(referenced_id.clone()) {
+                pending.push_back(referenced_id);
+            }
+        }
+        modules.push(Module { schema_id: id, name, types });
+    }
+    Ok(modules)
+}
+
+// Collects the types generated for one schema.
+struct ModuleBuilder<'a> {
+    authority: &'a mut Authority,
+    schema_id: &'a str,
+    module: String,
+    types: Vec<TypeModel>,
+    // The other schemas whose types this one's refer to.
+    referenced: Vec<String>,
+}
+
+impl ModuleBuilder<'_> {
+    // Adds a type for a type definition or inline type. Any inline types it holds are added
+    // after it.
+    fn add_type(&mut self, name: String, definition: &Value) -> Result<()> {
+        let index = self.types.len();
+        self.types.push(TypeModel { name: name.clone(), kind: Kind::Alias(FieldType::Scalar(Scalar::Any)), checks: Vec::new() });
+        let kind = if let Some(fields) = definition.get("fields") {
+            Kind::Struct(self.fields(&name, fields)?)
+        } else if let Some(alternatives) = definition.get("one_of").or_else(|| definition.get("any_of")) {
+            Kind::Union(self.alternatives(&name, alternatives)?)
+        } else {
+            self.types[index].checks = self.checks(definition)?;
+            Kind::Alias(self.base_type(&name, definition)?)
+        };
+        self.types[index].kind = kind;
+        Ok(())
+    }
+
+    fn fields(&mut self, name: &str, fields: &Value) -> Result<Vec<Field>> {
+        let declared = match &fields.data {
+            Data::Struct(declared) => declared,
+            _ => bail!("Schema '{}' has a `fields` constraint that isn't a struct.", self.schema_id),
+        };
+        let mut generated = Vec::with_capacity(declared.len());
+        for (field_name, field_isl) in declared {
+            let field_name = field_name.text()
+                .with_context(|| format!("Schema '{}' has a field name without text.", self.schema_id))?;
+            let (min, max) = occurs(self.schema_id, field_isl, (0, 1))?;
+            let cardinality = match (min, max) {
+                (_, max) if max > 1 => Cardinality::Repeated,
+                (0, _) => Cardinality::Optional,
+                _ => Cardinality::Required,
+            };
+            let field_type = self.field_type(&format!("{}{}", name, pascal_case(field_name)), field_isl)?;
+            let checks = match as_reference(field_isl) {
+                Some(_) => Vec::new(),
+                None => self.checks(field_isl)?,
+            };
+            generated.push(Field { name: field_name.to_owned(), field_type, cardinality, checks });
+        }
+        Ok(generated)
+    }
+
+    fn alternatives(&mut self, name: &str, alternatives: &Value) -> Result<Vec<FieldType>> {
+        let alternatives = match &alternatives.data {
+            Data::List(alternatives) => alternatives,
+            _ => bail!("Schema '{}' has a `one_of` or `any_of` that isn't a list: {}", self.schema_id, ion_text(alternatives)),
+        };
+        alternatives
+            .iter()
+            .enumerate()
+            .map(|(index, alternative)| self.field_type(&format!("{}{}", name, index + 1), alternative))
+            .collect()
+    }
+
+    // The type of a value matching a type reference or an inline type. An inline struct or union
+    // is added as a type of its own named `name`.
+    fn field_type(&mut self, name: &str, isl: &Value) -> Result<FieldType> {
+        let schema_id = self.schema_id;
+        let field_type = match as_reference(isl) {
+            Some(TypeReference::Named(type_name)) if is_built_in(&type_name) => built_in(&type_name),
+            Some(TypeReference::Named(type_name)) => self.named(schema_id, &type_name)?,
+            Some(TypeReference::Imported { id, type_name }) => self.named(&id, &type_name)?,
+            None if ["fields", "one_of", "any_of"].iter().any(|constraint| isl.get(constraint).is_some()) => {
+                self.add_type(name.to_owned(), isl)?;
+                FieldType::Named { module: self.module.clone(), name: name.to_owned() }
+            }
+            None => self.base_type(name, isl)?,
+        };
+        Ok(match field_type {
+            FieldType::Nullable(_) | FieldType::Scalar(Scalar::Any) => field_type,
+            _ if has_annotation(isl, "nullable") => FieldType::Nullable(Box::new(field_type)),
+            _ => field_type,
+        })
+    }
+
+    // The type of a value matching a type that isn't a struct or union: its `type`, or if it has
+    // an `element` constraint, a sequence of that.
+    fn base_type(&mut self, name: &str, definition: &Value) -> Result<FieldType> {
+        if let Some(element) = definition.get("element") {
+            let element = self.field_type(&format!("{}Element", name), element)?;
+            return Ok(FieldType::Sequence(Box::new(element)));
+        }
+        match definition.get("type") {
+            Some(reference) => self.field_type(name, reference),
+            None => Ok(FieldType::Scalar(Scalar::Any)),
+        }
+    }
+
+    // The constraints of an inline type or type definition that a generated type can't express.
+    fn checks(&self, isl: &Value) -> Result<Vec<Check>> {
+        let mut checks = Vec::new();
+        let description = |name: &str, constraint: &Value| format!("{}: {}", name, ion_text(constraint));
+        if let Some(valid_values) = isl.get("valid_values") {
+            let kind = match &valid_values.data {
+                Data::List(_) if has_annotation(valid_values, "range") => {
+                    let Range { lower, upper } = Range::from_value(self.schema_id, valid_values)?;
+                    CheckKind::Range { lower: bound(lower), upper: bound(upper) }
+                }
+                Data::List(values) => CheckKind::Values(values.clone()),
+                _ => bail!("Schema '{}' has a `valid_values` that isn't a list: {}", self.schema_id, ion_text(valid_values)),
+            };
+            checks.push(Check { description: description("valid_values", valid_values), kind });
+        }
+        if let Some(regex) = isl.get("regex") {
+            let mut pattern = String::new();
+            for flag in ["i", "m"].iter().filter(|flag| has_annotation(regex, flag)) {
+                pattern.push_str(&format!("(?{})", flag));
+            }
+            match &regex.data {
+                Data::String(text) => pattern.push_str(text),
+                _ => bail!("Schema '{}' has a `regex` constraint that isn't a string.", self.schema_id),
+            }
+            checks.push(Check { description: description("regex", regex), kind: CheckKind::Regex(pattern) });
+        }
+        for name in ["codepoint_length", "utf8_byte_length", "byte_length", "container_length"].iter() {
+            if let Some(length) = isl.get(name) {
+                let (min, max) = length_bounds(self.schema_id, name, length)?;
+                let kind = CheckKind::Length { constraint: (*name).to_owned(), min, max };
+                checks.push(Check { description: description(name, length), kind });
+            }
+        }
+        Ok(checks)
+    }
+
+    // A type defined in a schema, which may be this one or another.
+    fn named(&mut self, schema_id: &str, type_name: &str) -> Result<FieldType> {
+        let (id, name) = self.authority.resolve(schema_id, type_name)?
+            .with_context(|| format!("Schema '{}' refers to type '{}', which it doesn't define or import.", schema_id, type_name))?;
+        if id != self.schema_id && !self.referenced.contains(&id) {
+            self.referenced.push(id.clone());
+        }
+        Ok(FieldType::Named { module: module_name(&id), name: pascal_case(&name) })
+    }
+}
+
+fn bound(bound: Bound) -> Option<(Value, bool)> {
+    match bound {
+        Bound::Unbounded => None,
+        Bound::Inclusive(value) => Some((value, true)),
+        Bound::Exclusive(value) => Some((value, false)),
+    }
+}
+
+// Reads a length constraint, an exact length or a range, as the fewest and most allowed.
+fn length_bounds(schema_id: &str, name: &str, constraint: &Value) -> Result<(usize, Option<usize>)> {
+    let invalid = || format!("Schema '{}' has an invalid `{}`: {}", schema_id, name, ion_text(constraint));
+    let length = |value: &Value| match &value.data {
+        Data::Integer(length) => usize::try_from(*length).ok(),
+        _ => None,
+    };
+    match &constraint.data {
+        Data::Integer(_) => {
+            let exact = length(constraint).with_context(invalid)?;
+            Ok((exact, Some(exact)))
+        }
+        Data::List(_) if has_annotation(constraint, "range") => {
+            let range = Range::from_value(schema_id, constraint)?;
+            let min = match range.lower {
+                Bound::Unbounded => 0,
+                Bound::Inclusive(value) => length(&value).with_context(invalid)?,
+                Bound::Exclusive(value) => length(&value).with_context(invalid)? + 1,
+            };
+            let max = match range.upper {
+                Bound::Unbounded => None,
+                Bound::Inclusive(value) => Some(length(&value).with_context(invalid)?),
+                Bound::Exclusive(value) => Some(length(&value).and_then(|length| length.checked_sub(1)).with_context(invalid)?),
+            };
+            Ok((min, max))
+        }
+        _ => bail!(invalid()),
+    }
+}
+
+fn built_in(name: &str) -> FieldType {
+    if let Some(base) = name.strip_prefix('$') {
+        return match built_in(base) {
+            FieldType::Scalar(Scalar::Any) => FieldType::Scalar(Scalar::Any),
+            base => FieldType::Nullable(Box::new(base)),
+        };
+    }
+    let scalar = match name {
+        "bool" => Scalar::Bool,
+        "int" => Scalar::Int,
+        "float" => Scalar::Float,
+        "decimal" => Scalar::Decimal,
+        "timestamp" => Scalar::Timestamp,
+        "string" | "text" => Scalar::String,
+        "symbol" => Scalar::Symbol,
+        "blob" | "lob" => Scalar::Blob,
+        "clob" => Scalar::Clob,
+        "list" | "sexp" => return FieldType::Sequence(Box::new(FieldType::Scalar(Scalar::Any))),
+        _ => Scalar::Any,
+    };
+    FieldType::Scalar(scalar)
+}
+
+// The name of the module generated for a schema: its ID without `.isl`, in snake_case.
+pub fn module_name(schema_id: &str) -> String {
+    snake_case(schema_id.strip_suffix(".isl").unwrap_or(schema_id))
+}
+
+// Converts a name like `order_item`, `order-item`, or `orderItem` to `OrderItem`.
+pub fn pascal_case(name: &str) -> String {
+    let mut converted: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part[..1].to_ascii_uppercase() + &part[1..])
+        .collect();
+    if converted.is_empty() || converted.starts_with(|c: char| c.is_ascii_digit()) {
+        converted.insert(0, '_');
+    }
+    converted
+}
+
+// Converts a name like `orderItem`, `order-item`, or `OrderItem` to `order_item`.
+pub fn snake_case(name: &str) -> String {
+    let mut converted = String::with_capacity(name.len());
+    let mut previous = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if previous.is_some_and(|previous: char| previous.is_ascii_lowercase() || previous.is_ascii_digit()) {
+                converted.push('_');
+            }
+            converted.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            converted.push(c);
+        } else if !converted.is_empty() && !converted.ends_with('_') {
+            converted.push('_');
+        }
+        previous = Some(c);
+    }
+    while converted.ends_with('_') {
+        converted.pop();
+    }
+    if converted.is_empty() || converted.starts_with(|c: char| c.is_ascii_digit()) {
+        converted.insert(0, '_');
+    }
+    converted
+}
@@ -0,0 +1,668 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use crate::value::{Data, Value};
+
+use super::GENERATED_HEADER;
+use super::model::{snake_case, Cardinality, Check, CheckKind, Field, FieldType, Kind, Module, Scalar, TypeModel};
+
+// Generates Rust: a file for each module, and a `mod.rs` declaring them. Structs and unions
+// become structs and enums, and other types become type aliases. A reference that would make a
+// type contain itself, like a tree's `parent` field, is boxed.
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become",
+    "box", "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+// Keywords that can't be used as raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+// What to generate besides the types themselves.
+#[derive(Clone, Copy)]
+pub struct Options {
+    // A builder for each struct.
+    pub builders: bool,
+    // A `validate` method for each struct and union.
+    pub validation: bool,
+    // serde's Serialize and Deserialize for each type, so it can be read and written by ion-rs's
+    // serde support.
+    pub serde: bool,
+}
+
+// Returns the name and contents of each file to write.
+pub fn render(modules: &[Module], options: Options) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::with_capacity(modules.len() + 1);
+    let mut mod_rs = header(&format!("from schema '{}' and the schemas it refers to", modules[0].schema_id));
+    for module in modules {
+        if module.name == "mod" {
+            bail!("Schema '{}' would generate a module named 'mod', which Rust doesn't allow.", module.schema_id);
+        }
+        writeln!(mod_rs, "pub mod {};", identifier(&module.name))?;
+        files.push((format!("{}.rs", module.name), RustModule { modules, module, options }.render()?));
+    }
+    files.push(("mod.rs".to_owned(), mod_rs));
+    Ok(files)
+}
+
+fn header(source: &str) -> String {
+    format!(
+        "{} {}. Don't edit it by hand; change the\n\
+         // schema and generate it again.\n\n",
+        GENERATED_HEADER, source
+    )
+}
+
+struct RustModule<'a> {
+    modules: &'a [Module],
+    module: &'a Module,
+    options: Options,
+}
+
+impl RustModule<'_> {
+    fn render(&self) -> Result<String> {
+        let mut text = header(&format!("from schema '{}'", self.module.schema_id));
+        for (index, type_model) in self.module.types.iter().enumerate() {
+            if index > 0 {
+                text.push('\n');
+            }
+            self.render_type(type_model, &mut text)?;
+        }
+        Ok(text)
+    }
+
+    fn render_type(&self, type_model: &TypeModel, text: &mut String) -> Result<()> {
+        let name = &type_model.name;
+        match &type_model.kind {
+            Kind::Struct(fields) => {
+                // Each field with its name and type in Rust.
+                let mut rust_fields = Vec::with_capacity(fields.len());
+                let mut field_names = HashSet::new();
+                for field in fields {
+                    let field_name = identifier(&snake_case(&field.name));
+                    if !field_names.insert(field_name.clone()) {
+                        bail!("Type '{}' would have more than one field named '{}'.", name, field_name);
+                    }
+                    let repeated = field.cardinality == Cardinality::Repeated;
+                    let field_type = self.field_type(name, &field.field_type, repeated);
+                    rust_fields.push((field, field_name, field_type));
+                }
+                writeln!(text, "{}", self.derive(type_model)?)?;
+                writeln!(text, "pub struct {} {{", name)?;
+                for (field, field_name, field_type) in &rust_fields {
+                    if self.options.serde {
+                        self.render_serde_attributes(name, field, field_name, text)?;
+                    }
+                    writeln!(text, "    pub {}: {},", field_name, with_cardinality(field.cardinality, field_type))?;
+                }
+                writeln!(text, "}}")?;
+                if self.options.builders {
+                    self.render_builder(name, &rust_fields, text)?;
+                }
+                if self.options.validation {
+                    self.render_struct_validation(name, &rust_fields, text)?;
+                }
+            }
+            Kind::Union(alternatives) => {
+                let variants = variant_names(alternatives);
+                writeln!(text, "{}", self.derive(type_model)?)?;
+                // A union's value is written as whichever alternative it holds, with no tag.
+                if self.options.serde {
+                    writeln!(text, "#[serde(untagged)]")?;
+                }
+                writeln!(text, "pub enum {} {{", name)?;
+                for (variant, alternative) in variants.iter().zip(alternatives) {
+                    writeln!(text, "    {}({}),", variant, self.field_type(name, alternative, false))?;
+                }
+                writeln!(text, "}}")?;
+                if self.options.validation {
+                    self.render_union_validation(name, &variants, alternatives, text)?;
+                }
+            }
+            // An alias can't refer to itself, so one that does is a struct wrapping its type.
+            Kind::Alias(alias) if self.is_newtype(type_model) => {
+                writeln!(text, "{}", self.derive(type_model)?)?;
+                if self.options.serde {
+                    writeln!(text, "#[serde(transparent)]")?;
+                }
+                writeln!(text, "pub struct {}(pub {});", name, self.field_type(name, alias, false))?;
+            }
+            Kind::Alias(alias) => writeln!(text, "pub type {} = {};", name, self.field_type(name, alias, false))?,
+        }
+        Ok(())
+    }
+
+    // The derive attribute for a generated struct or enum.
+    fn derive(&self, type_model: &TypeModel) -> Result<String> {
+        if !self.options.serde {
+            return Ok("#[derive(Debug, Clone, PartialEq)]".to_owned());
+        }
+        let field_types: Vec<&FieldType> = match &type_model.kind {
+            Kind::Struct(fields) => fields.iter().map(|field| &field.field_type).collect(),
+            Kind::Union(alternatives) => alternatives.iter().collect(),
+            Kind::Alias(alias) => vec![alias],
+        };
+        if field_types.into_iter().any(|field_type| self.holds_element(field_type, &mut HashSet::new())) {
+            bail!(
+                "Type '{}' holds values of any type, as ion_rs::Element, which serde can't serialize; use a more specific type or generate without --serde.",
+                type_model.name
+            );
+        }
+        Ok("#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]".to_owned())
+    }
+
+    // Writes the serde attributes a struct's field needs to be read and written as the schema
+    // describes it.
+    fn render_serde_attributes(&self, owner: &str, field: &Field, field_name: &str, text: &mut String) -> Result<()> {
+        let mut attributes = Vec::new();
+        if field_name.trim_start_matches("r#") != field.name {
+            attributes.push(format!("rename = {:?}", field.name));
+        }
+        match field.cardinality {
+            Cardinality::Required => {}
+            Cardinality::Optional => attributes.push("default, skip_serializing_if = \"Option::is_none\"".to_owned()),
+            Cardinality::Repeated => {
+                eprintln!(
+                    "Warning: field '{}' of type '{}' may occur more than once, which serde can't represent; it's read and written as a list.",
+                    field.name, owner
+                );
+                attributes.push("default".to_owned());
+            }
+        }
+        // serde writes a Vec<u8> as a list of integers unless it's told it holds bytes.
+        if matches!(field.field_type, FieldType::Scalar(Scalar::Blob | Scalar::Clob)) && field.cardinality != Cardinality::Repeated {
+            attributes.push("with = \"serde_bytes\"".to_owned());
+        }
+        if !attributes.is_empty() {
+            writeln!(text, "    #[serde({})]", attributes.join(", "))?;
+        }
+        Ok(())
+    }
+
+    // Whether a value of `field_type` holds an ion_rs::Element, directly or through an alias.
+    fn holds_element<'m>(&'m self, field_type: &'m FieldType, seen: &mut HashSet<(&'m str, &'m str)>) -> bool {
+        match field_type {
+            FieldType::Scalar(scalar) => *scalar == Scalar::Any,
+            FieldType::Sequence(inner) | FieldType::Nullable(inner) => self.holds_element(inner, seen),
+            FieldType::Named { module, name } => {
+                if !seen.insert((module, name)) {
+                    return false;
+                }
+                match self.find(module, name).map(|type_model| &type_model.kind) {
+                    Some(Kind::Alias(alias)) => self.holds_element(alias, seen),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    // Writes a builder for a struct, which collects its fields one at a time and checks that the
+    // required ones were given.
+    fn render_builder(&self, name: &str, fields: &[(&Field, String, String)], text: &mut String) -> Result<()> {
+        let builder = format!("{}Builder", name);
+        if self.module.types.iter().any(|type_model| type_model.name == builder) {
+            bail!("Type '{}' can't have a builder, because there's already a type named '{}'.", name, builder);
+        }
+        writeln!(text)?;
+        writeln!(text, "impl {} {{", name)?;
+        writeln!(text, "    pub fn builder() -> {} {{", builder)?;
+        writeln!(text, "        {}::default()", builder)?;
+        writeln!(text, "    }}")?;
+        writeln!(text, "}}")?;
+        writeln!(text)?;
+        writeln!(text, "#[derive(Debug, Clone, Default)]")?;
+        writeln!(text, "pub struct {} {{", builder)?;
+        for (field, field_name, field_type) in fields {
+            let builder_type = match field.cardinality {
+                Cardinality::Repeated => format!("Vec<{}>", field_type),
+                _ => format!("Option<{}>", field_type),
+            };
+            writeln!(text, "    {}: {},", field_name, builder_type)?;
+        }
+        writeln!(text, "}}")?;
+        writeln!(text)?;
+        writeln!(text, "impl {} {{", builder)?;
+        for (field, field_name, field_type) in fields {
+            match field.cardinality {
+                Cardinality::Repeated => {
+                    writeln!(text, "    pub fn {}(mut self, values: Vec<{}>) -> Self {{", field_name, field_type)?;
+                    writeln!(text, "        self.{} = values;", field_name)?;
+                }
+                _ => {
+                    writeln!(text, "    pub fn {}(mut self, value: impl Into<{}>) -> Self {{", field_name, field_type)?;
+                    writeln!(text, "        self.{} = Some(value.into());", field_name)?;
+                }
+            }
+            writeln!(text, "        self")?;
+            writeln!(text, "    }}")?;
+            writeln!(text)?;
+        }
+        writeln!(text, "    pub fn build(self) -> Result<{}, Vec<String>> {{", name)?;
+        let required: Vec<_> = fields.iter().filter(|(field, _, _)| field.cardinality == Cardinality::Required).collect();
+        if !required.is_empty() {
+            writeln!(text, "        let mut missing = Vec::new();")?;
+            for (field, field_name, _) in &required {
+                writeln!(text, "        if self.{}.is_none() {{", field_name)?;
+                writeln!(text, "            missing.push({:?}.to_owned());", format!("missing required field '{}'", field.name))?;
+                writeln!(text, "        }}")?;
+            }
+            writeln!(text, "        if !missing.is_empty() {{")?;
+            writeln!(text, "            return Err(missing);")?;
+            writeln!(text, "        }}")?;
+        }
+        writeln!(text, "        let value = {} {{", name)?;
+        for (field, field_name, _) in fields {
+            match field.cardinality {
+                Cardinality::Required => writeln!(text, "            {}: self.{}.unwrap(),", field_name, field_name)?,
+                _ => writeln!(text, "            {}: self.{},", field_name, field_name)?,
+            }
+        }
+        writeln!(text, "        }};")?;
+        if self.options.validation {
+            writeln!(text, "        value.validate()?;")?;
+        }
+        writeln!(text, "        Ok(value)")?;
+        writeln!(text, "    }}")?;
+        writeln!(text, "}}")?;
+        Ok(())
+    }
+
+    // Writes a `validate` method for a struct, which checks the constraints on its fields that
+    // their types can't express, and validates the structs and unions it holds.
+    fn render_struct_validation(&self, name: &str, fields: &[(&Field, String, String)], text: &mut String) -> Result<()> {
+        let mut body = String::new();
+        for (field, field_name, _) in fields {
+            let context = format!("field '{}' of type '{}'", field.name, name);
+            let checks: Vec<&Check> = field.checks.iter().collect();
+            let mut checks_text = String::new();
+            self.render_checks(&field.field_type, &checks, &context, 3, &mut checks_text)?;
+            if checks_text.is_empty() {
+                continue;
+            }
+            match field.cardinality {
+                Cardinality::Required => {
+                    writeln!(body, "        {{")?;
+                    writeln!(body, "            let value = &self.{};", field_name)?;
+                }
+                Cardinality::Optional => writeln!(body, "        if let Some(value) = &self.{} {{", field_name)?,
+                Cardinality::Repeated => writeln!(body, "        for (index, value) in self.{}.iter().enumerate() {{", field_name)?,
+            }
+            match field.cardinality {
+                Cardinality::Repeated => writeln!(body, "            let label = format!(\"{}[{{}}]\", index);", field.name)?,
+                _ => writeln!(body, "            let label = {:?};", field.name)?,
+            }
+            body.push_str(&checks_text);
+            writeln!(body, "        }}")?;
+        }
+        writeln!(text)?;
+        writeln!(text, "impl {} {{", name)?;
+        writeln!(text, "    // Checks the constraints that the type can't express, returning a message for each")?;
+        writeln!(text, "    // value that doesn't meet them.")?;
+        writeln!(text, "    pub fn validate(&self) -> Result<(), Vec<String>> {{")?;
+        if body.is_empty() {
+            writeln!(text, "        Ok(())")?;
+        } else {
+            writeln!(text, "        let mut errors = Vec::new();")?;
+            text.push_str(&body);
+            writeln!(text, "        if errors.is_empty() {{")?;
+            writeln!(text, "            Ok(())")?;
+            writeln!(text, "        }} else {{")?;
+            writeln!(text, "            Err(errors)")?;
+            writeln!(text, "        }}")?;
+        }
+        writeln!(text, "    }}")?;
+        writeln!(text, "}}")?;
+        Ok(())
+    }
+
+    // Writes a `validate` method for a union, which validates the struct or union it holds.
+    fn render_union_validation(&self, name: &str, variants: &[String], alternatives: &[FieldType], text: &mut String) -> Result<()> {
+        let mut arms = String::new();
+        for (variant, alternative) in variants.iter().zip(alternatives) {
+            let pattern = match alternative {
+                FieldType::Nullable(inner) if self.is_validated(inner) => "Some(value)",
+                alternative if self.is_validated(alternative) => "value",
+                _ => continue,
+            };
+            writeln!(arms, "            {}::{}({}) => value.validate(),", name, variant, pattern)?;
+        }
+        writeln!(text)?;
+        writeln!(text, "impl {} {{", name)?;
+        writeln!(text, "    // Validates the struct or union the value holds, if it holds one.")?;
+        writeln!(text, "    pub fn validate(&self) -> Result<(), Vec<String>> {{")?;
+        if arms.is_empty() {
+            writeln!(text, "        Ok(())")?;
+        } else {
+            writeln!(text, "        match self {{")?;
+            text.push_str(&arms);
+            writeln!(text, "            _ => Ok(()),")?;
+            writeln!(text, "        }}")?;
+        }
+        writeln!(text, "    }}")?;
+        writeln!(text, "}}")?;
+        Ok(())
+    }
+
+    // Writes the code that checks `value`, a reference to a value of `field_type` named by
+    // `label` in messages, against `checks`, and validates it if it's a struct or union. Checks
+    // that can't be made on a value of that type are reported, naming `context`.
+    fn render_checks(&self, field_type: &FieldType, checks: &[&Check], context: &str, indent: usize, text: &mut String) -> Result<()> {
+        let pad = "    ".repeat(indent);
+        if let FieldType::Nullable(inner) = field_type {
+            let mut inner_text = String::new();
+            self.render_checks(inner, checks, context, indent + 1, &mut inner_text)?;
+            if !inner_text.is_empty() {
+                writeln!(text, "{}if let Some(value) = value {{", pad)?;
+                text.push_str(&inner_text);
+                writeln!(text, "{}}}", pad)?;
+            }
+            return Ok(());
+        }
+        // An alias's own constraints apply wherever it's used, and the constraints on a value of
+        // an alias are checked on the type it stands for.
+        if let FieldType::Named { module, name } = field_type {
+            if let Some(type_model) = self.find(module, name).filter(|type_model| !self.is_newtype(type_model)) {
+                if let Kind::Alias(alias) = &type_model.kind {
+                    let checks: Vec<&Check> = checks.iter().copied().chain(&type_model.checks).collect();
+                    return self.render_checks(alias, &checks, context, indent, text);
+                }
+            }
+        }
+        for check in checks {
+            let condition = match violated(check, field_type)? {
+                Some(condition) => condition,
+                None => {
+                    eprintln!("Warning: validate() can't check `{}` on {}.", check.description, context);
+                    continue;
+                }
+            };
+            // A range with no bounds can't be violated.
+            if condition.is_empty() {
+                continue;
+            }
+            writeln!(text, "{}if {} {{", pad, condition)?;
+            writeln!(text, "{}    errors.push(format!(\"{{}}: {{:?}} doesn't meet {{}}\", label, value, {:?}));", pad, check.description)?;
+            writeln!(text, "{}}}", pad)?;
+        }
+        match field_type {
+            FieldType::Named { .. } if self.is_validated(field_type) => {
+                writeln!(text, "{}if let Err(nested) = value.validate() {{", pad)?;
+                writeln!(text, "{}    errors.extend(nested.into_iter().map(|error| format!(\"{{}}.{{}}\", label, error)));", pad)?;
+                writeln!(text, "{}}}", pad)?;
+            }
+            FieldType::Sequence(element) => {
+                let mut element_text = String::new();
+                self.render_checks(element, &[], context, indent + 1, &mut element_text)?;
+                if !element_text.is_empty() {
+                    writeln!(text, "{}for (index, value) in value.iter().enumerate() {{", pad)?;
+                    writeln!(text, "{}    let label = format!(\"{{}}[{{}}]\", label, index);", pad)?;
+                    text.push_str(&element_text);
+                    writeln!(text, "{}}}", pad)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Whether a type is generated with a `validate` method: structs and unions.
+    fn is_validated(&self, field_type: &FieldType) -> bool {
+        match field_type {
+            FieldType::Named { module, name } => {
+                matches!(self.find(module, name).map(|type_model| &type_model.kind), Some(Kind::Struct(_) | Kind::Union(_)))
+            }
+            _ => false,
+        }
+    }
+
+    // Whether an alias is generated as a struct wrapping its type, because it refers to itself.
+    fn is_newtype(&self, type_model: &TypeModel) -> bool {
+        match &type_model.kind {
+            Kind::Alias(alias) => self.refers_to(alias, &type_model.name, &mut HashSet::new()),
+            _ => false,
+        }
+    }
+
+    // The Rust type for a field of the type `owner`. `in_vec` says whether it's held in a Vec,
+    // which can hold the owner without boxing it.
+    fn field_type(&self, owner: &str, field_type: &FieldType, in_vec: bool) -> String {
+        match field_type {
+            FieldType::Scalar(scalar) => scalar_type(*scalar).to_owned(),
+            FieldType::Named { module, name } => {
+                let path = if *module == self.module.name {
+                    name.clone()
+                } else {
+                    format!("super::{}::{}", identifier(module), name)
+                };
+                // The owner would contain itself if this type contains the owner.
+                if !in_vec && self.contains(module, name, owner) {
+                    format!("Box<{}>", path)
+                } else {
+                    path
+                }
+            }
+            FieldType::Sequence(element) => format!("Vec<{}>", self.field_type(owner, element, true)),
+            FieldType::Nullable(inner) => format!("Option<{}>", self.field_type(owner, inner, in_vec)),
+        }
+    }
+
+    // Whether the type `name` in `module` contains a value of the type `target` in this module,
+    // directly or through other types, without a Vec in between.
+    fn contains(&self, module: &str, name: &str, target: &str) -> bool {
+        let mut seen = HashSet::new();
+        self.contains_from(module, name, target, &mut seen)
+    }
+
+    fn contains_from<'m>(&'m self, module: &'m str, name: &'m str, target: &str, seen: &mut HashSet<(&'m str, &'m str)>) -> bool {
+        if module == self.module.name && name == target {
+            return true;
+        }
+        if !seen.insert((module, name)) {
+            return false;
+        }
+        let type_model = match self.find(module, name) {
+            Some(type_model) => type_model,
+            None => return false,
+        };
+        let mut contained = Vec::new();
+        match &type_model.kind {
+            Kind::Struct(fields) => {
+                for field in fields.iter().filter(|field| field.cardinality != Cardinality::Repeated) {
+                    contained_by_value(&field.field_type, &mut contained);
+                }
+            }
+            Kind::Union(alternatives) => alternatives.iter().for_each(|alternative| contained_by_value(alternative, &mut contained)),
+            Kind::Alias(alias) => contained_by_value(alias, &mut contained),
+        }
+        contained.into_iter().any(|(module, name)| self.contains_from(module, name, target, seen))
+    }
+
+    // Whether `field_type` refers to the alias `alias` in this module, directly or through other
+    // aliases.
+    fn refers_to<'m>(&'m self, field_type: &'m FieldType, alias: &str, seen: &mut HashSet<(&'m str, &'m str)>) -> bool {
+        match field_type {
+            FieldType::Scalar(_) => false,
+            FieldType::Sequence(inner) | FieldType::Nullable(inner) => self.refers_to(inner, alias, seen),
+            FieldType::Named { module, name } if *module == self.module.name && name == alias => true,
+            FieldType::Named { module, name } => {
+                if !seen.insert((module, name)) {
+                    return false;
+                }
+                match self.find(module, name).map(|type_model| &type_model.kind) {
+                    Some(Kind::Alias(inner)) => self.refers_to(inner, alias, seen),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn find(&self, module: &str, name: &str) -> Option<&TypeModel> {
+        self.modules
+            .iter()
+            .find(|candidate| candidate.name == module)
+            .and_then(|module| module.types.iter().find(|type_model| type_model.name == name))
+    }
+}
+
+// Adds the generated types that a value of `field_type` holds directly, rather than in a Vec.
+fn contained_by_value<'m>(field_type: &'m FieldType, contained: &mut Vec<(&'m str, &'m str)>) {
+    match field_type {
+        FieldType::Named { module, name } => contained.push((module, name)),
+        FieldType::Nullable(inner) => contained_by_value(inner, contained),
+        FieldType::Scalar(_) | FieldType::Sequence(_) => {}
+    }
+}
+
+fn with_cardinality(cardinality: Cardinality, field_type: &str) -> String {
+    match cardinality {
+        Cardinality::Required => field_type.to_owned(),
+        Cardinality::Optional => format!("Option<{}>", field_type),
+        Cardinality::Repeated => format!("Vec<{}>", field_type),
+    }
+}
+
+// The condition under which `value`, a reference to a value of `field_type`, violates `check`: an
+// empty string if it can't, or `None` if the check can't be made on that type.
+fn violated(check: &Check, field_type: &FieldType) -> Result<Option<String>> {
+    let scalar = match field_type {
+        FieldType::Scalar(scalar) => Some(*scalar),
+        _ => None,
+    };
+    let text = matches!(scalar, Some(Scalar::String | Scalar::Symbol));
+    let condition = match &check.kind {
+        CheckKind::Range { lower, upper } => {
+            let mut conditions = Vec::new();
+            for (bound, below) in [(lower, true), (upper, false)].iter() {
+                let (value, inclusive) = match bound {
+                    Some(bound) => bound,
+                    None => continue,
+                };
+                let operator = match (below, inclusive) {
+                    (true, true) => "<",
+                    (true, false) => "<=",
+                    (false, true) => ">",
+                    (false, false) => ">=",
+                };
+                let condition = match (scalar, &value.data) {
+                    (Some(Scalar::Int), Data::Integer(bound)) => format!("*value {} {}", operator, bound),
+                    (Some(Scalar::Int), _) => match float_literal(value) {
+                        Some(bound) => format!("(*value as f64) {} {}", operator, bound),
+                        None => return Ok(None),
+                    },
+                    (Some(Scalar::Float), _) => match float_literal(value) {
+                        Some(bound) => format!("*value {} {}", operator, bound),
+                        None => return Ok(None),
+                    },
+                    _ => return Ok(None),
+                };
+                conditions.push(condition);
+            }
+            conditions.join(" || ")
+        }
+        CheckKind::Values(values) => {
+            let literals: Option<Vec<String>> = values
+                .iter()
+                .map(|value| match (scalar, &value.data) {
+                    (Some(Scalar::String | Scalar::Symbol), Data::String(text)) => Some(format!("{:?}", text)),
+                    (Some(Scalar::String | Scalar::Symbol), Data::Symbol(symbol)) => Some(format!("{:?}", symbol.text()?)),
+                    (Some(Scalar::Int), Data::Integer(integer)) => Some(integer.to_string()),
+                    (Some(Scalar::Bool), Data::Boolean(boolean)) => Some(boolean.to_string()),
+                    _ => None,
+                })
+                .collect();
+            match literals {
+                Some(literals) if text => format!("![{}].contains(&value.as_str())", literals.join(", ")),
+                Some(literals) if !literals.is_empty() => format!("![{}].contains(value)", literals.join(", ")),
+                _ => return Ok(None),
+            }
+        }
+        CheckKind::Regex(pattern) if text => {
+            if let Err(error) = regex::Regex::new(pattern) {
+                bail!("The constraint `{}` can't be checked in Rust: {}", check.description, error);
+            }
+            format!("!regex::Regex::new({:?}).expect(\"The schema's regex is valid.\").is_match(value)", pattern)
+        }
+        CheckKind::Regex(_) => return Ok(None),
+        CheckKind::Length { constraint, min, max } => {
+            let length = match (constraint.as_str(), field_type) {
+                ("codepoint_length", _) if text => "value.chars().count()",
+                ("utf8_byte_length", _) if text => "value.len()",
+                ("byte_length", FieldType::Scalar(Scalar::Blob | Scalar::Clob)) => "value.len()",
+                ("container_length", FieldType::Sequence(_)) => "value.len()",
+                _ => return Ok(None),
+            };
+            let mut conditions = Vec::new();
+            if *min > 0 {
+                conditions.push(format!("{} < {}", length, min));
+            }
+            if let Some(max) = max {
+                conditions.push(format!("{} > {}", length, max));
+            }
+            conditions.join(" || ")
+        }
+    };
+    Ok(Some(condition))
+}
+
+// A number as an f64 literal, if it is one.
+fn float_literal(value: &Value) -> Option<String> {
+    let number = match &value.data {
+        Data::Integer(integer) => *integer as f64,
+        Data::Float(float) if float.is_finite() => *float,
+        Data::Decimal(decimal) => decimal.to_string().parse().ok()?,
+        _ => return None,
+    };
+    Some(format!("{:?}", number))
+}
+
+fn scalar_type(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Bool => "bool",
+        Scalar::Int => "i64",
+        Scalar::Float => "f64",
+        Scalar::Decimal => "ion_rs::Decimal",
+        Scalar::Timestamp => "ion_rs::Timestamp",
+        Scalar::String | Scalar::Symbol => "String",
+        Scalar::Blob | Scalar::Clob => "Vec<u8>",
+        Scalar::Any => "ion_rs::Element",
+    }
+}
+
+// Names an enum's variants after the types they hold, numbering any that would be the same.
+pub fn variant_names(alternatives: &[FieldType]) -> Vec<String> {
+    let names: Vec<String> = alternatives.iter().map(variant_name).collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if names.iter().filter(|other| *other == name).count() > 1 {
+                format!("{}{}", name, index + 1)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+fn variant_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Scalar(scalar) => format!("{:?}", scalar),
+        FieldType::Named { name, .. } => name.clone(),
+        FieldType::Sequence(_) => "List".to_owned(),
+        FieldType::Nullable(inner) => variant_name(inner),
+    }
+}
+
+// A name that can be used as an identifier, escaping it if it's a keyword.
+fn identifier(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_owned()
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use super::GENERATED_HEADER;
+use super::model::{Cardinality, Field, FieldType, Kind, Module, Scalar, TypeModel};
+use super::rust::variant_names;
+
+// Generates TypeScript for ion-js: a file for each module, with an interface or type for each
+// type and functions to decode it from an `ion.dom.Value` and encode it with an `ion.Writer`, a
+// `ion_codec.ts` with the functions they share, and an `index.ts` re-exporting the modules.
+
+// The module holding the functions the generated modules share.
+const CODEC: &str = "ion_codec";
+// Names the generated modules use for what they import.
+const IMPORTS: &[&str] = &["ion", "codec", "index", CODEC];
+
+const KEYWORDS: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do", "else",
+    "enum", "export", "extends", "false", "finally", "for", "function", "if", "import", "in", "instanceof",
+    "new", "null", "return", "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void",
+    "while", "with", "implements", "interface", "let", "package", "private", "protected", "public",
+    "static", "yield", "await",
+];
+
+const CODEC_TS: &str = r#"import * as ion from "ion-js";
+
+export type Decode<T> = (value: ion.dom.Value | null) => T;
+export type Encode<T> = (writer: ion.Writer, value: T) => void;
+
+export function expect(value: ion.dom.Value | null, types: ion.IonType[], expected: string): ion.dom.Value {
+  if (value === null || value.isNull() || !types.includes(value.getType())) {
+    const found = value === null ? "nothing" : ion.dumpText(value);
+    throw new Error(`Expected ${expected}, but found ${found}.`);
+  }
+  return value;
+}
+
+export function field(struct: ion.dom.Value, name: string): ion.dom.Value | null {
+  const values = struct.getAll(name) ?? [];
+  if (values.length > 1) {
+    throw new Error(`Expected one field named '${name}', but found ${values.length}.`);
+  }
+  return values.length === 0 ? null : values[0];
+}
+
+export function decodeRequired<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T {
+  const value = field(struct, name);
+  if (value === null) {
+    throw new Error(`Missing required field '${name}'.`);
+  }
+  return decode(value);
+}
+
+export function decodeOptional<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T | undefined {
+  const value = field(struct, name);
+  return value === null ? undefined : decode(value);
+}
+
+export function decodeRepeated<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T[] {
+  return (struct.getAll(name) ?? []).map(decode);
+}
+
+export function decodeNullable<T>(value: ion.dom.Value | null, decode: Decode<T>): T | null {
+  return value !== null && value.isNull() ? null : decode(value);
+}
+
+export function decodeList<T>(value: ion.dom.Value | null, decode: Decode<T>): T[] {
+  return expect(value, [ion.IonTypes.LIST, ion.IonTypes.SEXP], "a list").elements().map(decode);
+}
+
+// Decodes a union as the first alternative that the value matches.
+export function decodeUnion<T>(value: ion.dom.Value | null, name: string, alternatives: Decode<T>[]): T {
+  for (const alternative of alternatives) {
+    try {
+      return alternative(value);
+    } catch {
+      // Try the next alternative.
+    }
+  }
+  const found = value === null ? "nothing" : ion.dumpText(value);
+  throw new Error(`Expected a ${name}, but found ${found}.`);
+}
+
+export const decodeBool: Decode<boolean> = (value) => expect(value, [ion.IonTypes.BOOL], "a bool").booleanValue()!;
+export const decodeInt: Decode<number> = (value) => expect(value, [ion.IonTypes.INT], "an int").numberValue()!;
+export const decodeFloat: Decode<number> = (value) => expect(value, [ion.IonTypes.FLOAT], "a float").numberValue()!;
+export const decodeDecimal: Decode<ion.Decimal> = (value) =>
+  expect(value, [ion.IonTypes.DECIMAL], "a decimal").decimalValue()!;
+export const decodeTimestamp: Decode<ion.Timestamp> = (value) =>
+  expect(value, [ion.IonTypes.TIMESTAMP], "a timestamp").timestampValue()!;
+export const decodeString: Decode<string> = (value) => expect(value, [ion.IonTypes.STRING], "a string").stringValue()!;
+export const decodeSymbol: Decode<string> = (value) => expect(value, [ion.IonTypes.SYMBOL], "a symbol").stringValue()!;
+export const decodeBlob: Decode<Uint8Array> = (value) => expect(value, [ion.IonTypes.BLOB], "a blob").uInt8ArrayValue()!;
+export const decodeClob: Decode<Uint8Array> = (value) => expect(value, [ion.IonTypes.CLOB], "a clob").uInt8ArrayValue()!;
+export const decodeAny: Decode<ion.dom.Value> = (value) => {
+  if (value === null) {
+    throw new Error("Expected a value, but found nothing.");
+  }
+  return value;
+};
+
+export function encodeNullable<T>(writer: ion.Writer, value: T | null, encode: Encode<T>): void {
+  if (value === null) {
+    writer.writeNull(ion.IonTypes.NULL);
+  } else {
+    encode(writer, value);
+  }
+}
+
+export function encodeList<T>(writer: ion.Writer, values: T[], encode: Encode<T>): void {
+  writer.stepIn(ion.IonTypes.LIST);
+  for (const value of values) {
+    encode(writer, value);
+  }
+  writer.stepOut();
+}
+
+export const encodeBool: Encode<boolean> = (writer, value) => writer.writeBoolean(value);
+export const encodeInt: Encode<number> = (writer, value) => writer.writeInt(value);
+export const encodeFloat: Encode<number> = (writer, value) => writer.writeFloat64(value);
+export const encodeDecimal: Encode<ion.Decimal> = (writer, value) => writer.writeDecimal(value);
+export const encodeTimestamp: Encode<ion.Timestamp> = (writer, value) => writer.writeTimestamp(value);
+export const encodeString: Encode<string> = (writer, value) => writer.writeString(value);
+export const encodeSymbol: Encode<string> = (writer, value) => writer.writeSymbol(value);
+export const encodeBlob: Encode<Uint8Array> = (writer, value) => writer.writeBlob(value);
+export const encodeClob: Encode<Uint8Array> = (writer, value) => writer.writeClob(value);
+export const encodeAny: Encode<ion.dom.Value> = (writer, value) => value.writeTo(writer);
+"#;
+
+// Returns the name and contents of each file to write.
+pub fn render(modules: &[Module]) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::with_capacity(modules.len() + 2);
+    let mut index_ts = header(&format!("from schema '{}' and the schemas it refers to", modules[0].schema_id));
+    for module in modules {
+        if IMPORTS.contains(&module.name.as_str()) || KEYWORDS.contains(&module.name.as_str()) {
+            bail!("Schema '{}' would generate a module named '{}', which TypeScript can't use.", module.schema_id, module.name);
+        }
+        writeln!(index_ts, "export * as {} from \"./{}\";", module.name, module.name)?;
+        files.push((format!("{}.ts", module.name), TypeScriptModule { module }.render()?));
+    }
+    files.push((format!("{}.ts", CODEC), header("for the modules beside it") + CODEC_TS));
+    files.push(("index.ts".to_owned(), index_ts));
+    Ok(files)
+}
+
+fn header(source: &str) -> String {
+    format!(
+        "{} {}. Don't edit it by hand; change the\n\
+         // schema and generate it again.\n\n",
+        GENERATED_HEADER, source
+    )
+}
+
+struct TypeScriptModule<'a> {
+    module: &'a Module,
+}
+
+impl TypeScriptModule<'_> {
+    fn render(&self) -> Result<String> {
+        let mut text = header(&format!("from schema '{}'", self.module.schema_id));
+        writeln!(text, "import * as ion from \"ion-js\";")?;
+        writeln!(text, "import * as codec from \"./{}\";", CODEC)?;
+        for module in self.imports() {
+            writeln!(text, "import * as {} from \"./{}\";", module, module)?;
+        }
+        for type_model in &self.module.types {
+            text.push('\n');
+            self.render_type(type_model, &mut text)?;
+        }
+        Ok(text)
+    }
+
+    // The other modules whose types this one refers to.
+    fn imports(&self) -> BTreeSet<&str> {
+        fn add<'m>(field_type: &'m FieldType, own: &str, imports: &mut BTreeSet<&'m str>) {
+            match field_type {
+                FieldType::Named { module, .. } if module != own => {
+                    imports.insert(module);
+                }
+                FieldType::Sequence(inner) | FieldType::Nullable(inner) => add(inner, own, imports),
+                _ => {}
+            }
+        }
+        let mut imports = BTreeSet::new();
+        for type_model in &self.module.types {
+            match &type_model.kind {
+                Kind::Struct(fields) => fields.iter().for_each(|field| add(&field.field_type, &self.module.name, &mut imports)),
+                Kind::Union(alternatives) => alternatives.iter().for_each(|alternative| add(alternative, &self.module.name, &mut imports)),
+                Kind::Alias(alias) => add(alias, &self.module.name, &mut imports),
+            }
+        }
+        imports
+    }
+
+    fn render_type(&self, type_model: &TypeModel, text: &mut String) -> Result<()> {
+        let name = &type_model.name;
+        match &type_model.kind {
+            Kind::Struct(fields) => {
+                let mut field_names = BTreeSet::new();
+                for field in fields {
+                    if !field_names.insert(field.name.as_str()) {
+                        bail!("Type '{}' would have more than one field named '{}'.", name, field.name);
+                    }
+                }
+                writeln!(text, "export interface {} {{", name)?;
+                for field in fields {
+                    let field_type = self.type_name(&field.field_type);
+                    match field.cardinality {
+                        Cardinality::Required => writeln!(text, "  {}: {};", property(&field.name), field_type)?,
+                        Cardinality::Optional => writeln!(text, "  {}?: {};", property(&field.name), field_type)?,
+                        Cardinality::Repeated => writeln!(text, "  {}: {};", property(&field.name), array(&field_type))?,
+                    }
+                }
+                writeln!(text, "}}")?;
+                self.render_struct_codec(name, fields, text)?;
+            }
+            Kind::Union(alternatives) => {
+                let variants = variant_names(alternatives);
+                writeln!(text, "export type {} =", name)?;
+                for (index, (variant, alternative)) in variants.iter().zip(alternatives).enumerate() {
+                    let end = if index + 1 == variants.len() { ";" } else { "" };
+                    writeln!(text, "  | {{ kind: {:?}; value: {} }}{}", variant, self.type_name(alternative), end)?;
+                }
+                writeln!(text)?;
+                writeln!(text, "export function decode{}(value: ion.dom.Value | null): {} {{", name, name)?;
+                writeln!(text, "  return codec.decodeUnion<{}>(value, {:?}, [", name, name)?;
+                for (variant, alternative) in variants.iter().zip(alternatives) {
+                    writeln!(text, "    (value) => ({{ kind: {:?}, value: {}(value) }}),", variant, self.decoder(alternative))?;
+                }
+                writeln!(text, "  ]);")?;
+                writeln!(text, "}}")?;
+                writeln!(text)?;
+                writeln!(text, "export function encode{}(writer: ion.Writer, value: {}): void {{", name, name)?;
+                writeln!(text, "  switch (value.kind) {{")?;
+                for (variant, alternative) in variants.iter().zip(alternatives) {
+                    writeln!(text, "    case {:?}:", variant)?;
+                    writeln!(text, "      {}(writer, value.value);", self.encoder(alternative))?;
+                    writeln!(text, "      break;")?;
+                }
+                writeln!(text, "  }}")?;
+                writeln!(text, "}}")?;
+            }
+            Kind::Alias(alias) => {
+                writeln!(text, "export type {} = {};", name, self.type_name(alias))?;
+                writeln!(text)?;
+                writeln!(text, "export function decode{}(value: ion.dom.Value | null): {} {{", name, name)?;
+                writeln!(text, "  return {}(value);", self.decoder(alias))?;
+                writeln!(text, "}}")?;
+                writeln!(text)?;
+                writeln!(text, "export function encode{}(writer: ion.Writer, value: {}): void {{", name, name)?;
+                writeln!(text, "  {}(writer, value);", self.encoder(alias))?;
+                writeln!(text, "}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render_struct_codec(&self, name: &str, fields: &[Field], text: &mut String) -> Result<()> {
+        writeln!(text)?;
+        writeln!(text, "export function decode{}(value: ion.dom.Value | null): {} {{", name, name)?;
+        writeln!(text, "  const struct = codec.expect(value, [ion.IonTypes.STRUCT], {:?});", format!("a {}", name))?;
+        writeln!(text, "  return {{")?;
+        for field in fields {
+            let function = match field.cardinality {
+                Cardinality::Required => "decodeRequired",
+                Cardinality::Optional => "decodeOptional",
+                Cardinality::Repeated => "decodeRepeated",
+            };
+            let decoder = self.decoder(&field.field_type);
+            writeln!(text, "    {}: codec.{}(struct, {:?}, {}),", property(&field.name), function, field.name, decoder)?;
+        }
+        writeln!(text, "  }};")?;
+        writeln!(text, "}}")?;
+        writeln!(text)?;
+        writeln!(text, "export function encode{}(writer: ion.Writer, value: {}): void {{", name, name)?;
+        writeln!(text, "  writer.stepIn(ion.IonTypes.STRUCT);")?;
+        for field in fields {
+            let access = access("value", &field.name);
+            let encoder = self.encoder(&field.field_type);
+            match field.cardinality {
+                Cardinality::Required => {
+                    writeln!(text, "  writer.writeFieldName({:?});", field.name)?;
+                    writeln!(text, "  {}(writer, {});", encoder, access)?;
+                }
+                Cardinality::Optional => {
+                    writeln!(text, "  if ({} !== undefined) {{", access)?;
+                    writeln!(text, "    writer.writeFieldName({:?});", field.name)?;
+                    writeln!(text, "    {}(writer, {});", encoder, access)?;
+                    writeln!(text, "  }}")?;
+                }
+                Cardinality::Repeated => {
+                    writeln!(text, "  for (const element of {}) {{", access)?;
+                    writeln!(text, "    writer.writeFieldName({:?});", field.name)?;
+                    writeln!(text, "    {}(writer, element);", encoder)?;
+                    writeln!(text, "  }}")?;
+                }
+            }
+        }
+        writeln!(text, "  writer.stepOut();")?;
+        writeln!(text, "}}")?;
+        Ok(())
+    }
+
+    // The TypeScript type for a field type.
+    fn type_name(&self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Scalar(scalar) => scalar_type(*scalar).to_owned(),
+            FieldType::Named { module, name } => self.qualified(module, name, ""),
+            FieldType::Sequence(element) => array(&self.type_name(element)),
+            FieldType::Nullable(inner) => format!("{} | null", self.type_name(inner)),
+        }
+    }
+
+    // An expression for the function decoding a field type.
+    fn decoder(&self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Scalar(scalar) => format!("codec.decode{:?}", scalar),
+            FieldType::Named { module, name } => self.qualified(module, name, "decode"),
+            FieldType::Sequence(element) => {
+                format!("(value: ion.dom.Value | null) => codec.decodeList(value, {})", self.decoder(element))
+            }
+            FieldType::Nullable(inner) => {
+                format!("(value: ion.dom.Value | null) => codec.decodeNullable(value, {})", self.decoder(inner))
+            }
+        }
+    }
+
+    // An expression for the function encoding a field type.
+    fn encoder(&self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Scalar(scalar) => format!("codec.encode{:?}", scalar),
+            FieldType::Named { module, name } => self.qualified(module, name, "encode"),
+            FieldType::Sequence(element) => format!(
+                "(writer: ion.Writer, values: {}) => codec.encodeList(writer, values, {})",
+                array(&self.type_name(element)),
+                self.encoder(element)
+            ),
+            FieldType::Nullable(inner) => format!(
+                "(writer: ion.Writer, value: {} | null) => codec.encodeNullable(writer, value, {})",
+                self.type_name(inner),
+                self.encoder(inner)
+            ),
+        }
+    }
+
+    // A generated type or function, qualified by its module if it's in another one.
+    fn qualified(&self, module: &str, name: &str, prefix: &str) -> String {
+        if module == self.module.name {
+            format!("{}{}", prefix, name)
+        } else {
+            format!("{}.{}{}", module, prefix, name)
+        }
+    }
+}
+
+fn scalar_type(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Bool => "boolean",
+        Scalar::Int | Scalar::Float => "number",
+        Scalar::Decimal => "ion.Decimal",
+        Scalar::Timestamp => "ion.Timestamp",
+        Scalar::String | Scalar::Symbol => "string",
+        Scalar::Blob | Scalar::Clob => "Uint8Array",
+        Scalar::Any => "ion.dom.Value",
+    }
+}
+
+// An array of a type, parenthesizing a union like `T | null`.
+fn array(element: &str) -> String {
+    if element.contains('|') {
+        format!("({})[]", element)
+    } else {
+        format!("{}[]", element)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+// A field's name as an interface property, quoted if it isn't an identifier.
+fn property(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+// An expression reading a field from an object.
+fn access(object: &str, name: &str) -> String {
+    if is_identifier(name) {
+        format!("{}.{}", object, name)
+    } else {
+        format!("{}[{:?}]", object, name)
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use memmap::MmapOptions;
+
+use crate::binary::encoded_length;
+use crate::commands::beta::inspect::offsets::Entry;
+use crate::commands::CommandConfig;
+use crate::input::{reader_for, IonInput, IVM};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{read_projection, Path, Step};
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Reads a single value from a large binary Ion file using an offset index.";
+
+pub fn app() -> CommandConfig {
+    App::new("get")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("index")
+                .long("index")
+                .short("x")
+                .takes_value(true)
+                .required(true)
+                .help("Index written by `inspect --only-offsets` for the input file"),
+        )
+        .arg(
+            Arg::with_name("value")
+                .long("value")
+                .short("v")
+                .takes_value(true)
+                .required(true)
+                .help("Position of the top-level value to read, counting from 0"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .help("With an index built using --path, which of the value's indexed values to read, e.g. '(orders 3)'"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(
+            Arg::with_name("input")
+                .index(1)
+                .required(true)
+                .help("Binary Ion file that the index describes"),
+        )
+        .after_help(
+            "Looks up the value in the index, then reads only the bytes it needs from
+the input: the local symbol tables that define the value's symbols,
+followed by the value itself. However large the input is, the value is
+read without scanning the values before it.
+
+Create the index with
+  ion beta inspect --only-offsets data.10n -o data.idx
+and read values with
+  ion beta get --index data.idx --value 123456 data.10n
+If the index was built with --path, there may be several entries for
+each top-level value; --path chooses one by its path, and otherwise the
+first is read. An index that doesn't match the input is reported as an
+error, but only as far as the value's offset and length can be checked."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --index, --value, and the input are required, so we can unwrap these safely.
+    let index_file_name = matches.value_of("index").unwrap();
+    let input_file_name = matches.value_of("input").unwrap();
+    let value_arg = matches.value_of("value").unwrap();
+    let value_index = usize::from_str(value_arg)
+        .with_context(|| format!("Invalid value for '--value': '{}'", value_arg))?;
+    let path = match matches.value_of("path") {
+        Some(text) => Some(Path::from_str(text).with_context(|| format!("Invalid --path '{}'", text))?),
+        None => None,
+    };
+
+    let entry = find_entry(index_file_name, value_index, path.as_ref())?;
+
+    let file = File::open(input_file_name)
+        .with_context(|| format!("Could not open '{}'", input_file_name))?;
+    // mmap involves operating system interactions that inherently place its usage outside of Rust's
+    // safety guarantees. If the file is unexpectedly truncated while it's being read, for example,
+    // problems could arise.
+    let bytes = unsafe {
+        MmapOptions::new().map(&file)
+            .with_context(|| format!("Could not mmap '{}'", input_file_name))?
+    };
+    if !bytes.starts_with(&IVM) {
+        bail!("'{}' is not binary Ion.", input_file_name);
+    }
+
+    // Only the pages holding these values are read from the file.
+    let mut stream = IVM.to_vec();
+    for offset in &entry.symbol_tables {
+        stream.extend_from_slice(value_at(&bytes, *offset, None, input_file_name)?);
+    }
+    stream.extend_from_slice(value_at(&bytes, entry.offset, Some(entry.length), input_file_name)?);
+
+    let mut reader = reader_for(&stream);
+    if reader.next()?.is_none() {
+        bail!("The index entry for value {} doesn't point to a value in '{}'.", value_index, input_file_name);
+    }
+    let value = Value::read(&mut reader)
+        .with_context(|| format!("Could not read value {} from '{}'", value_index, input_file_name))?;
+    let mut output = IonOutput::from_matches(matches)?;
+    output.write_value(&value)?;
+    output.finish()
+}
+
+// Finds the entry describing the requested value and reads the whole of it.
+fn find_entry(index_file_name: &str, value_index: usize, path: Option<&Path>) -> Result<Entry> {
+    let position = match entry_position(index_file_name, value_index, path)? {
+        Some(position) => position,
+        None => match path {
+            Some(path) => bail!("'{}' has no entry for value {} at path {}.", index_file_name, value_index, path),
+            None => bail!("'{}' has no entry for value {}.", index_file_name, value_index),
+        },
+    };
+    // The entries before it are skipped over without being read.
+    let index = IonInput::open(index_file_name)?;
+    let mut reader = index.reader();
+    for _ in 0..=position {
+        reader.next()?;
+    }
+    let value = Value::read(&mut reader)
+        .with_context(|| format!("Could not read an entry from '{}'", index_file_name))?;
+    Entry::from_value(&value)
+        .with_context(|| format!("'{}' is not an index written by `inspect --only-offsets`", index_file_name))
+}
+
+// Scans the index for the position of the entry describing the requested value. Only each entry's
+// `value` and `path` fields are read; in a binary index, the rest of each entry, including its
+// list of symbol table offsets, is skipped without being decoded.
+fn entry_position(index_file_name: &str, value_index: usize, path: Option<&Path>) -> Result<Option<usize>> {
+    let fields: Vec<Path> = ["value", "path"]
+        .iter()
+        .map(|name| Path::from(vec![Step::Field(name.to_string())]))
+        .collect();
+    let projection: Vec<&Path> = fields.iter().collect();
+    let index = IonInput::open(index_file_name)?;
+    let mut reader = index.reader();
+    let mut position = 0;
+    while reader.next()?.is_some() {
+        let value = read_projection(&mut reader, &projection)
+            .with_context(|| format!("Could not read an entry from '{}'", index_file_name))?;
+        let entry_value = match value.get("value").map(|field| &field.data) {
+            Some(Data::Integer(number)) if *number >= 0 => *number as usize,
+            _ => bail!(
+                "'{}' is not an index written by `inspect --only-offsets`: entry {} has no valid 'value' field.",
+                index_file_name, position
+            ),
+        };
+        if entry_value == value_index {
+            let entry_path = match value.get("path").and_then(Value::as_text) {
+                Some(text) => Some(Path::from_str(text).with_context(|| {
+                    format!("'{}' is not an index written by `inspect --only-offsets`", index_file_name)
+                })?),
+                None => None,
+            };
+            if path.is_none() || entry_path.as_ref() == path {
+                return Ok(Some(position));
+            }
+        }
+        // Entries are written in order, so the value isn't in the index.
+        if entry_value > value_index {
+            break;
+        }
+        position += 1;
+    }
+    Ok(None)
+}
+
+// Returns the encoding of the value at `offset`, checking it against the length in the index if
+// there is one.
+fn value_at<'a>(bytes: &'a [u8], offset: usize, length: Option<usize>, file_name: &str) -> Result<&'a [u8]> {
+    let encoded = bytes.get(offset..).and_then(encoded_length);
+    match (encoded, length) {
+        (Some(encoded), Some(length)) if encoded != length => bail!(
+            "The index says the value at offset {} in '{}' is {} bytes long, but it's {}; is it the index for a different file?",
+            offset, file_name, length, encoded
+        ),
+        (Some(encoded), _) => Ok(&bytes[offset..offset + encoded]),
+        (None, _) => bail!(
+            "The index refers to offset {}, but '{}' has no valid value there; is it the index for a different file?",
+            offset, file_name
+        ),
+    }
+}
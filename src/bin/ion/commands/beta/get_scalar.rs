@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::{exit_status, CommandConfig};
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::output_writer;
+use crate::path::Path;
+use crate::value::{Data, TextFormatter, Value};
+
+const ABOUT: &str = "Prints the scalar at a path, unquoted, for use in shell scripts.";
+
+pub fn app() -> CommandConfig {
+    App::new("get-scalar")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the scalar within the top-level values, e.g. '(config db host)'"),
+        )
+        .arg(
+            Arg::with_name("quote")
+                .long("quote")
+                .short("q")
+                .help("Print strings and symbols as Ion text, quoted and escaped"),
+        )
+        .arg(
+            Arg::with_name("default")
+                .long("default")
+                .short("d")
+                .takes_value(true)
+                .help("Text to print, successfully, if the path selects nothing"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Looks for the path in each top-level value of the inputs in turn, and
+prints the first scalar it selects followed by a newline:
+  host=$(ion beta get-scalar --path '(config db host)' app.ion)
+Strings and symbols are printed as their text, without quotes or escapes,
+unless --quote is given. Blobs are printed as base64 and clobs as their
+bytes. Other scalars, including nulls, are printed as Ion text without
+their annotations, e.g. 2021-06-01T, 1.50, or null.string.
+
+The exit status is 0 if a scalar was printed, 1 if the path selects
+nothing and there's no --default, and 2 if something went wrong, such as
+an unreadable input or a path that selects a container:
+  if ion beta get-scalar --path '(features beta)' app.ion > /dev/null; then ..."
+        )
+}
+
+// Always exits with a status describing the outcome, so that scripts can tell a missing value
+// from an error.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    exit_status(get_scalar(matches))
+}
+
+// Prints the scalar, or the default, and returns whether either was printed.
+fn get_scalar(matches: &ArgMatches<'static>) -> Result<bool> {
+    // --path is required, so we can unwrap it safely.
+    let path_text = matches.value_of("path").unwrap();
+    let path = Path::from_str(path_text).with_context(|| format!("Invalid --path '{}'", path_text))?;
+    let quote = matches.is_present("quote");
+
+    let mut text = None;
+    'inputs: for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            if let Some(scalar) = path.select(&value).first() {
+                text = Some(scalar_text(scalar, quote)
+                    .with_context(|| format!("Could not print the value at {} in '{}'", path, input.name()))?);
+                break 'inputs;
+            }
+        }
+    }
+    let text = match (text, matches.value_of("default")) {
+        (Some(text), _) => text,
+        (None, Some(default)) => default.to_owned(),
+        (None, None) => return Ok(false),
+    };
+
+    let mut sink = output_writer(None)?;
+    writeln!(sink, "{}", text).with_context(|| "Failed to write to the output.")?;
+    sink.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(true)
+}
+
+// Returns the text to print for a scalar.
+fn scalar_text(value: &Value, quote: bool) -> Result<String> {
+    let text = match &value.data {
+        Data::List(_) | Data::SExpression(_) | Data::Struct(_) => {
+            bail!("It's a {:?}, not a scalar.", value.ion_type())
+        }
+        Data::String(_) | Data::Symbol(_) if !quote => match value.as_text() {
+            Some(text) => text.to_owned(),
+            None => bail!("The symbol has no text."),
+        },
+        Data::Blob(bytes) => base64::encode(bytes),
+        Data::Clob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        data => {
+            let mut text = String::new();
+            TextFormatter::new().format(&Value::new(data.clone()), &mut text)?;
+            text
+        }
+    };
+    Ok(text)
+}
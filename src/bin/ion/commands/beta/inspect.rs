@@ -3,7 +3,6 @@ use std::cmp::min;
 use std::fmt::{Display, Write};
 use std::fs::File;
 use std::io;
-use std::io::BufWriter;
 use std::ops::Range;
 use std::rc::Rc;
 use std::str::{from_utf8_unchecked, FromStr};
@@ -16,6 +15,16 @@ use ion_rs::result::IonResult;
 use ion_rs::text::writer::TextWriter;
 use memmap::MmapOptions;
 
+use crate::binary::is_version_marker;
+use crate::input::{copy_stdin, display_name, input_arg, input_names, listed_inputs, IVM, STDIN_NAME};
+use crate::output::output_writer;
+use crate::path::Path;
+
+mod compare;
+mod find;
+pub mod offsets;
+mod stats;
+
 const ABOUT: &str = "Displays hex-encoded binary Ion alongside its equivalent text for human-friendly debugging.";
 
 // Creates a `clap` (Command Line Arguments Parser) configuration for the `inspect` command.
@@ -31,16 +40,7 @@ pub fn app() -> App<'static, 'static> {
                 .takes_value(true)
                 .help("Output file [default: STDOUT]"),
         )
-        .arg(
-            // Any number of input files can be specified by repeating the "-i" or "--input" flags.
-            // Unlabeled positional arguments will also be considered input file names.
-            Arg::with_name("input")
-                .long("input")
-                .short("i")
-                .index(1)
-                .multiple(true)
-                .help("Input file"),
-        )
+        .arg(input_arg())
         .arg(
             // This is named `skip-bytes` instead of `skip` to accommodate a future `skip-values` option.
             Arg::with_name("skip-bytes")
@@ -73,6 +73,248 @@ processing `n` bytes of Ion data. If `n` falls within a value, the
 complete value will be displayed."
                 )
         )
+        .arg(
+            Arg::with_name("decode-nested")
+                .long("decode-nested")
+                .help("Inspect blobs that contain binary Ion documents")
+                .long_help(
+                    "When specified, blobs that begin with an Ion version marker will be
+inspected as nested Ion documents. Their contents are displayed
+beneath the blob, indented one level and bracketed by comments.
+Offsets of nested values are relative to the start of the outer
+stream."
+                )
+        )
+        .arg(
+            Arg::with_name("show-encoding")
+                .long("show-encoding")
+                .help("Describe how each value's length is encoded")
+                .long_help(
+                    "When specified, each value's text is followed by a comment describing
+its encoding: whether its length is stored in the type descriptor or in
+a VarUInt (and how many bytes that VarUInt takes), whether a VarUInt
+length is longer than it needed to be, and whether a struct uses the
+ordered encoding, which promises that its field IDs are sorted."
+                )
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .help("After the values, summarize the bytes spent on each part of the encoding")
+                .long_help(
+                    "When specified, each input is followed by a table of how many of its
+bytes were spent on type descriptors, lengths, field IDs, annotation
+wrappers, padding, version markers, and symbol tables, compared to the
+bytes holding scalar values' contents. The table always covers the
+whole stream, regardless of --skip-bytes and --limit-bytes."
+                )
+        )
+        .arg(
+            Arg::with_name("compare")
+                .long("compare")
+                .takes_value(true)
+                .help("Display the input side by side with this binary Ion file")
+                .long_help(
+                    "When specified, the input and this file are displayed side by side,
+one value per row, aligned by their position in each stream. Rows
+where the values are equal but encoded differently (for example,
+with different symbol IDs or length encodings) are marked with `~`;
+rows where the values differ are marked with `!`. Requires exactly
+one input file."
+                )
+        )
+        .arg(
+            Arg::with_name("find-hex")
+                .long("find-hex")
+                .takes_value(true)
+                .conflicts_with_all(&["find-text", "compare"])
+                .help("Display the rows around the first occurrence of these bytes, e.g. 'e0 01 00 ea'")
+                .long_help(
+                    "When specified, the inspector searches the stream for these bytes
+(given in hex, optionally separated by spaces) and displays only the
+row containing the first occurrence, with --context rows before and
+after it. The search begins at --skip-bytes. If --limit-bytes is also
+specified, it sets how many bytes are displayed from the first context
+row instead of the number of rows after the match."
+                )
+        )
+        .arg(
+            Arg::with_name("find-text")
+                .long("find-text")
+                .takes_value(true)
+                .conflicts_with("compare")
+                .help("Display the rows around the first value whose text contains this")
+                .long_help(
+                    "When specified, the inspector displays only the first value whose
+field name, annotations, or text (for strings, symbols, and clobs)
+contains this text, with --context rows before and after it. The
+search begins at --skip-bytes, and --limit-bytes works as it does with
+--find-hex."
+                )
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .takes_value(true)
+                .default_value("3")
+                .help("Number of rows to display before and after a --find-hex or --find-text match")
+        )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .short("c")
+                .takes_value(true)
+                .default_value("offset,length,hex,text")
+                .help("Comma-separated list of the columns to display, in any order")
+                .long_help(
+                    "A comma-separated list of the columns to display. The available
+columns are `offset`, `length`, `hex`, `utf8`, and `text`; they are
+always displayed in that order. For example, `--columns offset,hex`
+produces output resembling a hex editor's.
+
+The `utf8` column renders the bytes of strings and clobs beside their
+hex, one character per byte: printable characters are shown as is, the
+extra bytes of a multi-byte UTF-8 character are shown as spaces after
+it, and other bytes are shown as `.`. It's blank for other values."
+                )
+        )
+        .arg(
+            Arg::with_name("no-length")
+                .long("no-length")
+                .help("Do not display the length column")
+        )
+        .arg(
+            Arg::with_name("offset-radix")
+                .long("offset-radix")
+                .takes_value(true)
+                .default_value("dec")
+                .possible_values(&["dec", "hex"])
+                .help("Display offsets in decimal or (zero-padded) hexadecimal")
+        )
+        .arg(
+            Arg::with_name("max-scalar-bytes")
+                .long("max-scalar-bytes")
+                .takes_value(true)
+                .default_value("0")
+                .hide_default_value(true)
+                .help("Only display the first and last `n` bytes of each scalar's body in hex")
+                .long_help(
+                    "When specified, the hex column of a scalar whose body is longer than
+2 * `n` bytes shows only its first `n` and last `n` bytes, separated by
+a row noting how many bytes were left out. The length column still
+shows the value's full length, and the offsets of the values that
+follow are unchanged. 0, the default, displays every byte."
+                )
+        )
+        .arg(
+            Arg::with_name("only-offsets")
+                .long("only-offsets")
+                .help("Instead of displaying the input, write an index of each value's offset and length")
+                .long_help(
+                    "When specified, writes an index of the input's top-level values instead
+of displaying it, one struct per value, like
+  {value: 41, offset: 5120, length: 87, symbol_tables: [4]}
+`value` counts the top-level values from 0, and `offset` and `length`
+cover the value's encoding including its annotations, so other tools
+can read any value directly. `symbol_tables` lists the offsets of the
+local symbol tables that define the symbols the value uses, in the
+order they must be read. With --path, each value the path selects is
+listed instead, along with its `path` within its top-level value.
+Requires exactly one binary input file. `ion beta get` uses the index
+to read single values."
+                )
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .requires("only-offsets")
+                .help("With --only-offsets, index the values at this path, e.g. '(orders *)'")
+        )
+        .arg(
+            Arg::with_name("csv")
+                .long("csv")
+                .requires("only-offsets")
+                .help("With --only-offsets, write the index as CSV with a header row")
+        )
+}
+
+// The columns that the inspector displays, and how they are formatted.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    show_offset: bool,
+    show_length: bool,
+    show_hex: bool,
+    // Whether the bytes of strings and clobs are rendered as text beside their hex
+    show_utf8: bool,
+    show_text: bool,
+    hex_offsets: bool,
+    // Whether each value's text is followed by a description of its encoding
+    show_encoding: bool,
+    // If set, scalar bodies longer than twice this many bytes only have their first and last
+    // this many bytes displayed in hex
+    max_scalar_bytes: Option<usize>,
+}
+
+impl Layout {
+    // Creates a Layout from the `columns`, `no-length`, `offset-radix`, `show-encoding`, and
+    // `max-scalar-bytes` arguments.
+    fn from_matches(matches: &ArgMatches<'static>) -> Result<Layout> {
+        // --max-scalar-bytes has a default value, so we can unwrap this safely.
+        let max_scalar_bytes_arg = matches.value_of("max-scalar-bytes").unwrap();
+        let max_scalar_bytes = usize::from_str(max_scalar_bytes_arg)
+            .with_context(|| format!("Invalid value for '--max-scalar-bytes': '{}'", max_scalar_bytes_arg))?;
+        let mut layout = Layout {
+            show_offset: false,
+            show_length: false,
+            show_hex: false,
+            show_utf8: false,
+            show_text: false,
+            // --offset-radix has a default value, so we can unwrap this safely.
+            hex_offsets: matches.value_of("offset-radix").unwrap() == "hex",
+            show_encoding: matches.is_present("show-encoding"),
+            // As with --limit-bytes, "0" means no limit.
+            max_scalar_bytes: Some(max_scalar_bytes).filter(|n| *n > 0),
+        };
+        // --columns has a default value, so we can unwrap this safely.
+        for column in matches.value_of("columns").unwrap().split(',') {
+            match column.trim() {
+                "offset" => layout.show_offset = true,
+                "length" => layout.show_length = true,
+                "hex" => layout.show_hex = true,
+                "utf8" => layout.show_utf8 = true,
+                "text" => layout.show_text = true,
+                other => bail!("Unknown column '{}'. Valid columns are offset, length, hex, utf8, and text.", other),
+            }
+        }
+        if matches.is_present("no-length") {
+            layout.show_length = false;
+        }
+        if !(layout.show_offset || layout.show_length || layout.show_hex || layout.show_utf8 || layout.show_text) {
+            bail!("At least one column must be displayed.");
+        }
+        Ok(layout)
+    }
+
+    fn format_offset(&self, offset: usize) -> String {
+        if self.hex_offsets {
+            format!("{:08x}", offset)
+        } else {
+            offset.to_string()
+        }
+    }
+}
+
+// What the inspector displays, other than its columns.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    bytes_to_skip: usize,
+    limit_bytes: usize,
+    decode_nested: bool,
+    show_stats: bool,
+    // The number of rows to display around a --find-hex or --find-text match
+    context: usize,
 }
 
 // Create a type alias to simplify working with a shared, mutable reference to our output stream.
@@ -113,63 +355,74 @@ pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
         limit_bytes = usize::MAX
     }
 
-    let output: OutputRef;
-    // If the user has specified an output file, use it.
-    if let Some(file_name) = matches.value_of("output") {
-        let output_file = File::create(file_name)
-            .with_context(|| format!("Could not open '{}'", file_name))?;
-        let buf_writer = BufWriter::new(output_file);
-        output = Rc::new(RefCell::new(buf_writer));
-    } else {
-        // Otherwise, write to STDOUT.
-        // TODO: Using io::stdout() isn't ideal as each write to stdout requires acquiring the
-        //       STDOUT lock. Some research is required to see if there's a different handle we
-        //       could use to avoid that. `io::stdout().lock()` won't work because io::stdout()
-        //       (to which it refers) has a limited lifetime.
-        let buf_writer = BufWriter::new(io::stdout());
-        output = Rc::new(RefCell::new(buf_writer));
-    }
-
-    // Run the inspector on each input file that was specified.
-    if let Some(input_file_iter) = matches.values_of("input") {
-        for input_file_name in input_file_iter {
+    // --context has a default value, so we can unwrap this safely.
+    let context_arg = matches.value_of("context").unwrap();
+    let context = usize::from_str(context_arg)
+        .with_context(|| format!("Invalid value for '--context': '{}'", context_arg))?;
+
+    let options = Options {
+        bytes_to_skip,
+        limit_bytes,
+        decode_nested: matches.is_present("decode-nested"),
+        show_stats: matches.is_present("stats"),
+        context,
+    };
+    let target = match (matches.value_of("find-hex"), matches.value_of("find-text")) {
+        (Some(hex), _) => Some(find::Target::from_hex(hex)?),
+        (_, Some(text)) => Some(find::Target::Text(text.to_owned())),
+        _ => None,
+    };
+    let layout = Layout::from_matches(matches)?;
+
+    // Write to the output file if the user specified one, or to STDOUT otherwise. STDOUT is locked
+    // once for the whole command rather than for each write.
+    let output: OutputRef = Rc::new(RefCell::new(output_writer(matches.value_of("output"))?));
+
+    if let Some(other_file_name) = matches.value_of("compare") {
+        let input_file_name = match listed_inputs(matches) {
+            Some(inputs) if inputs.len() == 1 => inputs[0],
+            _ => bail!("--compare requires exactly one input file."),
+        };
+        return compare::compare_files(input_file_name, other_file_name, &output);
+    }
+
+    if matches.is_present("only-offsets") {
+        let input_file_name = match listed_inputs(matches) {
+            Some(inputs) if inputs.len() == 1 => inputs[0],
+            _ => bail!("--only-offsets requires exactly one input file."),
+        };
+        let path = match matches.value_of("path") {
+            Some(text) => Some(Path::from_str(text).with_context(|| format!("Invalid --path '{}'", text))?),
+            None => None,
+        };
+        return offsets::write_index(input_file_name, path.as_ref(), matches.is_present("csv"), &output);
+    }
+
+    // Run the inspector on each input, in order. STDIN is inspected where `-` is given, or if no
+    // inputs were.
+    for input_file_name in input_names(matches) {
+        if input_file_name == STDIN_NAME {
+            // The inspector expects its input to be a byte array or mmap()ed file acting as a byte
+            // array, so STDIN is inspected from a copy.
+            let mut temp_file = copy_stdin()?;
+            inspect_file(display_name(STDIN_NAME), temp_file.as_file_mut(), &output, layout, options, target.as_ref())?;
+        } else {
             let mut input_file = File::open(input_file_name)
                 .with_context(|| format!("Could not open '{}'", input_file_name))?;
-            inspect_file(input_file_name, &mut input_file, &output, bytes_to_skip, limit_bytes)?;
-        }
-    } else {
-        // If no input file was specified, run the inspector on STDIN.
-
-        // The inspector expects its input to be a byte array or mmap()ed file acting as a byte
-        // array. If the user wishes to provide data on STDIN, we'll need to copy those bytes to
-        // a temporary file and then read from that.
-
-        // Create a temporary file that will delete itself when the program ends.
-        let mut input_file = tempfile::tempfile()
-            .with_context(|| concat!(
-                "Failed to create a temporary file to store STDIN.",
-                "Try passing an --input flag instead."
-            ))?;
-
-        // Pipe the data from STDIN to the temporary file.
-        let mut writer = BufWriter::new(input_file);
-        io::copy(&mut io::stdin(), &mut writer)
-            .with_context(|| "Failed to copy STDIN to a temp file.")?;
-        // Get our file handle back from the BufWriter
-        input_file = writer.into_inner()
-            .with_context(|| "Failed to read from temp file containing STDIN data.")?;
-        // Read from the now-populated temporary file.
-        inspect_file("STDIN temp file", &mut input_file, &output, bytes_to_skip, limit_bytes)?;
+            inspect_file(input_file_name, &mut input_file, &output, layout, options, target.as_ref())?;
+        }
     }
     Ok(())
 }
 
-// Given a file, try to mmap() it and run the inspector over the resulting byte array.
+// Given a file, try to mmap() it and run the inspector over the resulting byte array. If `target`
+// is set, only the rows around its first match are displayed.
 fn inspect_file(input_file_name: &str,
                 input_file: &mut File,
                 output: &OutputRef,
-                bytes_to_skip: usize,
-                limit_bytes: usize) -> Result<()> {
+                layout: Layout,
+                options: Options,
+                target: Option<&find::Target>) -> Result<()> {
     // mmap involves operating system interactions that inherently place its usage outside of Rust's
     // safety guarantees. If the file is unexpectedly truncated while it's being read, for example,
     // problems could arise.
@@ -184,16 +437,47 @@ fn inspect_file(input_file_name: &str,
     match ion_data {
         // Pattern match the byte array to verify it starts with an IVM
         [0xE0, 0x01, 0x00, 0xEA, ..] => {
+            write_header(&output, &layout)?;
+            let (bytes_to_skip, limit_bytes) = match target {
+                None => (options.bytes_to_skip, options.limit_bytes),
+                Some(target) => {
+                    let window = find::find(ion_data, target, options.bytes_to_skip, options.context)
+                        .with_context(|| format!("Could not search '{}'", input_file_name))?;
+                    let window = match window {
+                        Some(window) => window,
+                        None => {
+                            let message = format!("// No match for {} in '{}'", target.describe(), input_file_name);
+                            output_comment(output, &layout, &message)?;
+                            return Ok(());
+                        }
+                    };
+                    let message = format!("// Found {} at offset {}", target.describe(), layout.format_offset(window.match_offset));
+                    output_comment(output, &layout, &message)?;
+                    // An explicit --limit-bytes takes precedence over the rows after the match.
+                    let limit_bytes = if options.limit_bytes == usize::MAX { window.limit_bytes } else { options.limit_bytes };
+                    (window.bytes_to_skip, limit_bytes)
+                }
+            };
             let mut inspector = IonInspector::new(
                 ion_data,
                 Rc::clone(output),
+                layout,
                 bytes_to_skip,
                 limit_bytes,
             );
+            inspector.decode_nested = options.decode_nested;
 
-            write_header(&output)?;
             // This inspects all values at the top level, recursing as necessary.
             inspector.inspect_level()?;
+            if options.show_stats {
+                stats::EncodingStats::collect(ion_data)?.write(output)?;
+            }
+        }
+        _ if is_version_marker(ion_data) => {
+            bail!(
+                "Input file '{}' begins with a version marker for Ion {}.{}, but the inspector only reads Ion 1.0.",
+                input_file_name, ion_data[1], ion_data[2]
+            );
         }
         _ => {
             // bail! constructs an `anyhow::Result` with the given context and returns.
@@ -208,22 +492,26 @@ fn inspect_file(input_file_name: &str,
 // it just writes a comment describing the event in the text Ion column.
 struct SystemLevelEventSummarizer {
     output: OutputRef,
+    layout: Layout,
     text_buffer: String,
+    // System events are always at the top level of a stream, but that stream may itself be
+    // nested inside of a blob.
+    indentation: String,
 }
 
 impl SystemLevelEventSummarizer {
-    pub fn new(output: OutputRef) -> SystemLevelEventSummarizer {
+    pub fn new(output: OutputRef, layout: Layout, indentation: &str) -> SystemLevelEventSummarizer {
         SystemLevelEventSummarizer {
             output,
+            layout,
             text_buffer: String::with_capacity(512),
+            indentation: indentation.to_owned(),
         }
     }
 }
 
 const IVM_HEX: &str = "e0 01 00 ea";
 const IVM_TEXT: &str = "// Ion 1.0 Version Marker";
-// System events (IVM, symtabs) are always at the top level.
-const SYSTEM_EVENT_INDENTATION: &str = "";
 
 impl SystemEventHandler for SystemLevelEventSummarizer {
     // TODO: At the moment, the SystemEventHandler trait's functions do not have a return type that
@@ -233,9 +521,10 @@ impl SystemEventHandler for SystemLevelEventSummarizer {
     fn on_ivm(&mut self, _ion_version: (u8, u8)) {
         output(
             &self.output,
+            &self.layout,
             None,
             None,
-            SYSTEM_EVENT_INDENTATION,
+            &self.indentation,
             IVM_HEX,
             IVM_TEXT.dimmed(),
         ).expect("output() failure from on_ivm()");
@@ -248,9 +537,10 @@ impl SystemEventHandler for SystemLevelEventSummarizer {
         self.text_buffer.push_str("\"]");
         output(
             &self.output,
+            &self.layout,
             None,
             None,
-            SYSTEM_EVENT_INDENTATION,
+            &self.indentation,
             "...",
             &self.text_buffer.dimmed(),
         ).expect("output() failure from on_symbol_table_append()");
@@ -269,9 +559,10 @@ impl SystemEventHandler for SystemLevelEventSummarizer {
 
         output(
             &self.output,
+            &self.layout,
             None,
             None,
-            SYSTEM_EVENT_INDENTATION,
+            &self.indentation,
             "...",
             &self.text_buffer.dimmed(),
         ).expect("output() failure from on_symbol_table_reset()");
@@ -283,11 +574,14 @@ const TEXT_WRITER_INITIAL_BUFFER_SIZE: usize = 128;
 
 struct IonInspector<'input> {
     output: OutputRef,
+    layout: Layout,
     reader: Reader<BinaryIonCursor<io::Cursor<&'input [u8]>>>,
     bytes_to_skip: usize,
     limit_bytes: usize,
     // Reusable buffer for formatting bytes as hex
     hex_buffer: String,
+    // Reusable buffer for rendering string and clob bytes as text, one character per byte
+    utf8_buffer: String,
     // Reusable buffer for formatting text
     text_buffer: String,
     // Reusable buffer for colorizing text
@@ -296,23 +590,45 @@ struct IonInspector<'input> {
     indentation_buffer: String,
     // Text Ion writer for formatting scalar values
     text_ion_writer: TextWriter<Vec<u8>>,
+    // Whether blobs containing binary Ion should be inspected as nested streams
+    decode_nested: bool,
+    // When inspecting a nested stream, the offset of the blob body containing it. Reader offsets
+    // are relative to the nested stream, so this is added to them for display.
+    base_offset: usize,
 }
 
 impl<'input> IonInspector<'input> {
-    fn new(input: &'input [u8], out: OutputRef, bytes_to_skip: usize, limit_bytes: usize) -> IonInspector<'input> {
+    fn new(input: &'input [u8],
+           out: OutputRef,
+           layout: Layout,
+           bytes_to_skip: usize,
+           limit_bytes: usize) -> IonInspector<'input> {
+        IonInspector::with_indentation(input, out, layout, bytes_to_skip, limit_bytes, "")
+    }
+
+    fn with_indentation(input: &'input [u8],
+                        out: OutputRef,
+                        layout: Layout,
+                        bytes_to_skip: usize,
+                        limit_bytes: usize,
+                        indentation: &str) -> IonInspector<'input> {
         let mut reader = Reader::new(BinaryIonCursor::new(io::Cursor::new(input)));
-        reader.set_symtab_event_handler(SystemLevelEventSummarizer::new(out.clone()));
+        reader.set_symtab_event_handler(SystemLevelEventSummarizer::new(out.clone(), layout, indentation));
         let text_ion_writer = TextWriter::new(Vec::with_capacity(TEXT_WRITER_INITIAL_BUFFER_SIZE));
         IonInspector {
             output: out,
+            layout,
             reader,
             bytes_to_skip,
             limit_bytes,
             hex_buffer: String::new(),
+            utf8_buffer: String::new(),
             text_buffer: String::new(),
             color_buffer: String::new(),
-            indentation_buffer: String::new(),
+            indentation_buffer: indentation.to_owned(),
             text_ion_writer,
+            decode_nested: false,
+            base_offset: 0,
         }
     }
 
@@ -363,6 +679,7 @@ impl<'input> IonInspector<'input> {
                 };
                 output(
                     &self.output,
+                    &self.layout,
                     None,
                     None,
                     &self.indentation_buffer,
@@ -380,6 +697,7 @@ impl<'input> IonInspector<'input> {
                 write!(&mut self.text_buffer, "// Skipped {} bytes of user-level data", bytes_skipped_this_level)?;
                 output(
                     &self.output,
+                    &self.layout,
                     None,
                     None,
                     &self.indentation_buffer,
@@ -403,6 +721,7 @@ impl<'input> IonInspector<'input> {
                     // Print the container's closing delimiter: }, ), or ]
                     output(
                         &self.output,
+                        &self.layout,
                         None,
                         None,
                         &self.indentation_buffer,
@@ -410,6 +729,7 @@ impl<'input> IonInspector<'input> {
                         &closing_delimiter_for(ion_type),
                     )?;
                 }
+                IonType::Blob if self.decode_nested => self.inspect_nested_stream_if_present()?,
                 _ => {}
             }
         }
@@ -418,6 +738,48 @@ impl<'input> IonInspector<'input> {
         Ok(())
     }
 
+    // If the current value is a blob containing binary Ion, inspects its contents as a stream of
+    // its own.
+    fn inspect_nested_stream_if_present(&mut self) -> Result<()> {
+        let nested_stream = match self.reader.raw_value_bytes() {
+            // The nested inspector needs its own copy of the bytes; our reader can't lend them out
+            // while it's being advanced.
+            Some(bytes) if bytes.starts_with(&IVM) => bytes.to_vec(),
+            _ => return Ok(()),
+        };
+        let nested_indentation = format!("{}{}", self.indentation_buffer, LEVEL_INDENTATION);
+        output(
+            &self.output,
+            &self.layout,
+            None,
+            None,
+            &nested_indentation,
+            "",
+            "// Nested Ion stream:".dimmed(),
+        )?;
+        let mut nested_inspector = IonInspector::with_indentation(
+            &nested_stream,
+            Rc::clone(&self.output),
+            self.layout,
+            0,
+            usize::MAX,
+            &nested_indentation,
+        );
+        nested_inspector.decode_nested = true;
+        nested_inspector.base_offset = self.base_offset + self.reader.value_range().start;
+        nested_inspector.inspect_level()?;
+        output(
+            &self.output,
+            &self.layout,
+            None,
+            None,
+            &nested_indentation,
+            "",
+            "// End of nested Ion stream".dimmed(),
+        )?;
+        Ok(())
+    }
+
     fn increase_indentation(&mut self) {
         // Remove a level's worth of indentation from the buffer.
         if self.reader.depth() > 0 {
@@ -448,7 +810,8 @@ impl<'input> IonInspector<'input> {
             write!(&mut self.text_buffer, "{}", &self.color_buffer.dimmed())?;
             output(
                 &self.output,
-                self.reader.field_id_offset(),
+                &self.layout,
+                self.reader.field_id_offset().map(|offset| self.base_offset + offset),
                 self.reader.field_id_length(),
                 &self.indentation_buffer,
                 &self.hex_buffer,
@@ -477,7 +840,8 @@ impl<'input> IonInspector<'input> {
             write!(self.text_buffer, "{}", self.color_buffer.dimmed())?;
             output(
                 &self.output,
-                self.reader.annotations_offset(),
+                &self.layout,
+                self.reader.annotations_offset().map(|offset| self.base_offset + offset),
                 self.reader.annotations_length(),
                 &self.indentation_buffer,
                 &self.hex_buffer,
@@ -493,29 +857,100 @@ impl<'input> IonInspector<'input> {
         // if it is a scalar. If the value is a container, format_value() will write the opening
         // delimiter of that container instead.
         self.format_value()?;
+        if self.layout.show_encoding {
+            self.color_buffer.clear();
+            self.describe_encoding()?;
+            write!(self.text_buffer, "{}", self.color_buffer.dimmed())?;
+        }
 
         self.hex_buffer.clear();
-        to_hex(&mut self.hex_buffer, self.reader.raw_header_bytes().unwrap());
+        self.utf8_buffer.clear();
+        let header = self.reader.raw_header_bytes().unwrap();
+        to_hex(&mut self.hex_buffer, header);
         // Only write the bytes representing the body of the value if it is a scalar.
         // If it is a container, `inspect_level` will handle stepping into it and writing any
         // nested values.
-        if !self.reader.ion_type().unwrap().is_container() {
+        let ion_type = self.reader.ion_type().unwrap();
+        if !ion_type.is_container() {
             self.hex_buffer.push_str(" ");
-            to_hex(&mut self.hex_buffer, self.reader.raw_value_bytes().unwrap());
+            let body = self.reader.raw_value_bytes().unwrap();
+            let show_utf8 = self.layout.show_utf8
+                && matches!(ion_type, IonType::String | IonType::Clob)
+                && !self.reader.is_null();
+            if show_utf8 {
+                // The header's bytes have no rendering, but they take up room in the row.
+                self.utf8_buffer.push_str(&" ".repeat(header.len()));
+            }
+            match self.layout.max_scalar_bytes {
+                Some(n) if body.len() > 2 * n => {
+                    to_hex_elided(&mut self.hex_buffer, body, n);
+                    if show_utf8 {
+                        to_utf8_elided(&mut self.utf8_buffer, body, n);
+                    }
+                }
+                _ => {
+                    to_hex(&mut self.hex_buffer, body);
+                    if show_utf8 {
+                        to_utf8(&mut self.utf8_buffer, body);
+                    }
+                }
+            }
         }
 
         const TYPE_DESCRIPTOR_SIZE: usize = 1;
         let length = TYPE_DESCRIPTOR_SIZE + self.reader.header_length() + self.reader.value_length();
-        output(
+        output_with_utf8(
             &self.output,
-            Some(self.reader.header_offset()),
+            &self.layout,
+            Some(self.base_offset + self.reader.header_offset()),
             Some(length),
             &self.indentation_buffer,
             &self.hex_buffer,
+            &self.utf8_buffer,
             &self.text_buffer,
         )
     }
 
+    // Writes a comment describing how the current value's length is encoded to `color_buffer`.
+    fn describe_encoding(&mut self) -> IonResult<()> {
+        // The low nibble of a type descriptor holds the value's length if it's less than 14.
+        // Otherwise, it's 14 and the length follows in a VarUInt. Structs use a nibble of 1 to
+        // mark the ordered encoding, which is also followed by a VarUInt length.
+        const VAR_UINT_LENGTH: u8 = 0x0E;
+        const ORDERED_STRUCT: u8 = 0x01;
+
+        let type_descriptor = self.reader.raw_header_bytes().unwrap()[0];
+        let length_code = type_descriptor & 0x0F;
+        let ion_type = self.reader.ion_type().unwrap();
+        let value_length = self.reader.value_length();
+        let is_ordered_struct = ion_type == IonType::Struct && length_code == ORDERED_STRUCT;
+
+        write!(self.color_buffer, " // ")?;
+        if self.reader.is_null() {
+            write!(self.color_buffer, "null, no length")?;
+            return Ok(());
+        }
+        if ion_type == IonType::Boolean {
+            write!(self.color_buffer, "value in type descriptor")?;
+            return Ok(());
+        }
+        if length_code != VAR_UINT_LENGTH && !is_ordered_struct {
+            write!(self.color_buffer, "length {} in type descriptor", value_length)?;
+            return Ok(());
+        }
+        if is_ordered_struct {
+            write!(self.color_buffer, "ordered struct (sorted field IDs), ")?;
+        }
+        let length_bytes = self.reader.header_length();
+        write!(self.color_buffer, "length {} in {}-byte VarUInt", value_length, length_bytes)?;
+        if value_length < VAR_UINT_LENGTH as usize && !is_ordered_struct {
+            write!(self.color_buffer, " (could be in type descriptor)")?;
+        } else if length_bytes > var_uint_length(value_length) {
+            write!(self.color_buffer, " (non-minimal)")?;
+        }
+        Ok(())
+    }
+
     fn format_value(&mut self) -> IonResult<()> {
         use ion_rs::IonType::*;
 
@@ -596,35 +1031,64 @@ const COLUMN_DELIMITER: &str = " | ";
 const CHARS_PER_HEX_BYTE: usize = 3;
 const HEX_BYTES_PER_ROW: usize = 8;
 const HEX_COLUMN_SIZE: usize = HEX_BYTES_PER_ROW * CHARS_PER_HEX_BYTE;
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
 
-fn write_header(output: &OutputRef) -> IonResult<()> {
+fn write_header(output: &OutputRef, layout: &Layout) -> IonResult<()> {
     // Unwrap our Rc<RefCell<dyn Write>> to get a &mut dyn Write for the rest of the function
     let mut output = output.borrow_mut();
 
-    let line = "-".repeat(24 + 24 + 9 + 9 + (COLUMN_DELIMITER.len() * 3));
+    let columns = [
+        (layout.show_offset, "Offset", 9),
+        (layout.show_length, "Length", 9),
+        (layout.show_hex, "Binary Ion", HEX_COLUMN_SIZE),
+        (layout.show_utf8, "UTF-8", HEX_BYTES_PER_ROW),
+        (layout.show_text, "Text Ion", 24),
+    ];
+    let shown: Vec<_> = columns.iter().filter(|(show, _, _)| *show).collect();
+    let width: usize = shown.iter().map(|(_, _, width)| width).sum::<usize>()
+        + COLUMN_DELIMITER.len() * (shown.len() - 1);
+    let line = "-".repeat(width);
 
     writeln!(output, "{}", line)?;
-    write!(output, "{:^9}{}", "Offset".bold().bright_white(), COLUMN_DELIMITER)?;
-    write!(output, "{:^9}{}", "Length".bold().bright_white(), COLUMN_DELIMITER)?;
-    write!(output, "{:^24}{}", "Binary Ion".bold().bright_white(), COLUMN_DELIMITER)?;
-    writeln!(output, "{:^24}", "Text Ion".bold().bright_white())?;
+    for (index, (_, title, width)) in shown.iter().enumerate() {
+        if index > 0 {
+            write!(output, "{}", COLUMN_DELIMITER)?;
+        }
+        write!(output, "{:^width$}", title.bold().bright_white(), width = *width)?;
+    }
+    writeln!(output)?;
     writeln!(output, "{}", line)?;
     Ok(())
 }
 
 // Accepting a `T` allows us to pass in `&str`, `&String`, `&ColoredString`, etc as out text_column
 fn output<T: Display>(output: &OutputRef,
+                      layout: &Layout,
                       offset: Option<usize>,
                       length: Option<usize>,
                       indentation: &str,
                       hex_column: &str,
                       text_column: T) -> IonResult<()> {
+    output_with_utf8(output, layout, offset, length, indentation, hex_column, "", text_column)
+}
+
+// Like `output`, but also writes the UTF-8 column, which holds one character for each byte in
+// `hex_column`.
+#[allow(clippy::too_many_arguments)]
+fn output_with_utf8<T: Display>(output: &OutputRef,
+                                layout: &Layout,
+                                offset: Option<usize>,
+                                length: Option<usize>,
+                                indentation: &str,
+                                hex_column: &str,
+                                utf8_column: &str,
+                                text_column: T) -> IonResult<()> {
 
     // Unwrap our Rc<RefCell<dyn Write>> to get a &mut dyn Write for the rest of the function
     let mut output = output.borrow_mut();
 
     // The current implementation always writes a single line of output for the offset, length,
-    // and text columns. Only the hex column can span multiple rows.
+    // and text columns. Only the hex and UTF-8 columns can span multiple rows.
     // TODO: It would be nice to allow important hex bytes (e.g. type descriptors or lengths)
     //       to be color-coded. This complicates the output function, however, as the length
     //       of a colored string is not the same as its display length. We would need to pass
@@ -633,57 +1097,112 @@ fn output<T: Display>(output: &OutputRef,
     //       the provided colors just before writing.
 
     // Write the offset column
-    if let Some(offset) = offset {
-        write!(output, "{:9}{}", offset, COLUMN_DELIMITER)?;
-    } else {
-        write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
+    if layout.show_offset {
+        let offset = offset.map(|offset| layout.format_offset(offset)).unwrap_or_default();
+        write!(output, "{:>9}{}", offset, COLUMN_DELIMITER)?;
     }
 
     // Write the length column
-    if let Some(length) = length {
-        write!(output, "{:9}{}", length, COLUMN_DELIMITER)?;
-    } else {
-        write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
-    }
-
-    // If the hex string is short enough to fit in a single row...
-    if hex_column.len() < HEX_COLUMN_SIZE {
-        // ...print the hex string...
-        write!(output, "{}", hex_column)?;
-        // ...and then write enough padding spaces to fill the rest of the row.
-        for _ in 0..(HEX_COLUMN_SIZE - hex_column.len()) {
-            write!(output, " ")?;
-        }
-    } else {
-        // Otherwise, write the first row's worth of the hex string.
-        write!(output, "{}", &hex_column[..HEX_COLUMN_SIZE])?;
-    }
-    // Write a delimiter, the write the text Ion as the final column.
-    write!(output, "{}", COLUMN_DELIMITER)?;
-    write!(output, " ")?;
-    writeln!(output, "{}{}", indentation, text_column)?;
-
-    // Revisit our hex column. Write as many additional rows as needed.
+    if layout.show_length {
+        if let Some(length) = length {
+            write!(output, "{:9}{}", length, COLUMN_DELIMITER)?;
+        } else {
+            write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
+        }
+    }
+
+    if layout.show_hex {
+        // If the hex string is short enough to fit in a single row...
+        if hex_column.len() < HEX_COLUMN_SIZE {
+            // ...print the hex string...
+            write!(output, "{}", hex_column)?;
+            // ...and then write enough padding spaces to fill the rest of the row.
+            for _ in 0..(HEX_COLUMN_SIZE - hex_column.len()) {
+                write!(output, " ")?;
+            }
+        } else {
+            // Otherwise, write the first row's worth of the hex string.
+            write!(output, "{}", &hex_column[..HEX_COLUMN_SIZE])?;
+        }
+        if layout.show_utf8 || layout.show_text {
+            write!(output, "{}", COLUMN_DELIMITER)?;
+        }
+    }
+    let utf8_chars: Vec<char> = utf8_column.chars().collect();
+    let utf8_row = |row: usize| -> String {
+        utf8_chars.iter().skip(row * HEX_BYTES_PER_ROW).take(HEX_BYTES_PER_ROW).collect()
+    };
+    if layout.show_utf8 {
+        write!(output, "{:<width$}", utf8_row(0), width = HEX_BYTES_PER_ROW)?;
+        if layout.show_text {
+            // Write a delimiter; the text Ion will be the final column.
+            write!(output, "{}", COLUMN_DELIMITER)?;
+        }
+    }
+    if layout.show_text {
+        write!(output, " ")?;
+        write!(output, "{}{}", indentation, text_column)?;
+    }
+    writeln!(output)?;
+
+    // Revisit our hex and UTF-8 columns. Write as many additional rows as needed.
+    let mut row = 1;
     let mut col_1_written = HEX_COLUMN_SIZE;
-    while col_1_written < hex_column.len() {
-        // Padding for offset column
-        write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
-        // Padding for length column
-        write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
-        let remaining_bytes = &hex_column.len() - col_1_written;
-        let bytes_to_write = min(remaining_bytes, HEX_COLUMN_SIZE);
-        let next_slice_to_write = &hex_column[col_1_written..(col_1_written + bytes_to_write)];
-        write!(output, "{}", next_slice_to_write)?;
-        for _ in 0..(HEX_COLUMN_SIZE - bytes_to_write) {
-            write!(output, " ")?;
-        }
-        writeln!(output, "{}", COLUMN_DELIMITER)?;
+    loop {
+        let more_hex = layout.show_hex && col_1_written < hex_column.len();
+        let more_utf8 = layout.show_utf8 && row * HEX_BYTES_PER_ROW < utf8_chars.len();
+        if !(more_hex || more_utf8) {
+            break;
+        }
+        if layout.show_offset {
+            // Padding for offset column
+            write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
+        }
+        if layout.show_length {
+            // Padding for length column
+            write!(output, "{:9}{}", "", COLUMN_DELIMITER)?;
+        }
+        if layout.show_hex {
+            let remaining_bytes = hex_column.len().saturating_sub(col_1_written);
+            let bytes_to_write = min(remaining_bytes, HEX_COLUMN_SIZE);
+            if bytes_to_write > 0 {
+                let next_slice_to_write = &hex_column[col_1_written..(col_1_written + bytes_to_write)];
+                write!(output, "{}", next_slice_to_write)?;
+            }
+            if layout.show_utf8 || layout.show_text {
+                for _ in 0..(HEX_COLUMN_SIZE - bytes_to_write) {
+                    write!(output, " ")?;
+                }
+                write!(output, "{}", COLUMN_DELIMITER)?;
+            }
+        }
+        if layout.show_utf8 {
+            let next_row = utf8_row(row);
+            if layout.show_text {
+                write!(output, "{:<width$}{}", next_row, COLUMN_DELIMITER, width = HEX_BYTES_PER_ROW)?;
+            } else {
+                write!(output, "{}", next_row)?;
+            }
+        }
+        writeln!(output)?;
+        row += 1;
         col_1_written += HEX_COLUMN_SIZE;
         // No need to write anything for the text column since it's the last one.
     }
     Ok(())
 }
 
+// Writes a row holding only a comment in the text column.
+fn output_comment(output_ref: &OutputRef, layout: &Layout, comment: &str) -> IonResult<()> {
+    output(output_ref, layout, None, None, "", "...", comment.dimmed())
+}
+
+// The number of bytes needed to encode `value` as a VarUInt, which holds 7 bits per byte.
+fn var_uint_length(value: usize) -> usize {
+    let bits = (usize::BITS - value.leading_zeros()).max(1) as usize;
+    bits.div_ceil(7)
+}
+
 fn closing_delimiter_for(container_type: IonType) -> &'static str {
     match container_type {
         IonType::List => "]",
@@ -694,13 +1213,72 @@ fn closing_delimiter_for(container_type: IonType) -> &'static str {
 }
 
 fn to_hex(buffer: &mut String, bytes: &[u8]) {
-    if bytes.len() == 0 {
+    if bytes.is_empty() {
         return;
     }
-    write!(buffer, "{:02x}", bytes[0]).unwrap();
-    for byte in &bytes[1..] {
-        write!(buffer, " {:02x}", *byte).unwrap();
+    // Formatting each byte with `write!` dominated the time spent inspecting large files, so each
+    // byte's digits are looked up in a table and appended directly.
+    // Only ASCII is appended, so the buffer remains valid UTF-8.
+    let hex = unsafe { buffer.as_mut_vec() };
+    hex.reserve(CHARS_PER_HEX_BYTE * bytes.len());
+    for byte in bytes {
+        hex.extend_from_slice(&[HEX_DIGITS[(byte >> 4) as usize], HEX_DIGITS[(byte & 0x0F) as usize], b' ']);
     }
+    // Drop the space after the last byte.
+    hex.pop();
+}
+
+// Like `to_hex`, but only writes the first and last `n` bytes. The bytes in between are replaced
+// by a row of their own noting how many were left out, so the bytes that follow it still start a
+// new row.
+fn to_hex_elided(buffer: &mut String, bytes: &[u8], n: usize) {
+    to_hex(buffer, &bytes[..n]);
+    let row_padding = (HEX_COLUMN_SIZE - buffer.len() % HEX_COLUMN_SIZE) % HEX_COLUMN_SIZE;
+    buffer.push_str(&" ".repeat(row_padding));
+    let marker = format!("... {} bytes ...", bytes.len() - 2 * n);
+    write!(buffer, "{:<width$}", marker, width = HEX_COLUMN_SIZE).unwrap();
+    to_hex(buffer, &bytes[bytes.len() - n..]);
+}
+
+// Renders each byte as one character: printable ASCII as itself, a complete multi-byte UTF-8
+// character as itself followed by a space for each of its extra bytes, and anything else as `.`.
+fn to_utf8(buffer: &mut String, bytes: &[u8]) {
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let width = match byte {
+            b' '..=b'~' => 1,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
+        let character = bytes
+            .get(index..index + width.max(1))
+            .and_then(|sequence| std::str::from_utf8(sequence).ok())
+            .and_then(|text| text.chars().next())
+            .filter(|character| width > 0 && !character.is_control());
+        match character {
+            Some(character) => {
+                buffer.push(character);
+                buffer.push_str(&" ".repeat(width - 1));
+                index += width;
+            }
+            None => {
+                buffer.push('.');
+                index += 1;
+            }
+        }
+    }
+}
+
+// Like `to_utf8`, laid out to match `to_hex_elided`.
+fn to_utf8_elided(buffer: &mut String, bytes: &[u8], n: usize) {
+    to_utf8(buffer, &bytes[..n]);
+    let written = buffer.chars().count();
+    let row_padding = (HEX_BYTES_PER_ROW - written % HEX_BYTES_PER_ROW) % HEX_BYTES_PER_ROW;
+    buffer.push_str(&" ".repeat(row_padding + HEX_BYTES_PER_ROW));
+    to_utf8(buffer, &bytes[bytes.len() - n..]);
 }
 
 fn join_into<T: Display>(buffer: &mut String,
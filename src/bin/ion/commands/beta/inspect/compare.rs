@@ -0,0 +1,185 @@
+use std::cmp::max;
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use ion_rs::IonType;
+
+use super::{closing_delimiter_for, to_hex, OutputRef, COLUMN_DELIMITER, HEX_COLUMN_SIZE};
+use crate::input::{reader_for, IonReader, IVM};
+use crate::value::{Data, Symbol, TextFormatter, UnknownSymbols, Value};
+
+// The width of the text column in each pane. Longer text is truncated.
+const TEXT_COLUMN_SIZE: usize = 32;
+const PANE_DELIMITER: &str = " || ";
+
+// A single value (or a container's closing delimiter) in one of the streams being compared.
+struct Row {
+    offset: Option<usize>,
+    // The value's field ID, annotations, header, and (for scalars) body, as hex.
+    hex: String,
+    // The value's field name, annotations, and text (for scalars) or opening delimiter.
+    text: String,
+    depth: usize,
+}
+
+// Displays two binary Ion streams side by side, aligned value by value, and marks the values
+// whose encodings differ.
+pub fn compare_files(left_file_name: &str, right_file_name: &str, output: &OutputRef) -> Result<()> {
+    let left = rows_for_file(left_file_name)?;
+    let right = rows_for_file(right_file_name)?;
+    let mut output = output.borrow_mut();
+
+    writeln!(output, "{:<pane$}{}{}", left_file_name.bold().bright_white(), PANE_DELIMITER,
+             right_file_name.bold().bright_white(), pane = pane_width() + 2)?;
+    writeln!(output, "{}", "-".repeat(2 * (pane_width() + 2) + PANE_DELIMITER.len()))?;
+
+    let (mut same_values, mut different_encodings, mut different_values) = (0, 0, 0);
+    for index in 0..max(left.len(), right.len()) {
+        let (left_row, right_row) = (left.get(index), right.get(index));
+        let marker = match (left_row, right_row) {
+            (Some(l), Some(r)) if l.hex == r.hex => {
+                same_values += 1;
+                " ".normal()
+            }
+            (Some(l), Some(r)) if l.text == r.text && l.depth == r.depth => {
+                different_encodings += 1;
+                "~".yellow().bold()
+            }
+            _ => {
+                different_values += 1;
+                "!".red().bold()
+            }
+        };
+        let hex_rows = max(hex_row_count(left_row), hex_row_count(right_row));
+        for hex_row in 0..hex_rows {
+            let marker = if hex_row == 0 { marker.clone() } else { " ".normal() };
+            writeln!(output, "{} {}{}{}", marker, pane(left_row, hex_row), PANE_DELIMITER, pane(right_row, hex_row))?;
+        }
+    }
+
+    writeln!(output)?;
+    writeln!(output, "{} identical, {} {}, {} {}",
+             same_values,
+             different_encodings, "encoded differently (~)".yellow(),
+             different_values, "different (!)".red())?;
+    Ok(())
+}
+
+fn pane_width() -> usize {
+    9 + COLUMN_DELIMITER.len() + HEX_COLUMN_SIZE + COLUMN_DELIMITER.len() + TEXT_COLUMN_SIZE
+}
+
+fn hex_row_count(row: Option<&Row>) -> usize {
+    match row {
+        Some(row) if !row.hex.is_empty() => row.hex.len().div_ceil(HEX_COLUMN_SIZE),
+        _ => 1,
+    }
+}
+
+// Formats one line of a pane: the offset, a row's worth of hex, and the text. Only the first
+// line of a row includes its offset and text.
+fn pane(row: Option<&Row>, hex_row: usize) -> String {
+    let row = match row {
+        Some(row) => row,
+        None => return " ".repeat(pane_width()),
+    };
+    let hex_start = (hex_row * HEX_COLUMN_SIZE).min(row.hex.len());
+    let hex_end = (hex_start + HEX_COLUMN_SIZE).min(row.hex.len());
+    let (offset, text) = if hex_row == 0 {
+        let offset = row.offset.map(|offset| offset.to_string()).unwrap_or_default();
+        let mut text = format!("{}{}", "  ".repeat(row.depth), row.text);
+        if text.chars().count() > TEXT_COLUMN_SIZE {
+            text = text.chars().take(TEXT_COLUMN_SIZE - 3).collect::<String>() + "...";
+        }
+        (offset, text)
+    } else {
+        (String::new(), String::new())
+    };
+    format!("{:>9}{}{:<hex_width$}{}{:<text_width$}",
+            offset, COLUMN_DELIMITER,
+            row.hex[hex_start..hex_end].trim(), COLUMN_DELIMITER,
+            text,
+            hex_width = HEX_COLUMN_SIZE, text_width = TEXT_COLUMN_SIZE)
+}
+
+fn rows_for_file(file_name: &str) -> Result<Vec<Row>> {
+    let bytes = fs::read(file_name).with_context(|| format!("Could not read '{}'", file_name))?;
+    if !bytes.starts_with(&IVM) {
+        bail!("Input file '{}' does not appear to be binary Ion.", file_name);
+    }
+    let mut reader = reader_for(&bytes);
+    let mut formatter = TextFormatter::new();
+    formatter.set_unknown_symbols(UnknownSymbols::PreserveSids);
+    let mut rows = Vec::new();
+    collect_rows(&mut reader, &mut formatter, 0, &mut rows)
+        .with_context(|| format!("Could not read '{}'", file_name))?;
+    Ok(rows)
+}
+
+// Appends a row for each value at the reader's current level (and their children) to `rows`.
+fn collect_rows(reader: &mut IonReader<'_>,
+                formatter: &mut TextFormatter,
+                depth: usize,
+                rows: &mut Vec<Row>) -> Result<()> {
+    while let Some((ion_type, is_null)) = reader.next()? {
+        let offset = reader.field_id_offset()
+            .or_else(|| reader.annotations_offset())
+            .unwrap_or_else(|| reader.header_offset());
+        let mut hex = String::new();
+        let mut text = String::new();
+        if let Some(field_id) = reader.field_id() {
+            append_hex(&mut hex, reader.raw_field_id_bytes().unwrap_or(&[]));
+            formatter.format(&symbol_value(Symbol::from_sid(reader, field_id)), &mut text)?;
+            text.push_str(": ");
+        }
+        if !reader.annotation_ids().is_empty() {
+            append_hex(&mut hex, reader.raw_annotations_bytes().unwrap_or(&[]));
+            for sid in reader.annotation_ids().to_vec() {
+                formatter.format(&symbol_value(Symbol::from_sid(reader, sid)), &mut text)?;
+                text.push_str("::");
+            }
+        }
+        append_hex(&mut hex, reader.raw_header_bytes().unwrap_or(&[]));
+
+        if ion_type.is_container() && !is_null {
+            text.push_str(match ion_type {
+                IonType::List => "[",
+                IonType::SExpression => "(",
+                _ => "{",
+            });
+            rows.push(Row { offset: Some(offset), hex, text, depth });
+            reader.step_in()?;
+            collect_rows(reader, formatter, depth + 1, rows)?;
+            reader.step_out()?;
+            rows.push(Row {
+                offset: None,
+                hex: String::new(),
+                text: closing_delimiter_for(ion_type).to_owned(),
+                depth,
+            });
+        } else {
+            append_hex(&mut hex, reader.raw_value_bytes().unwrap_or(&[]));
+            let mut value = Value::read(reader)?;
+            // The annotations have already been written.
+            value.annotations.clear();
+            formatter.format(&value, &mut text)?;
+            rows.push(Row { offset: Some(offset), hex, text, depth });
+        }
+    }
+    Ok(())
+}
+
+fn append_hex(buffer: &mut String, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    if !buffer.is_empty() {
+        buffer.push(' ');
+    }
+    to_hex(buffer, bytes);
+}
+
+fn symbol_value(symbol: Symbol) -> Value {
+    Value::new(Data::Symbol(symbol))
+}
@@ -0,0 +1,118 @@
+use anyhow::{bail, Context, Result};
+use memchr::memmem;
+
+use crate::input::{reader_for, IonReader};
+use crate::value::{Data, Value};
+
+// What --find-hex or --find-text is looking for.
+pub enum Target {
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+impl Target {
+    // Parses the hex given to --find-hex, which may separate its bytes with spaces.
+    pub fn from_hex(hex: &str) -> Result<Target> {
+        let digits: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+        if digits.is_empty() || digits.len() % 2 == 1 {
+            bail!("--find-hex needs an even number of hex digits, unlike '{}'.", hex);
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&digits[index..index + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .with_context(|| format!("Invalid value for '--find-hex': '{}'", hex))?;
+        Ok(Target::Bytes(bytes))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Target::Bytes(bytes) => {
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("bytes {}", hex.join(" "))
+            }
+            Target::Text(text) => format!("text {:?}", text),
+        }
+    }
+}
+
+// The part of a stream to display around the first match: the offset at which the match was
+// found, and the --skip-bytes and --limit-bytes that display the rows around it.
+pub struct Window {
+    pub match_offset: usize,
+    pub bytes_to_skip: usize,
+    pub limit_bytes: usize,
+}
+
+// Finds the first match for `target` at or after `start`, and the window that displays it with
+// `context` rows before and after it. Returns None if there's no match.
+pub fn find(ion_data: &[u8], target: &Target, start: usize, context: usize) -> Result<Option<Window>> {
+    let mut rows = Vec::new();
+    collect_rows(&mut reader_for(ion_data), target, &mut rows)?;
+    // Rows are in the order they're displayed, so their offsets are sorted.
+    let (match_offset, index) = match target {
+        Target::Bytes(bytes) => {
+            let match_offset = match ion_data.get(start..).and_then(|data| memmem::find(data, bytes)) {
+                Some(position) => start + position,
+                None => return Ok(None),
+            };
+            // The match is in the last row that starts at or before it, unless it's in a
+            // version marker or symbol table in front of the first row.
+            let index = rows.iter().rposition(|row| row.offset <= match_offset).unwrap_or(0);
+            (match_offset, index)
+        }
+        Target::Text(_) => match rows.iter().position(|row| row.offset >= start && row.matches) {
+            Some(index) => (rows[index].offset, index),
+            None => return Ok(None),
+        },
+    };
+    if rows.is_empty() {
+        return Ok(Some(Window { match_offset, bytes_to_skip: 0, limit_bytes: usize::MAX }));
+    }
+    let bytes_to_skip = rows[index.saturating_sub(context)].offset;
+    let limit_bytes = match rows.get(index + context + 1) {
+        Some(row) => row.offset - bytes_to_skip,
+        None => usize::MAX,
+    };
+    Ok(Some(Window { match_offset, bytes_to_skip, limit_bytes }))
+}
+
+// A value, as the inspector displays it on its first row.
+struct Row {
+    // The offset of the value's field ID, annotations, or header, whichever comes first.
+    offset: usize,
+    // Whether the value's field name, annotations, or text contain the --find-text target.
+    matches: bool,
+}
+
+// Appends a row for each value at the reader's current level (and their children) to `rows`.
+fn collect_rows(reader: &mut IonReader<'_>, target: &Target, rows: &mut Vec<Row>) -> Result<()> {
+    while let Some((ion_type, is_null)) = reader.next()? {
+        let offset = reader.field_id_offset()
+            .or_else(|| reader.annotations_offset())
+            .unwrap_or_else(|| reader.header_offset());
+        let mut matches = match target {
+            Target::Text(text) => {
+                reader.field_name().is_some_and(|name| name.contains(text.as_str()))
+                    || reader.annotations().any(|annotation| annotation.contains(text.as_str()))
+            }
+            Target::Bytes(_) => false,
+        };
+        if ion_type.is_container() && !is_null {
+            rows.push(Row { offset, matches });
+            reader.step_in()?;
+            collect_rows(reader, target, rows)?;
+            reader.step_out()?;
+            continue;
+        }
+        if let Target::Text(text) = target {
+            let value = Value::read(reader)?;
+            matches = matches || match &value.data {
+                Data::Clob(bytes) => String::from_utf8_lossy(bytes).contains(text.as_str()),
+                _ => value.as_text().is_some_and(|value_text| value_text.contains(text.as_str())),
+            };
+        }
+        rows.push(Row { offset, matches });
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use ion_rs::IonType;
+
+use super::OutputRef;
+use crate::binary::{encoded_length, local_symbol_table};
+use crate::commands::beta::pipeline::csv_quote;
+use crate::input::{reader_for, IonInput, IonReader, IVM};
+use crate::path::{Path, Step};
+use crate::schema::ion_text;
+use crate::value::{Data, Symbol, Value};
+
+// One value in the index.
+pub struct Entry {
+    // The position of the top-level value holding it, counting from 0.
+    pub value: usize,
+    // Where it is within that top-level value, if a path was given.
+    pub path: Option<Path>,
+    // The offset and length of its encoding, including its annotations.
+    pub offset: usize,
+    pub length: usize,
+    // The offsets of the local symbol tables that define the symbols in effect for it, in the
+    // order they must be read. A reader that starts from the first of these and reads each of
+    // them in turn has the right symbols for the value.
+    pub symbol_tables: Vec<usize>,
+}
+
+impl Entry {
+    // Reads an entry written by `to_value`.
+    pub fn from_value(value: &Value) -> Result<Entry> {
+        let number = |name: &str| match value.get(name).map(|field| &field.data) {
+            Some(Data::Integer(number)) if *number >= 0 => Ok(*number as usize),
+            _ => bail!("Index entry {} has no valid '{}' field.", ion_text(value).trim(), name),
+        };
+        let path = match value.get("path").and_then(Value::as_text) {
+            Some(text) => Some(Path::from_str(text)?),
+            None => None,
+        };
+        let symbol_tables = match value.get("symbol_tables").map(|field| &field.data) {
+            Some(Data::List(offsets)) => offsets
+                .iter()
+                .map(|offset| match offset.data {
+                    Data::Integer(offset) if offset >= 0 => Ok(offset as usize),
+                    _ => bail!("Index entry {} has an invalid symbol table offset.", ion_text(value).trim()),
+                })
+                .collect::<Result<Vec<usize>>>()?,
+            _ => Vec::new(),
+        };
+        Ok(Entry { value: number("value")?, path, offset: number("offset")?, length: number("length")?, symbol_tables })
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut fields = vec![(Symbol::from("value"), Value::new(Data::Integer(self.value as i64)))];
+        if let Some(path) = &self.path {
+            fields.push((Symbol::from("path"), Value::new(Data::String(path.to_string()))));
+        }
+        fields.push((Symbol::from("offset"), Value::new(Data::Integer(self.offset as i64))));
+        fields.push((Symbol::from("length"), Value::new(Data::Integer(self.length as i64))));
+        let symbol_tables = self.symbol_tables.iter().map(|offset| Value::new(Data::Integer(*offset as i64)));
+        fields.push((Symbol::from("symbol_tables"), Value::new(Data::List(symbol_tables.collect()))));
+        Value::new(Data::Struct(fields))
+    }
+
+    fn to_csv(&self) -> String {
+        let symbol_tables: Vec<String> = self.symbol_tables.iter().map(usize::to_string).collect();
+        let mut row = self.value.to_string();
+        if let Some(path) = &self.path {
+            row.push(',');
+            row.push_str(&csv_quote(&path.to_string()));
+        }
+        row.push_str(&format!(",{},{},{}", self.offset, self.length, symbol_tables.join(";")));
+        row
+    }
+}
+
+// Writes an index of the offsets and lengths of the top-level values in a binary Ion file, or of
+// the values within them that `path` selects, as Ion text or CSV.
+pub fn write_index(input_file_name: &str, path: Option<&Path>, csv: bool, output: &OutputRef) -> Result<()> {
+    let input = IonInput::open(input_file_name)?;
+    if input.was_transcoded() {
+        bail!("'{}' is not binary Ion; --only-offsets indexes binary files.", input.name());
+    }
+    let entries = index(&input, path)?;
+    let mut output = output.borrow_mut();
+    if csv {
+        let path_column = if path.is_some() { "path," } else { "" };
+        writeln!(output, "value,{}offset,length,symbol_tables", path_column)?;
+    }
+    for entry in &entries {
+        if csv {
+            writeln!(output, "{}", entry.to_csv())?;
+        } else {
+            writeln!(output, "{}", ion_text(&entry.to_value()).trim())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn index(input: &IonInput, path: Option<&Path>) -> Result<Vec<Entry>> {
+    let system_values = system_values(input.bytes())?;
+    let mut next_system_value = 0;
+    let mut symbol_tables = Vec::new();
+    let mut entries = Vec::new();
+    let mut reader = input.reader();
+    let mut value_index = 0;
+    while reader.next()?.is_some() {
+        let offset = value_offset(&reader);
+        // Catch up on the version markers and symbol tables that came before this value.
+        while let Some((system_offset, kind)) = system_values.get(next_system_value) {
+            if *system_offset > offset {
+                break;
+            }
+            match kind {
+                SystemValue::VersionMarker => symbol_tables.clear(),
+                SystemValue::SymbolTable => {
+                    symbol_tables.clear();
+                    symbol_tables.push(*system_offset);
+                }
+                SystemValue::SymbolTableAppend => symbol_tables.push(*system_offset),
+            }
+            next_system_value += 1;
+        }
+        let mut indexer = Indexer { value: value_index, symbol_tables: &symbol_tables, steps: Vec::new(), entries: &mut entries };
+        match path {
+            Some(path) => indexer.visit(&mut reader, path.steps())?,
+            None => indexer.record(&reader, false),
+        }
+        value_index += 1;
+    }
+    Ok(entries)
+}
+
+// Records the values within a top-level value that a path selects.
+struct Indexer<'a> {
+    value: usize,
+    symbol_tables: &'a [usize],
+    // The path to the value the reader is on.
+    steps: Vec<Step>,
+    entries: &'a mut Vec<Entry>,
+}
+
+impl Indexer<'_> {
+    fn visit(&mut self, reader: &mut IonReader, steps: &[Step]) -> Result<()> {
+        let (step, remaining_steps) = match steps.split_first() {
+            Some(split) => split,
+            None => {
+                self.record(reader, true);
+                return Ok(());
+            }
+        };
+        let is_container = matches!(reader.ion_type(), Some(IonType::List | IonType::SExpression | IonType::Struct));
+        if !is_container || reader.is_null() {
+            return Ok(());
+        }
+        let is_struct = reader.ion_type() == Some(IonType::Struct);
+        reader.step_in()?;
+        let mut index = 0;
+        while reader.next()?.is_some() {
+            let child_step = if is_struct {
+                let field_id = reader.field_id().expect("Struct field has no field ID.");
+                let name = Symbol::from_sid(reader, field_id);
+                if !step.matches_field(&name) {
+                    continue;
+                }
+                Step::Field(name.text().map_or_else(|| format!("${}", field_id), str::to_owned))
+            } else {
+                index += 1;
+                if !step.matches_index(index - 1) {
+                    continue;
+                }
+                Step::Index(index - 1)
+            };
+            self.steps.push(child_step);
+            self.visit(reader, remaining_steps)?;
+            self.steps.pop();
+        }
+        reader.step_out()?;
+        Ok(())
+    }
+
+    fn record(&mut self, reader: &IonReader, with_path: bool) {
+        let offset = value_offset(reader);
+        self.entries.push(Entry {
+            value: self.value,
+            path: if with_path { Some(Path::from(self.steps.clone())) } else { None },
+            offset,
+            length: reader.value_range().end - offset,
+            symbol_tables: self.symbol_tables.to_vec(),
+        });
+    }
+}
+
+// The offset of the value the reader is on, including its annotations.
+fn value_offset(reader: &IonReader) -> usize {
+    reader.annotations_offset().unwrap_or_else(|| reader.header_offset())
+}
+
+enum SystemValue {
+    VersionMarker,
+    // A local symbol table that replaces the symbols in effect.
+    SymbolTable,
+    // A local symbol table that adds to the symbols in effect.
+    SymbolTableAppend,
+}
+
+// Finds the version markers and local symbol tables among the top-level values of a binary stream.
+fn system_values(bytes: &[u8]) -> Result<Vec<(usize, SystemValue)>> {
+    let mut system_values = Vec::new();
+    let mut position = 0;
+    while position < bytes.len() {
+        let length = encoded_length(&bytes[position..])
+            .with_context(|| format!("Invalid value header at offset {}", position))?;
+        let value = &bytes[position..position + length];
+        if value.starts_with(&IVM) {
+            system_values.push((position, SystemValue::VersionMarker));
+        } else if let Some(symbol_table) = local_symbol_table(value) {
+            let kind = if is_append(symbol_table)? { SystemValue::SymbolTableAppend } else { SystemValue::SymbolTable };
+            system_values.push((position, kind));
+        }
+        position += length;
+    }
+    Ok(system_values)
+}
+
+// Whether a local symbol table's struct has `imports: $ion_symbol_table`.
+fn is_append(symbol_table: &[u8]) -> Result<bool> {
+    // Read the struct on its own, where its system symbols still resolve.
+    let mut stream = IVM.to_vec();
+    stream.extend_from_slice(symbol_table);
+    let mut reader = reader_for(&stream);
+    if reader.next()?.is_none() {
+        return Ok(false);
+    }
+    let value = Value::read(&mut reader)?;
+    Ok(value.get("imports").is_some_and(|imports| matches!(&imports.data, Data::Symbol(symbol) if symbol == "$ion_symbol_table")))
+}
@@ -0,0 +1,125 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use super::OutputRef;
+use crate::binary::encoded_length;
+use crate::input::{reader_for, IonReader, IVM};
+
+// The number of bytes a binary Ion stream spends on each part of its encoding.
+#[derive(Default)]
+pub struct EncodingStats {
+    values: usize,
+    type_descriptors: usize,
+    lengths: usize,
+    field_ids: usize,
+    annotations: usize,
+    scalar_contents: usize,
+    padding: usize,
+    version_markers: usize,
+    symbol_tables: usize,
+    total: usize,
+}
+
+impl EncodingStats {
+    pub fn collect(bytes: &[u8]) -> Result<EncodingStats> {
+        let mut stats = EncodingStats { total: bytes.len(), ..Default::default() };
+        let mut reader = reader_for(bytes);
+        // The reader only surfaces user values, so the bytes between them are version markers,
+        // symbol tables, and padding.
+        let mut position = 0;
+        while reader.next()?.is_some() {
+            let start = complete_value_start(&reader);
+            stats.count_system_bytes(bytes, position, start)?;
+            position = reader.value_range().end;
+            stats.count_value(&mut reader)?;
+        }
+        stats.count_system_bytes(bytes, position, bytes.len())?;
+        Ok(stats)
+    }
+
+    // Counts the reader's current value, including its children if it's a container.
+    fn count_value(&mut self, reader: &mut IonReader) -> Result<()> {
+        self.values += 1;
+        self.field_ids += reader.field_id_length().unwrap_or(0);
+        if let Some(offset) = reader.annotations_offset() {
+            self.annotations += reader.header_offset() - offset;
+        }
+        self.type_descriptors += 1;
+        self.lengths += reader.header_length();
+        let is_container = reader.ion_type().is_some_and(|t| t.is_container());
+        if !is_container || reader.is_null() {
+            self.scalar_contents += reader.value_length();
+            return Ok(());
+        }
+        // Whatever a container's body holds besides its children is padding.
+        let body_length = reader.value_length();
+        let mut children_length = 0;
+        reader.step_in()?;
+        while reader.next()?.is_some() {
+            children_length += reader.value_range().end - complete_value_start(reader);
+            self.count_value(reader)?;
+        }
+        reader.step_out()?;
+        self.padding += body_length - children_length;
+        Ok(())
+    }
+
+    // Counts the top-level values in bytes[start..end], which the reader didn't surface.
+    fn count_system_bytes(&mut self, bytes: &[u8], mut start: usize, end: usize) -> Result<()> {
+        while start < end {
+            let length = match encoded_length(&bytes[start..end]) {
+                Some(length) => length,
+                None => bail!("Could not read the system value at offset {}.", start),
+            };
+            if bytes[start..].starts_with(&IVM) {
+                self.version_markers += length;
+            } else if bytes[start] >> 4 == 0 {
+                // Type code 0 with a length is a NOP pad.
+                self.padding += length;
+            } else {
+                // Any other system value is an annotated local symbol table.
+                self.symbol_tables += length;
+            }
+            start += length;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, output: &OutputRef) -> Result<()> {
+        let mut output = output.borrow_mut();
+        let rows = [
+            ("Type descriptors", self.type_descriptors),
+            ("Lengths", self.lengths),
+            ("Field IDs", self.field_ids),
+            ("Annotation wrappers", self.annotations),
+            ("Padding", self.padding),
+            ("Version markers", self.version_markers),
+            ("Symbol tables", self.symbol_tables),
+            ("Scalar contents", self.scalar_contents),
+        ];
+        writeln!(output)?;
+        writeln!(output, "{}", format!("Encoding of {} values", self.values).bold().bright_white())?;
+        for (name, bytes) in rows {
+            writeln!(output, "{:<20} {:>12} bytes {:>6.1}%", name, bytes, percentage(bytes, self.total))?;
+        }
+        let overhead = self.total - self.scalar_contents;
+        writeln!(output, "{:<20} {:>12} bytes {:>6.1}%", "Overhead", overhead, percentage(overhead, self.total))?;
+        writeln!(output, "{:<20} {:>12} bytes", "Total", self.total)?;
+        Ok(())
+    }
+}
+
+// The offset of the first byte of the reader's current value, including its field ID and
+// annotations.
+fn complete_value_start(reader: &IonReader) -> usize {
+    reader.field_id_offset()
+        .or_else(|| reader.annotations_offset())
+        .unwrap_or_else(|| reader.header_offset())
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    100.0 * part as f64 / total as f64
+}
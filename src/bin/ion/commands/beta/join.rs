@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+use tempfile::NamedTempFile;
+
+use crate::commands::CommandConfig;
+use crate::input::{IonInput, IonReader};
+use crate::key::{fnv1a, KeyExtractor};
+use crate::output::{format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::path::Path;
+use crate::size::{max_memory, parse_size};
+use crate::value::{Data, Symbol, TextFormatter, UnknownSymbols, Value};
+
+const ABOUT: &str = "Joins two streams of values on a key.";
+
+pub fn app() -> CommandConfig {
+    App::new("join")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("left")
+                .long("left")
+                .short("l")
+                .takes_value(true)
+                .required(true)
+                .help("The stream whose values are looked up in the right stream"),
+        )
+        .arg(
+            Arg::with_name("right")
+                .long("right")
+                .short("r")
+                .takes_value(true)
+                .required(true)
+                .help("The stream that is loaded into memory and searched"),
+        )
+        .arg(
+            Arg::with_name("on")
+                .long("on")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the key within each value, e.g. '(id)'"),
+        )
+        .arg(
+            Arg::with_name("right-on")
+                .long("right-on")
+                .takes_value(true)
+                .help("Path to the key within the right stream's values [default: --on]"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .takes_value(true)
+                .default_value("inner")
+                .possible_values(&["inner", "left"])
+                .help("Whether left values without a match are dropped (inner) or kept (left)"),
+        )
+        .arg(
+            Arg::with_name("memory-limit")
+                .long("memory-limit")
+                .short("m")
+                .takes_value(true)
+                .default_value("256M")
+                .help("Approximate memory to use for the right stream. Accepts K, M, and G suffixes.")
+                .long_help(
+                    "Approximate amount of memory to use for the right stream's values. If
+they don't fit, both streams are split into partitions by key in
+temporary files, and each pair of partitions is joined separately.
+When this happens, the output is no longer in the left stream's order.
+If this isn't given, the global --max-memory is used instead, if any."
+                ),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .after_help(
+            "Each match is written as {left: <left value>, right: <right value>}.
+In a left join, left values without a match are written with `right: null`.
+Keys are compared as text Ion, so `1` and `1.0` are different keys.
+Values without a key never match, and neither do keys that are null."
+        )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JoinType {
+    Inner,
+    Left,
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --left, --right, and --on are required, and the remaining arguments have default values,
+    // so we can unwrap them safely.
+    let left = IonInput::open(matches.value_of("left").unwrap())?;
+    let right = IonInput::open(matches.value_of("right").unwrap())?;
+    let left_on = matches.value_of("on").unwrap();
+    let right_on = matches.value_of("right-on").unwrap_or(left_on);
+    let join_type = match matches.value_of("type").unwrap() {
+        "left" => JoinType::Left,
+        _ => JoinType::Inner,
+    };
+    // --max-memory applies unless --memory-limit is given explicitly.
+    let memory_limit = match max_memory() {
+        Some(max_memory) if matches.occurrences_of("memory-limit") == 0 => max_memory,
+        _ => parse_size(matches.value_of("memory-limit").unwrap())?,
+    };
+    let unknown_symbols = UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap());
+
+    let mut output = IonOutput::from_matches(matches)?;
+    output.set_unknown_symbols(unknown_symbols);
+    let mut joiner = Joiner {
+        left_keys: KeyExtractor::new(Path::from_str(left_on)?),
+        right_keys: KeyExtractor::new(Path::from_str(right_on)?),
+        join_type,
+        unknown_symbols,
+        output: &mut output,
+    };
+
+    match joiner.build(&right, Some(memory_limit))? {
+        Some(table) => joiner.probe(&left, &table)?,
+        None => {
+            // Each partition should be about half the memory limit, assuming the values take
+            // up about twice as much space in memory as they do in binary Ion.
+            let partitions = 1 + right.bytes().len() * 4 / memory_limit.max(1);
+            eprintln!("The right stream does not fit in memory; joining in {} partitions.", partitions);
+            joiner.partitioned_join(&left, &right, partitions)?;
+        }
+    }
+    output.finish()
+}
+
+// Matching right values, grouped by key.
+type HashTable = HashMap<String, Vec<Value>>;
+
+struct Joiner<'a> {
+    left_keys: KeyExtractor,
+    right_keys: KeyExtractor,
+    join_type: JoinType,
+    unknown_symbols: UnknownSymbols,
+    output: &'a mut IonOutput,
+}
+
+impl<'a> Joiner<'a> {
+    // Loads the values in `right` into a hash table. If a memory limit is given and the values
+    // exceed it, returns `None`.
+    fn build(&mut self, right: &IonInput, memory_limit: Option<usize>) -> Result<Option<HashTable>> {
+        let mut table = HashTable::new();
+        // Values are measured by the length of their text, which is a rough but cheap proxy for
+        // the memory they use.
+        let mut measure = TextFormatter::new();
+        measure.set_unknown_symbols(UnknownSymbols::Placeholder);
+        let mut text = String::new();
+        let mut estimated_size = 0;
+
+        let mut reader = right.reader();
+        while reader.next()?.is_some() {
+            let value = read_value(&mut reader, right)?;
+            let key = match key_of(&mut self.right_keys, &value, right)? {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(limit) = memory_limit {
+                text.clear();
+                measure.format(&value, &mut text)?;
+                estimated_size += key.len() + text.len();
+                if estimated_size > limit {
+                    return Ok(None);
+                }
+            }
+            table.entry(key).or_default().push(value);
+        }
+        Ok(Some(table))
+    }
+
+    // Looks up each value in `left` in the table and writes the results.
+    fn probe(&mut self, left: &IonInput, table: &HashTable) -> Result<()> {
+        let mut reader = left.reader();
+        while reader.next()?.is_some() {
+            let value = read_value(&mut reader, left)?;
+            let key = key_of(&mut self.left_keys, &value, left)?;
+            let matches = key.and_then(|key| table.get(&key));
+            self.write_matches(&value, matches.map(|values| values.as_slice()))?;
+        }
+        Ok(())
+    }
+
+    fn write_matches(&mut self, left: &Value, matches: Option<&[Value]>) -> Result<()> {
+        match matches {
+            Some(matches) => {
+                for right in matches {
+                    self.output.write_value(&joined(left.clone(), right.clone()))?;
+                }
+            }
+            None if self.join_type == JoinType::Left => {
+                self.output.write_value(&joined(left.clone(), Value::new(Data::Null(IonType::Null))))?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    // Splits both streams into partitions by key, then joins each pair of partitions in memory.
+    fn partitioned_join(&mut self, left: &IonInput, right: &IonInput, partitions: usize) -> Result<()> {
+        let right_partitions = self.partition(right, partitions, Side::Right)?;
+        let left_partitions = self.partition(left, partitions, Side::Left)?;
+        for (left_file, right_file) in left_partitions.iter().zip(right_partitions.iter()) {
+            let right = open_partition(right_file)?;
+            // Partitions that still exceed the memory limit are loaded anyway.
+            let table = self.build(&right, None)?.unwrap_or_default();
+            let left = open_partition(left_file)?;
+            self.probe(&left, &table)?;
+        }
+        Ok(())
+    }
+
+    // Writes the values of `input` to temporary files according to the hash of their keys.
+    // Left values without a key can't match anything, so they are written to the output (in a
+    // left join) or dropped immediately; so are right values without a key.
+    fn partition(&mut self, input: &IonInput, partitions: usize, side: Side) -> Result<Vec<NamedTempFile>> {
+        let mut files = Vec::with_capacity(partitions);
+        let mut outputs = Vec::with_capacity(partitions);
+        for _ in 0..partitions {
+            let file = NamedTempFile::new()
+                .with_context(|| "Failed to create a temporary file for a partition.")?;
+            let path = file.path()
+                .to_str()
+                .with_context(|| "Temporary partition file path is not valid UTF-8")?;
+            let mut output = IonOutput::new("text", Some(path))?;
+            output.set_unknown_symbols(self.unknown_symbols);
+            outputs.push(output);
+            files.push(file);
+        }
+
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = read_value(&mut reader, input)?;
+            let keys = match side {
+                Side::Left => &mut self.left_keys,
+                Side::Right => &mut self.right_keys,
+            };
+            match key_of(keys, &value, input)? {
+                Some(key) => {
+                    let index = (fnv1a(key.as_bytes()) % partitions as u64) as usize;
+                    outputs[index].write_value(&value)?;
+                }
+                None if side == Side::Left => self.write_matches(&value, None)?,
+                None => {}
+            }
+        }
+        for output in outputs {
+            output.finish()?;
+        }
+        Ok(files)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+fn read_value(reader: &mut IonReader<'_>, input: &IonInput) -> Result<Value> {
+    Value::read(reader).with_context(|| format!("Could not read a value from '{}'", input.name()))
+}
+
+// Returns the key of `value`, or `None` if it doesn't have one. Null keys never match, so they
+// are treated as missing.
+fn key_of(keys: &mut KeyExtractor, value: &Value, input: &IonInput) -> Result<Option<String>> {
+    let key = keys.key_of(value)
+        .with_context(|| format!("Could not read the key of a value in '{}'", input.name()))?;
+    let is_null = keys.path().select(value).first().is_some_and(|key| key.is_null());
+    Ok(if is_null { None } else { key })
+}
+
+fn open_partition(file: &NamedTempFile) -> Result<IonInput> {
+    let path = file.path()
+        .to_str()
+        .with_context(|| "Temporary partition file path is not valid UTF-8")?;
+    // The partition is text Ion, whatever format the inputs are in.
+    IonInput::open_ion(path)
+}
+
+fn joined(left: Value, right: Value) -> Value {
+    Value::new(Data::Struct(vec![
+        (Symbol::from("left"), left),
+        (Symbol::from("right"), right),
+    ]))
+}
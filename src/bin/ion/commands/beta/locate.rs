@@ -0,0 +1,263 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{IonInput, IonReader, IVM, STDIN_NAME};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::text;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Finds the value containing a byte offset in binary Ion or a line and column in text Ion.";
+
+pub fn app() -> CommandConfig {
+    App::new("locate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("offset")
+                .long("offset")
+                .short("n")
+                .takes_value(true)
+                .help("Byte offset to look up in binary Ion, counted from the start of the stream"),
+        )
+        .arg(
+            Arg::with_name("line")
+                .long("line")
+                .short("l")
+                .takes_value(true)
+                .help("Line to look up in text Ion, starting from 1"),
+        )
+        .arg(
+            Arg::with_name("column")
+                .long("column")
+                .short("c")
+                .takes_value(true)
+                .requires("line")
+                .help("Column to look up in text Ion, in characters starting from 1 [default: 1]"),
+        )
+        .group(
+            ArgGroup::with_name("position")
+                .args(&["offset", "line"])
+                .required(true)
+        )
+        .arg(
+            Arg::with_name("print-value")
+                .long("print-value")
+                .short("p")
+                .help("With --line, include the text of the top-level value in the output"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .short("i")
+                .index(1)
+                .help("Input file [default: STDIN]"),
+        )
+        .after_help(
+            "Writes a struct like
+  {index: 12, offset: 4096, length: 210, path: \"('records' 3 'id')\",
+   field_name: \"id\", nested_offset: 4160, nested_length: 38, value: {...}}
+where `index`, `offset`, and `length` describe the top-level value
+containing the offset, `path` leads from it to the innermost value
+containing the offset, `field_name` is the last field name along that
+path, `nested_offset` and `nested_length` give that innermost value's
+byte range, and `value` is the complete top-level value. Byte ranges
+include each value's field name and annotations.
+
+With --line, writes a struct like
+  {index: 12, line: 1040, column: 1, path: \"('records' 3 'id')\",
+   field_name: \"id\", nested_line: 1042, nested_column: 5}
+where `line` and `column` give the start of the top-level value and
+`nested_line` and `nested_column` give the start of the innermost value.
+--print-value adds a `text` field holding the top-level value's source
+text. Top-level values are counted as they appear in the text, including
+any version markers and symbol tables. Only the text before the end of
+the top-level value is scanned, so the rest of the file doesn't need to
+be valid Ion."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    if matches.is_present("line") {
+        return locate_line(matches);
+    }
+    // Either --offset or --line is required, so we can unwrap this safely.
+    let offset_text = matches.value_of("offset").unwrap();
+    let offset = usize::from_str(offset_text)
+        .with_context(|| format!("'{}' is not a valid byte offset.", offset_text))?;
+    let input = IonInput::open(matches.value_of("input").unwrap_or(STDIN_NAME))?;
+    if input.was_transcoded() {
+        bail!("'{}' is text Ion; offsets can only be located in binary Ion.", input.name());
+    }
+    if offset >= input.bytes().len() {
+        bail!("Offset {} is past the end of '{}' ({} bytes).", offset, input.name(), input.bytes().len());
+    }
+
+    // Find the top-level value containing the offset.
+    let mut reader = input.reader();
+    let mut index = 0;
+    let (start, end) = loop {
+        if reader.next()?.is_none() {
+            bail!("Offset {} is not within a user value; it may be part of a symbol table.", offset);
+        }
+        let (start, end) = value_range(&reader);
+        if start <= offset && offset < end {
+            break (start, end);
+        }
+        if offset < start {
+            bail!("Offset {} is not within a user value; it may be part of a symbol table.", offset);
+        }
+        index += 1;
+    };
+    let (steps, field_name, nested_start, nested_end) = descend(&mut reader, offset, start, end)
+        .with_context(|| format!("Could not read top-level value {} of '{}'", index, input.name()))?;
+
+    // The reader has moved past the start of the value, so read it again from a fresh reader.
+    let mut reader = input.reader();
+    for _ in 0..=index {
+        reader.next()?;
+    }
+    let value = Value::read(&mut reader)
+        .with_context(|| format!("Could not read top-level value {} of '{}'", index, input.name()))?;
+
+    let mut fields = vec![
+        (Symbol::from("index"), Value::new(Data::Integer(index as i64))),
+        (Symbol::from("offset"), Value::new(Data::Integer(start as i64))),
+        (Symbol::from("length"), Value::new(Data::Integer((end - start) as i64))),
+        (Symbol::from("path"), Value::new(Data::String(Path::from(steps).to_string()))),
+    ];
+    if let Some(name) = field_name {
+        fields.push((Symbol::from("field_name"), Value::new(Data::String(name))));
+    }
+    fields.push((Symbol::from("nested_offset"), Value::new(Data::Integer(nested_start as i64))));
+    fields.push((Symbol::from("nested_length"), Value::new(Data::Integer((nested_end - nested_start) as i64))));
+    fields.push((Symbol::from("value"), value));
+
+    let mut output = IonOutput::from_matches(matches)?;
+    output.write_value(&Value::new(Data::Struct(fields)))?;
+    output.finish()
+}
+
+// Returns the range of bytes occupied by the reader's current value, including its field name
+// and annotations.
+fn value_range(reader: &IonReader) -> (usize, usize) {
+    let start = reader.field_id_offset()
+        .or_else(|| reader.annotations_offset())
+        .unwrap_or_else(|| reader.header_offset());
+    (start, reader.value_range().end)
+}
+
+// Steps into containers from the reader's current value for as long as one of their children
+// contains `offset`. Returns the path that was followed, the last field name along it, and the
+// byte range of the innermost value found.
+fn descend(reader: &mut IonReader, offset: usize, mut start: usize, mut end: usize)
+    -> Result<(Vec<Step>, Option<String>, usize, usize)> {
+    let mut steps = Vec::new();
+    let mut field_name = None;
+    loop {
+        // Offsets within a container's own header don't belong to any of its children.
+        let is_container = reader.ion_type().is_some_and(|t| t.is_container());
+        if !is_container || reader.is_null() || offset < reader.header_offset() + reader.header_length() {
+            break;
+        }
+        reader.step_in()?;
+        let mut index = 0;
+        let mut found = false;
+        while reader.next()?.is_some() {
+            let (child_start, child_end) = value_range(reader);
+            if child_start <= offset && offset < child_end {
+                let step = match reader.field_id() {
+                    Some(sid) => {
+                        let name = match reader.symbol_table().text_for(sid) {
+                            Some(text) => text.to_owned(),
+                            None => format!("${}", sid),
+                        };
+                        field_name = Some(name.clone());
+                        Step::Field(name)
+                    }
+                    None => Step::Index(index),
+                };
+                steps.push(step);
+                start = child_start;
+                end = child_end;
+                found = true;
+                break;
+            }
+            index += 1;
+        }
+        if !found {
+            break;
+        }
+    }
+    Ok((steps, field_name, start, end))
+}
+
+// Finds the value at a line and column of a text Ion document. The document is scanned
+// directly rather than being transcoded, since the byte offsets of the transcoded values
+// wouldn't correspond to positions in the text.
+fn locate_line(matches: &ArgMatches<'static>) -> Result<()> {
+    let line = parse_position(matches, "line")?;
+    let column = match matches.value_of("column") {
+        Some(_) => parse_position(matches, "column")?,
+        None => 1,
+    };
+    let input_name = matches.value_of("input").unwrap_or(STDIN_NAME);
+    let mut bytes = Vec::new();
+    if input_name == STDIN_NAME {
+        io::stdin().read_to_end(&mut bytes).with_context(|| "Failed to read STDIN.")?;
+    } else {
+        bytes = fs::read(input_name).with_context(|| format!("Could not read '{}'", input_name))?;
+    }
+    if bytes.starts_with(&IVM) {
+        bail!("'{}' is binary Ion; use --offset to locate values in it.", input_name);
+    }
+    let text = String::from_utf8(bytes)
+        .with_context(|| format!("'{}' is not valid UTF-8 text.", input_name))?;
+
+    let offset = text::offset_of(&text, line, column)?;
+    let (index, span) = text::find_top_level_value(&text, offset)
+        .with_context(|| format!("Could not scan '{}'", input_name))?;
+    let (steps, nested) = span.descend(offset);
+    let field_name = steps.iter().rev().find_map(|step| match step {
+        Step::Field(name) => Some(name.clone()),
+        _ => None,
+    });
+    let (line, column) = text::line_and_column(&text, span.start);
+    let (nested_line, nested_column) = text::line_and_column(&text, nested.start);
+
+    let mut fields = vec![
+        (Symbol::from("index"), Value::new(Data::Integer(index as i64))),
+        (Symbol::from("line"), Value::new(Data::Integer(line as i64))),
+        (Symbol::from("column"), Value::new(Data::Integer(column as i64))),
+        (Symbol::from("path"), Value::new(Data::String(Path::from(steps).to_string()))),
+    ];
+    if let Some(name) = field_name {
+        fields.push((Symbol::from("field_name"), Value::new(Data::String(name))));
+    }
+    fields.push((Symbol::from("nested_line"), Value::new(Data::Integer(nested_line as i64))));
+    fields.push((Symbol::from("nested_column"), Value::new(Data::Integer(nested_column as i64))));
+    if matches.is_present("print-value") {
+        let source = text[span.start..span.end].to_owned();
+        fields.push((Symbol::from("text"), Value::new(Data::String(source))));
+    }
+
+    let mut output = IonOutput::from_matches(matches)?;
+    output.write_value(&Value::new(Data::Struct(fields)))?;
+    output.finish()
+}
+
+// Parses a 1-based line or column number.
+fn parse_position(matches: &ArgMatches<'static>, name: &str) -> Result<usize> {
+    let text = matches.value_of(name).unwrap();
+    match usize::from_str(text) {
+        Ok(position) if position > 0 => Ok(position),
+        _ => bail!("--{} must be a positive integer, not '{}'.", name, text),
+    }
+}
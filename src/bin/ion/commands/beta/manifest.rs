@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::{Path as FilePath, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+use sha2::{Digest, Sha256};
+
+use crate::commands::CommandConfig;
+use crate::input::{IonInput, IVM};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{read_projection, Path};
+use crate::timestamp::Timestamp;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Writes a manifest describing each Ion file in a directory.";
+
+pub fn app() -> CommandConfig {
+    App::new("manifest")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("timestamp")
+                .long("timestamp")
+                .short("t")
+                .takes_value(true)
+                .help("Path to a timestamp in each value; its range is included in the manifest"),
+        )
+        .arg(
+            Arg::with_name("extension")
+                .long("extension")
+                .short("e")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .default_value("ion")
+                .default_value("10n")
+                .help("Only include files with this extension"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(
+            Arg::with_name("directory")
+                .index(1)
+                .default_value(".")
+                .help("Directory to search, including its subdirectories"),
+        )
+        .after_help(
+            "Writes one struct per file, in order of their paths, like
+  {file: \"2021/06/part-0.10n\", size: 52133, format: binary,
+   ion_version: '$ion_1_0', values: 1200, sha256: \"9f86d0...\",
+   min_timestamp: 2021-06-01T00:00:02Z, max_timestamp: 2021-06-01T23:59:58Z}
+The timestamp fields are only included when --timestamp is given, and
+are null if no value had a timestamp at that path. The digest is
+computed over the file's bytes as they are stored."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --directory and --extension have default values, so we can unwrap them safely.
+    let directory = PathBuf::from(matches.value_of("directory").unwrap());
+    let extensions: Vec<&str> = matches.values_of("extension").unwrap().collect();
+    let timestamp_path = match matches.value_of("timestamp") {
+        Some(path) => Some(Path::from_str(path).with_context(|| "Invalid --timestamp path")?),
+        None => None,
+    };
+
+    let mut files = Vec::new();
+    find_files(&directory, &extensions, &mut files)?;
+    files.sort();
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for file in &files {
+        let entry = describe(&directory, file, timestamp_path.as_ref())?;
+        output.write_value(&entry)?;
+    }
+    output.finish()
+}
+
+// Appends the path of every file beneath `directory` with one of the given extensions to `files`.
+fn find_files(directory: &FilePath, extensions: &[&str], files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("Could not read directory '{}'", directory.display()))?
+            .path();
+        if path.is_dir() {
+            find_files(&path, extensions, files)?;
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| extensions.contains(&e)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Creates the manifest entry for a single file.
+fn describe(directory: &FilePath, file: &FilePath, timestamp_path: Option<&Path>) -> Result<Value> {
+    let file_name = file.to_str()
+        .with_context(|| format!("File path {:?} is not valid UTF-8", file))?;
+    let bytes = fs::read(file).with_context(|| format!("Could not read '{}'", file_name))?;
+    let is_binary = has_version_marker(&bytes);
+
+    let mut value_count = 0;
+    // The earliest and latest timestamps, kept as they were written.
+    let mut min_timestamp: Option<Timestamp> = None;
+    let mut max_timestamp: Option<Timestamp> = None;
+    let input = IonInput::open(file_name)?;
+    let mut reader = input.reader();
+    while reader.next()?.is_some() {
+        value_count += 1;
+        let path = match timestamp_path {
+            Some(path) => path,
+            None => continue,
+        };
+        let value = read_projection(&mut reader, &[path])
+            .with_context(|| format!("Could not read a value from '{}'", file_name))?;
+        for selected in path.select(&value) {
+            if let Data::Timestamp(timestamp) = &selected.data {
+                let instant = timestamp.instant();
+                if min_timestamp.as_ref().is_none_or(|min| instant < min.instant()) {
+                    min_timestamp = Some(timestamp.clone());
+                }
+                if max_timestamp.as_ref().is_none_or(|max| instant > max.instant()) {
+                    max_timestamp = Some(timestamp.clone());
+                }
+            }
+        }
+    }
+
+    let relative_name = file.strip_prefix(directory).unwrap_or(file).to_string_lossy().into_owned();
+    let digest: String = Sha256::digest(&bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let mut fields = vec![
+        (Symbol::from("file"), Value::new(Data::String(relative_name))),
+        (Symbol::from("size"), Value::new(Data::Integer(bytes.len() as i64))),
+        (Symbol::from("format"), Value::new(Data::Symbol(Symbol::from(if is_binary { "binary" } else { "text" })))),
+        (Symbol::from("ion_version"), Value::new(Data::Symbol(Symbol::from(ion_version(&bytes))))),
+        (Symbol::from("values"), Value::new(Data::Integer(value_count))),
+        (Symbol::from("sha256"), Value::new(Data::String(digest))),
+    ];
+    if timestamp_path.is_some() {
+        for (name, timestamp) in &[("min_timestamp", min_timestamp), ("max_timestamp", max_timestamp)] {
+            let data = match timestamp {
+                Some(timestamp) => Data::Timestamp(timestamp.clone()),
+                None => Data::Null(IonType::Timestamp),
+            };
+            fields.push((Symbol::from(*name), Value::new(data)));
+        }
+    }
+    Ok(Value::new(Data::Struct(fields)))
+}
+
+// Returns the version marker of the Ion stream in `bytes`. Text streams without an explicit
+// version marker are Ion 1.0.
+fn ion_version(bytes: &[u8]) -> String {
+    if has_version_marker(bytes) {
+        return format!("$ion_{}_{}", bytes[1], bytes[2]);
+    }
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]);
+    match text.split_whitespace().next() {
+        Some(token) if token.starts_with("$ion_") => token.to_owned(),
+        _ => "$ion_1_0".to_owned(),
+    }
+}
+
+// Tests whether `bytes` begins with a binary Ion version marker for any version of Ion.
+fn has_version_marker(bytes: &[u8]) -> bool {
+    bytes.len() >= IVM.len() && bytes[0] == IVM[0] && bytes[3] == IVM[3]
+}
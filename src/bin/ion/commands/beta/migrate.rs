@@ -0,0 +1,243 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::schema::{authority_arg, ion_text, Authority};
+use crate::timestamp::Timestamp;
+use crate::validation::{type_name, Validator};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Renames, retypes, defaults, and drops fields according to a migration spec.";
+
+pub fn app() -> CommandConfig {
+    App::new("migrate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("spec")
+                .long("spec")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .help("Ion file listing the migration's steps"),
+        )
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .takes_value(true)
+                .requires("type")
+                .help("ISL schema to validate each migrated value against"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .takes_value(true)
+                .requires("schema")
+                .help("Type in --schema that each migrated value must match"),
+        )
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "The spec holds one step per top-level value, applied to each value in
+order. Each step names the field it changes with a path, which must end
+in a field name:
+  rename::{path: \"(customer email)\", to: contact_email}
+  retype::{path: \"(amount)\", to: decimal}
+  default::{path: \"(region)\", value: \"us-east-1\"}
+  drop::{path: \"(legacy_id)\"}
+
+rename renames every field at the path. retype converts each value at the
+path to bool, int, float, decimal, timestamp, string, or symbol: text is
+parsed, numbers are converted if they fit, and any scalar can become
+text. Nulls become nulls of the new type. default adds the field to each
+struct at the parent path that doesn't have it, or replaces it if it's
+null. drop removes every field at the path.
+
+With --schema and --type, each migrated value is validated against the
+type, and the migration stops at the first value that doesn't match.
+Type references are resolved as in the `schema` commands, relative to
+--authority."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --spec is required, so we can unwrap it safely.
+    let spec_file = matches.value_of("spec").unwrap();
+    let steps = IonInput::open(spec_file)?
+        .read_all()?
+        .iter()
+        .map(MigrationStep::from_value)
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("Invalid migration spec '{}'", spec_file))?;
+    let mut target = match (matches.value_of("schema"), matches.value_of("type")) {
+        (Some(schema_file), Some(type_name)) => {
+            let authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+            let id = authority.id_of(schema_file)?;
+            Some((Validator::new(authority), id, type_name))
+        }
+        _ => None,
+    };
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        let mut index = 0;
+        while reader.next()?.is_some() {
+            index += 1;
+            let mut value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for step in &steps {
+                step.apply(&mut value)
+                    .with_context(|| format!("Could not migrate value {} of '{}'", index, input.name()))?;
+            }
+            if let Some((validator, id, type_name)) = &mut target {
+                let violations = validator.validate(&value, id, type_name)?;
+                if !violations.is_empty() {
+                    let report: Vec<String> = violations
+                        .iter()
+                        .map(|violation| format!("  {}: {}", violation.path, violation.message))
+                        .collect();
+                    bail!(
+                        "Value {} of '{}' doesn't match type '{}' after migration:\n{}",
+                        index,
+                        input.name(),
+                        type_name,
+                        report.join("\n")
+                    );
+                }
+            }
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
+
+enum Operation {
+    Rename(String),
+    Retype(IonType),
+    Default(Value),
+    Drop,
+}
+
+struct MigrationStep {
+    // The path to the structs that hold the field, and the field's name.
+    parent: Path,
+    field: String,
+    operation: Operation,
+}
+
+impl MigrationStep {
+    fn from_value(step: &Value) -> Result<MigrationStep> {
+        let text_field = |name: &str| match step.get(name).and_then(Value::as_text) {
+            Some(text) => Ok(text),
+            None => bail!("The step {} needs a `{}` field of text.", ion_text(step), name),
+        };
+        let path_text = text_field("path")?;
+        let path = Path::from_str(path_text)?;
+        let (parent, field) = match path.split_last() {
+            Some((parent, Step::Field(field))) => (parent, field.clone()),
+            _ => bail!("The path '{}' doesn't end in a field name.", path_text),
+        };
+        let operation = match step.annotations.first().and_then(Symbol::text) {
+            Some("rename") => Operation::Rename(text_field("to")?.to_owned()),
+            Some("retype") => Operation::Retype(match text_field("to")? {
+                "bool" => IonType::Boolean,
+                "int" => IonType::Integer,
+                "float" => IonType::Float,
+                "decimal" => IonType::Decimal,
+                "timestamp" => IonType::Timestamp,
+                "string" => IonType::String,
+                "symbol" => IonType::Symbol,
+                other => bail!("Values can't be retyped to '{}'.", other),
+            }),
+            Some("default") => match step.get("value") {
+                Some(value) => Operation::Default(value.clone()),
+                None => bail!("The step {} needs a `value` field.", ion_text(step)),
+            },
+            Some("drop") => Operation::Drop,
+            _ => bail!("Each step must be annotated with rename, retype, default, or drop, unlike {}.", ion_text(step)),
+        };
+        Ok(MigrationStep { parent, field, operation })
+    }
+
+    fn apply(&self, value: &mut Value) -> Result<()> {
+        self.parent.for_each_mut(value, &mut |parent| {
+            let fields = match &mut parent.data {
+                Data::Struct(fields) => fields,
+                _ => return Ok(()),
+            };
+            match &self.operation {
+                Operation::Rename(new_name) => {
+                    for (name, _) in fields.iter_mut().filter(|(name, _)| name == self.field.as_str()) {
+                        *name = Symbol::from(new_name.as_str());
+                    }
+                }
+                Operation::Retype(ion_type) => {
+                    for (_, field_value) in fields.iter_mut().filter(|(name, _)| name == self.field.as_str()) {
+                        *field_value = retyped(field_value, *ion_type)
+                            .with_context(|| format!("Could not retype field '{}'", self.field))?;
+                    }
+                }
+                Operation::Default(default) => {
+                    match fields.iter_mut().find(|(name, _)| name == self.field.as_str()) {
+                        Some((_, field_value)) if field_value.is_null() => *field_value = default.clone(),
+                        Some(_) => {}
+                        None => fields.push((Symbol::from(self.field.as_str()), default.clone())),
+                    }
+                }
+                Operation::Drop => fields.retain(|(name, _)| name != self.field.as_str()),
+            }
+            Ok(())
+        })
+    }
+}
+
+// Converts a scalar to another type, keeping its annotations.
+fn retyped(value: &Value, ion_type: IonType) -> Result<Value> {
+    let failed = || format!("{} can't be converted to {}", ion_text(value), type_name(ion_type));
+    let data = match (&value.data, ion_type) {
+        (Data::Null(_), _) => Data::Null(ion_type),
+        (data, _) if value.ion_type() == ion_type => data.clone(),
+        (Data::List(_) | Data::SExpression(_) | Data::Struct(_), _) => bail!(failed()),
+        (_, IonType::String) => Data::String(value.as_text().map_or_else(|| ion_text(value), str::to_owned)),
+        (_, IonType::Symbol) => Data::Symbol(Symbol::from(value.as_text().map_or_else(|| ion_text(value), str::to_owned))),
+        (_, IonType::Boolean) => match value.as_text() {
+            Some("true") => Data::Boolean(true),
+            Some("false") => Data::Boolean(false),
+            _ => bail!(failed()),
+        },
+        (Data::Float(f), IonType::Integer) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => Data::Integer(*f as i64),
+        (Data::Decimal(decimal), IonType::Integer) if decimal.is_integer() => {
+            Data::integer(decimal.with_scale(0).as_bigint_and_exponent().0)
+        }
+        (_, IonType::Integer) => {
+            Data::integer(value.as_text().and_then(|text| BigInt::from_str(text.trim()).ok()).with_context(failed)?)
+        }
+        (Data::Integer(n), IonType::Float) => Data::Float(*n as f64),
+        (Data::BigInteger(n), IonType::Float) => Data::Float(n.to_f64().with_context(failed)?),
+        (Data::Decimal(decimal), IonType::Float) => Data::Float(decimal.to_f64().with_context(failed)?),
+        (_, IonType::Float) => Data::Float(value.as_text().and_then(|text| text.trim().parse().ok()).with_context(failed)?),
+        (Data::Integer(n), IonType::Decimal) => Data::Decimal(BigDecimal::from(*n)),
+        (Data::BigInteger(n), IonType::Decimal) => Data::Decimal(BigDecimal::new(n.clone(), 0)),
+        (Data::Float(f), IonType::Decimal) => Data::Decimal(BigDecimal::from_f64(*f).with_context(failed)?),
+        (_, IonType::Decimal) => {
+            Data::Decimal(value.as_text().and_then(|text| BigDecimal::from_str(text.trim()).ok()).with_context(failed)?)
+        }
+        (_, IonType::Timestamp) => match value.as_text().map(|text| text.trim().parse::<Timestamp>()) {
+            Some(Ok(timestamp)) => Data::Timestamp(timestamp),
+            _ => bail!(failed()),
+        },
+        _ => bail!(failed()),
+    };
+    Ok(Value { annotations: value.annotations.clone(), data })
+}
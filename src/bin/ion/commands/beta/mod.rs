@@ -1,4 +1,49 @@
+pub mod agg;
+pub mod assemble;
+pub mod blob;
+pub mod cardinality;
+pub mod check_partition;
+pub mod compress;
+#[cfg(feature = "kafka")]
+pub mod consume;
+pub mod convert;
+pub mod decrypt_fields;
+pub mod dict;
+pub mod encrypt_fields;
+pub mod explain_encoding;
+pub mod explode;
+pub mod fetch;
+pub mod filter;
+pub mod flatten;
+pub mod from;
+pub mod generate;
+pub mod get;
+pub mod get_scalar;
 pub mod inspect;
+pub mod join;
+pub mod locate;
+pub mod manifest;
+pub mod migrate;
+pub mod pipeline;
+#[cfg(feature = "kafka")]
+pub mod produce;
+pub mod repair;
+pub mod route;
+pub mod sample;
+pub mod scan;
+pub mod schema;
+pub mod shard;
+pub mod sign;
+pub mod size;
+pub mod sketch;
+pub mod template;
+pub mod to;
+pub mod topn;
+pub mod truncate;
+pub mod unflatten;
+pub mod verify;
+pub mod watch_dir;
+pub mod wrap;
 
 use anyhow::Result;
 use clap::{App, ArgMatches};
@@ -9,14 +54,105 @@ use crate::commands::{CommandRunner, CommandConfig};
 
 // Creates a Vec of CLI configurations for all of the available built-in commands
 pub fn beta_subcommands() -> Vec<CommandConfig> {
-    vec![
+    #[allow(unused_mut)]
+    let mut subcommands = vec![
+        agg::app(),
+        assemble::app(),
+        blob::app(),
+        cardinality::app(),
+        check_partition::app(),
+        compress::app(),
+        convert::app(),
+        decrypt_fields::app(),
+        dict::app(),
+        encrypt_fields::app(),
+        explain_encoding::app(),
+        explode::app(),
+        fetch::app(),
+        filter::app(),
+        flatten::app(),
+        from::app(),
+        generate::app(),
+        get::app(),
+        get_scalar::app(),
         inspect::app(),
-    ]
+        join::app(),
+        locate::app(),
+        manifest::app(),
+        migrate::app(),
+        pipeline::app(),
+        repair::app(),
+        route::app(),
+        sample::app(),
+        scan::app(),
+        schema::app(),
+        shard::app(),
+        sign::app(),
+        size::app(),
+        sketch::app(),
+        template::app(),
+        to::app(),
+        topn::app(),
+        truncate::app(),
+        unflatten::app(),
+        verify::app(),
+        watch_dir::app(),
+        wrap::app(),
+    ];
+    // Commands that depend on optional features are only available when they're enabled.
+    #[cfg(feature = "kafka")]
+    subcommands.extend(vec![consume::app(), produce::app()]);
+    subcommands
 }
 
 pub fn runner_for_beta_subcommand(command_name: &str) -> Option<CommandRunner> {
     let runner = match command_name {
+        "agg" => agg::run,
+        "assemble" => assemble::run,
+        "blob" => blob::run,
+        "cardinality" => cardinality::run,
+        "check-partition" => check_partition::run,
+        "compress" => compress::run,
+        #[cfg(feature = "kafka")]
+        "consume" => consume::run,
+        "convert" => convert::run,
+        "decrypt-fields" => decrypt_fields::run,
+        "dict" => dict::run,
+        "encrypt-fields" => encrypt_fields::run,
+        "explain-encoding" => explain_encoding::run,
+        "explode" => explode::run,
+        "fetch" => fetch::run,
+        "filter" => filter::run,
+        "flatten" => flatten::run,
+        "from" => from::run,
+        "generate" => generate::run,
+        "get" => get::run,
+        "get-scalar" => get_scalar::run,
         "inspect" => inspect::run,
+        "join" => join::run,
+        "locate" => locate::run,
+        "manifest" => manifest::run,
+        "migrate" => migrate::run,
+        "pipeline" => pipeline::run,
+        #[cfg(feature = "kafka")]
+        "produce" => produce::run,
+        "repair" => repair::run,
+        "route" => route::run,
+        "sample" => sample::run,
+        "scan" => scan::run,
+        "schema" => schema::run,
+        "shard" => shard::run,
+        "sign" => sign::run,
+        "size" => size::run,
+        "sketch" => sketch::run,
+        "template" => template::run,
+        "to" => to::run,
+        "topn" => topn::run,
+        "truncate" => truncate::run,
+        "unflatten" => unflatten::run,
+        "verify" => verify::run,
+        "watch-dir" => watch_dir::run,
+        "wrap" => wrap::run,
         _ => return None
     };
     Some(runner)
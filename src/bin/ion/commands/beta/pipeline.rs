@@ -0,0 +1,535 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+
+use super::agg::path_name;
+use crate::commands::{exit_status, exit_status_arg, CommandConfig};
+use crate::input::{input_arg, input_names, IonInput};
+use crate::json::{to_json, Dialect};
+use crate::nested::{decode_nested, read_document};
+use crate::output::{output_arg, output_writer, IonOutput};
+use crate::path::Path;
+use crate::profile::{self, Phase};
+use crate::transform::Transform;
+use crate::value::{Data, Symbol, TextFormatter, UnknownSymbols, Value};
+
+const ABOUT: &str = "Runs values through a sequence of stages in a single pass.";
+
+pub fn app() -> CommandConfig {
+    App::new("pipeline")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("pipeline")
+                .long("pipeline")
+                .short("p")
+                .takes_value(true)
+                .help("Stages separated by '|', e.g. 'filter: status == \"ERROR\" | extract: id, message | to: csv'"),
+        )
+        .arg(
+            Arg::with_name("pipeline-file")
+                .long("pipeline-file")
+                .short("F")
+                .takes_value(true)
+                .help("File containing the pipeline, with one stage per line"),
+        )
+        .group(
+            ArgGroup::with_name("spec")
+                .args(&["pipeline", "pipeline-file"])
+                .required(true)
+        )
+        .arg(
+            Arg::with_name("raw-strings")
+                .long("raw-strings")
+                .short("r")
+                .help("Write strings and symbols as their bare text, without quotes or escapes"),
+        )
+        .arg(
+            Arg::with_name("print0")
+                .long("print0")
+                .short("0")
+                .requires("raw-strings")
+                .help("With --raw-strings, follow each value with a NUL character instead of a newline"),
+        )
+        .arg(exit_status_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each top-level value is read once and passed through the stages in
+order; a stage may change the value or drop it. Stages:
+  filter: PATH OP ION     keeps values where a value at PATH compares to
+                          the Ion value with OP: ==, !=, <, <=, >, or >=
+  filter: PATH            keeps values with a non-null value at PATH
+  extract: PATH, ...      replaces each value with a struct of the values
+                          at the paths, named by their last field names
+  select: PATH            replaces each value with the value at PATH, or a
+                          list if there are several; drops values where
+                          PATH selects nothing
+  transform: SPEC         decodes values like `dump --transform`
+  decode-nested           decodes nested Ion like `dump --decode-nested`
+  drop-null-fields        removes struct fields whose values are null
+  strip-annotations       removes all annotations
+  limit: N                keeps only the first N values to get this far
+  to: FORMAT              writes the values as binary, text, or pretty
+                          Ion, JSON Lines (json), or CSV (csv)
+`to` may only be the last stage; without it, values are written as
+pretty Ion.
+
+Paths are written as for other commands, e.g. `status` or
+`(request headers host)`. Strings and symbols compare by their text,
+and numbers of any type by their numeric value, so `status == \"ERROR\"`
+also matches the symbol ERROR. Other values are only equal if they hold
+the same data, and can't be ordered. A value with several values at
+PATH is kept if any of them compares.
+
+In a --pipeline-file, each non-empty line is a stage, and lines starting
+with `#` are ignored. CSV columns are the fields of the first value.
+
+With --raw-strings, top-level strings and symbols are written as their
+text, like `jq -r`, so the results can be passed to other commands:
+  ion beta pipeline -r -p 'filter: status == \"ERROR\" | select: id' logs.ion
+Other values are written as Ion text on a single line, or as JSON with
+`to: json`, and can't be written as binary or CSV. Text containing line
+breaks is written as is; use --print0 to end each value with a NUL
+character instead, e.g. for `xargs -0`.
+
+With --exit-status, the exit status is 1 if no values made it through
+the pipeline and 2 if it failed, so it can be used as a condition:
+  if ion beta pipeline --exit-status -p 'filter: level == FATAL' log.ion; then"
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let written = run_pipeline(matches);
+    if matches.is_present("exit-status") {
+        return exit_status(written);
+    }
+    written.map(|_| ())
+}
+
+// Writes the values that make it through the pipeline and returns whether there were any.
+fn run_pipeline(matches: &ArgMatches<'static>) -> Result<bool> {
+    let spec = match (matches.value_of("pipeline"), matches.value_of("pipeline-file")) {
+        (Some(spec), _) => spec.to_owned(),
+        // The `spec` group is required, so one of them was given.
+        (None, file_name) => {
+            let file_name = file_name.unwrap();
+            let text = fs::read_to_string(file_name)
+                .with_context(|| format!("Could not read pipeline file '{}'", file_name))?;
+            text.lines()
+                .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        }
+    };
+    let (mut stages, format) = parse_pipeline(&spec)?;
+    let raw_terminator = match (matches.is_present("raw-strings"), matches.is_present("print0")) {
+        (false, _) => None,
+        (true, false) => Some('\n'),
+        (true, true) => Some('\0'),
+    };
+    let mut sink = Sink::new(&format, matches.value_of("output"), raw_terminator)?;
+    let mut written = false;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        'values: while reader.next()?.is_some() {
+            let mut value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for stage in stages.iter_mut() {
+                value = match profile::time(Phase::Transform, || stage.apply(value))
+                    .with_context(|| format!("Could not process a value from '{}'", input.name()))? {
+                    Some(value) => value,
+                    None => continue 'values,
+                };
+            }
+            sink.write(&value)?;
+            written = true;
+        }
+    }
+    sink.finish()?;
+    Ok(written)
+}
+
+// Splits a pipeline into its stages and the format named by its final `to` stage.
+fn parse_pipeline(spec: &str) -> Result<(Vec<Stage>, String)> {
+    let mut stages = Vec::new();
+    let mut format = None;
+    for stage_text in split_outside_quotes(spec, '|') {
+        let stage_text = stage_text.trim();
+        if format.is_some() {
+            bail!("The 'to' stage must be the last stage of the pipeline, but it's followed by '{}'.", stage_text);
+        }
+        let (name, argument) = match stage_text.split_once(':') {
+            Some((name, argument)) => (name.trim(), argument.trim()),
+            None => (stage_text, ""),
+        };
+        let stage = match (name, argument) {
+            ("filter", "") | ("extract", "") | ("select", "") | ("transform", "") | ("limit", "") | ("to", "") => {
+                bail!("The '{}' stage needs an argument, e.g. '{}: ...'.", name, name)
+            }
+            ("filter", argument) => Stage::Filter(Filter::from_str(argument)?),
+            ("extract", argument) => {
+                let mut fields = Vec::new();
+                for path_text in split_outside_quotes(argument, ',') {
+                    let path = Path::from_str(path_text.trim())
+                        .with_context(|| format!("Invalid path '{}' in extract stage", path_text.trim()))?;
+                    fields.push((path_name(&path), path));
+                }
+                Stage::Extract(fields)
+            }
+            ("select", argument) => Stage::Select(Path::from_str(argument)
+                .with_context(|| format!("Invalid path '{}' in select stage", argument))?),
+            ("transform", argument) => Stage::Transform(Transform::from_str(argument)?),
+            ("decode-nested", "") => Stage::DecodeNested,
+            ("drop-null-fields", "") => Stage::DropNullFields,
+            ("strip-annotations", "") => Stage::StripAnnotations,
+            ("limit", argument) => match argument.parse() {
+                Ok(limit) => Stage::Limit(limit),
+                Err(_) => bail!("The limit stage needs a number of values, not '{}'.", argument),
+            },
+            ("to", "binary") | ("to", "text") | ("to", "pretty") | ("to", "json") | ("to", "csv") => {
+                format = Some(argument.to_owned());
+                continue;
+            }
+            ("to", argument) => bail!("Unknown format '{}' in to stage; expected binary, text, pretty, json, or csv.", argument),
+            ("", _) => bail!("The pipeline has an empty stage."),
+            (name, _) if argument.is_empty() => bail!("Unknown pipeline stage '{}'.", name),
+            (name, _) => bail!("Unknown pipeline stage '{}', or it doesn't take an argument.", name),
+        };
+        stages.push(stage);
+    }
+    Ok((stages, format.unwrap_or_else(|| "pretty".to_owned())))
+}
+
+// Splits `text` at each `separator` that isn't inside quotes or parentheses.
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') | (None, '[') | (None, '{') => depth += 1,
+            (None, ')') | (None, ']') | (None, '}') => depth -= 1,
+            (None, c) if c == separator && depth == 0 => {
+                pieces.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&text[start..]);
+    pieces
+}
+
+enum Stage {
+    Filter(Filter),
+    Extract(Vec<(String, Path)>),
+    Select(Path),
+    Transform(Transform),
+    DecodeNested,
+    DropNullFields,
+    StripAnnotations,
+    // The number of values still to be kept.
+    Limit(usize),
+}
+
+impl Stage {
+    // Returns the value the stage passes on, or None if it drops the value.
+    fn apply(&mut self, mut value: Value) -> Result<Option<Value>> {
+        match self {
+            Stage::Filter(filter) => {
+                if !filter.matches(&value) {
+                    return Ok(None);
+                }
+            }
+            Stage::Extract(fields) => {
+                let mut extracted = Vec::with_capacity(fields.len());
+                for (name, path) in fields.iter() {
+                    let selected = match path.select(&value).as_slice() {
+                        [] => continue,
+                        [single] => (*single).clone(),
+                        several => Value::new(Data::List(several.iter().map(|v| (*v).clone()).collect())),
+                    };
+                    extracted.push((Symbol::from(name.as_str()), selected));
+                }
+                value = Value::new(Data::Struct(extracted));
+            }
+            Stage::Select(path) => {
+                value = match path.select(&value).as_slice() {
+                    [] => return Ok(None),
+                    [single] => (*single).clone(),
+                    several => Value::new(Data::List(several.iter().map(|v| (*v).clone()).collect())),
+                };
+            }
+            Stage::Transform(transform) => transform.apply(&mut value)?,
+            Stage::DecodeNested => decode_nested(&mut value),
+            Stage::DropNullFields => value.drop_null_fields(),
+            Stage::StripAnnotations => strip_annotations(&mut value),
+            Stage::Limit(0) => return Ok(None),
+            Stage::Limit(remaining) => *remaining -= 1,
+        }
+        Ok(Some(value))
+    }
+}
+
+fn strip_annotations(value: &mut Value) {
+    value.annotations.clear();
+    match &mut value.data {
+        Data::List(children) | Data::SExpression(children) => children.iter_mut().for_each(strip_annotations),
+        Data::Struct(fields) => fields.iter_mut().for_each(|(_, child)| strip_annotations(child)),
+        _ => {}
+    }
+}
+
+// A test of the values at a path, like `status == "ERROR"`, or `status` alone to test that the
+// path selects a non-null value.
+struct Filter {
+    path: Path,
+    comparison: Option<(Operator, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+// Longer operators come first so that `<=` isn't read as `<`.
+const OPERATORS: [(&str, Operator); 6] = [
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    ("<=", Operator::LessOrEqual),
+    (">=", Operator::GreaterOrEqual),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+];
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        // The operator is the first one outside of the path's quotes and parentheses.
+        let found = OPERATORS.iter().find_map(|(symbol, operator)| {
+            split_outside_quotes(text, symbol.chars().next().unwrap())
+                .first()
+                .map(|before| before.len())
+                .filter(|offset| text[*offset..].starts_with(symbol))
+                .map(|offset| (offset, *symbol, *operator))
+        });
+        let (path_text, comparison) = match found {
+            Some((offset, symbol, operator)) => {
+                let literal_text = text[offset + symbol.len()..].trim();
+                let literal = match read_document(literal_text.as_bytes())
+                    .with_context(|| format!("Invalid Ion value '{}' in filter stage", literal_text))?
+                    .as_slice() {
+                    [literal] => literal.clone(),
+                    _ => bail!("The filter stage needs exactly one Ion value after {}, not '{}'.", symbol, literal_text),
+                };
+                (&text[..offset], Some((operator, literal)))
+            }
+            None => (text, None),
+        };
+        let path = Path::from_str(path_text.trim())
+            .with_context(|| format!("Invalid path '{}' in filter stage", path_text.trim()))?;
+        Ok(Filter { path, comparison })
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let selected = self.path.select(value);
+        match &self.comparison {
+            None => selected.iter().any(|v| !v.is_null()),
+            Some((operator, literal)) => selected.iter().any(|v| {
+                let ordering = compare(v, literal);
+                match operator {
+                    Operator::Equal => ordering == Some(Ordering::Equal),
+                    Operator::NotEqual => ordering != Some(Ordering::Equal),
+                    Operator::Less => ordering == Some(Ordering::Less),
+                    Operator::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Operator::Greater => ordering == Some(Ordering::Greater),
+                    Operator::GreaterOrEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }),
+        }
+    }
+}
+
+// Compares two values for a filter. Text compares with text and numbers with numbers, whatever
+// their types. Other values are equal if they have the same type and data, and are unordered.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (a.as_text(), b.as_text()) {
+        return Some(a.cmp(b));
+    }
+    match (&a.data, &b.data) {
+        (Data::Timestamp(a), Data::Timestamp(b)) => return Some(a.instant().cmp(&b.instant())),
+        (Data::Null(_), _) | (_, Data::Null(_)) => {}
+        (Data::Integer(_) | Data::BigInteger(_) | Data::Decimal(_) | Data::Float(_),
+         Data::Integer(_) | Data::BigInteger(_) | Data::Decimal(_) | Data::Float(_)) => {
+            return match (exact_number(a), exact_number(b)) {
+                (Some(a), Some(b)) => Some(a.cmp(&b)),
+                _ => float_number(a).partial_cmp(&float_number(b)),
+            };
+        }
+        _ => {}
+    }
+    let mut formatter = TextFormatter::new();
+    formatter.set_unknown_symbols(UnknownSymbols::Placeholder);
+    let (mut a_text, mut b_text) = (String::new(), String::new());
+    match (formatter.format(a, &mut a_text), formatter.format(b, &mut b_text)) {
+        (Ok(()), Ok(())) if a_text == b_text => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn exact_number(value: &Value) -> Option<BigDecimal> {
+    match &value.data {
+        Data::Integer(i) => Some(BigDecimal::from(*i)),
+        Data::BigInteger(i) => Some(BigDecimal::new(i.clone(), 0)),
+        Data::Decimal(d) => Some(d.clone()),
+        _ => None,
+    }
+}
+
+fn float_number(value: &Value) -> f64 {
+    match &value.data {
+        Data::Integer(i) => *i as f64,
+        Data::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
+        Data::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+        Data::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+// Where the values that make it through the pipeline are written.
+enum Sink {
+    Ion(IonOutput),
+    Json(BufWriter<Box<dyn Write>>),
+    Csv {
+        writer: BufWriter<Box<dyn Write>>,
+        // The columns, taken from the first value's fields.
+        columns: Option<Vec<String>>,
+        formatter: TextFormatter,
+    },
+    // Strings and symbols are written as their text, and other values as single-line Ion text or,
+    // for `to: json`, as JSON. Each is followed by `terminator`.
+    Raw {
+        writer: BufWriter<Box<dyn Write>>,
+        formatter: TextFormatter,
+        json: bool,
+        terminator: char,
+    },
+}
+
+impl Sink {
+    fn new(format: &str, output_file: Option<&str>, raw_terminator: Option<char>) -> Result<Sink> {
+        if let Some(terminator) = raw_terminator {
+            if format != "text" && format != "pretty" && format != "json" {
+                bail!("--raw-strings can't be used with {} output; it needs text, pretty, or json.", format);
+            }
+            let writer = output_writer(output_file)?;
+            return Ok(Sink::Raw { writer, formatter: TextFormatter::new(), json: format == "json", terminator });
+        }
+        if format != "json" && format != "csv" {
+            return Ok(Sink::Ion(IonOutput::new(format, output_file)?));
+        }
+        let writer = output_writer(output_file)?;
+        if format == "json" {
+            return Ok(Sink::Json(writer));
+        }
+        let mut formatter = TextFormatter::new();
+        formatter.set_unknown_symbols(UnknownSymbols::Placeholder);
+        Ok(Sink::Csv { writer, columns: None, formatter })
+    }
+
+    fn write(&mut self, value: &Value) -> Result<()> {
+        match self {
+            Sink::Ion(output) => output.write_value(value),
+            Sink::Json(writer) => {
+                let json = to_json(value, Dialect::Plain)?;
+                writeln!(writer, "{}", json).with_context(|| "Failed to write to the output.")
+            }
+            Sink::Csv { writer, columns, formatter } => {
+                let fields = match &value.data {
+                    Data::Struct(fields) => fields,
+                    _ => bail!("CSV output needs struct values, but found a {:?}.", value.ion_type()),
+                };
+                let columns = match columns {
+                    Some(columns) => columns,
+                    None => {
+                        let names: Vec<String> = fields
+                            .iter()
+                            .map(|(name, _)| name.text().unwrap_or_default().to_owned())
+                            .collect();
+                        let header: Vec<String> = names.iter().map(|name| csv_quote(name)).collect();
+                        writeln!(writer, "{}", header.join(",")).with_context(|| "Failed to write to the output.")?;
+                        columns.insert(names)
+                    }
+                };
+                let mut cells = Vec::with_capacity(columns.len());
+                for column in columns.iter() {
+                    let cell = match fields.iter().find(|(name, _)| name.text() == Some(column.as_str())) {
+                        None => String::new(),
+                        Some((_, field)) if field.is_null() => String::new(),
+                        Some((_, field)) => match field.as_text() {
+                            Some(text) => text.to_owned(),
+                            None => {
+                                let mut text = String::new();
+                                formatter.format(field, &mut text)?;
+                                text
+                            }
+                        },
+                    };
+                    cells.push(csv_quote(&cell));
+                }
+                writeln!(writer, "{}", cells.join(",")).with_context(|| "Failed to write to the output.")
+            }
+            Sink::Raw { writer, formatter, json, terminator } => {
+                let text = match value.as_text() {
+                    Some(text) => text.to_owned(),
+                    None if *json => to_json(value, Dialect::Plain)?.to_string(),
+                    None => {
+                        let mut text = String::new();
+                        formatter.format(value, &mut text)?;
+                        text
+                    }
+                };
+                write!(writer, "{}{}", text, terminator).with_context(|| "Failed to write to the output.")
+            }
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Sink::Ion(output) => output.finish(),
+            Sink::Json(mut writer) | Sink::Csv { mut writer, .. } | Sink::Raw { mut writer, .. } => {
+                writer.flush().with_context(|| "Failed to write to the output.")
+            }
+        }
+    }
+}
+
+// Quotes a CSV cell if it contains a delimiter, quote, or line break.
+pub fn csv_quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
@@ -0,0 +1,188 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext};
+use rdkafka::{ClientConfig, ClientContext};
+
+use super::consume::parse_property;
+use crate::commands::CommandConfig;
+use crate::framing::for_each_record;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::key::KeyExtractor;
+use crate::path::Path;
+use crate::value::{TextFormatter, UnknownSymbols, Value};
+
+const ABOUT: &str = "Sends each top-level value to a Kafka topic as a message of its own.";
+
+// How long to wait for the messages still queued to be delivered before giving up.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn app() -> CommandConfig {
+    App::new("produce")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("broker")
+                .long("broker")
+                .short("b")
+                .takes_value(true)
+                .required(true)
+                .help("Kafka broker to connect to, e.g. 'localhost:9092'; can be a comma-separated list"),
+        )
+        .arg(
+            Arg::with_name("topic")
+                .long("topic")
+                .short("t")
+                .takes_value(true)
+                .required(true)
+                .help("Topic to send messages to"),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .help("Use the value at this path as each message's key, e.g. '(customer_id)'"),
+        )
+        .arg(
+            Arg::with_name("message-format")
+                .long("message-format")
+                .short("m")
+                .takes_value(true)
+                .default_value("binary")
+                .possible_values(&["binary", "text"])
+                .help("How to encode each message's value"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .short("X")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Set a librdkafka configuration property, written as 'name=value'"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Each binary message is a complete Ion stream, with its own version marker
+and symbol table, so consumers can read it without any other message.
+Text messages are compact text Ion.
+
+With --key, each message's key is the text of the string or symbol at
+the path, or the compact text Ion of any other value (e.g. `42`).
+Values without one are sent without a key. A path that selects more
+than one value is an error.
+
+Only available when ion-cli is built with the `kafka` feature."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --broker, --topic, and --message-format are required or have default values, so we can
+    // unwrap these safely.
+    let broker = matches.value_of("broker").unwrap();
+    let topic = matches.value_of("topic").unwrap();
+    let text_messages = matches.value_of("message-format").unwrap() == "text";
+    let mut key = match matches.value_of("key") {
+        Some(path) => Some(KeyExtractor::new(Path::from_str(path).with_context(|| "Invalid --key path")?)),
+        None => None,
+    };
+
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", broker);
+    for property in matches.values_of("config").into_iter().flatten() {
+        let (name, value) = parse_property(property)?;
+        config.set(name, value);
+    }
+    let producer: BaseProducer<DeliveryReports> = config.create_with_context(DeliveryReports::default())
+        .with_context(|| format!("Could not create a Kafka producer for '{}'", broker))?;
+
+    let mut formatter = TextFormatter::new();
+    formatter.set_unknown_symbols(UnknownSymbols::Placeholder);
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        for_each_record(input.bytes(), |reader, record| {
+            // The value itself is only needed for its key or its text.
+            let value = if key.is_some() || text_messages {
+                Some(Value::read(reader)
+                    .with_context(|| format!("Could not read a value from '{}'", input.name()))?)
+            } else {
+                None
+            };
+            let message_key = match (&mut key, &value) {
+                (Some(keys), Some(value)) => match keys.path().select(value).as_slice() {
+                    [selected] if selected.as_text().is_some() => selected.as_text().map(str::to_owned),
+                    _ => keys.key_of(value)?,
+                },
+                _ => None,
+            };
+            let payload = match &value {
+                Some(value) if text_messages => {
+                    let mut text = String::new();
+                    formatter.format(value, &mut text)?;
+                    text.into_bytes()
+                }
+                _ => record,
+            };
+            send(&producer, topic, message_key.as_deref(), &payload)
+        })?;
+    }
+    producer.flush(FLUSH_TIMEOUT);
+    if producer.in_flight_count() > 0 {
+        bail!(
+            "{} messages had not been delivered to '{}' after {} seconds.",
+            producer.in_flight_count(),
+            topic,
+            FLUSH_TIMEOUT.as_secs()
+        );
+    }
+    let failures = producer.context().failures.lock().unwrap();
+    if let Some(first_error) = failures.first() {
+        bail!("{} messages could not be delivered to '{}'; the first failed with: {}", failures.len(), topic, first_error);
+    }
+    Ok(())
+}
+
+// Keeps the errors that librdkafka reports for messages it couldn't deliver. The producer only
+// reports them while it's being polled or flushed.
+#[derive(Default)]
+struct DeliveryReports {
+    failures: Mutex<Vec<String>>,
+}
+
+impl ClientContext for DeliveryReports {}
+
+impl ProducerContext for DeliveryReports {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult, _: ()) {
+        if let Err((error, _)) = result {
+            self.failures.lock().unwrap().push(error.to_string());
+        }
+    }
+}
+
+// Queues a message to be sent, waiting for room in the queue if it's full.
+fn send(producer: &BaseProducer<DeliveryReports>, topic: &str, key: Option<&str>, payload: &[u8]) -> Result<()> {
+    loop {
+        let mut record: BaseRecord<str, [u8]> = BaseRecord::to(topic).payload(payload);
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+        match producer.send(record) {
+            Ok(()) => break,
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                producer.poll(Duration::from_millis(100));
+            }
+            Err((error, _)) => {
+                return Err(error).with_context(|| format!("Could not send a message to topic '{}'", topic));
+            }
+        }
+    }
+    // Serve delivery reports so that the queue drains as we go.
+    producer.poll(Duration::from_millis(0));
+    Ok(())
+}
@@ -0,0 +1,380 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use anyhow::Result;
+use clap::{App, Arg, ArgMatches};
+
+use crate::binary::{encoded_length, is_system_value, stream_preamble, SymbolTableTracker};
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, reader_for, IonInput, IVM};
+use crate::output::{format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::value::{ConversionError, Data, Symbol, UnknownSymbols, Value};
+
+const ABOUT: &str = "Recovers every readable top-level value from a damaged binary Ion stream.";
+
+// The annotation on the structs that --annotate-errors writes in place of damaged regions.
+const ERROR_ANNOTATION: &str = "ion_error";
+
+// When looking for a place to resume reading after a damaged region, a candidate offset is only
+// accepted if this many values in a row can be read from it (or it leads to the end of the file).
+const RESYNC_CONFIRMATION_VALUES: usize = 3;
+
+pub fn app() -> CommandConfig {
+    App::new("repair")
+        .about(ABOUT)
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .short("r")
+                .takes_value(true)
+                .help("Write the report as Ion to this file [default: STDERR, as prose]")
+                .long_help(
+                    "The report lists each byte range that had to be skipped, as a struct
+like {input: \"data.10n\", start: 1024, end: 1337, length: 313}."
+                ),
+        )
+        .arg(
+            Arg::with_name("annotate-errors")
+                .long("annotate-errors")
+                .short("e")
+                .help("Write a struct describing each damaged region, with its bytes, in place of it"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Values are read until the first decoding error. The repair command then
+searches for the next offset at which the stream can be read again
+(an Ion version marker, or several consecutive readable values) and
+continues from there, using the symbol table that was in effect when
+the error occurred. Each skipped byte range is reported.
+
+With --annotate-errors, each skipped range is also written to the
+output where it was found, as a struct like
+  ion_error::{input: \"data.10n\", start: 1024, end: 1337,
+              error: \"...\", bytes: {{...}}}
+so that the values around it keep their positions in the stream. When a
+value's header is intact but its contents can't be decoded, like a
+string that isn't valid UTF-8, only that value is skipped, so each bad
+value is replaced one for one. `bytes` holds the skipped bytes as they
+appear in the input, after decompression; symbols in them refer to the
+symbol table that was in effect where they were found.
+
+Only values that are encoded incorrectly count as damage. A value that's
+intact but can't be read, like a struct with a repeated field name under
+--duplicate-fields error, stops the repair with an error instead."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let mut output = IonOutput::from_matches(matches)?;
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    output.set_unknown_symbols(UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()));
+
+    // ion-rs can panic when it encounters some kinds of malformed data. We treat those panics as
+    // ordinary decoding errors, so their messages are silenced while we work.
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let annotate_errors = matches.is_present("annotate-errors");
+    let mut damage = Vec::new();
+    let mut values_recovered = 0;
+    let result = input_names(matches).iter().try_for_each(|input_name| -> Result<()> {
+        let input = IonInput::open(input_name)?;
+        let mut repair = Repair {
+            input_name: input.name(),
+            output: &mut output,
+            annotate_errors,
+            values_recovered: &mut values_recovered,
+        };
+        for skipped in repair.stream(input.bytes())? {
+            damage.push((input.name().to_owned(), skipped));
+        }
+        Ok(())
+    });
+    panic::set_hook(default_panic_hook);
+    result?;
+    output.finish()?;
+
+    match matches.value_of("report") {
+        Some(report_file) => {
+            let mut report = IonOutput::new("text", Some(report_file))?;
+            for (input_name, range) in &damage {
+                report.write_value(&Value::new(Data::Struct(vec![
+                    (Symbol::from("input"), Value::new(Data::String(input_name.clone()))),
+                    (Symbol::from("start"), Value::new(Data::Integer(range.start as i64))),
+                    (Symbol::from("end"), Value::new(Data::Integer(range.end as i64))),
+                    (Symbol::from("length"), Value::new(Data::Integer(range.len() as i64))),
+                ])))?;
+            }
+            report.finish()?;
+        }
+        None => {
+            for (input_name, range) in &damage {
+                eprintln!("{}: skipped {} bytes at offsets {}..{}",
+                          input_name, range.len(), range.start, range.end);
+            }
+        }
+    }
+    eprintln!("Recovered {} values; skipped {} damaged region(s).", values_recovered, damage.len());
+    Ok(())
+}
+
+// Recovers the values in one input.
+struct Repair<'a> {
+    input_name: &'a str,
+    output: &'a mut IonOutput,
+    // Whether each skipped range is written to the output in place of the values it held.
+    annotate_errors: bool,
+    values_recovered: &'a mut usize,
+}
+
+impl Repair<'_> {
+    // Writes every value that can be read from `data` to the output, returning the byte ranges
+    // that had to be skipped.
+    fn stream(&mut self, data: &[u8]) -> Result<Vec<Range<usize>>> {
+        let mut skipped = Vec::new();
+        // The local symbols in effect at `position`.
+        let mut symbols: Vec<String> = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            let segment = read_segment(data, position, &symbols);
+            for value in &segment.values {
+                self.output.write_value(value)?;
+            }
+            *self.values_recovered += segment.values.len();
+            symbols = segment.symbols;
+            // A value that's intact but can't be converted isn't damage, so it isn't skipped.
+            if let Some(error) = segment.unconvertible {
+                return Err(error.context(format!(
+                    "The value at offset {} of '{}' isn't damaged, but can't be read",
+                    segment.end, self.input_name
+                )));
+            }
+
+            if !segment.failed {
+                // The reader may stop quietly at a value that was cut short, so make sure that
+                // whatever follows the last value read consists of complete values.
+                if !is_well_formed(&data[segment.end..]) {
+                    self.skip(data, segment.end..data.len(), "The stream ends partway through a value.")?;
+                    skipped.push(segment.end..data.len());
+                }
+                break;
+            }
+            let range = match &segment.bad_value {
+                // With an intact header, the bad value can be skipped on its own.
+                Some((range, _)) if self.annotate_errors && range.end <= data.len() => range.clone(),
+                _ => segment.end..find_resync_point(data, segment.end + 1, &symbols),
+            };
+            let error = segment.bad_value.map(|(_, error)| error)
+                .unwrap_or_else(|| "Could not find where the value ends.".to_owned());
+            self.skip(data, range.clone(), &error)?;
+            position = range.end;
+            skipped.push(range);
+        }
+        Ok(skipped)
+    }
+
+    // With --annotate-errors, writes a struct describing a skipped range in its place.
+    fn skip(&mut self, data: &[u8], range: Range<usize>, error: &str) -> Result<()> {
+        if !self.annotate_errors {
+            return Ok(());
+        }
+        let mut description = Value::new(Data::Struct(vec![
+            (Symbol::from("input"), Value::new(Data::String(self.input_name.to_owned()))),
+            (Symbol::from("start"), Value::new(Data::Integer(range.start as i64))),
+            (Symbol::from("end"), Value::new(Data::Integer(range.end as i64))),
+            (Symbol::from("error"), Value::new(Data::String(error.to_owned()))),
+            (Symbol::from("bytes"), Value::new(Data::Blob(data[range].to_vec()))),
+        ]));
+        description.annotations.push(Symbol::from(ERROR_ANNOTATION));
+        self.output.write_value(&description)
+    }
+}
+
+// The values that could be read from a stream starting at a given offset.
+struct Segment {
+    values: Vec<Value>,
+    // The offset just past the last value that was read successfully.
+    end: usize,
+    // The local symbols in effect at `end`.
+    symbols: Vec<String>,
+    // Whether reading stopped because of an error rather than the end of the stream.
+    failed: bool,
+    // If reading stopped at a value whose header could be read but whose contents couldn't, the
+    // value's byte range (with its annotations) and the error.
+    bad_value: Option<(Range<usize>, String)>,
+    // If reading stopped at a value that's encoded correctly but couldn't be converted to a Value,
+    // like a struct that breaks --duplicate-fields error, the error.
+    unconvertible: Option<anyhow::Error>,
+}
+
+// Reads values from `data`, starting at `position`, until the stream ends or an error occurs.
+// `symbols` are the local symbols that were in effect at `position`.
+fn read_segment(data: &[u8], position: usize, symbols: &[String]) -> Segment {
+    let (stream, prefix_length) = resumable_stream(&data[position..], symbols);
+    let mut reader = reader_for(&stream);
+    let (tracker, tracked_symbols) = SymbolTableTracker::new();
+    reader.set_symtab_event_handler(tracker);
+
+    let mut segment = Segment {
+        values: Vec::new(),
+        end: position,
+        symbols: symbols.to_vec(),
+        failed: false,
+        bad_value: None,
+        unconvertible: None,
+    };
+    loop {
+        // The range of the value being read, within `data`, once its header has been read.
+        let mut current = None;
+        let next_value = panic::catch_unwind(AssertUnwindSafe(|| -> Result<Option<(Value, usize)>> {
+            if reader.next()?.is_none() {
+                return Ok(None);
+            }
+            let start = reader.annotations_offset().unwrap_or_else(|| reader.header_offset());
+            let end = reader.value_range().end;
+            current = Some(position + start - prefix_length..position + end - prefix_length);
+            let value = Value::read(&mut reader)?;
+            Ok(Some((value, end)))
+        }));
+        match next_value {
+            Ok(Ok(Some((value, end)))) => {
+                segment.values.push(value);
+                segment.end = position + end - prefix_length;
+            }
+            Ok(Ok(None)) => break,
+            Ok(Err(error)) if error.chain().any(|cause| cause.is::<ConversionError>()) => {
+                segment.unconvertible = Some(error);
+                break;
+            }
+            Ok(Err(error)) => {
+                segment.failed = true;
+                segment.bad_value = current.map(|range| (range, format!("{:#}", error)));
+                break;
+            }
+            Err(_) => {
+                segment.failed = true;
+                segment.bad_value = current.map(|range| (range, "The value is malformed.".to_owned()));
+                break;
+            }
+        }
+    }
+    // The tracker also saw the symbol table in the preamble, if there was one.
+    segment.symbols = tracked_symbols.borrow().clone();
+    segment
+}
+
+// Returns a stream that can be read from the beginning of `data` (a slice of a larger stream)
+// along with the number of bytes that were prepended to make that possible.
+fn resumable_stream<'a>(data: &'a [u8], symbols: &[String]) -> (Cow<'a, [u8]>, usize) {
+    if data.starts_with(&IVM) {
+        return (Cow::Borrowed(data), 0);
+    }
+    let mut stream = stream_preamble(symbols);
+    let prefix_length = stream.len();
+    stream.extend_from_slice(data);
+    (Cow::Owned(stream), prefix_length)
+}
+
+// Scans forward from `start` for the first offset at which reading can plausibly resume. If there
+// is none, returns the length of `data`.
+fn find_resync_point(data: &[u8], start: usize, symbols: &[String]) -> usize {
+    (start..data.len())
+        .find(|candidate| {
+            data[*candidate..].starts_with(&IVM) || values_can_be_read_at(data, *candidate, symbols)
+        })
+        .unwrap_or(data.len())
+}
+
+// Tests whether several values in a row can be read starting at `candidate`.
+fn values_can_be_read_at(data: &[u8], candidate: usize, symbols: &[String]) -> bool {
+    // Checking the headers first is cheap and rules out most candidates.
+    let (end, values_expected) = match values_end(data, candidate, RESYNC_CONFIRMATION_VALUES) {
+        Some(found) => found,
+        None => return false,
+    };
+    // A value that can't be converted is still a sign that the stream is intact.
+    let segment = read_segment(&data[..end], candidate, symbols);
+    !segment.failed && (segment.end == end && segment.values.len() == values_expected || segment.unconvertible.is_some())
+}
+
+// Finds where the first `count` values from `start` end, using their headers alone, and how many
+// there were, which is fewer than `count` if the data ends first. Symbol tables and padding
+// aren't counted, since the reader doesn't return them, and neither is any that follow the last
+// value. Returns None if a header is invalid.
+fn values_end(data: &[u8], start: usize, count: usize) -> Option<(usize, usize)> {
+    let (mut position, mut end) = (start, start);
+    let mut values = 0;
+    while values < count && position < data.len() {
+        let length = encoded_length(&data[position..])?;
+        let is_value = !is_system_value(&data[position..position + length]);
+        position += length;
+        if is_value {
+            values += 1;
+            end = position;
+        }
+    }
+    Some((end, values))
+}
+
+// Tests whether `data` consists entirely of values with valid headers.
+fn is_well_formed(mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        match encoded_length(data) {
+            Some(length) => data = &data[length..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::encode_symbol_table;
+
+    // Two values, then padding and a symbol table, then two more values.
+    fn stream_with_system_values() -> Vec<u8> {
+        let mut data = vec![0x21, 0x01, 0x82, b'a', b'b', 0x02, 0x00, 0x00];
+        data.extend(encode_symbol_table(&["a".to_owned()]));
+        data.extend(&[0x20, 0x21, 0x02]);
+        data
+    }
+
+    #[test]
+    fn symbol_tables_and_padding_arent_counted_as_values() {
+        let data = stream_with_system_values();
+        let third_value_end = data.len() - 2;
+        assert_eq!(values_end(&data, 0, 3), Some((third_value_end, 3)));
+        assert_eq!(values_end(&data, 0, 2), Some((5, 2)));
+        // Starting at the padding, the symbol table is skipped as well.
+        assert_eq!(values_end(&data, 5, 1), Some((third_value_end, 1)));
+    }
+
+    #[test]
+    fn values_end_stops_at_the_end_of_the_data() {
+        assert_eq!(values_end(&[0x20, 0x21, 0x01], 0, 3), Some((3, 2)));
+        // Padding after the last value isn't part of it.
+        assert_eq!(values_end(&[0x20, 0x00], 0, 3), Some((1, 1)));
+        assert_eq!(values_end(&[0x00, 0x00], 0, 3), Some((0, 0)));
+    }
+
+    #[test]
+    fn values_end_rejects_invalid_headers() {
+        // Type code 15 is reserved.
+        assert_eq!(values_end(&[0x20, 0xF0, 0x20], 0, 3), None);
+        // The string's length runs past the end of the data.
+        assert_eq!(values_end(&[0x20, 0x85, b'a'], 0, 3), None);
+    }
+
+    #[test]
+    fn well_formed_data_holds_only_complete_values() {
+        assert!(is_well_formed(&[]));
+        assert!(is_well_formed(&stream_with_system_values()));
+        assert!(!is_well_formed(&[0x20, 0x21]));
+        assert!(!is_well_formed(&[0xF0]));
+    }
+}
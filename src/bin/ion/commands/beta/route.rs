@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::key::KeyExtractor;
+use crate::output::{format_arg, IonOutput};
+use crate::path::Path;
+use crate::value::Value;
+
+const ABOUT: &str = "Sends each top-level value to an output file chosen by its annotation or a field.";
+
+pub fn app() -> CommandConfig {
+    App::new("route")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("route")
+                .long("route")
+                .short("r")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Sends values with a key to a file, written as key=>file, e.g. 'audit=>audit.10n'"),
+        )
+        .arg(
+            Arg::with_name("default")
+                .long("default")
+                .short("d")
+                .takes_value(true)
+                .help("File for values that don't match any route [default: they are dropped]"),
+        )
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .short("k")
+                .takes_value(true)
+                .help("Route by the value at this path, e.g. '(type)' [default: the first annotation]"),
+        )
+        .arg(format_arg())
+        .arg(input_arg())
+        .after_help(
+            "By default, each value is routed by the text of its first annotation.
+With --field, it is routed by the value at the path instead: the text of
+a string or symbol, or the compact text Ion of any other value (e.g.
+`42` or `[a, b]`). Files ending in .10n are written as binary Ion; other
+files use --format. Several routes can share a file. A --field path
+that selects more than one value is an error."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --format has a default value, so we can unwrap this safely.
+    let format = matches.value_of("format").unwrap();
+    let mut router = Router {
+        routes: HashMap::new(),
+        outputs: Vec::new(),
+        output_indexes: HashMap::new(),
+        default: None,
+    };
+    // --route is required, so we can unwrap this safely.
+    for route in matches.values_of("route").unwrap() {
+        let (key, file_name) = match route.find("=>") {
+            Some(index) => (&route[..index], &route[index + 2..]),
+            None => bail!("--route '{}' should be written as key=>file.", route),
+        };
+        let index = router.output_for(file_name, format)?;
+        if router.routes.insert(key.to_owned(), index).is_some() {
+            bail!("There is more than one route for '{}'.", key);
+        }
+    }
+    if let Some(file_name) = matches.value_of("default") {
+        router.default = Some(router.output_for(file_name, format)?);
+    }
+    let mut field = match matches.value_of("field") {
+        Some(path) => Some(KeyExtractor::new(Path::from_str(path)?)),
+        None => None,
+    };
+
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let key = match &mut field {
+                Some(keys) => match keys.path().select(&value).as_slice() {
+                    [selected] if selected.as_text().is_some() => selected.as_text().map(str::to_owned),
+                    _ => keys.key_of(&value)
+                        .with_context(|| format!("Could not read the route of a value in '{}'", input.name()))?,
+                },
+                None => value.annotations.first().and_then(|annotation| annotation.text()).map(str::to_owned),
+            };
+            let index = key.and_then(|key| router.routes.get(&key).copied()).or(router.default);
+            if let Some(index) = index {
+                router.outputs[index].write_value(&value)?;
+            }
+        }
+    }
+    for output in router.outputs {
+        output.finish()?;
+    }
+    Ok(())
+}
+
+struct Router {
+    // Maps each key to the index of its output.
+    routes: HashMap<String, usize>,
+    outputs: Vec<IonOutput>,
+    // Maps each file name to the index of its output, so that routes can share files.
+    output_indexes: HashMap<String, usize>,
+    default: Option<usize>,
+}
+
+impl Router {
+    // Returns the index of the output for `file_name`, opening it if necessary.
+    fn output_for(&mut self, file_name: &str, format: &str) -> Result<usize> {
+        if let Some(index) = self.output_indexes.get(file_name) {
+            return Ok(*index);
+        }
+        let format = if file_name.ends_with(".10n") { "binary" } else { format };
+        self.outputs.push(IonOutput::new(format, Some(file_name))?);
+        self.output_indexes.insert(file_name.to_owned(), self.outputs.len() - 1);
+        Ok(self.outputs.len() - 1)
+    }
+}
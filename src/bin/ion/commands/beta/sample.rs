@@ -0,0 +1,167 @@
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{deterministic, format_arg, output_arg, unknown_symbols_arg, IonOutput};
+use crate::size::{check_max_memory, SizeEstimator};
+use crate::value::{UnknownSymbols, Value};
+
+const ABOUT: &str = "Writes a random sample of the top-level values in a stream.";
+
+pub fn app() -> CommandConfig {
+    App::new("sample")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .short("n")
+                .takes_value(true)
+                .help("Select exactly this many values (or all of them, if there are fewer)"),
+        )
+        .arg(
+            Arg::with_name("probability")
+                .long("probability")
+                .short("p")
+                .takes_value(true)
+                .help("Select each value independently with this probability, from 0 to 1"),
+        )
+        .group(
+            ArgGroup::with_name("mode")
+                .args(&["count", "probability"])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed for the random number generator [default: chosen at random]")
+                .long_help(
+                    "Seed for the random number generator. Sampling the same input with the
+same seed always selects the same values. If no seed is given, one is
+chosen at random and written to STDERR so the sample can be reproduced."
+                ),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(unknown_symbols_arg())
+        .arg(input_arg())
+        .after_help(
+            "When several inputs are given, they are sampled as a single stream.
+Selected values are written in the order they appeared in the input,
+with their annotations. Binary output only defines the symbols that
+the selected values use.
+
+With --probability, each value is written as soon as it's selected.
+With --count, the selected values are held in memory until the end;
+with the global --max-memory, sample fails if they would take more."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let seed = match matches.value_of("seed") {
+        Some(seed) => seed.parse::<u64>()
+            .with_context(|| format!("Invalid seed '{}'; expected a non-negative integer", seed))?,
+        None if deterministic() => bail!("--deterministic requires a --seed."),
+        None => {
+            let seed = rand::random();
+            eprintln!("Sampling with seed {}", seed);
+            seed
+        }
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let inputs = input_names(matches)
+        .iter()
+        .map(|name| IonInput::open(name))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut output = IonOutput::from_matches(matches)?;
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    output.set_unknown_symbols(UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()));
+    if let Some(count) = matches.value_of("count") {
+        let count = count.parse::<usize>()
+            .with_context(|| format!("Invalid count '{}'", count))?;
+        for value in &reservoir_sample(&inputs, count, &mut rng)? {
+            output.write_value(value)?;
+        }
+    } else {
+        // The `mode` group is required, so --probability must be present.
+        let probability = matches.value_of("probability").unwrap();
+        let probability = match probability.parse::<f64>() {
+            Ok(p) if (0.0..=1.0).contains(&p) => p,
+            _ => bail!("Invalid probability '{}'; expected a number from 0 to 1", probability),
+        };
+        bernoulli_sample(&inputs, probability, &mut rng, &mut output)?;
+    }
+    output.finish()
+}
+
+// Calls `f` with each top-level value in `inputs`, in order.
+fn for_each_value<F>(inputs: &[IonInput], mut f: F) -> Result<()>
+where
+    F: FnMut(Value) -> Result<()>,
+{
+    for input in inputs {
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            f(value)?;
+        }
+    }
+    Ok(())
+}
+
+// Selects `count` values uniformly at random using reservoir sampling (Algorithm R), which
+// only needs to hold the selected values in memory.
+fn reservoir_sample(inputs: &[IonInput], count: usize, rng: &mut StdRng) -> Result<Vec<Value>> {
+    // Each selected value is stored with its position in the stream so that the sample can be
+    // put back in order. The vectors grow as values are read, since `count` may be far more than
+    // the inputs hold.
+    let mut reservoir: Vec<(usize, Value)> = Vec::new();
+    let mut seen = 0;
+    // The approximate size of each value in the reservoir, and their total, for --max-memory.
+    let mut estimator = SizeEstimator::new();
+    let mut sizes: Vec<usize> = Vec::new();
+    let mut estimated_size = 0;
+    for_each_value(inputs, |value| {
+        let slot = if reservoir.len() < count {
+            reservoir.push((seen, value));
+            sizes.push(0);
+            reservoir.len() - 1
+        } else {
+            let slot = rng.gen_range(0..=seen);
+            if slot < count {
+                reservoir[slot] = (seen, value);
+            }
+            slot
+        };
+        seen += 1;
+        if slot < count {
+            let size = estimator.size_of(&reservoir[slot].1)?;
+            estimated_size = estimated_size - sizes[slot] + size;
+            sizes[slot] = size;
+            check_max_memory(
+                estimated_size,
+                || format!("Holding a sample of {} values", reservoir.len()),
+                "try a smaller --count, or --probability",
+            )?;
+        }
+        Ok(())
+    })?;
+    reservoir.sort_by_key(|(position, _)| *position);
+    Ok(reservoir.into_iter().map(|(_, value)| value).collect())
+}
+
+// Selects each value independently with the given probability and writes it to `output`.
+fn bernoulli_sample(inputs: &[IonInput], probability: f64, rng: &mut StdRng, output: &mut IonOutput) -> Result<()> {
+    for_each_value(inputs, |value| {
+        if rng.gen_bool(probability) {
+            output.write_value(&value)?;
+        }
+        Ok(())
+    })
+}
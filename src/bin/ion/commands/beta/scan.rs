@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use regex::bytes::Regex;
+
+use crate::commands::{exit_status, exit_status_arg, CommandConfig};
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Reports where values matching identifier patterns (UUIDs, ARNs, etc.) appear.";
+
+// The patterns that can be selected with --builtin.
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("uuid", r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b"),
+    ("arn", r"\barn:[a-z0-9-]+:[a-z0-9-]+:[a-z0-9-]*:[0-9]{0,12}:[^\s\x22']+"),
+    ("account-id", r"\b[0-9]{12}\b"),
+];
+
+pub fn app() -> CommandConfig {
+    App::new("scan")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("builtin")
+                .long("builtin")
+                .short("b")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(&["uuid", "arn", "account-id"])
+                .help("Search for a built-in pattern [default: all of them, unless --pattern is given]"),
+        )
+        .arg(
+            Arg::with_name("pattern")
+                .long("pattern")
+                .short("p")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Search for a named regular expression, written as name=regex"),
+        )
+        .arg(exit_status_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Strings, symbols, field names, annotations, blobs, and clobs are searched.
+Writes one struct per pattern and location, like
+  {pattern: uuid, path: \"('records' * 'id')\", location: value, count: 12}
+where `location` is `value`, `field_name`, or `annotation`. List and
+s-expression indexes in paths are replaced with `*` so that matches in
+every element of a list are counted together. With --exit-status, the
+exit status is 1 if nothing matched."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let found = scan(matches);
+    if matches.is_present("exit-status") {
+        return exit_status(found);
+    }
+    found.map(|_| ())
+}
+
+// Writes where each pattern matched and returns whether any did.
+fn scan(matches: &ArgMatches<'static>) -> Result<bool> {
+    let patterns = patterns(matches)?;
+    let mut scanner = Scanner {
+        patterns: &patterns,
+        steps: Vec::new(),
+        counts: BTreeMap::new(),
+    };
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            scanner.scan(&value);
+        }
+    }
+
+    let found = !scanner.counts.is_empty();
+    let mut output = IonOutput::from_matches(matches)?;
+    for ((pattern_index, path, location), count) in scanner.counts {
+        output.write_value(&Value::new(Data::Struct(vec![
+            (Symbol::from("pattern"), Value::new(Data::Symbol(Symbol::from(patterns[pattern_index].0.as_str())))),
+            (Symbol::from("path"), Value::new(Data::String(path))),
+            (Symbol::from("location"), Value::new(Data::Symbol(Symbol::from(location.name())))),
+            (Symbol::from("count"), Value::new(Data::Integer(count as i64))),
+        ])))?;
+    }
+    output.finish()?;
+    Ok(found)
+}
+
+// Compiles the patterns selected on the command line.
+fn patterns(matches: &ArgMatches<'static>) -> Result<Vec<(String, Regex)>> {
+    let mut patterns = Vec::new();
+    let builtins: Vec<&str> = match matches.values_of("builtin") {
+        Some(names) => names.collect(),
+        None if matches.is_present("pattern") => Vec::new(),
+        None => BUILTIN_PATTERNS.iter().map(|(name, _)| *name).collect(),
+    };
+    for (name, regex) in BUILTIN_PATTERNS {
+        if builtins.contains(name) {
+            // The built-in patterns are known to be valid.
+            patterns.push((name.to_string(), Regex::new(regex).unwrap()));
+        }
+    }
+    for pattern in matches.values_of("pattern").into_iter().flatten() {
+        let (name, regex) = match pattern.find('=') {
+            Some(index) => (&pattern[..index], &pattern[index + 1..]),
+            None => bail!("Pattern '{}' must have the form name=regex", pattern),
+        };
+        let regex = Regex::new(regex)
+            .with_context(|| format!("Invalid regular expression for pattern '{}'", name))?;
+        patterns.push((name.to_owned(), regex));
+    }
+    Ok(patterns)
+}
+
+// Where within a value a match was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Location {
+    Value,
+    FieldName,
+    Annotation,
+}
+
+impl Location {
+    fn name(&self) -> &'static str {
+        match self {
+            Location::Value => "value",
+            Location::FieldName => "field_name",
+            Location::Annotation => "annotation",
+        }
+    }
+}
+
+struct Scanner<'a> {
+    patterns: &'a [(String, Regex)],
+    // The path to the value currently being scanned.
+    steps: Vec<Step>,
+    // The number of matches for each pattern (by index), path, and location.
+    counts: BTreeMap<(usize, String, Location), usize>,
+}
+
+impl<'a> Scanner<'a> {
+    fn scan(&mut self, value: &Value) {
+        for annotation in &value.annotations {
+            self.scan_symbol(annotation, Location::Annotation);
+        }
+        match &value.data {
+            Data::String(text) => self.scan_bytes(text.as_bytes(), Location::Value),
+            Data::Symbol(symbol) => self.scan_symbol(symbol, Location::Value),
+            Data::Blob(bytes) | Data::Clob(bytes) => self.scan_bytes(bytes, Location::Value),
+            Data::List(values) | Data::SExpression(values) => {
+                self.steps.push(Step::Wildcard);
+                for child in values {
+                    self.scan(child);
+                }
+                self.steps.pop();
+            }
+            Data::Struct(fields) => {
+                for (name, child) in fields {
+                    self.steps.push(Step::Field(field_name(name)));
+                    self.scan_symbol(name, Location::FieldName);
+                    self.scan(child);
+                    self.steps.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scan_symbol(&mut self, symbol: &Symbol, location: Location) {
+        if let Some(text) = symbol.text() {
+            self.scan_bytes(text.as_bytes(), location);
+        }
+    }
+
+    fn scan_bytes(&mut self, bytes: &[u8], location: Location) {
+        for (index, (_, regex)) in self.patterns.iter().enumerate() {
+            let count = regex.find_iter(bytes).count();
+            if count > 0 {
+                let path = Path::from(self.steps.clone()).to_string();
+                *self.counts.entry((index, path, location)).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+// Symbols without known text are named by their symbol ID.
+fn field_name(name: &Symbol) -> String {
+    match (name.text(), name.sid()) {
+        (Some(text), _) => text.to_owned(),
+        (None, Some(sid)) => format!("${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::schema::{authority_arg, Authority};
+use crate::validation::Validator;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Reports which of a schema's types each value in the input matches.";
+
+pub fn app() -> CommandConfig {
+    App::new("classify")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .help("ISL schema whose types each value is checked against"),
+        )
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Checks every value against every type that --schema defines, and writes
+one struct per value listing the types it matches, which may be several
+or none:
+  {input: \"legacy.ion\", value: 3, types: [order_v1, order_v2]}
+Values are counted from 1 across all of the inputs. Afterwards, how many
+values match each type, and how many match none, is written to STDERR.
+Types the schema imports are used to check its own types, but aren't
+reported themselves. Type references are resolved as in the other
+`schema` commands, relative to --authority."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --schema is required, so we can unwrap it safely.
+    let schema_file = matches.value_of("schema").unwrap();
+    let mut authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+    let id = authority.id_of(schema_file)?;
+    let type_names: Vec<String> = authority.load(&id)?.types.iter().map(|definition| definition.name.clone()).collect();
+    if type_names.is_empty() {
+        bail!("Schema '{}' doesn't define any types.", id);
+    }
+    let mut validator = Validator::new(authority);
+
+    let mut output = IonOutput::from_matches(matches)?;
+    let mut values = 0;
+    // How many values match each type, in the order the schema defines them, and how many match
+    // none of them.
+    let mut counts = vec![0; type_names.len()];
+    let mut unmatched = 0;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            values += 1;
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let mut matched = Vec::new();
+            for (index, type_name) in type_names.iter().enumerate() {
+                if validator.validate(&value, &id, type_name)?.is_empty() {
+                    counts[index] += 1;
+                    matched.push(Value::new(Data::Symbol(Symbol::from(type_name.as_str()))));
+                }
+            }
+            if matched.is_empty() {
+                unmatched += 1;
+            }
+            output.write_value(&Value::new(Data::Struct(vec![
+                (Symbol::from("input"), Value::new(Data::String(input.name().to_owned()))),
+                (Symbol::from("value"), Value::new(Data::Integer(values as i64))),
+                (Symbol::from("types"), Value::new(Data::List(matched))),
+            ])))?;
+        }
+    }
+    output.finish()?;
+
+    for (type_name, count) in type_names.iter().zip(&counts) {
+        eprintln!("{}: {} of {} values match", type_name, count, values);
+    }
+    eprintln!("{} of {} values match none of the types", unmatched, values);
+    Ok(())
+}
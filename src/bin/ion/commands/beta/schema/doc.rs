@@ -0,0 +1,391 @@
+use std::io::Write;
+use std::path::Path as FilePath;
+
+use anyhow::Result;
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::output::output_writer;
+use crate::schema::{
+    as_reference, authority_arg, ion_text, Authority, TypeReference, REFERENCE_CONSTRAINTS,
+    REFERENCE_LIST_CONSTRAINTS,
+};
+use crate::value::{Data, Value};
+
+const ABOUT: &str = "Renders an Ion Schema as Markdown or HTML documentation.";
+
+pub fn app() -> CommandConfig {
+    App::new("doc")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .default_value("markdown")
+                .possible_values(&["markdown", "html"])
+                .help("Output format"),
+        )
+        .arg(authority_arg())
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(
+            Arg::with_name("schema")
+                .index(1)
+                .required(true)
+                .help("Schema file to document"),
+        )
+        .after_help(
+            "Writes a section for each type the schema defines, with a table of its
+constraints and, for struct types, a table of its fields. Types that the
+schema defines are linked to their sections. Imported types are linked
+to the documentation of the schema that defines them, which is expected
+to sit alongside this one with the schema's file extension replaced:
+the `meters` type in `units.isl` links to `units.md#meters`, or to
+`units.html#meters` with --format html.
+
+Imports are resolved against the --authority directory, so `imports:
+[{id: \"common/units.isl\"}]` reads `<authority>/common/units.isl`."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --format has a default value and `schema` is required, so we can unwrap these safely.
+    let format = matches.value_of("format").unwrap();
+    let schema_file = matches.value_of("schema").unwrap();
+    let mut authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+    let id = authority.id_of(schema_file)?;
+    let blocks = document(&mut authority, &id)?;
+
+    let extension = if format == "html" { "html" } else { "md" };
+    let text = match format {
+        "html" => render_html(&id, &blocks, extension),
+        _ => render_markdown(&blocks, extension),
+    };
+    let mut writer = output_writer(matches.value_of("output"))?;
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+// The documentation is built as a sequence of blocks, which are then rendered in the requested
+// format.
+enum Block {
+    Heading(usize, String),
+    Paragraph(Vec<Inline>),
+    List(Vec<Vec<Inline>>),
+    Table(&'static [&'static str], Vec<Vec<Vec<Inline>>>),
+}
+
+enum Inline {
+    Text(String),
+    Code(String),
+    // A link to a type. `schema` is None for types defined in the schema being documented.
+    TypeLink { text: String, schema: Option<String>, type_name: String },
+    // A link to the documentation of another schema.
+    SchemaLink(String),
+}
+
+fn document(authority: &mut Authority, id: &str) -> Result<Vec<Block>> {
+    let schema = authority.load(id)?;
+    let imports = schema.imports.clone();
+    let types: Vec<(String, Value)> = schema.types
+        .iter()
+        .map(|t| (t.name.clone(), t.definition.clone()))
+        .collect();
+
+    let mut blocks = vec![Block::Heading(1, format!("Schema {}", id))];
+    if !imports.is_empty() {
+        blocks.push(Block::Heading(2, "Imports".to_owned()));
+        let items = imports.iter().map(|import| {
+            let mut item = vec![Inline::SchemaLink(import.id.clone())];
+            match (&import.type_name, &import.alias) {
+                (Some(type_name), Some(alias)) => {
+                    item.push(Inline::Text(": type ".to_owned()));
+                    item.push(Inline::Code(type_name.clone()));
+                    item.push(Inline::Text(" as ".to_owned()));
+                    item.push(Inline::Code(alias.clone()));
+                }
+                (Some(type_name), None) => {
+                    item.push(Inline::Text(": type ".to_owned()));
+                    item.push(Inline::Code(type_name.clone()));
+                }
+                _ => item.push(Inline::Text(": all types".to_owned())),
+            }
+            item
+        }).collect();
+        blocks.push(Block::List(items));
+    }
+
+    blocks.push(Block::Heading(2, "Types".to_owned()));
+    if types.is_empty() {
+        blocks.push(Block::Paragraph(vec![Inline::Text("This schema defines no types.".to_owned())]));
+    }
+    for (name, definition) in &types {
+        blocks.push(Block::Heading(3, name.clone()));
+        let fields = match &definition.data {
+            Data::Struct(fields) => fields,
+            _ => continue,
+        };
+        let mut constraints = Vec::new();
+        let mut field_rows = Vec::new();
+        for (constraint, value) in fields {
+            match constraint.text() {
+                Some("name") => {}
+                Some("fields") => {
+                    if let Data::Struct(field_types) = &value.data {
+                        for (field_name, field_type) in field_types {
+                            field_rows.push(field_row(authority, id, field_name.text().unwrap_or("<unknown>"), field_type)?);
+                        }
+                    }
+                }
+                text => constraints.push(vec![
+                    vec![Inline::Code(text.unwrap_or("<unknown>").to_owned())],
+                    constraint_value(authority, id, text.unwrap_or_default(), value)?,
+                ]),
+            }
+        }
+        if !constraints.is_empty() {
+            blocks.push(Block::Table(&["Constraint", "Value"], constraints));
+        }
+        if !field_rows.is_empty() {
+            blocks.push(Block::Table(&["Field", "Type", "Occurs", "Constraints"], field_rows));
+        }
+    }
+    Ok(blocks)
+}
+
+// Describes a constraint's value, linking any types it refers to.
+fn constraint_value(authority: &mut Authority, id: &str, constraint: &str, value: &Value) -> Result<Vec<Inline>> {
+    let refers_to_types = REFERENCE_CONSTRAINTS.contains(&constraint);
+    let refers_to_type_list = REFERENCE_LIST_CONSTRAINTS.contains(&constraint);
+    match &value.data {
+        _ if refers_to_types => type_description(authority, id, value),
+        Data::List(elements) if refers_to_type_list => {
+            let mut inlines = Vec::new();
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    inlines.push(Inline::Text(", ".to_owned()));
+                }
+                inlines.extend(type_description(authority, id, element)?);
+            }
+            Ok(inlines)
+        }
+        _ => Ok(vec![Inline::Code(ion_text(value))]),
+    }
+}
+
+// Describes a type: a link if it's a reference to a type with its own documentation, or its
+// text otherwise.
+fn type_description(authority: &mut Authority, id: &str, value: &Value) -> Result<Vec<Inline>> {
+    let reference = match as_reference(value) {
+        Some(reference) => reference,
+        None => return Ok(vec![Inline::Code(ion_text(value))]),
+    };
+    let (text, target) = match reference {
+        TypeReference::Named(name) => {
+            let target = authority.resolve(id, &name)?;
+            (name, target)
+        }
+        TypeReference::Imported { id: schema, type_name } => {
+            let target = authority.resolve(&schema, &type_name)?;
+            (type_name, target)
+        }
+    };
+    let inline = match target {
+        Some((schema, type_name)) => Inline::TypeLink {
+            text,
+            schema: if schema == id { None } else { Some(schema) },
+            type_name,
+        },
+        // Built-in types like `string` have no documentation to link to.
+        None => Inline::Code(text),
+    };
+    Ok(vec![inline])
+}
+
+// Describes one of a struct type's fields. A field's type is usually a reference or an inline
+// type with a `type` constraint; the inline type's other constraints are listed separately.
+fn field_row(authority: &mut Authority, id: &str, name: &str, field_type: &Value) -> Result<Vec<Vec<Inline>>> {
+    let name = vec![Inline::Code(name.to_owned())];
+    let inline_constraints = match (&field_type.data, as_reference(field_type)) {
+        (Data::Struct(constraints), None) => constraints,
+        _ => return Ok(vec![
+            name,
+            type_description(authority, id, field_type)?,
+            vec![Inline::Text("optional".to_owned())],
+            Vec::new(),
+        ]),
+    };
+    let mut type_column = Vec::new();
+    let mut occurs = vec![Inline::Text("optional".to_owned())];
+    let mut others = Vec::new();
+    for (constraint, value) in inline_constraints {
+        match constraint.text() {
+            Some("type") => type_column = type_description(authority, id, value)?,
+            Some("occurs") => occurs = vec![Inline::Code(ion_text(value))],
+            text => {
+                if !others.is_empty() {
+                    others.push(Inline::Text(", ".to_owned()));
+                }
+                others.push(Inline::Code(text.unwrap_or("<unknown>").to_owned()));
+                others.push(Inline::Text(": ".to_owned()));
+                others.extend(constraint_value(authority, id, text.unwrap_or_default(), value)?);
+            }
+        }
+    }
+    Ok(vec![name, type_column, occurs, others])
+}
+
+// The documentation file for a schema: its ID with the extension replaced.
+fn documentation_file(schema: &str, extension: &str) -> String {
+    FilePath::new(schema).with_extension(extension).to_string_lossy().replace('\\', "/")
+}
+
+fn render_markdown(blocks: &[Block], extension: &str) -> String {
+    let mut text = String::new();
+    for block in blocks {
+        match block {
+            Block::Heading(level, heading) => {
+                text.push_str(&"#".repeat(*level));
+                text.push(' ');
+                text.push_str(heading);
+                text.push('\n');
+            }
+            Block::Paragraph(inlines) => {
+                text.push_str(&markdown_inlines(inlines, extension));
+                text.push('\n');
+            }
+            Block::List(items) => {
+                for item in items {
+                    text.push_str("- ");
+                    text.push_str(&markdown_inlines(item, extension));
+                    text.push('\n');
+                }
+            }
+            Block::Table(headers, rows) => {
+                text.push_str(&format!("| {} |\n", headers.join(" | ")));
+                text.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(|cell| markdown_inlines(cell, extension)).collect();
+                    text.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn markdown_inlines(inlines: &[Inline], extension: &str) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        // Pipes would end a table cell, so they're escaped everywhere.
+        let piece = match inline {
+            Inline::Text(t) => t.clone(),
+            Inline::Code(code) => markdown_code(code),
+            Inline::TypeLink { text, schema, type_name } => {
+                let file = schema.as_deref().map(|s| documentation_file(s, extension)).unwrap_or_default();
+                format!("[{}]({}#{})", markdown_code(text), file, type_name)
+            }
+            Inline::SchemaLink(schema) => {
+                format!("[{}]({})", markdown_code(schema), documentation_file(schema, extension))
+            }
+        };
+        text.push_str(&piece.replace('|', "\\|"));
+    }
+    text
+}
+
+// Wraps text in a code span, using a longer run of backticks if the text contains any.
+fn markdown_code(code: &str) -> String {
+    if code.contains('`') {
+        format!("`` {} ``", code)
+    } else {
+        format!("`{}`", code)
+    }
+}
+
+fn render_html(id: &str, blocks: &[Block], extension: &str) -> String {
+    let mut text = String::new();
+    text.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    text.push_str(&format!("<title>{}</title>\n</head>\n<body>\n", html_escape(id)));
+    for block in blocks {
+        match block {
+            Block::Heading(level, heading) => {
+                // Type sections are headings level 3; they're the targets of type links.
+                if *level == 3 {
+                    text.push_str(&format!("<h3 id=\"{0}\">{0}</h3>\n", html_escape(heading)));
+                } else {
+                    text.push_str(&format!("<h{0}>{1}</h{0}>\n", level, html_escape(heading)));
+                }
+            }
+            Block::Paragraph(inlines) => {
+                text.push_str(&format!("<p>{}</p>\n", html_inlines(inlines, extension)));
+            }
+            Block::List(items) => {
+                text.push_str("<ul>\n");
+                for item in items {
+                    text.push_str(&format!("<li>{}</li>\n", html_inlines(item, extension)));
+                }
+                text.push_str("</ul>\n");
+            }
+            Block::Table(headers, rows) => {
+                text.push_str("<table>\n<tr>");
+                for header in headers.iter() {
+                    text.push_str(&format!("<th>{}</th>", header));
+                }
+                text.push_str("</tr>\n");
+                for row in rows {
+                    text.push_str("<tr>");
+                    for cell in row {
+                        text.push_str(&format!("<td>{}</td>", html_inlines(cell, extension)));
+                    }
+                    text.push_str("</tr>\n");
+                }
+                text.push_str("</table>\n");
+            }
+        }
+    }
+    text.push_str("</body>\n</html>\n");
+    text
+}
+
+fn html_inlines(inlines: &[Inline], extension: &str) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) => text.push_str(&html_escape(t)),
+            Inline::Code(code) => text.push_str(&format!("<code>{}</code>", html_escape(code))),
+            Inline::TypeLink { text: link_text, schema, type_name } => {
+                let file = schema.as_deref().map(|s| documentation_file(s, extension)).unwrap_or_default();
+                text.push_str(&format!(
+                    "<a href=\"{}#{}\"><code>{}</code></a>",
+                    html_escape(&file),
+                    html_escape(type_name),
+                    html_escape(link_text)
+                ));
+            }
+            Inline::SchemaLink(schema) => {
+                text.push_str(&format!(
+                    "<a href=\"{}\"><code>{}</code></a>",
+                    html_escape(&documentation_file(schema, extension)),
+                    html_escape(schema)
+                ));
+            }
+        }
+    }
+    text
+}
+
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
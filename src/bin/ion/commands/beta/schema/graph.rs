@@ -0,0 +1,236 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+
+use anyhow::Result;
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::output::output_writer;
+use crate::schema::{authority_arg, references, Authority, TypeReference};
+
+const ABOUT: &str = "Draws the imports and type references of Ion Schemas as a Graphviz or Mermaid graph.";
+
+pub fn app() -> CommandConfig {
+    App::new("graph")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .default_value("dot")
+                .possible_values(&["dot", "mermaid"])
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("level")
+                .long("level")
+                .short("l")
+                .takes_value(true)
+                .default_value("types")
+                .possible_values(&["types", "schemas"])
+                .help("Whether each node is a type or a whole schema"),
+        )
+        .arg(authority_arg())
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(
+            Arg::with_name("schema")
+                .index(1)
+                .multiple(true)
+                .required(true)
+                .help("Schema files to start from"),
+        )
+        .after_help(
+            "Starts from the given schemas and follows their imports, so the graph
+covers every schema they depend on. Without --authority, imports are
+resolved against the directory of the first schema, and the other
+schemas must be within it.
+
+With `--level types`, each schema is drawn as a cluster of the types it
+defines, and each type has an edge to every type it refers to, including
+through fields and inline types. Built-in types and names that can't be
+resolved are left out. With `--level schemas`, each schema is a node
+with an edge to every schema it imports.
+
+Render the output with Graphviz, e.g. `ion beta schema graph a.isl |
+dot -Tsvg > a.svg`, or paste --format mermaid output into a Mermaid
+diagram."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --format and --level have default values and `schema` is required, so we can unwrap these
+    // safely.
+    let format = matches.value_of("format").unwrap();
+    let level = matches.value_of("level").unwrap();
+    let schema_files: Vec<&str> = matches.values_of("schema").unwrap().collect();
+    let mut authority = Authority::for_schema_file(matches.value_of("authority"), schema_files[0]);
+    let mut ids = Vec::new();
+    for schema_file in &schema_files {
+        ids.push(authority.id_of(schema_file)?);
+    }
+
+    let graph = if level == "schemas" {
+        schema_graph(&mut authority, ids)?
+    } else {
+        type_graph(&mut authority, ids)?
+    };
+    let text = match format {
+        "mermaid" => graph.to_mermaid(),
+        _ => graph.to_dot(),
+    };
+    let mut writer = output_writer(matches.value_of("output"))?;
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Returns the IDs of the given schemas and every schema they depend on, directly or indirectly,
+// in the order they're found. A schema depends on the schemas it imports and on those named by
+// references like `{id: "units.isl", type: meters}`.
+fn all_schemas(authority: &mut Authority, ids: Vec<String>) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    let mut queue: VecDeque<String> = ids.into();
+    while let Some(id) = queue.pop_front() {
+        if found.contains(&id) {
+            continue;
+        }
+        let schema = authority.load(&id)?;
+        for import in &schema.imports {
+            queue.push_back(import.id.clone());
+        }
+        for definition in &schema.types {
+            for reference in references(&definition.definition) {
+                if let TypeReference::Imported { id, .. } = reference {
+                    queue.push_back(id);
+                }
+            }
+        }
+        found.push(id);
+    }
+    Ok(found)
+}
+
+fn schema_graph(authority: &mut Authority, ids: Vec<String>) -> Result<Graph> {
+    let mut graph = Graph::default();
+    for id in all_schemas(authority, ids)? {
+        graph.add_node(&id, &id, None);
+        for import in &authority.load(&id)?.imports {
+            graph.add_edge(&id, &import.id);
+        }
+    }
+    Ok(graph)
+}
+
+fn type_graph(authority: &mut Authority, ids: Vec<String>) -> Result<Graph> {
+    let mut graph = Graph::default();
+    let schemas = all_schemas(authority, ids)?;
+    for (index, id) in schemas.iter().enumerate() {
+        let types: Vec<(String, Vec<TypeReference>)> = authority.load(id)?.types
+            .iter()
+            .map(|t| (t.name.clone(), references(&t.definition)))
+            .collect();
+        for (name, type_references) in types {
+            let node = type_node(id, &name);
+            graph.add_node(&node, &name, Some(index));
+            for reference in type_references {
+                let target = match reference {
+                    TypeReference::Named(name) => authority.resolve(id, &name)?,
+                    TypeReference::Imported { id: schema, type_name } => authority.resolve(&schema, &type_name)?,
+                };
+                // An import may name a type that its schema doesn't define, which has no node.
+                if let Some((schema, type_name)) = target {
+                    if authority.load(&schema)?.type_named(&type_name).is_some() {
+                        graph.add_edge(&node, &type_node(&schema, &type_name));
+                    }
+                }
+            }
+        }
+    }
+    graph.clusters = schemas;
+    Ok(graph)
+}
+
+fn type_node(schema: &str, type_name: &str) -> String {
+    format!("{}#{}", schema, type_name)
+}
+
+// A directed graph whose nodes may be grouped into labeled clusters.
+#[derive(Default)]
+struct Graph {
+    // Each node's ID, label, and the index of its cluster.
+    nodes: Vec<(String, String, Option<usize>)>,
+    edges: Vec<(String, String)>,
+    clusters: Vec<String>,
+}
+
+impl Graph {
+    fn add_node(&mut self, id: &str, label: &str, cluster: Option<usize>) {
+        self.nodes.push((id.to_owned(), label.to_owned(), cluster));
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str) {
+        let edge = (from.to_owned(), to.to_owned());
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut text = String::from("digraph schemas {\n  rankdir=LR;\n  node [shape=box];\n");
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            text.push_str(&format!("  subgraph \"cluster_{}\" {{\n    label={};\n", index, dot_quote(cluster)));
+            for (id, label, _) in self.nodes.iter().filter(|(_, _, c)| *c == Some(index)) {
+                text.push_str(&format!("    {} [label={}];\n", dot_quote(id), dot_quote(label)));
+            }
+            text.push_str("  }\n");
+        }
+        for (id, label, _) in self.nodes.iter().filter(|(_, _, c)| c.is_none()) {
+            text.push_str(&format!("  {} [label={}];\n", dot_quote(id), dot_quote(label)));
+        }
+        for (from, to) in &self.edges {
+            text.push_str(&format!("  {} -> {};\n", dot_quote(from), dot_quote(to)));
+        }
+        text.push_str("}\n");
+        text
+    }
+
+    // Mermaid node IDs can't contain most punctuation, so nodes are numbered instead.
+    fn to_mermaid(&self) -> String {
+        let numbers: HashMap<&str, usize> = self.nodes
+            .iter()
+            .enumerate()
+            .map(|(number, (id, _, _))| (id.as_str(), number))
+            .collect();
+        let mut text = String::from("flowchart LR\n");
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            text.push_str(&format!("  subgraph s{} [{}]\n", index, mermaid_quote(cluster)));
+            for (id, label, _) in self.nodes.iter().filter(|(_, _, c)| *c == Some(index)) {
+                text.push_str(&format!("    n{}[{}]\n", numbers[id.as_str()], mermaid_quote(label)));
+            }
+            text.push_str("  end\n");
+        }
+        for (id, label, _) in self.nodes.iter().filter(|(_, _, c)| c.is_none()) {
+            text.push_str(&format!("  n{}[{}]\n", numbers[id.as_str()], mermaid_quote(label)));
+        }
+        for (from, to) in &self.edges {
+            // Every edge leads from and to a node in a loaded schema, so both have numbers.
+            text.push_str(&format!("  n{} --> n{}\n", numbers[from.as_str()], numbers[to.as_str()]));
+        }
+        text
+    }
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
@@ -0,0 +1,59 @@
+pub mod classify;
+pub mod doc;
+pub mod graph;
+pub mod resolve;
+pub mod test;
+pub mod validate;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `schema` subcommands
+pub fn schema_subcommands() -> Vec<CommandConfig> {
+    vec![
+        classify::app(),
+        doc::app(),
+        graph::app(),
+        resolve::app(),
+        test::app(),
+        validate::app(),
+    ]
+}
+
+pub fn runner_for_schema_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "classify" => classify::run,
+        "doc" => doc::run,
+        "graph" => graph::run,
+        "resolve" => resolve::run,
+        "test" => test::run,
+        "validate" => validate::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `schema` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_schema_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested schema command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("schema")
+        .about("Works with Ion Schema Language (ISL) documents.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(schema_subcommands())
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path as FilePath;
+
+use anyhow::{bail, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::schema::{
+    as_reference, authority_arg, has_annotation, is_built_in, references, rewrite_references, Authority, TypeReference,
+};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Bundles an Ion Schema and the types it imports into a single self-contained schema.";
+
+pub fn app() -> CommandConfig {
+    App::new("resolve")
+        .about(ABOUT)
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(
+            Arg::with_name("schema")
+                .index(1)
+                .required(true)
+                .help("Schema file to resolve"),
+        )
+        .after_help(
+            "Writes the schema with its imports removed and every imported type it
+depends on, directly or indirectly, added alongside its own types. Each
+type is included once, however many schemas import it, and references
+to imported types are rewritten to name the bundled copies, so the
+result can be loaded without access to the --authority directory.
+
+The schema's own types keep their names. An imported type keeps its
+name unless another bundled type already has it, in which case it's
+prefixed with the name of the file that defines it, like `units_meters`.
+
+A reference to a type that is neither built in nor defined or imported
+by its schema is an error."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // `schema` is required, so we can unwrap this safely.
+    let schema_file = matches.value_of("schema").unwrap();
+    let mut authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+    let id = authority.id_of(schema_file)?;
+    let values = bundle(&mut authority, &id)?;
+    let mut output = IonOutput::from_matches(matches)?;
+    for value in &values {
+        output.write_value(value)?;
+    }
+    output.finish()
+}
+
+// A type within a particular schema: the schema's ID and the type's name.
+type TypeKey = (String, String);
+
+fn bundle(authority: &mut Authority, id: &str) -> Result<Vec<Value>> {
+    let root = authority.load(id)?;
+    let root_values = root.values.clone();
+    let root_types: Vec<TypeKey> = root.types
+        .iter()
+        .map(|t| (id.to_owned(), t.name.clone()))
+        .collect();
+
+    // Find every type that the schema's own types depend on, naming each one as it's found.
+    let mut taken: HashSet<String> = root_types.iter().map(|(_, name)| name.clone()).collect();
+    let mut names: HashMap<TypeKey, String> = root_types.iter().map(|key| (key.clone(), key.1.clone())).collect();
+    let mut order = root_types;
+    let mut index = 0;
+    while index < order.len() {
+        let (schema, type_name) = order[index].clone();
+        index += 1;
+        // Types are only added to `order` once they're known to exist.
+        let type_references = references(&authority.load(&schema)?.type_named(&type_name).unwrap().definition);
+        for reference in type_references {
+            if let Some(key) = resolve_reference(authority, &schema, &reference)? {
+                if !names.contains_key(&key) {
+                    names.insert(key.clone(), unique_name(&key, &mut taken));
+                    order.push(key);
+                }
+            }
+        }
+    }
+
+    let mut types = Vec::with_capacity(order.len());
+    for (schema, type_name) in &order {
+        let mut definition = authority.load(schema)?.type_named(type_name).unwrap().definition.clone();
+        if let Data::Struct(fields) = &mut definition.data {
+            for (name, value) in fields.iter_mut() {
+                if name == "name" {
+                    let bundled_name = names[&(schema.clone(), type_name.clone())].as_str();
+                    value.data = Data::Symbol(Symbol::from(bundled_name));
+                }
+            }
+        }
+        rewrite_references(&mut definition, &mut |reference| {
+            // `rewrite_references` only passes us type references, and every one was resolved
+            // above, so each is either built in or bundled.
+            let type_reference = as_reference(reference).unwrap();
+            if let Some(key) = resolve_reference(authority, schema, &type_reference)? {
+                reference.data = Data::Symbol(Symbol::from(names[&key].as_str()));
+            }
+            Ok(())
+        })?;
+        types.push(definition);
+    }
+
+    // Write the schema's own values in order, with the bundled types where its types were, and
+    // without the imports in its header.
+    let mut values = Vec::new();
+    for mut value in root_values {
+        if has_annotation(&value, "type") {
+            values.append(&mut types);
+            continue;
+        }
+        if has_annotation(&value, "schema_header") {
+            if let Data::Struct(fields) = &mut value.data {
+                fields.retain(|(name, _)| name != "imports");
+            }
+        }
+        values.push(value);
+    }
+    Ok(values)
+}
+
+// Finds the type that a reference within `schema` leads to, or None if it's a built-in type.
+fn resolve_reference(authority: &mut Authority, schema: &str, reference: &TypeReference) -> Result<Option<TypeKey>> {
+    let (target, type_name) = match reference {
+        TypeReference::Named(name) => match authority.resolve(schema, name)? {
+            None if is_built_in(name) => return Ok(None),
+            target => (target, name),
+        },
+        TypeReference::Imported { id, type_name } => (authority.resolve(id, type_name)?, type_name),
+    };
+    match target {
+        Some((target_schema, target_name)) => {
+            if authority.load(&target_schema)?.type_named(&target_name).is_none() {
+                bail!("Schema '{}' imports type '{}' from '{}', which doesn't define it.", schema, target_name, target_schema);
+            }
+            Ok(Some((target_schema, target_name)))
+        }
+        None => bail!("Schema '{}' refers to type '{}', which is not defined, imported, or built in.", schema, type_name),
+    }
+}
+
+// Chooses a name for an imported type that no other bundled type has.
+fn unique_name((schema, type_name): &TypeKey, taken: &mut HashSet<String>) -> String {
+    let mut name = type_name.clone();
+    if taken.contains(&name) {
+        let stem = FilePath::new(schema)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let prefix: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        name = format!("{}_{}", prefix, type_name);
+        let mut suffix = 2;
+        while taken.contains(&name) {
+            name = format!("{}_{}_{}", prefix, type_name, suffix);
+            suffix += 1;
+        }
+    }
+    taken.insert(name.clone());
+    name
+}
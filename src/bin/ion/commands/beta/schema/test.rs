@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::IonInput;
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::schema::{authority_arg, ion_text, Authority};
+use crate::validation::{Validator, Violation};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Runs a file of test cases, each a value that should or shouldn't match a type.";
+
+pub fn app() -> CommandConfig {
+    App::new("test")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .help("ISL schema defining the types under test"),
+        )
+        .arg(
+            Arg::with_name("cases")
+                .long("cases")
+                .short("c")
+                .takes_value(true)
+                .required(true)
+                .help("File of test cases"),
+        )
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .after_help(
+            "Each value in --cases is a struct describing one case, like
+  {name: \"id must be an int\", type: order, value: {id: \"x\"},
+   valid: false, violations: [fields]}
+`type` names a type that --schema defines or imports, or a built-in
+type, and `valid` says whether `value` should match it. An invalid case
+may also list the constraints it should violate in `violations`; the
+case then fails unless exactly those constraints find violations. A
+violation belongs to the innermost constraint that found it, as in
+`schema validate --explain`; one that no constraint found, like a value
+that isn't the built-in type being tested, belongs to `type`. `name` is
+optional and only used in the report.
+
+Writes a report for each case that fails, like
+  {case: 3, name: \"id must be an int\", problem: \"...\",
+   violations: [{path: \"(id)\", constraint: fields, message: \"...\"}]}
+and then fails, saying how many cases failed. Cases are counted from 1.
+Keeping a cases file beside each schema, and running it whenever the
+schema changes, catches types that start accepting or rejecting values
+they shouldn't."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --schema and --cases are required, so we can unwrap them safely.
+    let schema_file = matches.value_of("schema").unwrap();
+    let cases_file = matches.value_of("cases").unwrap();
+    let authority = Authority::for_schema_file(matches.value_of("authority"), schema_file);
+    let id = authority.id_of(schema_file)?;
+    let cases = IonInput::open(cases_file)?
+        .read_all()?
+        .iter()
+        .enumerate()
+        .map(|(index, case)| {
+            Case::from_value(case).with_context(|| format!("Invalid case {} in '{}'", index + 1, cases_file))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut validator = Validator::new(authority);
+
+    let mut output = IonOutput::from_matches(matches)?;
+    let mut failed = 0;
+    for (index, case) in cases.iter().enumerate() {
+        if !validator.has_type(&id, &case.type_name)? {
+            bail!("Case {} in '{}' tests type '{}', which isn't in schema '{}'.", index + 1, cases_file, case.type_name, id);
+        }
+        let violations = validator.validate(&case.value, &id, &case.type_name)?;
+        if let Some(problem) = case.problem(&violations) {
+            failed += 1;
+            output.write_value(&report(index + 1, case, problem, &violations))?;
+        }
+    }
+    output.finish()?;
+    if failed > 0 {
+        bail!("{} of {} cases failed.", failed, cases.len());
+    }
+    eprintln!("All {} cases passed.", cases.len());
+    Ok(())
+}
+
+// A value and whether it should match a type.
+struct Case {
+    name: Option<String>,
+    type_name: String,
+    value: Value,
+    valid: bool,
+    // For an invalid case, the constraints that should find violations, if they're given.
+    constraints: Option<BTreeSet<String>>,
+}
+
+impl Case {
+    fn from_value(case: &Value) -> Result<Case> {
+        if !matches!(case.data, Data::Struct(_)) {
+            bail!("Each case must be a struct, unlike {}.", ion_text(case));
+        }
+        let type_name = match case.get("type").and_then(Value::as_text) {
+            Some(type_name) => type_name.to_owned(),
+            None => bail!("The case {} needs a `type` field of text.", ion_text(case)),
+        };
+        let value = match case.get("value") {
+            Some(value) => value.clone(),
+            None => bail!("The case {} needs a `value` field.", ion_text(case)),
+        };
+        let valid = match case.get("valid").map(|valid| &valid.data) {
+            Some(Data::Boolean(valid)) => *valid,
+            _ => bail!("The case {} needs a `valid` field of true or false.", ion_text(case)),
+        };
+        let constraints = match case.get("violations").map(|violations| &violations.data) {
+            None => None,
+            Some(_) if valid => bail!("The case {} lists violations, but is valid.", ion_text(case)),
+            Some(Data::List(names)) => Some(
+                names
+                    .iter()
+                    .map(|name| name.as_text().map(str::to_owned))
+                    .collect::<Option<BTreeSet<_>>>()
+                    .with_context(|| format!("The case {} has violations that aren't constraint names.", ion_text(case)))?,
+            ),
+            Some(_) => bail!("The case {} has violations that aren't a list.", ion_text(case)),
+        };
+        let name = case.get("name").and_then(Value::as_text).map(str::to_owned);
+        Ok(Case { name, type_name, value, valid, constraints })
+    }
+
+    // Describes how the violations differ from what the case expects, if they do.
+    fn problem(&self, violations: &[Violation]) -> Option<String> {
+        if self.valid {
+            return match violations.len() {
+                0 => None,
+                count => Some(format!("expected the value to match type '{}', but it has {} violation(s)", self.type_name, count)),
+            };
+        }
+        if violations.is_empty() {
+            return Some(format!("expected the value not to match type '{}', but it does", self.type_name));
+        }
+        let expected = self.constraints.as_ref()?;
+        let found: BTreeSet<String> = violations
+            .iter()
+            .map(|violation| violation.constraint.clone().unwrap_or_else(|| "type".to_owned()))
+            .collect();
+        if found == *expected {
+            return None;
+        }
+        let list = |names: &BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join(", ");
+        Some(format!("expected violations of [{}], but found violations of [{}]", list(expected), list(&found)))
+    }
+}
+
+// Describes a case that failed, with the violations that were found.
+fn report(number: usize, case: &Case, problem: String, violations: &[Violation]) -> Value {
+    let violations = violations
+        .iter()
+        .map(|violation| {
+            let mut fields = vec![(Symbol::from("path"), Value::new(Data::String(violation.path.to_string())))];
+            if let Some(constraint) = &violation.constraint {
+                fields.push((Symbol::from("constraint"), Value::new(Data::Symbol(Symbol::from(constraint.as_str())))));
+            }
+            fields.push((Symbol::from("message"), Value::new(Data::String(violation.message.clone()))));
+            Value::new(Data::Struct(fields))
+        })
+        .collect();
+    let mut fields = vec![(Symbol::from("case"), Value::new(Data::Integer(number as i64)))];
+    if let Some(name) = &case.name {
+        fields.push((Symbol::from("name"), Value::new(Data::String(name.clone()))));
+    }
+    fields.push((Symbol::from("type"), Value::new(Data::Symbol(Symbol::from(case.type_name.as_str())))));
+    fields.push((Symbol::from("problem"), Value::new(Data::String(problem))));
+    fields.push((Symbol::from("violations"), Value::new(Data::List(violations))));
+    Value::new(Data::Struct(fields))
+}
@@ -0,0 +1,330 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, output_writer, IonOutput};
+use crate::path::Path;
+use crate::schema::{authority_arg, Authority};
+use crate::validation::{Validator, Violation};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Checks that each value in the input matches a type in an Ion Schema.";
+
+// The ID of the schema holding a --type-def, and the name it's given if it doesn't have one.
+const INLINE_SCHEMA_ID: &str = "--type-def";
+const INLINE_TYPE_NAME: &str = "type_def";
+
+pub fn app() -> CommandConfig {
+    App::new("validate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .short("s")
+                .takes_value(true)
+                .help("ISL schema defining the type"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .short("t")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required_unless_one(&["type-def", "type-from-annotation"])
+                .requires("schema")
+                .help("Type in --schema that each value must match; repeat to choose by annotation"),
+        )
+        .arg(
+            Arg::with_name("type-from-annotation")
+                .long("type-from-annotation")
+                .conflicts_with_all(&["type", "type-def"])
+                .requires("schema")
+                .help("Match each value against the type in --schema named by its first annotation"),
+        )
+        .arg(
+            Arg::with_name("type-def")
+                .long("type-def")
+                .short("d")
+                .takes_value(true)
+                .conflicts_with("type")
+                .help("A type definition to match instead of a named type, e.g. '{type: struct, fields: {id: int}}'"),
+        )
+        .arg(
+            Arg::with_name("explain")
+                .long("explain")
+                .short("e")
+                .takes_value(true)
+                .value_name("VALUE")
+                .help("Trace how value number VALUE, counting from 1, was checked"),
+        )
+        .arg(authority_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes a report for each value that doesn't match the type, like
+  {input: \"orders.ion\", value: 3, violations: [
+    {path: \"(items 0 price)\", message: \"expected decimal, found a string\"}]}
+and then fails, saying how many values didn't match. Values are counted
+from 1 across all of the inputs. Type references are resolved as in the
+other `schema` commands, relative to --authority.
+
+--explain VALUE writes a trace of how that value was checked instead,
+for finding out why a complicated type rejects it:
+  type order -> failed
+    fields: {id:int,payment:{any_of:[card,invoice]}} -> failed
+      type int at (id) -> ok
+      type {any_of:[card,invoice]} at (payment) -> failed
+        any_of: [card,invoice] -> failed
+          type card -> failed
+            ...
+          violation at (payment): matches none of the types in any_of
+Each type and constraint is listed as it's checked, nested within the
+one that checked it, and marked with whether it passed. Each violation
+is listed under the constraint that found it. Lines also say where
+checking stopped early: a null matching a nullable type, and the parts
+of ordered_elements that weren't tried again.
+
+When --type is given more than once, each value is checked against the
+one named by its first annotation, so a stream that interleaves several
+kinds of records can be checked in one pass; with --type-from-annotation,
+the annotation may name any type that --schema defines or imports. A
+value whose first annotation doesn't name one of those types is reported
+as not matching. Reports then say which type each value was checked
+against, and a summary of how many values of each type matched is
+written to STDERR.
+
+--type-def checks values against a type written on the command line,
+with no schema file:
+  ion beta schema validate --type-def '{type: struct, fields: {id: int}}'
+The definition is a type's struct, as it would appear in a schema,
+without the `type::` annotation. It may refer to built-in types, and,
+if --schema is given, to the types that schema defines or imports."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let explain = match matches.value_of("explain") {
+        Some(text) => match usize::from_str(text) {
+            Ok(number) if number > 0 => Some(number),
+            _ => bail!("Invalid value for '--explain': '{}'; values are counted from 1.", text),
+        },
+        None => None,
+    };
+    let (mut validator, id, selection) = target(matches)?;
+
+    let mut output = match explain {
+        Some(_) => None,
+        None => Some(IonOutput::from_matches(matches)?),
+    };
+    let mut values = 0;
+    let mut invalid = 0;
+    // How many values were checked against each type, and how many of them didn't match, in the
+    // order the types were first seen.
+    let mut counts: Vec<(String, usize, usize)> = Vec::new();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            values += 1;
+            if explain.is_some_and(|number| number != values) {
+                continue;
+            }
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let type_name = match selection.select(&mut validator, &id, &value)? {
+                Ok(type_name) => type_name,
+                Err(problem) => {
+                    if explain.is_some() {
+                        return write_lines(matches, &[format!("Value {} (in '{}') {}.", values, input.name(), problem)]);
+                    }
+                    invalid += 1;
+                    let violation = Violation::new(Path::from(Vec::new()), problem);
+                    // The output is only missing while explaining.
+                    output.as_mut().unwrap().write_value(&report(input.name(), values, None, &[violation]))?;
+                    continue;
+                }
+            };
+            if explain.is_some() {
+                let (violations, trace) = validator.explain(&value, &id, &type_name)?;
+                let mut lines = vec![format!("Value {} (in '{}') against type '{}':", values, input.name(), type_name)];
+                lines.extend(trace.iter().map(|line| format!("  {}", line)));
+                lines.push(match violations.len() {
+                    0 => "The value matches.".to_owned(),
+                    count => format!("The value doesn't match, with {} violation(s).", count),
+                });
+                return write_lines(matches, &lines);
+            }
+            let violations = validator.validate(&value, &id, &type_name)?;
+            let index = match counts.iter().position(|(name, _, _)| *name == type_name) {
+                Some(index) => index,
+                None => {
+                    counts.push((type_name.clone(), 0, 0));
+                    counts.len() - 1
+                }
+            };
+            counts[index].1 += 1;
+            if !violations.is_empty() {
+                invalid += 1;
+                counts[index].2 += 1;
+                let checked_type = if selection.is_single() { None } else { Some(type_name.as_str()) };
+                output.as_mut().unwrap().write_value(&report(input.name(), values, checked_type, &violations))?;
+            }
+        }
+    }
+    if let Some(number) = explain {
+        bail!("The input has {} values, so there's no value {} to explain.", values, number);
+    }
+    // The output is only missing while explaining, which has returned by now.
+    output.unwrap().finish()?;
+    match &selection {
+        Selection::One(type_name) => {
+            if invalid > 0 {
+                bail!("{} of {} values don't match type '{}'.", invalid, values, type_name);
+            }
+        }
+        _ => {
+            for (type_name, checked, failed) in &counts {
+                eprintln!("{}: {} of {} values match", type_name, checked - failed, checked);
+            }
+            let checked: usize = counts.iter().map(|(_, checked, _)| checked).sum();
+            if checked < values {
+                eprintln!("{} values have no annotation naming a type", values - checked);
+            }
+            if invalid > 0 {
+                bail!("{} of {} values don't match their types.", invalid, values);
+            }
+        }
+    }
+    Ok(())
+}
+
+// How the type each value must match is chosen.
+enum Selection {
+    One(String),
+    // By the value's first annotation, which must be one of these.
+    Listed(Vec<String>),
+    // By the value's first annotation, which may be any type the schema defines or imports.
+    FromAnnotation,
+}
+
+impl Selection {
+    fn is_single(&self) -> bool {
+        matches!(self, Selection::One(_))
+    }
+
+    // Returns the type a value must match, or why none could be chosen.
+    fn select(&self, validator: &mut Validator, schema_id: &str, value: &Value) -> Result<std::result::Result<String, String>> {
+        let listed = match self {
+            Selection::One(type_name) => return Ok(Ok(type_name.clone())),
+            Selection::Listed(listed) => Some(listed),
+            Selection::FromAnnotation => None,
+        };
+        let annotation = match value.annotations.first() {
+            Some(annotation) => annotation.text().unwrap_or("$0"),
+            None => return Ok(Err("has no annotation to choose its type".to_owned())),
+        };
+        let known = match listed {
+            Some(listed) => listed.iter().any(|type_name| type_name == annotation),
+            None => validator.has_type(schema_id, annotation)?,
+        };
+        if known {
+            Ok(Ok(annotation.to_owned()))
+        } else if listed.is_some() {
+            Ok(Err(format!("has first annotation '{}', which isn't one of the --type types", annotation)))
+        } else {
+            Ok(Err(format!("has first annotation '{}', which isn't a type in the schema", annotation)))
+        }
+    }
+}
+
+// The schema to validate against, with the ID and name of the type in it that values must
+// match. A --type-def is added to the authority as a schema of its own.
+fn target(matches: &ArgMatches<'static>) -> Result<(Validator, String, Selection)> {
+    let schema_file = matches.value_of("schema");
+    let mut authority = match schema_file {
+        Some(schema_file) => Authority::for_schema_file(matches.value_of("authority"), schema_file),
+        None => Authority::new(PathBuf::from(matches.value_of("authority").unwrap_or("."))),
+    };
+    let schema_id = schema_file.map(|schema_file| authority.id_of(schema_file)).transpose()?;
+    let text = match matches.value_of("type-def") {
+        Some(text) => text,
+        None => {
+            // Without --type-def, --type or --type-from-annotation is required, and both require
+            // --schema.
+            let selection = match matches.values_of("type") {
+                Some(types) if types.len() > 1 => Selection::Listed(types.map(str::to_owned).collect()),
+                Some(mut types) => Selection::One(types.next().unwrap().to_owned()),
+                None => Selection::FromAnnotation,
+            };
+            return Ok((Validator::new(authority), schema_id.unwrap(), selection));
+        }
+    };
+
+    let mut values = IonInput::from_bytes("--type-def", text.as_bytes())
+        .and_then(|input| input.read_all())
+        .with_context(|| format!("Invalid --type-def '{}'", text))?;
+    let mut definition = match values.pop() {
+        Some(definition) if values.is_empty() && matches!(definition.data, Data::Struct(_)) => definition,
+        _ => bail!("--type-def must be a single struct, like '{{type: struct, fields: {{id: int}}}}', not '{}'.", text),
+    };
+    let type_name = match definition.get("name").and_then(Value::as_text) {
+        Some(name) => name.to_owned(),
+        None => {
+            if let Data::Struct(fields) = &mut definition.data {
+                fields.insert(0, (Symbol::from("name"), Value::new(Data::Symbol(Symbol::from(INLINE_TYPE_NAME)))));
+            }
+            INLINE_TYPE_NAME.to_owned()
+        }
+    };
+    definition.annotations = vec![Symbol::from("type")];
+    let mut schema = Vec::new();
+    if let Some(schema_id) = schema_id {
+        let import = Value::new(Data::Struct(vec![(Symbol::from("id"), Value::new(Data::String(schema_id)))]));
+        let mut header = Value::new(Data::Struct(vec![(Symbol::from("imports"), Value::new(Data::List(vec![import])))]));
+        header.annotations = vec![Symbol::from("schema_header")];
+        schema.push(header);
+    }
+    schema.push(definition);
+    authority.add(INLINE_SCHEMA_ID, schema)?;
+    Ok((Validator::new(authority), INLINE_SCHEMA_ID.to_owned(), Selection::One(type_name)))
+}
+
+// Describes a value that didn't match, along with the type it was checked against if there were
+// several to choose from.
+fn report(input_name: &str, number: usize, type_name: Option<&str>, violations: &[Violation]) -> Value {
+    let violations = violations
+        .iter()
+        .map(|violation| {
+            Value::new(Data::Struct(vec![
+                (Symbol::from("path"), Value::new(Data::String(violation.path.to_string()))),
+                (Symbol::from("message"), Value::new(Data::String(violation.message.clone()))),
+            ]))
+        })
+        .collect();
+    let mut fields = vec![
+        (Symbol::from("input"), Value::new(Data::String(input_name.to_owned()))),
+        (Symbol::from("value"), Value::new(Data::Integer(number as i64))),
+    ];
+    if let Some(type_name) = type_name {
+        fields.push((Symbol::from("type"), Value::new(Data::Symbol(Symbol::from(type_name)))));
+    }
+    fields.push((Symbol::from("violations"), Value::new(Data::List(violations))));
+    Value::new(Data::Struct(fields))
+}
+
+// Writes an explanation to the output as lines of text.
+fn write_lines(matches: &ArgMatches<'static>, lines: &[String]) -> Result<()> {
+    let mut sink = output_writer(matches.value_of("output"))?;
+    let mut text = lines.join("\n");
+    text.push('\n');
+    sink.write_all(text.as_bytes()).with_context(|| "Failed to write to the output.")?;
+    sink.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, unknown_symbols_arg, IonOutput};
+use crate::key::{fnv1a, KeyExtractor};
+use crate::path::Path;
+use crate::value::{UnknownSymbols, Value};
+
+const ABOUT: &str = "Partitions a stream into several files by hashing a key in each value.";
+
+pub fn app() -> CommandConfig {
+    App::new("shard")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the key within each top-level value, e.g. '(customer id)'"),
+        )
+        .arg(
+            Arg::with_name("shards")
+                .long("shards")
+                .short("n")
+                .takes_value(true)
+                .required(true)
+                .help("Number of output files"),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .short("d")
+                .takes_value(true)
+                .default_value(".")
+                .help("Directory in which to write the shards"),
+        )
+        .arg(
+            Arg::with_name("prefix")
+                .long("prefix")
+                .takes_value(true)
+                .default_value("shard")
+                .help("Shards are named <prefix>-<number>.ion (or .10n for binary)"),
+        )
+        .arg(format_arg())
+        .arg(unknown_symbols_arg())
+        .arg(input_arg())
+        .after_help(
+            "Each value is sent to the shard numbered (hash % shards), where hash is
+the 64-bit FNV-1a hash of the key written as compact text Ion. Equal
+keys are always sent to the same shard, regardless of the input's
+format or symbol tables. Values without the key are hashed as if the
+key were `null`. A key path that selects more than one value is an error."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --key and --shards are required, so we can unwrap them safely.
+    let key_path = Path::from_str(matches.value_of("key").unwrap())?;
+    let shard_count = matches.value_of("shards").unwrap();
+    let shard_count = match shard_count.parse::<usize>() {
+        Ok(count) if count > 0 => count,
+        _ => bail!("Invalid number of shards '{}'; expected a positive integer", shard_count),
+    };
+    // These all have default values, so we can unwrap them safely.
+    let output_dir = PathBuf::from(matches.value_of("output-dir").unwrap());
+    let prefix = matches.value_of("prefix").unwrap();
+    let format = matches.value_of("format").unwrap();
+    let unknown_symbols = matches.value_of("unknown-symbols").unwrap();
+
+    fs::create_dir_all(&output_dir).with_context(|| {
+        format!("Could not create output directory '{}'", output_dir.display())
+    })?;
+    let extension = if format == "binary" { "10n" } else { "ion" };
+    let mut shards = (0..shard_count)
+        .map(|index| {
+            let file_path = output_dir.join(format!("{}-{}.{}", prefix, index, extension));
+            let file_name = file_path.to_str()
+                .with_context(|| format!("Shard path {:?} is not valid UTF-8", file_path))?;
+            let mut shard = IonOutput::new(format, Some(file_name))?;
+            shard.set_unknown_symbols(UnknownSymbols::from_arg(unknown_symbols));
+            Ok(shard)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut keys = KeyExtractor::new(key_path);
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let key = keys.key_of(&value)
+                .with_context(|| format!("Could not read the key of a value in '{}'", input.name()))?;
+            let key_text = key.as_deref().unwrap_or("null");
+            let index = (fnv1a(key_text.as_bytes()) % shard_count as u64) as usize;
+            shards[index].write_value(&value)?;
+        }
+    }
+    for shard in shards {
+        shard.finish()?;
+    }
+    Ok(())
+}
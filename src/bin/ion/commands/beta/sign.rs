@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ed25519_dalek::{ExpandedSecretKey, PublicKey};
+
+use crate::canonical::canonical_bytes;
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, output_writer, IonOutput};
+use crate::signature::{is_signature, read_secret_key, signature_value};
+
+const ABOUT: &str = "Signs the canonical form of an Ion stream.";
+
+pub fn app() -> CommandConfig {
+    App::new("sign")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .required(true)
+                .help("File containing a 32-byte Ed25519 secret key in base64"),
+        )
+        .arg(
+            Arg::with_name("detached")
+                .long("detached")
+                .short("d")
+                .takes_value(true)
+                .help("Write the signature to this file instead of embedding it in the output"),
+        )
+        .arg(
+            Arg::with_name("print-public-key")
+                .long("print-public-key")
+                .help("Write the base64 public key for --key to the output and exit"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "The signature covers the stream's canonical form: compact text Ion with
+struct fields sorted. Converting a signed stream between text and binary,
+reformatting it, or reordering struct fields doesn't invalidate it.
+
+By default, the input is written to the output followed by the signature:
+  ion_signature::{algorithm: ed25519, public_key: {{...}}, signature: {{...}}}
+With --detached, only the signature is written, as text Ion, to the given file.
+
+A secret key can be generated with `head -c 32 /dev/urandom | base64`."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --key is required, so we can unwrap this safely.
+    let secret_key = read_secret_key(matches.value_of("key").unwrap())?;
+    let public_key = PublicKey::from(&secret_key);
+    if matches.is_present("print-public-key") {
+        let mut writer = output_writer(matches.value_of("output"))?;
+        writeln!(writer, "{}", base64::encode(public_key.as_bytes()))?;
+        writer.flush().with_context(|| "Failed to write to the output.")?;
+        return Ok(());
+    }
+
+    let mut values = Vec::new();
+    for input_name in input_names(matches) {
+        values.extend(IonInput::open(input_name)?.read_all()?);
+    }
+    if values.last().is_some_and(is_signature) {
+        bail!("The input already ends with an embedded signature.");
+    }
+    let message = canonical_bytes(&values)
+        .with_context(|| "Could not put the input in canonical form")?;
+    let signature = ExpandedSecretKey::from(&secret_key).sign(&message, &public_key);
+    let signature = signature_value(&signature, &public_key);
+
+    match matches.value_of("detached") {
+        Some(signature_file) => {
+            let mut output = IonOutput::new("text", Some(signature_file))?;
+            output.write_value(&signature)?;
+            output.finish()
+        }
+        None => {
+            let mut output = IonOutput::from_matches(matches)?;
+            for value in values.iter().chain(Some(&signature)) {
+                output.write_value(value)?;
+            }
+            output.finish()
+        }
+    }
+}
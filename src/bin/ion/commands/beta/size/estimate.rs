@@ -0,0 +1,298 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ion_rs::IonType;
+use tempfile::NamedTempFile;
+
+use crate::binary::encoded_length;
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, path_str, reader_for, IonInput, IonReader, IVM};
+use crate::output::{output_writer, IonOutput};
+use crate::value::Value;
+
+const ABOUT: &str = "Estimates the size of the input in a matrix of encodings and compression levels.";
+
+// The encodings that are written and measured. The others are modeled from the binary encoding.
+const WRITTEN_FORMATS: &[&str] = &["text", "pretty", "binary"];
+
+// About how many bytes a local symbol table that only imports a shared symbol table takes:
+// `$ion_symbol_table::{imports: [{name: "...", version: 1, max_id: ...}]}`, with a name of about
+// 16 characters.
+const SHARED_IMPORT_BYTES: usize = 32;
+
+pub fn app() -> CommandConfig {
+    App::new("estimate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("gzip-levels")
+                .long("gzip-levels")
+                .takes_value(true)
+                .default_value("1,6,9")
+                .help("Comma-separated gzip levels (0-9) to try; 'none' for none"),
+        )
+        .arg(
+            Arg::with_name("zstd-levels")
+                .long("zstd-levels")
+                .takes_value(true)
+                .default_value("1,3,19")
+                .help("Comma-separated zstd levels (1-22) to try; 'none' for none"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Reads every input as one dataset and prints a table of how many bytes
+it would take in each encoding, alongside its size relative to binary
+Ion. Nothing but the table is written; the encodings are measured in
+temporary files.
+
+These encodings are written by ion-c and measured exactly, both as they
+are and compressed with each of --gzip-levels and --zstd-levels:
+  text    compact text Ion, one value per line
+  pretty  indented text Ion
+  binary  binary Ion with inline local symbol tables
+
+These are modeled from the binary encoding, so they're estimates
+(marked with ~) and aren't compressed:
+  binary, shared symbol table
+      each local symbol table is replaced by an import of a shared
+      table, assumed to take about 32 bytes
+  binary, Ion 1.1 inline symbols
+      the local symbol tables are dropped, and each symbol ID used
+      for a field name, annotation, or symbol value is replaced by
+      the symbol's text with a length prefix, as Ion 1.1 allows.
+      Ion 1.1's other encoding changes aren't modeled."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --gzip-levels and --zstd-levels have default values, so we can unwrap them safely.
+    let gzip_levels = parse_levels(matches.value_of("gzip-levels").unwrap(), "gzip-levels", 0..=9)?;
+    let zstd_levels = parse_levels(matches.value_of("zstd-levels").unwrap(), "zstd-levels", 1..=22)?;
+
+    // Write every input to a temporary file in each format.
+    let mut temp_files = Vec::new();
+    let mut outputs = Vec::new();
+    for format in WRITTEN_FORMATS {
+        let temp_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file for the {} encoding.", format))?;
+        outputs.push(IonOutput::new(format, Some(path_str(&temp_file)?))?);
+        temp_files.push(temp_file);
+    }
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for output in &mut outputs {
+                output.write_value(&value)?;
+            }
+        }
+    }
+    for output in outputs {
+        output.finish()?;
+    }
+
+    let mut rows = Vec::new();
+    let mut binary_size = 0;
+    for (format, temp_file) in WRITTEN_FORMATS.iter().zip(&temp_files) {
+        // ion-c may have replaced the file, so it's measured and read by its path.
+        let size = std::fs::metadata(temp_file.path())
+            .with_context(|| format!("Could not measure the {} encoding.", format))?
+            .len() as usize;
+        rows.push(Row { name: format.to_string(), size, modeled: false });
+        for level in &gzip_levels {
+            let mut encoder = GzEncoder::new(ByteCounter(0), Compression::new(*level as u32));
+            copy_from(temp_file, &mut encoder)?;
+            let size = encoder.finish().with_context(|| "Failed to compress with gzip.")?.0;
+            rows.push(Row { name: format!("{} + gzip -{}", format, level), size, modeled: false });
+        }
+        for level in &zstd_levels {
+            let mut encoder = zstd::stream::write::Encoder::new(ByteCounter(0), *level)
+                .with_context(|| "Failed to start compressing with zstd.")?;
+            copy_from(temp_file, &mut encoder)?;
+            let size = encoder.finish().with_context(|| "Failed to compress with zstd.")?.0;
+            rows.push(Row { name: format!("{} + zstd -{}", format, level), size, modeled: false });
+        }
+        if *format == "binary" {
+            binary_size = size;
+            let bytes = std::fs::read(temp_file.path())
+                .with_context(|| "Could not read back the binary encoding.")?;
+            let symbols = SymbolCosts::collect(&bytes)?;
+            rows.push(Row {
+                name: "binary, shared symbol table".to_owned(),
+                size: size - symbols.symbol_table_bytes + symbols.symbol_tables * SHARED_IMPORT_BYTES,
+                modeled: true,
+            });
+            rows.push(Row {
+                name: "binary, Ion 1.1 inline symbols".to_owned(),
+                size: size - symbols.symbol_table_bytes - symbols.symbol_id_bytes + symbols.inline_text_bytes,
+                modeled: true,
+            });
+        }
+    }
+
+    let mut writer = output_writer(matches.value_of("output"))?;
+    writeln!(writer, "{:<34} {:>14} {:>10}", "Encoding", "Bytes", "vs. binary")?;
+    for row in &rows {
+        let size = if row.modeled { format!("~{}", row.size) } else { row.size.to_string() };
+        let relative = if binary_size == 0 {
+            "-".to_owned()
+        } else {
+            format!("{:.1}%", 100.0 * row.size as f64 / binary_size as f64)
+        };
+        writeln!(writer, "{:<34} {:>14} {:>10}", row.name, size, relative)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+struct Row {
+    name: String,
+    size: usize,
+    // Whether the size was modeled rather than measured.
+    modeled: bool,
+}
+
+// Parses a comma-separated list of compression levels, or "none".
+fn parse_levels(text: &str, name: &str, valid: std::ops::RangeInclusive<i32>) -> Result<Vec<i32>> {
+    if text == "none" {
+        return Ok(Vec::new());
+    }
+    let mut levels = Vec::new();
+    for level_text in text.split(',') {
+        let level = match level_text.trim().parse::<i32>() {
+            Ok(level) if valid.contains(&level) => level,
+            _ => bail!("--{} must list levels from {} to {}, not '{}'.", name, valid.start(), valid.end(), level_text),
+        };
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+fn copy_from(temp_file: &NamedTempFile, writer: &mut impl Write) -> Result<()> {
+    let mut file = File::open(temp_file.path()).with_context(|| "Could not reopen a temporary file.")?;
+    io::copy(&mut file, writer).with_context(|| "Failed to compress a temporary file.")?;
+    Ok(())
+}
+
+// A writer that only counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// What a binary stream spends on symbols, and what it would spend with inline symbol text.
+#[derive(Default)]
+struct SymbolCosts {
+    symbol_tables: usize,
+    symbol_table_bytes: usize,
+    // The bytes of the symbol IDs used for field names, annotations, and symbol values.
+    symbol_id_bytes: usize,
+    // The bytes that the same symbols would take as text with a length prefix.
+    inline_text_bytes: usize,
+}
+
+impl SymbolCosts {
+    fn collect(bytes: &[u8]) -> Result<SymbolCosts> {
+        let mut costs = SymbolCosts::default();
+        let mut reader = reader_for(bytes);
+        // The reader only surfaces user values, so the other top-level values between them are
+        // version markers, symbol tables, and padding.
+        let mut position = 0;
+        while reader.next()?.is_some() {
+            let start = reader.field_id_offset()
+                .or_else(|| reader.annotations_offset())
+                .unwrap_or_else(|| reader.header_offset());
+            costs.count_system_values(&bytes[position..start]);
+            position = reader.value_range().end;
+            costs.count_value(&mut reader)?;
+        }
+        costs.count_system_values(&bytes[position..]);
+        Ok(costs)
+    }
+
+    fn count_system_values(&mut self, mut bytes: &[u8]) {
+        while let Some(length) = encoded_length(bytes) {
+            // NOP pads have type code 0; anything else besides an IVM is a symbol table.
+            if !bytes.starts_with(&IVM) && bytes[0] >> 4 != 0 {
+                self.symbol_tables += 1;
+                self.symbol_table_bytes += length;
+            }
+            bytes = &bytes[length..];
+        }
+    }
+
+    // Counts the symbols used by the reader's current value, and by its children if it's a
+    // container.
+    fn count_value(&mut self, reader: &mut IonReader) -> Result<()> {
+        if let Some(field_id) = reader.field_id() {
+            self.symbol_id_bytes += reader.field_id_length().unwrap_or(0);
+            self.inline_text_bytes += inline_length(symbol_text_length(reader, field_id), 64);
+        }
+        for sid in reader.annotation_ids().to_vec() {
+            self.symbol_id_bytes += var_uint_length(sid);
+            self.inline_text_bytes += inline_length(symbol_text_length(reader, sid), 64);
+        }
+        let ion_type = match reader.ion_type() {
+            Some(ion_type) => ion_type,
+            None => return Ok(()),
+        };
+        if reader.is_null() {
+            return Ok(());
+        }
+        if ion_type == IonType::Symbol {
+            // Ion 1.1 symbols with up to 15 bytes of text keep their length in the opcode.
+            let sid = reader.read_symbol_id()?.unwrap_or(0);
+            self.symbol_id_bytes += reader.header_length() + reader.value_length();
+            let text_length = symbol_text_length(reader, sid);
+            self.inline_text_bytes += if text_length <= 15 { text_length } else { inline_length(text_length, 128) };
+        } else if ion_type.is_container() {
+            reader.step_in()?;
+            while reader.next()?.is_some() {
+                self.count_value(reader)?;
+            }
+            reader.step_out()?;
+        }
+        Ok(())
+    }
+}
+
+// The length of a symbol's text, or of `$<sid>` if its text is unknown.
+fn symbol_text_length(reader: &IonReader, sid: usize) -> usize {
+    match reader.symbol_table().text_for(sid) {
+        Some(text) => text.len(),
+        None => 1 + sid.to_string().len(),
+    }
+}
+
+// The length of text with a variable-length prefix that holds lengths under `one_byte_limit` in
+// a single byte and longer ones in two.
+fn inline_length(text_length: usize, one_byte_limit: usize) -> usize {
+    text_length + if text_length < one_byte_limit { 1 } else { 2 }
+}
+
+// The number of bytes needed to encode `value` as a VarUInt, which holds 7 bits per byte.
+fn var_uint_length(value: usize) -> usize {
+    let bits = (usize::BITS - value.leading_zeros()).max(1) as usize;
+    bits.div_ceil(7)
+}
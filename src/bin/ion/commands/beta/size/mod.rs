@@ -0,0 +1,44 @@
+pub mod estimate;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `size` subcommands
+pub fn size_subcommands() -> Vec<CommandConfig> {
+    vec![
+        estimate::app(),
+    ]
+}
+
+pub fn runner_for_size_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "estimate" => estimate::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `size` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_size_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested size command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("size")
+        .about("Measures how large Ion data is, or would be, in different encodings.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(size_subcommands())
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{Path, Step};
+use crate::validation::type_name;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Reports how often each field path occurs, its types, and example values.";
+
+// Strings and symbols used as examples are cut off after this many characters.
+const MAX_EXAMPLE_CHARS: usize = 64;
+
+pub fn app() -> CommandConfig {
+    App::new("sketch")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("examples")
+                .long("examples")
+                .short("e")
+                .takes_value(true)
+                .default_value("3")
+                .help("Number of distinct example values to report for each path"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes one struct per path, in the order the paths were first seen, like
+  {path: \"(orders * total)\", count: 1834, present: 97.5,
+   types: {decimal: 99.1, null: 0.9}, examples: [12.50, 3.99, 120.00]}
+`count` is how many times the path occurs, and `present` is the
+percentage of top-level values in which it occurs at least once. `types`
+gives the percentage of occurrences with each type, with nulls of any
+type counted as `null`. `examples` holds the first distinct scalars
+seen at the path; long strings and symbols are cut off after 64
+characters, and lobs aren't shown.
+
+List and s-expression indexes in paths are replaced with `*`, so every
+element of a list is counted together. The path `()` is the top-level
+values themselves."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --examples has a default value, so we can unwrap this safely.
+    let examples_arg = matches.value_of("examples").unwrap();
+    let max_examples = usize::from_str(examples_arg)
+        .with_context(|| format!("Invalid value for '--examples': '{}'", examples_arg))?;
+    let mut sketch = Sketch {
+        max_examples,
+        top_level_values: 0,
+        steps: Vec::new(),
+        paths: Vec::new(),
+        index: HashMap::new(),
+    };
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            sketch.top_level_values += 1;
+            sketch.add(&value);
+        }
+    }
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for path in &sketch.paths {
+        let types = path.types
+            .iter()
+            .map(|(name, count)| (Symbol::from(*name), percentage(*count, path.count)))
+            .collect();
+        output.write_value(&Value::new(Data::Struct(vec![
+            (Symbol::from("path"), Value::new(Data::String(path.path.to_string()))),
+            (Symbol::from("count"), Value::new(Data::Integer(path.count as i64))),
+            (Symbol::from("present"), percentage(path.values_present, sketch.top_level_values)),
+            (Symbol::from("types"), Value::new(Data::Struct(types))),
+            (Symbol::from("examples"), Value::new(Data::List(path.examples.clone()))),
+        ])))?;
+    }
+    output.finish()
+}
+
+// A percentage rounded to one decimal place.
+fn percentage(part: usize, total: usize) -> Value {
+    let percent = if total == 0 { 0.0 } else { 100.0 * part as f64 / total as f64 };
+    // The formatted number is always a valid decimal.
+    Value::new(Data::Decimal(BigDecimal::from_str(&format!("{:.1}", percent)).unwrap()))
+}
+
+// What's been seen at one path.
+struct PathSketch {
+    path: Path,
+    count: usize,
+    // The number of top-level values in which the path occurred.
+    values_present: usize,
+    // The last top-level value (counting from 1) in which the path occurred.
+    last_value: usize,
+    // The number of occurrences of each type, in the order the types were first seen.
+    types: Vec<(&'static str, usize)>,
+    examples: Vec<Value>,
+}
+
+struct Sketch {
+    max_examples: usize,
+    top_level_values: usize,
+    // The path to the value currently being added.
+    steps: Vec<Step>,
+    // Each path seen, in the order they were first seen, and each one's index by its text.
+    paths: Vec<PathSketch>,
+    index: HashMap<String, usize>,
+}
+
+impl Sketch {
+    fn add(&mut self, value: &Value) {
+        self.record(value);
+        match &value.data {
+            Data::List(values) | Data::SExpression(values) => {
+                self.steps.push(Step::Wildcard);
+                for child in values {
+                    self.add(child);
+                }
+                self.steps.pop();
+            }
+            Data::Struct(fields) => {
+                for (name, child) in fields {
+                    self.steps.push(Step::Field(field_name(name)));
+                    self.add(child);
+                    self.steps.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Records an occurrence of the current path.
+    fn record(&mut self, value: &Value) {
+        let path = Path::from(self.steps.clone());
+        let key = path.to_string();
+        let index = match self.index.get(&key) {
+            Some(index) => *index,
+            None => {
+                self.paths.push(PathSketch {
+                    path,
+                    count: 0,
+                    values_present: 0,
+                    last_value: 0,
+                    types: Vec::new(),
+                    examples: Vec::new(),
+                });
+                self.index.insert(key, self.paths.len() - 1);
+                self.paths.len() - 1
+            }
+        };
+        let sketch = &mut self.paths[index];
+        sketch.count += 1;
+        if sketch.last_value != self.top_level_values {
+            sketch.last_value = self.top_level_values;
+            sketch.values_present += 1;
+        }
+        let type_name = if value.is_null() { "null" } else { type_name(value.ion_type()) };
+        match sketch.types.iter_mut().find(|(name, _)| *name == type_name) {
+            Some((_, count)) => *count += 1,
+            None => sketch.types.push((type_name, 1)),
+        }
+        if sketch.examples.len() < self.max_examples {
+            if let Some(example) = example(value) {
+                if !sketch.examples.contains(&example) {
+                    sketch.examples.push(example);
+                }
+            }
+        }
+    }
+}
+
+// The value as an example, if it's a scalar that can be shown. Annotations are kept.
+fn example(value: &Value) -> Option<Value> {
+    let data = match &value.data {
+        Data::List(_) | Data::SExpression(_) | Data::Struct(_) | Data::Blob(_) | Data::Clob(_) => return None,
+        Data::String(text) => Data::String(shortened(text)),
+        Data::Symbol(symbol) => match symbol.text() {
+            Some(text) => Data::Symbol(Symbol::from(shortened(text))),
+            None => Data::Symbol(symbol.clone()),
+        },
+        data => data.clone(),
+    };
+    Some(Value { annotations: value.annotations.clone(), data })
+}
+
+fn shortened(text: &str) -> String {
+    match text.char_indices().nth(MAX_EXAMPLE_CHARS) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_owned(),
+    }
+}
+
+// Symbols without known text are named by their symbol ID.
+fn field_name(name: &Symbol) -> String {
+    match (name.text(), name.sid()) {
+        (Some(text), _) => text.to_owned(),
+        (None, Some(sid)) => format!("${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::nested::read_document;
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Renders an Ion template by substituting variables with Ion values.";
+
+// Annotation that marks a symbol or string as the name of a variable.
+const VARIABLE_ANNOTATION: &str = "var";
+
+pub fn app() -> CommandConfig {
+    App::new("template")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("var")
+                .long("var")
+                .short("v")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Binds a variable to a text Ion value, written as name=value"),
+        )
+        .arg(
+            Arg::with_name("bindings")
+                .long("bindings")
+                .short("b")
+                .takes_value(true)
+                .help("Ion file containing a struct of variable bindings"),
+        )
+        .arg(
+            Arg::with_name("allow-unbound")
+                .long("allow-unbound")
+                .help("Leave placeholders for unbound variables in place instead of failing"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "A placeholder is either a symbol whose text is `$` followed by the
+variable's name, like `$port`, or a symbol or string annotated with
+`var`, like `var::port`. Each placeholder is replaced by the value bound
+to its variable, so `port: $port` with `--var port=8080` becomes
+`port: 8080`. Other annotations on the placeholder are kept.
+
+--var values are parsed as text Ion: `--var env=prod` binds a symbol and
+`--var 'env=\"prod\"'` binds a string. --var bindings take precedence
+over those in the --bindings file."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let bindings = bindings(matches)?;
+    let allow_unbound = matches.is_present("allow-unbound");
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let mut value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let mut unbound = BTreeSet::new();
+            substitute(&mut value, &bindings, &mut unbound);
+            if !unbound.is_empty() && !allow_unbound {
+                let names: Vec<String> = unbound.into_iter().collect();
+                bail!("'{}' uses unbound variables: {}", input.name(), names.join(", "));
+            }
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
+
+// Collects the bindings from the --bindings file and the --var flags.
+fn bindings(matches: &ArgMatches<'static>) -> Result<HashMap<String, Value>> {
+    let mut bindings = HashMap::new();
+    if let Some(file_name) = matches.value_of("bindings") {
+        let input = IonInput::open(file_name)?;
+        let mut values = input.read_all()?;
+        let fields = match values.pop() {
+            Some(Value { data: Data::Struct(fields), .. }) if values.is_empty() => fields,
+            _ => bail!("The bindings file '{}' must contain a single struct.", file_name),
+        };
+        for (name, value) in fields {
+            match name.text() {
+                Some(text) => bindings.insert(text.to_owned(), value),
+                None => bail!("The bindings file '{}' has a field name with unknown text.", file_name),
+            };
+        }
+    }
+    for binding in matches.values_of("var").into_iter().flatten() {
+        let (name, text) = match binding.find('=') {
+            Some(index) => (&binding[..index], &binding[index + 1..]),
+            None => bail!("--var '{}' should be written as name=value.", binding),
+        };
+        let mut values = read_document(text.as_bytes())
+            .with_context(|| format!("The value of --var '{}' is not valid text Ion", name))?;
+        if values.len() != 1 {
+            bail!("The value of --var '{}' must be a single Ion value, not {}.", name, values.len());
+        }
+        bindings.insert(name.to_owned(), values.pop().unwrap());
+    }
+    Ok(bindings)
+}
+
+// Replaces each placeholder within `value` with its variable's value. The names of variables
+// that have no binding are added to `unbound`.
+fn substitute(value: &mut Value, bindings: &HashMap<String, Value>, unbound: &mut BTreeSet<String>) {
+    if let Some(name) = variable_name(value) {
+        match bindings.get(name) {
+            Some(binding) => {
+                let mut annotations = mem::take(&mut value.annotations);
+                annotations.retain(|annotation| annotation != VARIABLE_ANNOTATION);
+                annotations.extend(binding.annotations.iter().cloned());
+                *value = Value { annotations, data: binding.data.clone() };
+            }
+            None => {
+                unbound.insert(name.to_owned());
+            }
+        }
+        return;
+    }
+    match &mut value.data {
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                substitute(child, bindings, unbound);
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                substitute(child, bindings, unbound);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Returns the name of the variable that `value` is a placeholder for, if it is one.
+fn variable_name(value: &Value) -> Option<&str> {
+    let is_annotated = value.annotations.iter().any(|annotation| annotation == VARIABLE_ANNOTATION);
+    match &value.data {
+        Data::Symbol(symbol) if is_annotated => symbol.text(),
+        Data::String(text) if is_annotated => Some(text),
+        Data::Symbol(symbol) => symbol_variable_name(symbol),
+        _ => None,
+    }
+}
+
+// Symbols like `$port` are placeholders. Symbols like `$10` are symbol IDs, and symbols
+// beginning with `$ion` are reserved by Ion, so neither is treated as a placeholder.
+fn symbol_variable_name(symbol: &Symbol) -> Option<&str> {
+    let name = symbol.text()?.strip_prefix('$')?;
+    let first = name.chars().next()?;
+    if first.is_ascii_digit() || name.starts_with("ion") {
+        return None;
+    }
+    Some(name)
+}
@@ -0,0 +1,219 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::beta::schema::doc::html_escape;
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::output_writer;
+use crate::schema::ion_text;
+use crate::validation::type_name;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Renders Ion as a standalone HTML page with collapsible containers.";
+
+// Colors each kind of value, and shows a collapsed container's closing bracket after an ellipsis.
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+.ion { font-family: monospace; white-space: pre-wrap; line-height: 1.4; }
+.ion .top { margin-bottom: 0.5em; }
+.ion details > div { margin-left: 2em; }
+.ion details > summary { cursor: pointer; list-style: none; }
+.ion details > summary::-webkit-details-marker { display: none; }
+.ion details > summary::before { content: \"\\25BE  \"; color: #888; }
+.ion details:not([open]) > summary::before { content: \"\\25B8  \"; }
+.ion details:not([open]) > summary::after { content: \" \\2026 \" attr(data-close) attr(data-comma); color: #888; }
+.ion .count { color: #888; font-style: italic; }
+.ion .field { color: #881391; }
+.ion .annotation { color: #6f42c1; font-style: italic; }
+.ion .null { color: #808080; }
+.ion .bool { color: #0d22aa; }
+.ion .int, .ion .float, .ion .decimal { color: #1c00cf; }
+.ion .timestamp { color: #b35900; }
+.ion .symbol { color: #005cc5; }
+.ion .string { color: #c41a16; }
+.ion .clob, .ion .blob { color: #22863a; }
+[title] { cursor: help; }
+";
+
+pub fn app() -> CommandConfig {
+    App::new("html")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("title")
+                .long("title")
+                .short("t")
+                .takes_value(true)
+                .help("Title of the page [default: the input file names]"),
+        )
+        .arg(
+            Arg::with_name("collapse-depth")
+                .long("collapse-depth")
+                .short("d")
+                .takes_value(true)
+                .help("Show containers nested deeper than this collapsed [default: show all expanded]"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Writes a single HTML page, with its styles inline and no scripts, so it
+can be attached to a ticket or pasted into a wiki and opened anywhere.
+Values are written as Ion text and colored by type; hovering over one
+shows its type. Each list, s-expression, and struct can be collapsed or
+expanded by clicking its opening bracket, which also shows how many
+elements or fields it has. Top-level values are at depth 0, so
+--collapse-depth 0 shows each top-level container collapsed."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let collapse_depth = match matches.value_of("collapse-depth") {
+        Some(depth_arg) => Some(usize::from_str(depth_arg)
+            .with_context(|| format!("Invalid value for '--collapse-depth': '{}'", depth_arg))?),
+        None => None,
+    };
+    let input_names = input_names(matches);
+    let title = match matches.value_of("title") {
+        Some(title) => title.to_owned(),
+        None => input_names.join(", "),
+    };
+
+    let mut renderer = Renderer { collapse_depth, html: String::new() };
+    for input_name in &input_names {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            renderer.html.push_str("<div class=\"top\">");
+            renderer.render(&value, 0, "", "");
+            renderer.html.push_str("</div>\n");
+        }
+    }
+
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str(&format!("<title>{}</title>\n", html_escape(&title)));
+    page.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+    page.push_str(&format!("<h1>{}</h1>\n<div class=\"ion\">\n", html_escape(&title)));
+    page.push_str(&renderer.html);
+    page.push_str("</div>\n</body>\n</html>\n");
+
+    let mut sink = output_writer(matches.value_of("output"))?;
+    sink.write_all(page.as_bytes()).with_context(|| "Failed to write to the output.")?;
+    sink.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
+
+struct Renderer {
+    collapse_depth: Option<usize>,
+    html: String,
+}
+
+impl Renderer {
+    // Renders `value`, which is nested `depth` containers deep, after `label`, the HTML for its
+    // field name if it has one, and before `separator`, a comma between the elements of lists and
+    // structs.
+    fn render(&mut self, value: &Value, depth: usize, label: &str, separator: &str) {
+        // A container's label and annotations go in its summary, so they stay on its first line
+        // and visible when it's collapsed.
+        let mut prefix = label.to_owned();
+        for annotation in &value.annotations {
+            prefix.push_str(&format!(
+                "<span class=\"annotation\" title=\"annotation\">{}::</span>",
+                html_escape(&symbol_text(annotation))
+            ));
+        }
+        let (open, close, children) = match &value.data {
+            Data::List(values) => ("[", "]", Children::Elements(values, ",")),
+            Data::SExpression(values) => ("(", ")", Children::Elements(values, "")),
+            Data::Struct(fields) => ("{", "}", Children::Fields(fields)),
+            data => {
+                self.html.push_str(&prefix);
+                self.render_scalar(value, data);
+                self.html.push_str(separator);
+                return;
+            }
+        };
+        let (kind, count) = match &children {
+            Children::Elements(values, _) => (type_name(value.ion_type()), plural(values.len(), "element")),
+            Children::Fields(fields) => ("struct", plural(fields.len(), "field")),
+        };
+        let is_open = self.collapse_depth.is_none_or(|collapse_depth| depth < collapse_depth);
+        self.html.push_str(&format!(
+            "<details{}><summary data-close=\"{}\" data-comma=\"{}\" title=\"{}\">{}{} <span class=\"count\">{}</span></summary><div>",
+            if is_open { " open" } else { "" },
+            close,
+            separator,
+            kind,
+            prefix,
+            open,
+            count
+        ));
+        match children {
+            Children::Elements(values, comma) => {
+                for (index, child) in values.iter().enumerate() {
+                    self.html.push_str("<div>");
+                    self.render(child, depth + 1, "", if index + 1 < values.len() { comma } else { "" });
+                    self.html.push_str("</div>");
+                }
+            }
+            Children::Fields(fields) => {
+                for (index, (name, child)) in fields.iter().enumerate() {
+                    let label = format!(
+                        "<span class=\"field\" title=\"field name\">{}</span>: ",
+                        html_escape(&symbol_text(name))
+                    );
+                    self.html.push_str("<div>");
+                    self.render(child, depth + 1, &label, if index + 1 < fields.len() { "," } else { "" });
+                    self.html.push_str("</div>");
+                }
+            }
+        }
+        self.html.push_str(&format!("</div>{}{}</details>", close, separator));
+    }
+
+    fn render_scalar(&mut self, value: &Value, data: &Data) {
+        let class = if value.is_null() { "null" } else { type_name(value.ion_type()) };
+        let tooltip = match data {
+            Data::Null(_) => format!("null.{}", type_name(value.ion_type())),
+            Data::String(text) => format!("string, {}", plural(text.chars().count(), "character")),
+            Data::Clob(bytes) | Data::Blob(bytes) => format!("{}, {}", class, plural(bytes.len(), "byte")),
+            _ => class.to_owned(),
+        };
+        // Annotations have already been written.
+        let text = ion_text(&Value::new(data.clone()));
+        self.html.push_str(&format!(
+            "<span class=\"{}\" title=\"{}\">{}</span>",
+            class,
+            html_escape(&tooltip),
+            html_escape(text.trim())
+        ));
+    }
+}
+
+enum Children<'a> {
+    // The elements of a list or s-expression, and the separator written between them.
+    Elements(&'a [Value], &'static str),
+    Fields(&'a [(Symbol, Value)]),
+}
+
+// A field name or annotation as Ion text, quoted if it needs to be.
+fn symbol_text(symbol: &Symbol) -> String {
+    ion_text(&Value::new(Data::Symbol(symbol.clone()))).trim().to_owned()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
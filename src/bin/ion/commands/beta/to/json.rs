@@ -0,0 +1,149 @@
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ion_rs::IonType;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::json::{big_numbers_arg, dialect_arg, protect_big_numbers, to_json, BigNumbers, Dialect};
+use crate::output::output_writer;
+use crate::profile::{self, Phase};
+use crate::value::{Data, TextFormatter, Value, ValuePool};
+
+const ABOUT: &str = "Converts Ion to JSON, writing one JSON value per line.";
+
+pub fn app() -> CommandConfig {
+    App::new("json")
+        .about(ABOUT)
+        .arg(dialect_arg())
+        .arg(
+            Arg::with_name("pretty")
+                .long("pretty")
+                .short("p")
+                .help("Write each value over several indented lines"),
+        )
+        .arg(big_numbers_arg())
+        .arg(
+            Arg::with_name("omit-nulls")
+                .long("omit-nulls")
+                .help("Leave out struct fields whose values are null, at every depth"),
+        )
+        .arg(
+            Arg::with_name("null-as")
+                .long("null-as")
+                .takes_value(true)
+                .default_value("null")
+                .possible_values(&["null", "string"])
+                .help("How to write typed nulls like null.int")
+                .long_help(
+                    "Controls how nulls with a type, like `null.int` or `null.struct`, are
+written. Untyped nulls are always written as JSON null.
+  null    writes JSON null, losing the type
+  string  writes the null's Ion text, e.g. \"null.int\"
+--omit-nulls is applied first, so typed nulls in struct fields are
+dropped rather than converted when both are given."
+                ),
+        )
+        .arg(
+            Arg::with_name("with-offsets")
+                .long("with-offsets")
+                .help("Wrap each value with the byte offset and length of its Ion encoding")
+                .long_help(
+                    "Writes each top-level value as {\"offset\": 1024, \"length\": 57, \"value\": ...},
+where `offset` and `length` describe the bytes of the original binary
+Ion value (including its annotations). Offsets are only meaningful for
+binary Ion inputs."
+                ),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(input_arg())
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --dialect has a default value, so we can unwrap this safely.
+    let dialect = Dialect::from_arg(matches.value_of("dialect").unwrap());
+    let pretty = matches.is_present("pretty");
+    let with_offsets = matches.is_present("with-offsets");
+    let omit_nulls = matches.is_present("omit-nulls");
+    // --big-numbers has a default value, so we can unwrap this safely.
+    let big_numbers = BigNumbers::from_arg(matches.value_of("big-numbers").unwrap());
+    // --null-as has a default value, so we can unwrap this safely.
+    let typed_nulls_as_strings = matches.value_of("null-as").unwrap() == "string";
+    let mut writer = output_writer(matches.value_of("output"))?;
+
+    // Each value's allocations are reused for the next.
+    let mut pool = ValuePool::new();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        if with_offsets && input.was_transcoded() {
+            eprintln!("Warning: '{}' is text Ion; its offsets refer to a binary transcoding of it.", input.name());
+        }
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let start = reader.annotations_offset().unwrap_or_else(|| reader.header_offset());
+            let end = reader.value_range().end;
+            let mut value = pool.read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            if omit_nulls {
+                value.drop_null_fields();
+            }
+            if typed_nulls_as_strings {
+                typed_nulls_to_strings(&mut value)?;
+            }
+            // DynamoDB numbers are already written as strings.
+            if dialect == Dialect::Plain {
+                protect_big_numbers(&mut value, big_numbers);
+            }
+            let mut json = profile::time(Phase::Encode, || to_json(&value, dialect))
+                .with_context(|| format!("Could not convert a value in '{}' to JSON", input.name()))?;
+            pool.recycle(value);
+            if with_offsets {
+                let mut wrapper = Map::new();
+                wrapper.insert("offset".to_owned(), JsonValue::from(start));
+                wrapper.insert("length".to_owned(), JsonValue::from(end - start));
+                wrapper.insert("value".to_owned(), json);
+                json = JsonValue::Object(wrapper);
+            }
+            profile::time(Phase::Encode, || if pretty {
+                serde_json::to_writer_pretty(&mut writer, &json)
+            } else {
+                serde_json::to_writer(&mut writer, &json)
+            })
+            .with_context(|| "Failed to write to the output.")?;
+            writeln!(writer).with_context(|| "Failed to write to the output.")?;
+        }
+    }
+    writer.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
+
+// Replaces each typed null within `value`, like `null.int`, with a string holding its Ion text.
+fn typed_nulls_to_strings(value: &mut Value) -> Result<()> {
+    match &mut value.data {
+        Data::Null(ion_type) if *ion_type != IonType::Null => {
+            let mut text = String::new();
+            TextFormatter::new().format(&Value::new(Data::Null(*ion_type)), &mut text)?;
+            value.data = Data::String(text.trim().to_owned());
+        }
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                typed_nulls_to_strings(child)?;
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                typed_nulls_to_strings(child)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
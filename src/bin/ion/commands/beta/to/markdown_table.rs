@@ -0,0 +1,162 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::output_writer;
+use crate::schema::ion_text;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Writes a stream of flat structs as a GitHub-flavored Markdown table.";
+
+pub fn app() -> CommandConfig {
+    App::new("markdown-table")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("fields")
+                .long("fields")
+                .short("f")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("Comma-separated fields to use as the columns, in order [default: every field seen]"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(input_arg())
+        .after_help(
+            "Writes one row per top-level struct. Without --fields, the columns are
+every field name seen in any struct, in the order they were first seen.
+Strings and symbols are written as their text, and other values as Ion
+text; nested containers are written as Ion text in a code span. Cells for
+fields a struct doesn't have are left empty. If a struct has a field more
+than once, its first value is used. Pipes and newlines in cells are
+escaped so the table stays intact.
+
+Fails if the input has a top-level value that isn't a struct."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let mut rows = Vec::new();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            match value.data {
+                Data::Struct(fields) => rows.push(fields),
+                _ => bail!(
+                    "Value {} in '{}' is not a struct; only streams of structs can be written as a table.",
+                    rows.len() + 1,
+                    input.name()
+                ),
+            }
+        }
+    }
+
+    let columns: Vec<String> = match matches.values_of("fields") {
+        Some(fields) => fields.map(str::to_owned).collect(),
+        None => {
+            let mut columns: Vec<String> = Vec::new();
+            for fields in &rows {
+                for (name, _) in fields {
+                    let name = field_name(name);
+                    if !columns.contains(&name) {
+                        columns.push(name);
+                    }
+                }
+            }
+            columns
+        }
+    };
+    if columns.is_empty() {
+        bail!("The input has no fields to use as columns.");
+    }
+
+    let mut table = String::new();
+    table.push_str(&table_row(columns.iter().map(|column| escape_cell(column))));
+    table.push_str(&table_row(columns.iter().map(|_| "---".to_owned())));
+    for fields in &rows {
+        table.push_str(&table_row(columns.iter().map(|column| {
+            fields
+                .iter()
+                .find(|(name, _)| field_name(name) == *column)
+                .map(|(_, value)| cell(value))
+                .unwrap_or_default()
+        })));
+    }
+
+    let mut sink = output_writer(matches.value_of("output"))?;
+    sink.write_all(table.as_bytes()).with_context(|| "Failed to write to the output.")?;
+    sink.flush().with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
+
+fn table_row(cells: impl Iterator<Item = String>) -> String {
+    let mut row = String::from("|");
+    for cell in cells {
+        row.push(' ');
+        row.push_str(&cell);
+        row.push_str(" |");
+    }
+    row.push('\n');
+    row
+}
+
+// The Markdown for a field's value.
+fn cell(value: &Value) -> String {
+    match (&value.data, value.annotations.is_empty()) {
+        (Data::String(text), true) => escape_cell(text),
+        (Data::Symbol(symbol), true) if symbol.text().is_some() => escape_cell(symbol.text().unwrap()),
+        (Data::List(_), _) | (Data::SExpression(_), _) | (Data::Struct(_), _) => {
+            code_span(ion_text(value).trim())
+        }
+        _ => escape_cell(ion_text(value).trim()),
+    }
+}
+
+// Escapes the characters that would end a cell or a row, and the Markdown that would change how
+// the text is shown.
+fn escape_cell(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Wraps text in a code span, using a longer run of backticks if the text contains any. Inside
+// code spans only pipes need escaping.
+fn code_span(text: &str) -> String {
+    let code = text.replace('|', "\\|").replace('\n', " ");
+    if code.contains('`') {
+        format!("`` {} ``", code)
+    } else {
+        format!("`{}`", code)
+    }
+}
+
+// Symbols without known text are named by their symbol ID.
+fn field_name(name: &Symbol) -> String {
+    match (name.text(), name.sid()) {
+        (Some(text), _) => text.to_owned(),
+        (None, Some(sid)) => format!("${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    }
+}
@@ -0,0 +1,50 @@
+pub mod html;
+pub mod json;
+pub mod markdown_table;
+
+use anyhow::Result;
+use clap::{App, AppSettings, ArgMatches};
+use crate::commands::{CommandRunner, CommandConfig};
+
+// Creates a Vec of CLI configurations for all of the available `to` subcommands
+pub fn to_subcommands() -> Vec<CommandConfig> {
+    vec![
+        html::app(),
+        json::app(),
+        markdown_table::app(),
+    ]
+}
+
+pub fn runner_for_to_subcommand(command_name: &str) -> Option<CommandRunner> {
+    let runner = match command_name {
+        "html" => html::run,
+        "json" => json::run,
+        "markdown-table" => markdown_table::run,
+        _ => return None
+    };
+    Some(runner)
+}
+
+// The functions below are used by the `beta` command when `to` is invoked.
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let (command_name, command_args) = matches.subcommand();
+    if let Some(runner) = runner_for_to_subcommand(command_name) {
+        // If a runner is registered for the given command name, command_args is guaranteed to
+        // be defined; we can safely unwrap it.
+        runner(command_name, command_args.unwrap())?;
+    } else {
+        let message = format!(
+            "The requested to command ('{}') is not supported and clap did not generate an error message.",
+            command_name
+        );
+        unreachable!("{}", message);
+    }
+    Ok(())
+}
+
+pub fn app() -> CommandConfig {
+    App::new("to")
+        .about("Converts Ion streams to other formats.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommands(to_subcommands())
+}
@@ -0,0 +1,157 @@
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::path::{read_projection, Path};
+use crate::schema::ion_text;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Reports the most frequent values at a path, with their counts.";
+
+pub fn app() -> CommandConfig {
+    App::new("topn")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .short("p")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the values to count, e.g. '(user_id)'"),
+        )
+        .arg(
+            Arg::with_name("n")
+                .long("n")
+                .short("n")
+                .takes_value(true)
+                .default_value("10")
+                .help("Number of values to report"),
+        )
+        .arg(
+            Arg::with_name("capacity")
+                .long("capacity")
+                .takes_value(true)
+                .help("Number of distinct values to keep counts for [default: 100 × --n, at least 1000]"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Writes one struct per value, most frequent first, like
+  {value: \"u-1234\", count: 5120, error: 0}
+Values are compared by their Ion text, so `1` and `1.0` are different
+values, and annotations are significant. Missing values aren't counted.
+
+Counts are kept for at most --capacity distinct values at a time, using
+the Space-Saving algorithm, so memory stays bounded however large the
+input is. When a new value arrives and every slot is taken, it replaces
+the least frequent value and inherits its count. Each reported count may
+therefore be too high, but never by more than `error`: the true count
+is between `count - error` and `count`. If the input has no more
+distinct values than --capacity, every error is 0 and the counts are
+exact. Any value that occurs more than 1/--capacity of the time is
+guaranteed to be kept."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --path is required and --n has a default value, so we can unwrap these safely.
+    let path_text = matches.value_of("path").unwrap();
+    let path = Path::from_str(path_text).with_context(|| format!("Invalid --path '{}'", path_text))?;
+    let n_arg = matches.value_of("n").unwrap();
+    let n = usize::from_str(n_arg)
+        .with_context(|| format!("Invalid value for '--n': '{}'", n_arg))?;
+    let capacity = match matches.value_of("capacity") {
+        Some(capacity_arg) => usize::from_str(capacity_arg)
+            .with_context(|| format!("Invalid value for '--capacity': '{}'", capacity_arg))?,
+        None => n.saturating_mul(100).max(1000),
+    };
+    if capacity < n || capacity == 0 {
+        bail!("--capacity must be at least 1 and at least --n.");
+    }
+
+    let mut counter = SpaceSaving::new(capacity);
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = read_projection(&mut reader, &[&path])
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            for selected in path.select(&value) {
+                counter.add(selected);
+            }
+        }
+    }
+
+    let mut output = IonOutput::from_matches(matches)?;
+    for (value, count, error) in counter.top(n) {
+        output.write_value(&Value::new(Data::Struct(vec![
+            (Symbol::from("value"), value.clone()),
+            (Symbol::from("count"), Value::new(Data::Integer(count as i64))),
+            (Symbol::from("error"), Value::new(Data::Integer(error as i64))),
+        ])))?;
+    }
+    output.finish()
+}
+
+// A counter for a value being tracked.
+struct Slot {
+    value: Value,
+    count: usize,
+    // How much of `count` may have been inherited from values this one replaced.
+    error: usize,
+}
+
+// Approximate counts of the most frequent values in a stream, using the Space-Saving algorithm:
+// at most `capacity` values are tracked, and a new value replaces the one with the lowest count.
+struct SpaceSaving {
+    capacity: usize,
+    // Each tracked value's slot, by its Ion text.
+    slots: HashMap<String, Slot>,
+    // The tracked values ordered by count, so the lowest can be found quickly.
+    by_count: BTreeSet<(usize, String)>,
+}
+
+impl SpaceSaving {
+    fn new(capacity: usize) -> SpaceSaving {
+        SpaceSaving { capacity, slots: HashMap::new(), by_count: BTreeSet::new() }
+    }
+
+    fn add(&mut self, value: &Value) {
+        let key = ion_text(value);
+        if let Some(slot) = self.slots.get_mut(&key) {
+            self.by_count.remove(&(slot.count, key.clone()));
+            slot.count += 1;
+            self.by_count.insert((slot.count, key));
+            return;
+        }
+        let mut slot = Slot { value: value.clone(), count: 1, error: 0 };
+        if self.slots.len() == self.capacity {
+            // The set isn't empty, since the capacity is at least 1.
+            let (lowest_count, lowest_key) = self.by_count.pop_first().unwrap();
+            self.slots.remove(&lowest_key);
+            slot.count += lowest_count;
+            slot.error = lowest_count;
+        }
+        self.by_count.insert((slot.count, key.clone()));
+        self.slots.insert(key, slot);
+    }
+
+    // Returns the `n` values with the highest counts, highest first, with their counts and errors.
+    fn top(&self, n: usize) -> Vec<(&Value, usize, usize)> {
+        self.by_count
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(_, key)| {
+                let slot = &self.slots[key];
+                (&slot.value, slot.count, slot.error)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+
+use crate::binary::encoded_length;
+use crate::commands::CommandConfig;
+use crate::input::{IonInput, IVM, STDIN_NAME};
+use crate::output::output_writer;
+use crate::size::parse_size;
+
+const ABOUT: &str = "Writes the first part of a binary Ion stream, up to a given size.";
+
+pub fn app() -> CommandConfig {
+    App::new("truncate")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .help("Maximum size of the output, in bytes. Accepts K, M, and G suffixes.")
+        )
+        .arg(
+            Arg::with_name("at-value-boundary")
+                .long("at-value-boundary")
+                .short("b")
+                .help("Cut at the end of the last top-level value that fits")
+                .long_help(
+                    "Instead of cutting the stream at exactly the requested size, cut it at
+the end of the last complete top-level value that fits. Symbol tables
+are top-level values too, so the output is always a valid stream
+containing every value before the cut with the same symbols."
+                ),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("Output file [default: STDOUT]"),
+        )
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .short("i")
+                .index(1)
+                .help("Input file [default: STDIN]"),
+        )
+        .after_help("Text Ion input is transcoded to binary Ion before it is truncated.")
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --size is required, so we can unwrap this safely.
+    let size = parse_size(matches.value_of("size").unwrap())?;
+    let input = IonInput::open(matches.value_of("input").unwrap_or(STDIN_NAME))?;
+    let bytes = input.bytes();
+
+    let end = if matches.is_present("at-value-boundary") {
+        last_value_boundary(bytes, size)
+            .with_context(|| format!("Could not read the top-level values of '{}'", input.name()))?
+    } else {
+        size.min(bytes.len())
+    };
+
+    let mut output = output_writer(matches.value_of("output"))?;
+    output.write_all(&bytes[..end])
+        .and_then(|_| output.flush())
+        .with_context(|| "Failed to write to the output.")?;
+    Ok(())
+}
+
+// Returns the offset just past the last top-level value in `bytes` that ends at or before
+// `size`. Fails if the stream's IVM doesn't fit or a value's header can't be read.
+fn last_value_boundary(bytes: &[u8], size: usize) -> Result<usize> {
+    if bytes.is_empty() {
+        return Ok(0);
+    }
+    if size < IVM.len() {
+        bail!("The size must be at least {} bytes to leave room for the Ion version marker.", IVM.len());
+    }
+    let mut boundary = 0;
+    while boundary < bytes.len() {
+        let length = match encoded_length(&bytes[boundary..]) {
+            Some(length) => length,
+            None => bail!("Invalid or incomplete value at offset {}", boundary),
+        };
+        if boundary + length > size {
+            break;
+        }
+        boundary += length;
+    }
+    Ok(boundary)
+}
@@ -0,0 +1,144 @@
+use anyhow::{bail, Context, Result};
+use clap::{App, ArgMatches};
+use std::str::FromStr;
+
+use crate::commands::beta::flatten::{lists_arg, separator_arg};
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Rebuilds nested structs from structs with dotted field names.";
+
+pub fn app() -> CommandConfig {
+    App::new("unflatten")
+        .about(ABOUT)
+        .arg(separator_arg())
+        .arg(lists_arg())
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "Reverses `ion beta flatten`: each top-level struct like
+  {id: 7, 'owner.name': \"ana\", 'owner.tags.0': a, 'owner.tags.1': b}
+is written as
+  {id: 7, owner: {name: \"ana\", tags: [a, b]}}
+A group of fields whose names are exactly the indexes 0 through N-1
+becomes a list, unless `--lists keep` is given. A field name can't be
+both a value and a prefix of other field names. Top-level values that
+aren't structs are written unchanged."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --separator and --lists have default values, so we can unwrap them safely.
+    let separator = matches.value_of("separator").unwrap();
+    let index_lists = matches.value_of("lists").unwrap() == "index";
+    let mut output = IonOutput::from_matches(matches)?;
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let value = match value.data {
+                Data::Struct(fields) => {
+                    let mut root = Vec::new();
+                    for (name, child) in fields {
+                        let name = match name.text() {
+                            Some(text) => text.to_owned(),
+                            None => bail!("'{}' has a field name with unknown text.", input.name()),
+                        };
+                        let parts: Vec<&str> = name.split(separator).collect();
+                        insert(&mut root, &parts, child)
+                            .with_context(|| format!("Could not unflatten field '{}' in '{}'", name, input.name()))?;
+                    }
+                    // The top level is always a struct, even if its fields look like indexes.
+                    let fields = root.into_iter().map(|(name, node)| (Symbol::from(name), node.into_value(index_lists))).collect();
+                    Value { annotations: value.annotations, data: Data::Struct(fields) }
+                }
+                _ => value,
+            };
+            output.write_value(&value)?;
+        }
+    }
+    output.finish()
+}
+
+// A field of the struct being rebuilt: either a value or a group of fields that share a prefix.
+enum Node {
+    Leaf(Value),
+    Branch(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn into_value(self, index_lists: bool) -> Value {
+        let fields = match self {
+            Node::Leaf(value) => return value,
+            Node::Branch(fields) => fields,
+        };
+        if index_lists {
+            if let Some(values) = as_list(&fields) {
+                let mut values: Vec<(usize, Node)> = fields
+                    .into_iter()
+                    .zip(values)
+                    .map(|((_, node), index)| (index, node))
+                    .collect();
+                values.sort_by_key(|(index, _)| *index);
+                let values = values.into_iter().map(|(_, node)| node.into_value(index_lists)).collect();
+                return Value::new(Data::List(values));
+            }
+        }
+        let fields = fields
+            .into_iter()
+            .map(|(name, node)| (Symbol::from(name), node.into_value(index_lists)))
+            .collect();
+        Value::new(Data::Struct(fields))
+    }
+}
+
+// If the field names are exactly the indexes 0 through N-1 in some order, returns them.
+fn as_list(fields: &[(String, Node)]) -> Option<Vec<usize>> {
+    let mut indexes = Vec::with_capacity(fields.len());
+    let mut seen = vec![false; fields.len()];
+    for (name, _) in fields {
+        // Reject names like "01" or "+1" that wouldn't be written back the same way.
+        let index = usize::from_str(name).ok().filter(|index| index.to_string() == *name)?;
+        if index >= seen.len() || seen[index] {
+            return None;
+        }
+        seen[index] = true;
+        indexes.push(index);
+    }
+    Some(indexes)
+}
+
+// Adds `value` to `fields` at the position named by `parts`.
+fn insert(fields: &mut Vec<(String, Node)>, parts: &[&str], value: Value) -> Result<()> {
+    let (first, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => unreachable!("Splitting a field name always produces at least one part."),
+    };
+    let existing = fields.iter_mut().position(|(name, _)| name == first);
+    if rest.is_empty() {
+        if let Some(index) = existing {
+            if let Node::Branch(_) = fields[index].1 {
+                bail!("'{}' is both a value and a prefix of other field names.", first);
+            }
+        }
+        // Ion allows repeated field names, so a repeated leaf is kept as it is.
+        fields.push((first.to_string(), Node::Leaf(value)));
+        return Ok(());
+    }
+    let index = match existing {
+        Some(index) => index,
+        None => {
+            fields.push((first.to_string(), Node::Branch(Vec::new())));
+            fields.len() - 1
+        }
+    };
+    match &mut fields[index].1 {
+        Node::Branch(children) => insert(children, rest, value),
+        Node::Leaf(_) => bail!("'{}' is both a value and a prefix of other field names.", first),
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use ed25519_dalek::Verifier;
+
+use crate::canonical::canonical_bytes;
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::signature::{is_signature, parse_signature_value, read_public_key};
+
+const ABOUT: &str = "Verifies a signature created by 'ion beta sign'.";
+
+pub fn app() -> CommandConfig {
+    App::new("verify")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("public-key")
+                .long("public-key")
+                .short("k")
+                .takes_value(true)
+                .required(true)
+                .help("File containing the signer's 32-byte Ed25519 public key in base64"),
+        )
+        .arg(
+            Arg::with_name("signature")
+                .long("signature")
+                .short("s")
+                .takes_value(true)
+                .help("File containing a detached signature [default: the last value of the input]"),
+        )
+        .arg(input_arg())
+        .after_help("Exits with an error if the signature is missing or does not match.")
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // --public-key is required, so we can unwrap this safely.
+    let public_key = read_public_key(matches.value_of("public-key").unwrap())?;
+
+    let mut values = Vec::new();
+    for input_name in input_names(matches) {
+        values.extend(IonInput::open(input_name)?.read_all()?);
+    }
+    let signature_value = match matches.value_of("signature") {
+        Some(signature_file) => {
+            let mut signature_values = IonInput::open(signature_file)?.read_all()?;
+            if signature_values.len() != 1 {
+                bail!("Signature file '{}' must contain exactly one value", signature_file);
+            }
+            signature_values.remove(0)
+        }
+        None => match values.pop() {
+            Some(value) if is_signature(&value) => value,
+            _ => bail!("The input does not end with an embedded signature. Use --signature for detached signatures."),
+        },
+    };
+    let signature = parse_signature_value(&signature_value)?;
+
+    let message = canonical_bytes(&values)
+        .with_context(|| "Could not put the input in canonical form")?;
+    if public_key.verify(&message, &signature).is_err() {
+        bail!("The signature is NOT valid.");
+    }
+    eprintln!("The signature is valid.");
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::{Path as FilePath, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
+use glob::Pattern;
+
+use crate::commands::CommandConfig;
+use crate::input::IonInput;
+use crate::output::{format_arg, unknown_symbols_arg, IonOutput};
+use crate::value::{UnknownSymbols, Value};
+
+const ABOUT: &str = "Watches a directory and converts each new file that appears in it.";
+
+pub fn app() -> CommandConfig {
+    App::new("watch-dir")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("directory")
+                .index(1)
+                .required(true)
+                .help("Directory to watch"),
+        )
+        .arg(
+            Arg::with_name("pattern")
+                .long("pattern")
+                .short("p")
+                .takes_value(true)
+                .default_value("*")
+                .help("Only convert files whose names match this glob, e.g. '*.json'"),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .short("d")
+                .takes_value(true)
+                .required(true)
+                .help("Directory in which to write each converted file"),
+        )
+        .arg(
+            Arg::with_name("done-dir")
+                .long("done-dir")
+                .takes_value(true)
+                .help("Directory to move each file to once it's converted [default: <directory>/done]"),
+        )
+        .arg(
+            Arg::with_name("failed-dir")
+                .long("failed-dir")
+                .takes_value(true)
+                .help("Directory to move each file that can't be converted to [default: <directory>/failed]"),
+        )
+        .arg(
+            Arg::with_name("require-annotation")
+                .long("require-annotation")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Fail files with a top-level value that has none of these annotations; can be repeated"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .takes_value(true)
+                .default_value("2")
+                .help("Seconds between scans of the directory"),
+        )
+        .arg(
+            Arg::with_name("settle")
+                .long("settle")
+                .takes_value(true)
+                .default_value("5")
+                .help("Only convert files that haven't been modified for this many seconds"),
+        )
+        .arg(
+            Arg::with_name("once")
+                .long("once")
+                .help("Convert the files that are ready and exit instead of watching"),
+        )
+        .arg(format_arg())
+        .arg(unknown_symbols_arg())
+        .after_help(
+            "Every --interval seconds, looks for files in the directory whose names
+match --pattern and that haven't changed for --settle seconds, so files
+that are still being written are left alone. Each file is read like
+any other input, so it may be binary Ion, text Ion, JSON, or gzipped,
+and is written to --output-dir in --format with .10n (binary) or .ion
+added to its name, like a.json.10n, so files that differ only by
+extension don't share an output. The global --strict, --duplicate-fields,
+--frame, and --verify-round-trip options apply to every file.
+
+A converted file is written under a temporary name and renamed when
+it's complete, and only then is the original moved to --done-dir, so
+each file is converted at least once: if the watcher stops between the
+two, the file is converted again when it restarts. A file that can't be
+converted is moved to --failed-dir, next to a <name>.error file that
+explains why, and the watcher carries on.
+
+Runs until interrupted, or with --once, until the files that are ready
+have been converted."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // `directory`, --output-dir, --pattern, --format, --interval, and --settle are required or
+    // have default values, so we can unwrap these safely.
+    let directory = PathBuf::from(matches.value_of("directory").unwrap());
+    let pattern_text = matches.value_of("pattern").unwrap();
+    let pattern = Pattern::new(pattern_text)
+        .with_context(|| format!("Invalid --pattern '{}'", pattern_text))?;
+    let converter = Converter {
+        output_dir: PathBuf::from(matches.value_of("output-dir").unwrap()),
+        format: matches.value_of("format").unwrap(),
+        unknown_symbols: UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap()),
+        required_annotations: matches.values_of("require-annotation").into_iter().flatten().collect(),
+    };
+    let done_dir = matches.value_of("done-dir").map_or_else(|| directory.join("done"), PathBuf::from);
+    let failed_dir = matches.value_of("failed-dir").map_or_else(|| directory.join("failed"), PathBuf::from);
+    let interval = Duration::from_secs(parse_seconds(matches, "interval")?);
+    let settle = Duration::from_secs(parse_seconds(matches, "settle")?);
+    for dir in [&converter.output_dir, &done_dir, &failed_dir] {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create '{}'", dir.display()))?;
+    }
+    // Converted files written to the watched directory would be converted again themselves.
+    if fs::canonicalize(&converter.output_dir)? == fs::canonicalize(&directory)? {
+        bail!("--output-dir must be a different directory than the one being watched.");
+    }
+
+    loop {
+        for file in ready_files(&directory, &pattern, settle)? {
+            let file_name = file.file_name().unwrap().to_owned();
+            match converter.convert(&file) {
+                Ok(output_file) => {
+                    move_file(&file, &done_dir.join(&file_name))?;
+                    eprintln!("Converted '{}' to '{}'", file.display(), output_file.display());
+                }
+                Err(error) => {
+                    let failed_file = failed_dir.join(&file_name);
+                    move_file(&file, &failed_file)?;
+                    let mut error_file = failed_file.into_os_string();
+                    error_file.push(".error");
+                    fs::write(&error_file, format!("{:?}\n", error))
+                        .with_context(|| format!("Could not write '{}'", error_file.to_string_lossy()))?;
+                    eprintln!("Could not convert '{}': {:#}", file.display(), error);
+                }
+            }
+        }
+        if matches.is_present("once") {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn parse_seconds(matches: &ArgMatches<'static>, name: &str) -> Result<u64> {
+    // Both arguments that this is used for have default values.
+    let text = matches.value_of(name).unwrap();
+    match text.parse() {
+        Ok(seconds) => Ok(seconds),
+        Err(_) => bail!("--{} must be a whole number of seconds, not '{}'.", name, text),
+    }
+}
+
+// Returns the files in `directory` that match `pattern` and haven't been modified for `settle`,
+// oldest first.
+fn ready_files(directory: &FilePath, pattern: &Pattern, settle: Duration) -> Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata,
+            // The file may have been removed since the directory was read.
+            _ => continue,
+        };
+        if !pattern.matches(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let modified = metadata.modified()
+            .with_context(|| format!("Could not read the modification time of '{}'", entry.path().display()))?;
+        // A modification time in the future counts as just modified.
+        if now.duration_since(modified).unwrap_or_default() >= settle {
+            files.push((modified, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+// Renames a file, replacing anything already at `to`.
+fn move_file(from: &FilePath, to: &FilePath) -> Result<()> {
+    fs::rename(from, to)
+        .with_context(|| format!("Could not move '{}' to '{}'", from.display(), to.display()))
+}
+
+struct Converter<'a> {
+    output_dir: PathBuf,
+    format: &'a str,
+    unknown_symbols: UnknownSymbols,
+    required_annotations: Vec<&'a str>,
+}
+
+impl<'a> Converter<'a> {
+    // Converts a file, returning the path it was written to.
+    fn convert(&self, file: &FilePath) -> Result<PathBuf> {
+        let extension = if self.format == "binary" { ".10n" } else { ".ion" };
+        // The file's own extension is kept, so that files like a.json and a.ion have different
+        // outputs. `ready_files` only returns files, which have names.
+        let mut output_name = file.file_name().unwrap().to_owned();
+        output_name.push(extension);
+        let output_file = self.output_dir.join(&output_name);
+        let mut partial_name = output_name;
+        partial_name.push(".partial");
+        let partial_file = self.output_dir.join(partial_name);
+        let partial_name = partial_file.to_str()
+            .with_context(|| format!("Output path {:?} is not valid UTF-8", partial_file))?;
+        let input_name = file.to_str()
+            .with_context(|| format!("Input path {:?} is not valid UTF-8", file))?;
+
+        let written = self.write(input_name, partial_name);
+        if written.is_err() {
+            let _ = fs::remove_file(&partial_file);
+        }
+        written?;
+        move_file(&partial_file, &output_file)?;
+        Ok(output_file)
+    }
+
+    fn write(&self, input_name: &str, output_name: &str) -> Result<()> {
+        let input = IonInput::open(input_name)?;
+        let mut output = IonOutput::new(self.format, Some(output_name))?;
+        output.set_unknown_symbols(self.unknown_symbols);
+        let mut reader = input.reader();
+        let mut index = 0;
+        while reader.next()?.is_some() {
+            index += 1;
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read value {} from '{}'", index, input.name()))?;
+            let has_required_annotation = self.required_annotations.is_empty()
+                || value.annotations.iter().any(|a| self.required_annotations.iter().any(|r| a == *r));
+            if !has_required_annotation {
+                bail!("Value {} of '{}' has none of the required annotations.", index, input.name());
+            }
+            output.write_value(&value)?;
+        }
+        output.finish()
+    }
+}
@@ -0,0 +1,145 @@
+use std::env;
+use std::mem;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use clap::{App, Arg, ArgMatches};
+
+use crate::commands::CommandConfig;
+use crate::input::{input_arg, input_names, IonInput};
+use crate::output::{deterministic, format_arg, output_arg, IonOutput};
+use crate::timestamp::Timestamp;
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Groups top-level values into lists or annotated struct envelopes.";
+
+pub fn app() -> CommandConfig {
+    App::new("wrap")
+        .about(ABOUT)
+        .arg(
+            Arg::with_name("batch-size")
+                .long("batch-size")
+                .short("n")
+                .takes_value(true)
+                .help("Number of values in each batch [default: all of them]"),
+        )
+        .arg(
+            Arg::with_name("container")
+                .long("container")
+                .short("c")
+                .takes_value(true)
+                .default_value("list")
+                .possible_values(&["list", "struct"])
+                .help("Write each batch as a list or as a struct envelope with metadata"),
+        )
+        .arg(
+            Arg::with_name("annotation")
+                .long("annotation")
+                .short("a")
+                .takes_value(true)
+                .help("Annotation to add to each batch"),
+        )
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .takes_value(true)
+                .default_value("values")
+                .help("Name of the envelope field that holds the batch's values"),
+        )
+        .arg(format_arg())
+        .arg(output_arg())
+        .arg(input_arg())
+        .after_help(
+            "With `--container struct`, each batch is written as
+  {count: 100, timestamp: 2021-06-01T12:00:00.000Z, values: [...]}
+where `timestamp` is the time the batch was written, or the time in
+SOURCE_DATE_EPOCH (seconds since the Unix epoch) if that environment
+variable is set, for reproducible output. The last batch may
+hold fewer than --batch-size values. Nothing is written if there are no
+input values. `ion beta explode --path values` reverses this."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    let batch_size = match matches.value_of("batch-size") {
+        Some(text) => match text.parse::<usize>() {
+            Ok(size) if size > 0 => Some(size),
+            _ => bail!("Invalid batch size '{}'; expected a positive integer", text),
+        },
+        None => None,
+    };
+    let envelope = matches.value_of("container") == Some("struct");
+    let timestamp = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(seconds) => Some(source_date(&seconds)?),
+        Err(_) if envelope && deterministic() => {
+            bail!("--deterministic requires SOURCE_DATE_EPOCH to be set to timestamp each batch.")
+        }
+        Err(_) => None,
+    };
+    let mut wrapper = Wrapper {
+        envelope,
+        timestamp,
+        annotation: matches.value_of("annotation"),
+        // --field has a default value, so we can unwrap this safely.
+        field: matches.value_of("field").unwrap(),
+        output: IonOutput::from_matches(matches)?,
+    };
+
+    let mut batch = Vec::new();
+    for input_name in input_names(matches) {
+        let input = IonInput::open(input_name)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            batch.push(value);
+            if batch_size == Some(batch.len()) {
+                wrapper.write_batch(mem::take(&mut batch))?;
+            }
+        }
+    }
+    if !batch.is_empty() {
+        wrapper.write_batch(batch)?;
+    }
+    wrapper.output.finish()
+}
+
+struct Wrapper<'a> {
+    envelope: bool,
+    // The time to write in each envelope, or `None` to use the time each batch is written.
+    timestamp: Option<DateTime<FixedOffset>>,
+    annotation: Option<&'a str>,
+    field: &'a str,
+    output: IonOutput,
+}
+
+impl<'a> Wrapper<'a> {
+    fn write_batch(&mut self, values: Vec<Value>) -> Result<()> {
+        let count = values.len();
+        let mut batch = Value::new(Data::List(values));
+        if self.envelope {
+            // The offset is always valid, so we can unwrap it safely.
+            let now = self.timestamp.unwrap_or_else(|| Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
+            batch = Value::new(Data::Struct(vec![
+                (Symbol::from("count"), Value::new(Data::Integer(count as i64))),
+                (Symbol::from("timestamp"), Value::new(Data::Timestamp(Timestamp::from_datetime(now)))),
+                (Symbol::from(self.field), batch),
+            ]));
+        }
+        if let Some(annotation) = self.annotation {
+            batch.annotations.push(Symbol::from(annotation));
+        }
+        self.output.write_value(&batch)
+    }
+}
+
+// Parses SOURCE_DATE_EPOCH, the number of seconds since the Unix epoch, as a UTC timestamp.
+fn source_date(seconds: &str) -> Result<DateTime<FixedOffset>> {
+    let parsed = seconds.trim().parse::<i64>().ok()
+        // The offset is always valid, so we can unwrap it safely.
+        .and_then(|seconds| FixedOffset::east_opt(0).unwrap().timestamp_opt(seconds, 0).single());
+    match parsed {
+        Some(timestamp) => Ok(timestamp),
+        None => bail!("SOURCE_DATE_EPOCH must be a number of seconds since the Unix epoch, not '{}'.", seconds),
+    }
+}
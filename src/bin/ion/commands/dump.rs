@@ -1,45 +1,18 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{App, Arg, ArgMatches};
+use std::fs;
+use std::str::FromStr;
 
-use libc::c_char;
-use libc::c_int;
-use std::ffi::CString;
-use std::ptr;
-use crate::commands::CommandConfig;
-
-// ion_c_cli_main is a C function that lives in the ion-c CLI, to which ion-cli is
-// statically linked.
-extern "C" {
-    fn ion_c_cli_main(argc: c_int, argv: *const *const c_char);
-}
-
-fn run_ion_c_cli(args: &[&str]) {
-    // Convert the length-prefixed Rust str arguments to null-terminated C strings
-    let argv_as_c_str = args
-        .iter()
-        .map(|arg| CString::new(*arg).unwrap())
-        .collect::<Vec<CString>>();
-
-    // Convert the C strings to char * pointers. Note: it's important that we collect()
-    // the values below into a separate vector from the values above; it guarantees that
-    // the memory being pointed to will still be valid by the time the ion_c_cli accesses it.
-    let mut argv_as_char_star = argv_as_c_str
-        .iter()
-        .map(|arg| arg.as_ptr())
-        .collect::<Vec<*const c_char>>();
-
-    // The number of arguments as a C int
-    let argc = argv_as_char_star.len() as c_int;
+use tempfile::NamedTempFile;
 
-    // Programs sometimes rely on argv being null-terminated, so we'll push a null onto the array.
-    argv_as_char_star.push(ptr::null());
-
-    let argv = argv_as_char_star.as_ptr();
-
-    unsafe {
-        ion_c_cli_main(argc, argv);
-    }
-}
+use crate::commands::CommandConfig;
+use crate::input::{copy_stdin, input_arg, input_names, needs_decoding, path_str, IonInput, IVM, STDIN_NAME};
+use crate::nested::decode_nested;
+use crate::output::{run_ion_c, unknown_symbols_arg, verify_round_trip, IonOutput};
+use crate::profile::{self, Phase};
+use crate::text::{self, ValueComments};
+use crate::transform::Transform;
+use crate::value::{Data, FloatStyle, Symbol, SymbolMode, UnknownSymbols, Value, ValuePool};
 
 pub fn app() -> CommandConfig {
     App::new("dump")
@@ -61,16 +34,242 @@ pub fn app() -> CommandConfig {
                 .help("Output file [default: STDOUT]"),
         )
         .arg(
-            // All argv entries after the program name (argv[0])
-            // and any `clap`-managed options are considered input files.
-            Arg::with_name("input")
-                .index(1)
+            Arg::with_name("decode-nested")
+                .long("decode-nested")
+                .help("Decode blobs and strings that contain Ion documents")
+                .long_help(
+                    "When specified, blobs that begin with an Ion version marker and
+strings that contain a text Ion container will be decoded and
+replaced by a list of the values they contain, annotated with
+`nested_ion`. Nested documents are decoded recursively."
+                ),
+        )
+        .arg(
+            Arg::with_name("symbols")
+                .long("symbols")
+                .takes_value(true)
+                .default_value("text")
+                .possible_values(&["text", "as-sids", "verbose"])
+                .help("How to write symbols in text output")
+                .long_help(
+                    "Controls how symbol values, field names, and annotations are written.
+  text     writes each symbol's text
+  as-sids  writes each symbol's ID (e.g. `$14`) instead of its text
+  verbose  writes each symbol's text followed by a comment with its ID
+Modes other than `text` require `--format text`."
+                ),
+        )
+        .arg(unknown_symbols_arg())
+        .arg(
+            Arg::with_name("float-style")
+                .long("float-style")
+                .takes_value(true)
+                .default_value("default")
+                .possible_values(&["default", "shortest", "precise", "engineering"])
+                .help("How to write floats in text output")
+                .long_help(
+                    "Controls how finite floats are written.
+  default      lets the text writer decide
+  shortest     writes the fewest digits that read back as the same
+               float, e.g. `1e-1`
+  precise      writes every digit of the float's exact binary value,
+               e.g. `1.000000000000000055511151231257827021181583404541015625e-1`
+  engineering  writes the shortest digits with an exponent that is a
+               multiple of 3, e.g. `12.5e3`
+Styles other than `default` require `--format text`."
+                ),
+        )
+        .arg(
+            Arg::with_name("readable-numbers")
+                .long("readable-numbers")
+                .help("Follow large integers and decimals with a comment grouping their digits")
+                .long_help(
+                    "Follows each integer or decimal with more than three digits before its
+decimal point with a comment showing its digits grouped in thousands,
+e.g. `1234567 /* 1,234,567 */`. Requires `--format text`."
+                ),
+        )
+        .arg(
+            Arg::with_name("digit-separator")
+                .long("digit-separator")
+                .takes_value(true)
+                .requires("readable-numbers")
+                .help("Character used to group digits with --readable-numbers [default: ,]"),
+        )
+        .arg(
+            Arg::with_name("transform")
+                .long("transform")
+                .short("t")
+                .takes_value(true)
                 .multiple(true)
-                .help("Input file [default: STDIN]"),
+                .number_of_values(1)
+                .help("Decode the values at a path, e.g. '(payload) : base64,gunzip,ion'")
+                .long_help(
+                    "Applies a sequence of decoding steps to the values at a path. The
+argument has the form `path : step,step,...`. Available steps:
+  base64  decodes base64 text in a string, symbol, or lob to a blob
+  gunzip  decompresses a gzipped lob to a blob
+  utf8    decodes a lob containing UTF-8 text to a string
+  ion     decodes a lob or string containing an Ion document to a
+          nested_ion::[...] list of its values
+This option can be repeated; transforms are applied in the order
+they are specified, before --decode-nested."
+                ),
         )
+        .arg(
+            Arg::with_name("preserve-comments")
+                .long("preserve-comments")
+                .help("Keep the comments from text Ion inputs")
+                .long_help(
+                    "Copies `//` and `/* */` comments from text Ion inputs to the output.
+Comments between top-level values are written before the value that
+follows them, and a comment on the same line as the end of a value is
+written after it. Values are written on a single line, so comments
+inside a value are written before it. Requires `--format text`."
+                ),
+        )
+        .arg(
+            Arg::with_name("one-per-line")
+                .long("one-per-line")
+                .conflicts_with("preserve-comments")
+                .help("Write each top-level value as compact text on a line of its own")
+                .long_help(
+                    "Writes each top-level value on exactly one line, with nothing else on
+the line, so that line-oriented tools like `split`, `grep`, and
+`parallel` see one value per line. Implies `--format text`, and can't
+be combined with another format."
+                ),
+        )
+        .arg(
+            Arg::with_name("document-separator")
+                .long("document-separator")
+                .takes_value(true)
+                .value_name("TEXT")
+                .help("Write a line containing TEXT between top-level values")
+                .long_help(
+                    "Writes a line containing TEXT, such as `---`, between consecutive
+top-level values, including values from different inputs. An empty
+TEXT separates values with a blank line. Unless TEXT is blank, the
+output is no longer a single Ion stream. Requires `--format text` or
+--one-per-line."
+                ),
+        )
+        .arg(
+            Arg::with_name("framed")
+                .long("framed")
+                .help("Write each top-level value as a length-prefixed binary record")
+                .long_help(
+                    "Writes each top-level value as a record: a 4-byte big-endian length
+followed by a complete binary Ion stream, with its own version marker
+and symbol table, that holds just that value. Streaming frameworks can
+find the record boundaries without parsing Ion, and each record can be
+read on its own. Use `--frame length-prefixed` to read the records back.
+Requires `--format binary`."
+                ),
+        )
+        .arg(
+            Arg::with_name("compress-records")
+                .long("compress-records")
+                .requires("framed")
+                .help("With --framed, compress each record on its own with zstd, using --dict if given"),
+        )
+        .arg(
+            Arg::with_name("drop-null-fields")
+                .long("drop-null-fields")
+                .help("Remove struct fields whose values are null, of any type, at every depth"),
+        )
+        .arg(
+            Arg::with_name("strip-annotations")
+                .long("strip-annotations")
+                .conflicts_with("strip-annotation")
+                .help("Remove all annotations, at every depth"),
+        )
+        .arg(
+            Arg::with_name("strip-annotation")
+                .long("strip-annotation")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Remove this annotation wherever it appears; can be repeated"),
+        )
+        .arg(
+            Arg::with_name("require-annotation")
+                .long("require-annotation")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only write top-level values with this annotation; can be repeated")
+                .long_help(
+                    "Skips top-level values that don't have this annotation. When repeated,
+values with any of the annotations are written. Values are checked
+before --strip-annotations and --strip-annotation are applied, so
+`--require-annotation event --strip-annotation event` selects the
+event:: values and removes their marker."
+                ),
+        )
+        .arg(
+            Arg::with_name("tag-source")
+                .long("tag-source")
+                .takes_value(true)
+                .possible_values(&["annotation", "struct"])
+                .help("Mark each top-level value with the input and position it came from")
+                .long_help(
+                    "Records where each top-level value came from, so that values merged
+from several inputs can be traced back to them.
+  annotation  adds an annotation like `'source:events.10n#3@1024'`
+              before the value's own annotations
+  struct      wraps the value as
+                {source: {file: \"events.10n\", index: 3, offset: 1024},
+                 value: ...}
+The index counts every top-level value in the input from 1, including
+values skipped by --require-annotation. The offset is the position of
+the value's first byte in a binary input, after decompression; it's
+left out for text Ion and JSON inputs, whose values are re-encoded
+before they're read. STDIN is named `STDIN`, or by --stdin-name."
+                ),
+        )
+        .arg(input_arg())
 }
 
 pub fn run(command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // STDIN is copied to a file first, so that it can be examined like any other input and then
+    // read from the copy, by ion-c or otherwise.
+    let mut stdin_copy = None;
+    for input_name in input_names(matches) {
+        if input_name == STDIN_NAME {
+            stdin_copy = Some(copy_stdin()?);
+        }
+    }
+    let stdin_path = match &stdin_copy {
+        Some(temp_file) => path_str(temp_file)?,
+        None => STDIN_NAME,
+    };
+    let input_paths: Vec<&str> = input_names(matches)
+        .into_iter()
+        .map(|input_name| if input_name == STDIN_NAME { stdin_path } else { input_name })
+        .collect();
+
+    // Transformations need to look at each value, and gzipped or JSON inputs need to be decoded
+    // first, so they can't be handed off to ion-c as-is. ion-c's output can still be verified.
+    if input_paths.iter().any(|path| needs_decoding(path))
+        || matches.is_present("decode-nested")
+        || matches.is_present("transform")
+        || matches.is_present("drop-null-fields")
+        || matches.is_present("preserve-comments")
+        || matches.is_present("strip-annotations")
+        || matches.is_present("strip-annotation")
+        || matches.is_present("require-annotation")
+        || matches.is_present("one-per-line")
+        || matches.is_present("framed")
+        || matches.is_present("document-separator")
+        || matches.is_present("tag-source")
+        || matches.value_of("symbols") != Some("text")
+        || matches.value_of("unknown-symbols") != Some("error")
+        || matches.value_of("float-style") != Some("default")
+        || matches.is_present("readable-numbers") {
+        return dump_values(matches, stdin_copy.as_ref());
+    }
+
     let mut args: Vec<&str> = vec![command_name, "process"];
 
     // -f pretty|text|binary
@@ -79,21 +278,215 @@ pub fn run(command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
         args.push(format);
     }
 
-    // -o filename
-    if let Some(output_file) = matches.value_of("output") {
-        args.push("-o");
-        args.push(output_file);
+    // -o filename, and then the files
+    run_ion_c(&args, matches.value_of("output"), &input_paths)
+}
+
+// Reads each value into memory, applies the requested transformations, and writes it out. STDIN
+// has already been copied to `stdin_copy` if it's one of the inputs.
+fn dump_values(matches: &ArgMatches<'static>, stdin_copy: Option<&NamedTempFile>) -> Result<()> {
+    let transforms = match matches.values_of("transform") {
+        Some(specs) => specs.map(Transform::from_str).collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    // --format has a default value, so we can unwrap this safely.
+    let format = match (matches.is_present("one-per-line"), matches.value_of("format").unwrap()) {
+        // Values are written as text a line at a time; the other formats are re-rendered by ion-c.
+        (true, _) if matches.occurrences_of("format") == 0 => "text",
+        (true, "text") => "text",
+        (true, format) => bail!("--one-per-line can't be combined with --format {}.", format),
+        (false, format) => format,
+    };
+    let decode_nested_values = matches.is_present("decode-nested");
+    let drop_null_fields = matches.is_present("drop-null-fields");
+    let preserve_comments = matches.is_present("preserve-comments");
+    if preserve_comments && format != "text" {
+        bail!("--preserve-comments requires --format text.");
+    }
+    let required_annotations: Vec<&str> = matches.values_of("require-annotation").into_iter().flatten().collect();
+    let stripped_annotations = if matches.is_present("strip-annotations") {
+        Some(AnnotationFilter::All)
+    } else {
+        matches.values_of("strip-annotation").map(|names| AnnotationFilter::Named(names.collect()))
+    };
+    // --symbols has a default value, so we can unwrap this safely.
+    let symbol_mode = match matches.value_of("symbols").unwrap() {
+        "as-sids" => SymbolMode::Sids,
+        "verbose" => SymbolMode::Verbose,
+        _ => SymbolMode::Text,
+    };
+    if symbol_mode != SymbolMode::Text && format != "text" {
+        bail!("--symbols as-sids and --symbols verbose require --format text.");
+    }
+    // --unknown-symbols has a default value, so we can unwrap this safely.
+    let unknown_symbols = UnknownSymbols::from_arg(matches.value_of("unknown-symbols").unwrap());
+    if unknown_symbols == UnknownSymbols::PreserveSids && format != "text" {
+        // Without the symbol table, ion-c would have no way to encode these symbol IDs.
+        bail!("--unknown-symbols preserve-sids requires --format text.");
     }
 
-    // ...files
-    if let Some(input_file_iter) = matches.values_of("input") {
-        for input_file in input_file_iter {
-            args.push(input_file);
+    // --float-style has a default value, so we can unwrap this safely.
+    let float_style = FloatStyle::from_arg(matches.value_of("float-style").unwrap());
+    let digit_separator = match (matches.is_present("readable-numbers"), matches.value_of("digit-separator")) {
+        (false, _) => None,
+        (true, None) => Some(','),
+        (true, Some(text)) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(separator), None) => Some(separator),
+                _ => bail!("--digit-separator must be a single character, not '{}'.", text),
+            }
+        }
+    };
+    if (float_style != FloatStyle::Default || digit_separator.is_some()) && format != "text" {
+        // The other formats are transcoded from text by ion-c, which re-renders numbers and
+        // drops comments.
+        bail!("--float-style and --readable-numbers require --format text.");
+    }
+    let tag_source = matches.value_of("tag-source");
+    let framed = matches.is_present("framed");
+    if framed && format != "binary" {
+        bail!("--framed requires --format binary.");
+    }
+    let document_separator = matches.value_of("document-separator");
+    if let Some(separator) = document_separator {
+        if format != "text" {
+            bail!("--document-separator requires --format text or --one-per-line.");
+        }
+        if verify_round_trip() && !separator.trim().is_empty() {
+            bail!("--document-separator '{}' makes the output something other than Ion, so it can't be verified.", separator);
         }
-    } else {
-        args.push("-"); // Signifies STDIN
     }
 
-    run_ion_c_cli(&args);
-    Ok(())
+    let mut output = IonOutput::new(format, matches.value_of("output"))?;
+    output.set_symbol_mode(symbol_mode);
+    output.set_unknown_symbols(unknown_symbols);
+    output.set_float_style(float_style);
+    output.set_digit_separator(digit_separator);
+    output.set_framed(framed);
+    output.set_compress_records(matches.is_present("compress-records"));
+    let mut values_written = 0;
+    // Each value's allocations are reused for the next.
+    let mut pool = ValuePool::new();
+    for input_name in input_names(matches) {
+        let (input, path) = match stdin_copy {
+            Some(temp_file) if input_name == STDIN_NAME => (IonInput::from_stdin_copy(temp_file)?, path_str(temp_file)?),
+            _ => (IonInput::open(input_name)?, input_name),
+        };
+        let (comments, final_comments) = if preserve_comments {
+            comments_in(input.name(), path)?
+        } else {
+            Default::default()
+        };
+        let mut reader = input.reader();
+        let mut index = 0;
+        while reader.next()?.is_some() {
+            let value_comments = comments.get(index);
+            index += 1;
+            let offset = if input.was_transcoded() {
+                None
+            } else {
+                Some(reader.annotations_offset().unwrap_or_else(|| reader.header_offset()))
+            };
+            let mut value = pool.read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", input.name()))?;
+            let has_required_annotation = required_annotations.is_empty()
+                || value.annotations.iter().any(|a| required_annotations.iter().any(|r| a == *r));
+            if !has_required_annotation {
+                continue;
+            }
+            profile::time(Phase::Transform, || -> Result<()> {
+                if let Some(filter) = &stripped_annotations {
+                    strip_annotations(&mut value, filter);
+                }
+                for transform in &transforms {
+                    transform.apply(&mut value)?;
+                }
+                if decode_nested_values {
+                    decode_nested(&mut value);
+                }
+                if drop_null_fields {
+                    value.drop_null_fields();
+                }
+                Ok(())
+            })?;
+            if let Some(style) = tag_source {
+                value = tagged(value, style, input.name(), index, offset);
+            }
+            if let (Some(separator), true) = (document_separator, values_written > 0) {
+                output.write_line(separator)?;
+            }
+            values_written += 1;
+            match value_comments {
+                Some(c) => output.write_value_with_comments(&value, &c.leading, c.trailing.as_deref())?,
+                None => output.write_value(&value)?,
+            }
+            pool.recycle(value);
+        }
+        for comment in &final_comments {
+            output.write_line(comment)?;
+        }
+    }
+    output.finish()
+}
+
+// Marks a value with its source for --tag-source, as an annotation or by wrapping it in a struct.
+fn tagged(mut value: Value, style: &str, input_name: &str, index: usize, offset: Option<usize>) -> Value {
+    if style == "annotation" {
+        let mut source = format!("source:{}#{}", input_name, index);
+        if let Some(offset) = offset {
+            source.push_str(&format!("@{}", offset));
+        }
+        value.annotations.insert(0, Symbol::from(source.as_str()));
+        return value;
+    }
+    let mut source = vec![
+        (Symbol::from("file"), Value::new(Data::String(input_name.to_owned()))),
+        (Symbol::from("index"), Value::new(Data::Integer(index as i64))),
+    ];
+    if let Some(offset) = offset {
+        source.push((Symbol::from("offset"), Value::new(Data::Integer(offset as i64))));
+    }
+    Value::new(Data::Struct(vec![
+        (Symbol::from("source"), Value::new(Data::Struct(source))),
+        (Symbol::from("value"), value),
+    ]))
+}
+
+// Reads the comments from a text Ion file, or a copy of STDIN, for --preserve-comments. Binary and
+// gzipped inputs have no comments.
+fn comments_in(input_name: &str, path: &str) -> Result<(Vec<ValueComments>, Vec<String>)> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read '{}'", input_name))?;
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.as_bytes().starts_with(&IVM) => text::comments(&text)
+            .with_context(|| format!("Could not find the comments in '{}'", input_name)),
+        _ => Ok(Default::default()),
+    }
+}
+
+// The annotations removed by --strip-annotations or --strip-annotation.
+enum AnnotationFilter<'a> {
+    All,
+    Named(Vec<&'a str>),
+}
+
+// Removes the annotations matched by `filter` from `value` and everything nested inside of it.
+fn strip_annotations(value: &mut Value, filter: &AnnotationFilter) {
+    match filter {
+        AnnotationFilter::All => value.annotations.clear(),
+        AnnotationFilter::Named(names) => value.annotations.retain(|a| !names.iter().any(|name| a == *name)),
+    }
+    match &mut value.data {
+        Data::List(children) | Data::SExpression(children) => {
+            for child in children.iter_mut() {
+                strip_annotations(child, filter);
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                strip_annotations(child, filter);
+            }
+        }
+        _ => {}
+    }
 }
@@ -1,8 +1,11 @@
+use std::fmt;
+
 use anyhow::Result;
-use clap::{App, ArgMatches};
+use clap::{App, Arg, ArgMatches};
 
 pub mod beta;
 pub mod dump;
+pub mod version_info;
 
 pub type CommandConfig = App<'static, 'static>;
 pub type CommandRunner = fn(&str, &ArgMatches<'static>) -> Result<()>;
@@ -11,7 +14,8 @@ pub type CommandRunner = fn(&str, &ArgMatches<'static>) -> Result<()>;
 pub fn built_in_commands() -> Vec<CommandConfig> {
     vec![
         dump::app(),
-        beta::app()
+        beta::app(),
+        version_info::app(),
     ]
 }
 
@@ -20,7 +24,47 @@ pub fn runner_for_built_in_command(command_name: &str) -> Option<CommandRunner>
     let runner = match command_name {
         "dump" => dump::run,
         "beta" => beta::run,
+        "version-info" => version_info::run,
         _ => return None,
     };
     Some(runner)
 }
+
+// Creates the `exit-status` argument for commands that select values, so that shell scripts can
+// test whether anything was selected without reading the output.
+pub fn exit_status_arg() -> Arg<'static, 'static> {
+    Arg::with_name("exit-status")
+        .long("exit-status")
+        .help("Exit with status 0 if anything matched, 1 if nothing did, or 2 on errors, like grep")
+}
+
+// An error asking `main` to end the program with a particular exit status, after reporting the
+// error it wraps, if any. Commands return it rather than exiting themselves, so that their
+// output is flushed, their temporary files are removed, and --profile still reports.
+#[derive(Debug)]
+pub struct ExitStatus {
+    pub code: i32,
+    pub error: Option<anyhow::Error>,
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(error) => write!(f, "{} (exit status {})", error, self.code),
+            None => write!(f, "exit status {}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+// Ends a command that reports whether anything matched, with exit status 1 if nothing did and
+// status 2 if it failed. Errors would otherwise end the program with status 1, which couldn't be
+// told apart from finding nothing.
+pub fn exit_status(matched: Result<bool>) -> Result<()> {
+    match matched {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ExitStatus { code: 1, error: None }.into()),
+        Err(error) => Err(ExitStatus { code: 2, error: Some(error) }.into()),
+    }
+}
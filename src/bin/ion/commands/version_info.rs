@@ -0,0 +1,107 @@
+use anyhow::Result;
+use clap::{crate_version, App, ArgMatches};
+
+use crate::commands::beta::beta_subcommands;
+use crate::commands::{built_in_commands, CommandConfig};
+use crate::compression::Codec;
+use crate::framing::{Framing, FRAME_ARGS};
+use crate::output::{format_arg, output_arg, IonOutput};
+use crate::value::{Data, Symbol, Value};
+
+const ABOUT: &str = "Describes the formats, codecs, and features this build supports, as Ion.";
+
+pub fn app() -> CommandConfig {
+    App::new("version-info")
+        .about(ABOUT)
+        .arg(format_arg())
+        .arg(output_arg())
+        .after_help(
+            "Writes a single struct that scripts and orchestration can check before
+handing work to this build, rather than parsing --version:
+  {
+    name: \"ion-cli\",
+    version: \"0.1.1\",
+    ion_versions: [\"1.0\"],
+    formats: {read: [binary, text, json], write: [binary, text, pretty]},
+    framings: [none, 'length-prefixed', kinesis, embedded],
+    compression: {read: [gzip, zstd], write: [gzip, zstd], zstd_dictionaries: true},
+    isl_versions: [\"1.0\"],
+    features: {kafka: false, s3: false, zstd: true},
+    commands: [dump, 'version-info', 'beta agg', ...]
+  }
+Fields may be added in later versions, but won't change meaning. Ion
+1.1 data is rejected unless it's read with --force-version 1.0. Kafka
+support is only built with the `kafka` feature; S3 isn't supported by
+any build yet."
+        )
+}
+
+pub fn run(_command_name: &str, matches: &ArgMatches<'static>) -> Result<()> {
+    // The beta commands are listed individually, since any of them may change or go away.
+    let mut commands: Vec<String> = built_in_commands()
+        .iter()
+        .map(|command| command.get_name().to_owned())
+        .filter(|name| name != "beta")
+        .collect();
+    let mut beta_commands: Vec<String> = beta_subcommands()
+        .iter()
+        .map(|command| format!("beta {}", command.get_name()))
+        .collect();
+    beta_commands.sort();
+    commands.extend(beta_commands);
+    // --embedded is a framing of its own, with no --frame value.
+    let embedded = Framing::Embedded { start: String::new(), end: None };
+    let framings: Vec<&str> = FRAME_ARGS
+        .iter()
+        .map(|arg| Framing::from_arg(arg).name())
+        .chain(std::iter::once(embedded.name()))
+        .collect();
+    let codecs: Vec<&str> = Codec::ALL.iter().map(|codec| codec.name()).collect();
+
+    let info = Value::new(Data::Struct(vec![
+        field("name", string(env!("CARGO_PKG_NAME"))),
+        field("version", string(crate_version!())),
+        field("ion_versions", list(&["1.0"], string)),
+        field("formats", Value::new(Data::Struct(vec![
+            field("read", list(&["binary", "text", "json"], symbol)),
+            field("write", list(&["binary", "text", "pretty"], symbol)),
+        ]))),
+        field("framings", list(&framings, symbol)),
+        field("compression", Value::new(Data::Struct(vec![
+            field("read", list(&codecs, symbol)),
+            field("write", list(&codecs, symbol)),
+            field("zstd_dictionaries", boolean(true)),
+        ]))),
+        field("isl_versions", list(&["1.0"], string)),
+        field("features", Value::new(Data::Struct(vec![
+            field("kafka", boolean(cfg!(feature = "kafka"))),
+            field("s3", boolean(false)),
+            field("zstd", boolean(true)),
+        ]))),
+        field("commands", Value::new(Data::List(commands.iter().map(|name| symbol(name)).collect()))),
+    ]));
+
+    let mut output = IonOutput::from_matches(matches)?;
+    output.write_value(&info)?;
+    output.finish()
+}
+
+fn field(name: &str, value: Value) -> (Symbol, Value) {
+    (Symbol::from(name), value)
+}
+
+fn string(text: &str) -> Value {
+    Value::new(Data::String(text.to_owned()))
+}
+
+fn symbol(text: &str) -> Value {
+    Value::new(Data::Symbol(Symbol::from(text)))
+}
+
+fn boolean(value: bool) -> Value {
+    Value::new(Data::Boolean(value))
+}
+
+fn list(items: &[&str], item: fn(&str) -> Value) -> Value {
+    Value::new(Data::List(items.iter().map(|text| item(text)).collect()))
+}
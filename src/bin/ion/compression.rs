@@ -0,0 +1,191 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use clap::Arg;
+use flate2::write::GzEncoder;
+
+// The largest zstd window that compressed inputs may use, as a power of two. Outputs written with
+// `--long` use large windows, and decompressing them needs a limit at least as large.
+pub const MAX_WINDOW_LOG: u32 = 31;
+
+// The window used by `--long` when no --window-log is given, matching the zstd CLI.
+const LONG_WINDOW_LOG: u32 = 27;
+
+// The level that records are compressed at.
+const RECORD_LEVEL: i32 = 3;
+
+// The zstd dictionary that compressed inputs, records, and outputs use, if one was given. This
+// applies to every command, so it's set once by `main`.
+static DICTIONARY: OnceLock<Vec<u8>> = OnceLock::new();
+
+// Creates the global `dict` argument, which can be given to any command.
+pub fn dict_arg() -> Arg<'static, 'static> {
+    Arg::with_name("dict")
+        .long("dict")
+        .takes_value(true)
+        .global(true)
+        .help("zstd dictionary for decompressing inputs and records, and for compressing output")
+        .long_help(
+            "A zstd dictionary, like one written by `ion beta dict train`.
+zstd-compressed inputs, and zstd-compressed records read with
+--frame, are decompressed with it. `dump --framed --compress-records`
+and `beta compress` compress with it. Data compressed with a dictionary
+can only be decompressed with the same one, but data compressed
+without one can still be read."
+        )
+}
+
+// Reads the dictionary given by --dict. Only the first call has any effect.
+pub fn set_dictionary(file_name: &str) -> Result<()> {
+    let dictionary = fs::read(file_name)
+        .with_context(|| format!("Could not read dictionary '{}'", file_name))?;
+    let _ = DICTIONARY.set(dictionary);
+    Ok(())
+}
+
+pub fn dictionary() -> Option<&'static [u8]> {
+    DICTIONARY.get().map(Vec::as_slice)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    // Every codec, each of which can both compress and decompress.
+    pub const ALL: [Codec; 2] = [Codec::Gzip, Codec::Zstd];
+
+    // Parses the value of a `--codec` argument.
+    pub fn from_arg(arg: &str) -> Codec {
+        match arg {
+            "gzip" => Codec::Gzip,
+            _ => Codec::Zstd,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+        }
+    }
+}
+
+// How a stream is compressed.
+pub struct Compression {
+    pub codec: Codec,
+    pub level: i32,
+    // Whether zstd looks for matches across the whole window rather than only nearby.
+    pub long: bool,
+    pub window_log: Option<u32>,
+    // The size zstd aims for when dividing compressed data into blocks.
+    pub block_size: Option<u32>,
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl Compression {
+    pub fn new(codec: Codec) -> Compression {
+        let level = match codec {
+            Codec::Gzip => 6,
+            Codec::Zstd => 3,
+        };
+        Compression { codec, level, long: false, window_log: None, block_size: None, dictionary: None }
+    }
+
+    // Fails if the settings can't be used with the codec.
+    pub fn check(&self) -> Result<()> {
+        let (name, levels) = match self.codec {
+            Codec::Gzip => ("gzip", 0..=9),
+            Codec::Zstd => ("zstd", 1..=22),
+        };
+        if !levels.contains(&self.level) {
+            bail!("{} compression levels are {} to {}, not {}.", name, levels.start(), levels.end(), self.level);
+        }
+        if let Some(window_log) = self.window_log {
+            if !(10..=MAX_WINDOW_LOG).contains(&window_log) {
+                bail!("zstd window logs are 10 to {}, not {}.", MAX_WINDOW_LOG, window_log);
+            }
+        }
+        let zstd_only = self.long || self.window_log.is_some() || self.block_size.is_some() || self.dictionary.is_some();
+        if self.codec == Codec::Gzip && zstd_only {
+            bail!("Long-range matching, window logs, block sizes, and dictionaries are only available with zstd.");
+        }
+        Ok(())
+    }
+
+    // Wraps `writer` in an encoder with these settings.
+    pub fn compressor<W: Write>(&self, writer: W) -> Result<Compressor<W>> {
+        if self.codec == Codec::Gzip {
+            let encoder = GzEncoder::new(writer, flate2::Compression::new(self.level as u32));
+            return Ok(Compressor::Gzip(encoder));
+        }
+        let dictionary = self.dictionary.as_deref().unwrap_or(&[]);
+        let mut encoder = zstd::stream::write::Encoder::with_dictionary(writer, self.level, dictionary)
+            .with_context(|| "Failed to start compressing with zstd.")?;
+        let window_log = match (self.window_log, self.long) {
+            (Some(window_log), _) => Some(window_log),
+            (None, true) => Some(LONG_WINDOW_LOG),
+            (None, false) => None,
+        };
+        encoder.long_distance_matching(self.long).with_context(|| "Failed to configure zstd.")?;
+        if let Some(window_log) = window_log {
+            encoder.window_log(window_log).with_context(|| "Failed to configure zstd.")?;
+        }
+        encoder.set_target_cblock_size(self.block_size).with_context(|| "Failed to configure zstd.")?;
+        Ok(Compressor::Zstd(encoder))
+    }
+}
+
+// A writer that compresses what's written to it with one of the codecs.
+pub enum Compressor<W: Write> {
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> Compressor<W> {
+    // Writes the end of the compressed stream and returns the writer it was written to.
+    pub fn finish(self) -> Result<W> {
+        match self {
+            Compressor::Gzip(encoder) => encoder.finish().with_context(|| "Failed to compress with gzip."),
+            Compressor::Zstd(encoder) => encoder.finish().with_context(|| "Failed to compress with zstd."),
+        }
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Compressor::Gzip(encoder) => encoder.write(buf),
+            Compressor::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Compressor::Gzip(encoder) => encoder.flush(),
+            Compressor::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Creates a compressor for values that are compressed one at a time, using the dictionary if
+// there is one. It's prepared once and reused for each record.
+pub fn record_compressor() -> Result<zstd::bulk::Compressor<'static>> {
+    zstd::bulk::Compressor::with_dictionary(RECORD_LEVEL, dictionary().unwrap_or(&[]))
+        .with_context(|| "Failed to start compressing records with zstd.")
+}
+
+// Trains a zstd dictionary of at most `max_size` bytes on samples of the data it will compress.
+pub fn train_dictionary(samples: &[Vec<u8>], max_size: usize) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size).with_context(|| {
+        format!(
+            "Could not train a dictionary on {} samples; training needs many samples, varied enough to find what they have in common",
+            samples.len()
+        )
+    })
+}
@@ -0,0 +1,79 @@
+use std::convert::TryInto;
+
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{bail, Context, Result};
+
+use crate::input::read_key_file;
+use crate::nested::read_document;
+use crate::value::{Data, Symbol, TextFormatter, Value};
+
+// The annotation on the blob that replaces an encrypted value.
+pub const ENCRYPTED_VALUE_ANNOTATION: &str = "ion_encrypted";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+// Encrypts and decrypts individual values with AES-256-GCM. An encrypted value is replaced by a
+// blob, `ion_encrypted::{{...}}`, holding a random 12-byte nonce followed by the ciphertext of
+// the value (including its annotations) as text Ion.
+pub struct ValueCipher {
+    cipher: Aes256Gcm,
+    formatter: TextFormatter,
+}
+
+impl ValueCipher {
+    // Creates a cipher using the 32-byte key in base64 in the named file.
+    pub fn from_key_file(file_name: &str) -> Result<ValueCipher> {
+        let key = read_key_file(file_name)?;
+        if key.len() != KEY_LENGTH {
+            bail!("Key file '{}' must contain {} bytes, not {}", file_name, KEY_LENGTH, key.len());
+        }
+        // The key's length was checked above.
+        Ok(ValueCipher {
+            cipher: Aes256Gcm::new_from_slice(&key).unwrap(),
+            formatter: TextFormatter::new(),
+        })
+    }
+
+    pub fn encrypt(&mut self, value: &Value) -> Result<Value> {
+        let mut plaintext = String::new();
+        self.formatter.format(value, &mut plaintext)?;
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let ciphertext = self.cipher
+            .encrypt(&Nonce::from(nonce), plaintext.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        let mut envelope = nonce.to_vec();
+        envelope.extend_from_slice(&ciphertext);
+        let mut encrypted = Value::new(Data::Blob(envelope));
+        encrypted.annotations.push(Symbol::from(ENCRYPTED_VALUE_ANNOTATION));
+        Ok(encrypted)
+    }
+
+    // Decrypts a value created by `encrypt`. Fails if the key is wrong or the envelope was modified.
+    pub fn decrypt(&self, envelope: &[u8]) -> Result<Value> {
+        if envelope.len() < NONCE_LENGTH {
+            bail!("The encrypted value is too short to be valid");
+        }
+        let (nonce, ciphertext) = envelope.split_at(NONCE_LENGTH);
+        // split_at guarantees the nonce's length.
+        let nonce: [u8; NONCE_LENGTH] = nonce.try_into().unwrap();
+        let plaintext = self.cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Could not decrypt a value; the key is wrong or the value was modified"))?;
+        let mut values = read_document(&plaintext)
+            .with_context(|| "The decrypted data is not valid Ion")?;
+        if values.len() != 1 {
+            bail!("The decrypted data contains {} values instead of one", values.len());
+        }
+        Ok(values.remove(0))
+    }
+}
+
+// Returns the envelope bytes if `value` is an encrypted value.
+pub fn encrypted_envelope(value: &Value) -> Option<&[u8]> {
+    match &value.data {
+        Data::Blob(bytes) if value.annotations.iter().any(|a| a == ENCRYPTED_VALUE_ANNOTATION) => Some(bytes),
+        _ => None,
+    }
+}
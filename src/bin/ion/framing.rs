@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::MultiGzDecoder;
+use serde_json::Value as JsonValue;
+
+use crate::binary::{stream_preamble, SymbolTableTracker};
+use crate::compression::record_compressor;
+use crate::input::{reader_for, IonReader, GZIP_MAGIC};
+
+// Streaming frameworks often need to know where each record ends without parsing it. A framed
+// stream is a sequence of records, each a 4-byte big-endian length followed by that many bytes
+// of payload. Each payload is a complete Ion stream holding one top-level value, with its own
+// version marker and symbol table, so it can be read without the records before it.
+
+// The number of bytes in each record's length prefix.
+const LENGTH_PREFIX_BYTES: usize = 4;
+
+// The bytes that begin a record aggregated by the Kinesis Producer Library, and the length of the
+// MD5 checksum that ends it.
+const KPL_MAGIC: [u8; 4] = [0xF3, 0x89, 0x9A, 0xC2];
+const KPL_CHECKSUM_BYTES: usize = 16;
+
+// How inputs are divided into records before their contents are read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Framing {
+    // The input is a single Ion stream.
+    None,
+    // The input is a sequence of length-prefixed records.
+    LengthPrefixed,
+    // The input is Kinesis or Firehose data: KPL-aggregated records, `aws kinesis get-records`
+    // output, or concatenated records, any of which may be gzipped.
+    Kinesis,
+    // The input is text, like a log, with Ion values embedded in it. Each value follows `start`
+    // and runs until `end`, or the end of the line if there's no `end`.
+    Embedded { start: String, end: Option<String> },
+}
+
+// The values of --frame, each of which `Framing::from_arg` accepts.
+pub const FRAME_ARGS: &[&str] = &["none", "length-prefixed", "kinesis"];
+
+impl Framing {
+    pub fn from_arg(arg: &str) -> Framing {
+        match arg {
+            "length-prefixed" => Framing::LengthPrefixed,
+            "kinesis" => Framing::Kinesis,
+            _ => Framing::None,
+        }
+    }
+
+    // The framing's name, as --frame writes it. Embedded values are found with --embedded instead.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Framing::None => "none",
+            Framing::LengthPrefixed => "length-prefixed",
+            Framing::Kinesis => "kinesis",
+            Framing::Embedded { .. } => "embedded",
+        }
+    }
+}
+
+// Splits an input into the payloads of its records.
+pub fn split_records<'a>(framing: &Framing, name: &str, bytes: &'a [u8]) -> Result<Vec<Cow<'a, [u8]>>> {
+    match framing {
+        Framing::None => Ok(vec![Cow::Borrowed(bytes)]),
+        Framing::LengthPrefixed => Ok(read_frames(name, bytes)?.into_iter().map(Cow::Borrowed).collect()),
+        Framing::Kinesis => kinesis_records(name, bytes),
+        Framing::Embedded { start, end } => {
+            let records = embedded_records(name, bytes, start.as_bytes(), end.as_deref().map(str::as_bytes))?;
+            Ok(records.into_iter().map(Cow::Borrowed).collect())
+        }
+    }
+}
+
+// Finds the text between each occurrence of `start` and the following `end` (or the end of the
+// line), ignoring everything else.
+fn embedded_records<'a>(name: &str, bytes: &'a [u8], start: &[u8], end: Option<&[u8]>) -> Result<Vec<&'a [u8]>> {
+    let mut records = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = find(&bytes[position..], start) {
+        let record_start = position + offset + start.len();
+        let rest = &bytes[record_start..];
+        let record_length = match end {
+            Some(end) => match find(rest, end) {
+                Some(length) => length,
+                None => {
+                    let line = bytes[..record_start].iter().filter(|byte| **byte == b'\n').count() + 1;
+                    bail!("The value embedded on line {} of '{}' has no end marker.", line, name);
+                }
+            },
+            None => rest.iter().position(|byte| *byte == b'\n').unwrap_or(rest.len()),
+        };
+        records.push(&rest[..record_length]);
+        position = record_start + record_length + end.map_or(0, <[u8]>::len);
+    }
+    Ok(records)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// Firehose writes the records it receives one after another, with no delimiters, and may gzip
+// the result. Concatenated Ion streams, JSON values, and gzip members can already be read as a
+// single input, so only aggregated records and `get-records` output need to be unpacked.
+fn kinesis_records<'a>(name: &str, bytes: &'a [u8]) -> Result<Vec<Cow<'a, [u8]>>> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(bytes).read_to_end(&mut decompressed)
+            .with_context(|| format!("Failed to decompress '{}'", name))?;
+        let records = kinesis_records(name, &decompressed)?;
+        return Ok(records.into_iter().map(|record| Cow::Owned(record.into_owned())).collect());
+    }
+    if bytes.starts_with(&KPL_MAGIC) {
+        return aggregated_records(name, bytes);
+    }
+    if let Some(records) = get_records_output(name, bytes)? {
+        return Ok(records.into_iter().map(Cow::Owned).collect());
+    }
+    Ok(vec![Cow::Borrowed(bytes)])
+}
+
+// Reads the data of each user record in a KPL aggregated record. After the magic number comes an
+// `AggregatedRecord` protobuf message, whose field 3 holds `Record` messages, whose field 3
+// holds each record's data. The checksum at the end isn't verified.
+fn aggregated_records<'a>(name: &str, bytes: &'a [u8]) -> Result<Vec<Cow<'a, [u8]>>> {
+    if bytes.len() < KPL_MAGIC.len() + KPL_CHECKSUM_BYTES {
+        bail!("'{}' is too short to be an aggregated Kinesis record.", name);
+    }
+    let message = &bytes[KPL_MAGIC.len()..bytes.len() - KPL_CHECKSUM_BYTES];
+    let mut records = Vec::new();
+    for (field, record) in protobuf_fields(name, message)? {
+        if field != 3 {
+            continue;
+        }
+        let data = protobuf_fields(name, record)?
+            .into_iter()
+            .find(|(field, _)| *field == 3)
+            .map(|(_, data)| data)
+            .unwrap_or(&[]);
+        records.push(Cow::Borrowed(data));
+    }
+    Ok(records)
+}
+
+// Returns the field number and contents of each length-delimited field in a protobuf message,
+// skipping fields of other wire types.
+fn protobuf_fields<'a>(name: &str, mut message: &'a [u8]) -> Result<Vec<(u64, &'a [u8])>> {
+    let mut fields = Vec::new();
+    let malformed = || format!("'{}' contains a malformed aggregated Kinesis record.", name);
+    while !message.is_empty() {
+        let key = read_protobuf_varint(&mut message).with_context(malformed)?;
+        let skipped = match key & 0x7 {
+            0 => read_protobuf_varint(&mut message).map(|_| 0),
+            1 => Some(8),
+            2 => {
+                let length = read_protobuf_varint(&mut message).with_context(malformed)? as usize;
+                if length > message.len() {
+                    bail!(malformed());
+                }
+                fields.push((key >> 3, &message[..length]));
+                Some(length)
+            }
+            5 => Some(4),
+            _ => None,
+        };
+        match skipped {
+            Some(length) if length <= message.len() => message = &message[length..],
+            _ => bail!(malformed()),
+        }
+    }
+    Ok(fields)
+}
+
+// Reads a protobuf varint, which holds 7 bits per byte, least significant bits first, with the
+// high bit set on every byte but the last.
+fn read_protobuf_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for (index, byte) in bytes.iter().enumerate().take(10) {
+        value |= ((byte & 0x7F) as u64) << (7 * index);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[index + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+// Reads the output of `aws kinesis get-records`, a JSON object whose `Records` each have their
+// payload in `Data` as base64. Returns None if the input isn't in that form.
+fn get_records_output(name: &str, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>> {
+    let json: JsonValue = match serde_json::from_slice(bytes) {
+        Ok(json) => json,
+        Err(_) => return Ok(None),
+    };
+    let records = match json.get("Records").and_then(JsonValue::as_array) {
+        Some(records) => records,
+        None => return Ok(None),
+    };
+    let mut payloads = Vec::with_capacity(records.len());
+    for (index, record) in records.iter().enumerate() {
+        let data = match record.get("Data").and_then(JsonValue::as_str) {
+            Some(data) => data,
+            None => bail!("Record {} in '{}' has no Data.", index + 1, name),
+        };
+        payloads.push(base64::decode(data)
+            .with_context(|| format!("The Data of record {} in '{}' is not valid base64", index + 1, name))?);
+    }
+    Ok(Some(payloads))
+}
+
+// Splits a length-prefixed stream into the payloads of its records.
+fn read_frames<'a>(name: &str, mut bytes: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while !bytes.is_empty() {
+        if bytes.len() < LENGTH_PREFIX_BYTES {
+            bail!("'{}' ends with a partial record length at offset {}.", name, offset);
+        }
+        let (prefix, rest) = bytes.split_at(LENGTH_PREFIX_BYTES);
+        // The prefix is exactly 4 bytes, so this conversion can't fail.
+        let length = u32::from_be_bytes(prefix.try_into().unwrap()) as usize;
+        if rest.len() < length {
+            bail!(
+                "The record at offset {} of '{}' is {} bytes long, but only {} bytes remain.",
+                offset,
+                name,
+                length,
+                rest.len()
+            );
+        }
+        frames.push(&rest[..length]);
+        bytes = &rest[length..];
+        offset += LENGTH_PREFIX_BYTES + length;
+    }
+    Ok(frames)
+}
+
+// Calls `on_record` with each top-level value of a binary Ion stream, encoded as a stream of its
+// own, along with a reader positioned on the value.
+pub fn for_each_record(
+    binary: &[u8],
+    mut on_record: impl FnMut(&mut IonReader, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+    let mut reader = reader_for(binary);
+    let (tracker, tracked_symbols) = SymbolTableTracker::new();
+    reader.set_symtab_event_handler(tracker);
+    while reader.next()?.is_some() {
+        // A top-level value has no field name, so it begins with its annotations, if any.
+        let start = reader.annotations_offset().unwrap_or_else(|| reader.header_offset());
+        let mut record = stream_preamble(&tracked_symbols.borrow());
+        record.extend_from_slice(&binary[start..reader.value_range().end]);
+        on_record(&mut reader, record)?;
+    }
+    Ok(())
+}
+
+// Writes each top-level value of a binary Ion stream as a length-prefixed record, compressing
+// each record on its own with zstd if `compress` is set.
+pub fn write_frames(binary: &[u8], writer: &mut dyn Write, compress: bool) -> Result<()> {
+    let mut compressor = if compress { Some(record_compressor()?) } else { None };
+    for_each_record(binary, |_, record| {
+        let record = match &mut compressor {
+            Some(compressor) => compressor.compress(&record)
+                .with_context(|| "Failed to compress a record with zstd.")?,
+            None => record,
+        };
+        let length = match u32::try_from(record.len()) {
+            Ok(length) => length,
+            Err(_) => bail!("A value is too large to frame ({} bytes).", record.len()),
+        };
+        writer.write_all(&length.to_be_bytes())
+            .and_then(|_| writer.write_all(&record))
+            .with_context(|| "Failed to write to the output.")
+    })
+}
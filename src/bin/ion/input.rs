@@ -0,0 +1,970 @@
+use std::borrow::Cow;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::OnceLock;
+use std::thread;
+
+use anyhow::{bail, Context, Result};
+use clap::{Arg, ArgMatches};
+use flate2::read::MultiGzDecoder;
+use glob::Pattern;
+use ion_rs::{BinaryIonCursor, Reader};
+use memmap::{Mmap, MmapOptions};
+use serde_json::{Deserializer, Value as JsonValue};
+use tempfile::NamedTempFile;
+
+use crate::binary::{is_version_marker, unsupported_version_marker};
+use crate::compression::{dictionary, MAX_WINDOW_LOG};
+use crate::framing::{split_records, Framing, FRAME_ARGS};
+use crate::ion_c;
+use crate::json::{from_json, Dialect, NumbersAs};
+use crate::profile::{self, Phase};
+use crate::text::{check, line_and_column, render, version_markers, StrictChecks};
+use crate::value::{DuplicateFields, TextFormatter, Value};
+
+// The Ion 1.0 version marker that begins every binary Ion stream.
+pub const IVM: [u8; 4] = [0xE0, 0x01, 0x00, 0xEA];
+
+// The name used to refer to STDIN on the command line.
+pub const STDIN_NAME: &str = "-";
+
+// The name STDIN is given in messages and in the values that record where they came from,
+// unless --stdin-name gives it another.
+const STDIN_DEFAULT_LABEL: &str = "STDIN";
+
+// The first two bytes of every gzip stream.
+pub const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+// The first four bytes of every zstd frame.
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+// Compressed inputs are decompressed in chunks of this size, and decompression pauses when this
+// many chunks are waiting to be written.
+const DECOMPRESSED_CHUNK_SIZE: usize = 256 * 1024;
+const DECOMPRESSED_CHUNKS_AHEAD: usize = 16;
+
+// The format that inputs are read as, unless they're binary Ion or compressed (which are always
+// detected). This applies to every input of every command, so it's set once by `main`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    // JSON if the input is a JSON document (or several), and text Ion otherwise.
+    Auto,
+    Ion,
+    Json,
+}
+
+static INPUT_FORMAT: OnceLock<InputFormat> = OnceLock::new();
+
+static DUPLICATE_FIELDS: OnceLock<DuplicateFields> = OnceLock::new();
+
+static FRAMING: OnceLock<Framing> = OnceLock::new();
+
+// The checks made on text Ion inputs before they're transcoded, if --strict was given.
+static STRICT_CHECKS: OnceLock<Option<StrictChecks>> = OnceLock::new();
+
+// Whether text Ion inputs that declare another version of Ion are read as Ion 1.0 anyway.
+static FORCE_ION_1_0: OnceLock<bool> = OnceLock::new();
+
+static STDIN_LABEL: OnceLock<String> = OnceLock::new();
+
+// The files found under the --input-dir directories, in the order they're read.
+static DIRECTORY_INPUTS: OnceLock<Vec<String>> = OnceLock::new();
+
+// A file listing patterns for --input-dir to skip in the directory holding it and beneath it.
+const IGNORE_FILE_NAME: &str = ".ionignore";
+
+// Whether STDIN has been read. It can only be read once, so it can only be one of the inputs.
+static STDIN_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+// Creates the global `input-format` argument, which can be given to any command.
+pub fn input_format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("input-format")
+        .long("input-format")
+        .takes_value(true)
+        .global(true)
+        .possible_values(&["auto", "ion", "json"])
+        .help("How to read inputs that aren't binary Ion [default: auto]")
+        .long_help(
+            "Binary Ion inputs, and gzip- or zstd-compressed inputs of any format, are
+always detected. Other inputs are read as:
+  auto  JSON if the whole input parses as JSON values, or text Ion
+  ion   text Ion
+  json  JSON, using the same rules as `ion beta from json`"
+        )
+}
+
+// Creates the global `frame` argument, which can be given to any command.
+pub fn frame_arg() -> Arg<'static, 'static> {
+    Arg::with_name("frame")
+        .long("frame")
+        .takes_value(true)
+        .global(true)
+        .possible_values(FRAME_ARGS)
+        .help("How inputs are divided into records [default: none]")
+        .long_help(
+            "Controls how inputs are divided into records before they're read:
+  none             each input is a single Ion stream
+  length-prefixed  each input is a sequence of records, each a 4-byte
+                   big-endian length followed by that many bytes,
+                   as written by `ion dump --framed`
+  kinesis          each input is Kinesis or Firehose data: an aggregated
+                   record from the Kinesis Producer Library, the JSON
+                   output of `aws kinesis get-records`, or records that
+                   Firehose concatenated, any of them gzipped
+Each record is read as an input of its own, so records may be binary
+Ion, text Ion, JSON, or gzipped, and their values are read in order.
+Aggregated records inside Firehose output are not unpacked."
+        )
+}
+
+// Creates the global `embedded` argument, which can be given to any command.
+pub fn embedded_arg() -> Arg<'static, 'static> {
+    Arg::with_name("embedded")
+        .long("embedded")
+        .takes_value(true)
+        .value_name("START")
+        .global(true)
+        .conflicts_with("frame")
+        .help("Read only the Ion values that follow START in text inputs, like logs")
+        .long_help(
+            "Scans each input, such as an application log, for values embedded in
+other text. Each embedded value begins just after START and runs to the
+end of its line, or to the next occurrence of --embedded-end if one was
+given, so values can span lines. Everything else is ignored. Each value
+may be text Ion or JSON, and the values are read in order. For example,
+`--embedded 'ION: '` reads `{id: 1}` from the line
+`12:00:01 INFO handled request ION: {id: 1}`."
+        )
+}
+
+// Creates the global `embedded-end` argument, which can be given to any command.
+pub fn embedded_end_arg() -> Arg<'static, 'static> {
+    Arg::with_name("embedded-end")
+        .long("embedded-end")
+        .takes_value(true)
+        .value_name("END")
+        .global(true)
+        .requires("embedded")
+        .help("With --embedded, the text that ends each embedded value [default: the end of the line]")
+}
+
+// Creates the global `force-version` argument, which can be given to any command.
+pub fn force_version_arg() -> Arg<'static, 'static> {
+    Arg::with_name("force-version")
+        .long("force-version")
+        .takes_value(true)
+        .global(true)
+        .possible_values(&["1.0"])
+        .help("Read text inputs that declare another Ion version as Ion 1.0")
+        .long_help(
+            "Inputs that declare a version of Ion other than 1.0, with a binary
+version marker like `e0 01 01 ea` or a text one like `$ion_1_1`, are
+rejected with a message naming the version and where it was declared.
+With `--force-version 1.0`, the version markers in text inputs are read
+as `$ion_1_0` instead, which is safe as long as the data doesn't use
+any features of the newer version (those still fail to parse). Binary
+Ion 1.1 is encoded differently from 1.0, so binary inputs can't be
+forced."
+        )
+}
+
+// Creates the global `strict` argument, which can be given to any command.
+pub fn strict_arg() -> Arg<'static, 'static> {
+    Arg::with_name("strict")
+        .long("strict")
+        .global(true)
+        .help("Reject text Ion inputs with unknown escapes or non-canonical numbers")
+        .long_help(
+            "Checks text Ion inputs before reading them, and fails with a report of
+every problem found, each with its line, column, and a snippet. Unknown
+escape sequences and numbers that aren't written the way Ion writes them
+(e.g. `1E5`, `1e+5`, `0X1F`, or `-0`) are rejected, as are syntax errors."
+        )
+}
+
+// Creates the global `reject-duplicate-fields` argument, which can be given to any command.
+pub fn reject_duplicate_fields_arg() -> Arg<'static, 'static> {
+    Arg::with_name("reject-duplicate-fields")
+        .long("reject-duplicate-fields")
+        .global(true)
+        .help("With --strict, also reject structs that repeat a field name (implies --strict)")
+}
+
+// Creates the global `duplicate-fields` argument, which can be given to any command.
+pub fn duplicate_fields_arg() -> Arg<'static, 'static> {
+    Arg::with_name("duplicate-fields")
+        .long("duplicate-fields")
+        .takes_value(true)
+        .global(true)
+        .possible_values(&["error", "first", "last", "keep-all"])
+        .help("What to do with structs that repeat a field name [default: keep-all]")
+        .long_help(
+            "Ion allows a struct to have several fields with the same name. This
+controls what happens when commands read such a struct:
+  error     stops with an error naming the field
+  first     keeps only the first field with each name
+  last      keeps only the last field with each name
+  keep-all  keeps every field
+Commands that examine the encoding rather than the values, like
+`inspect`, are not affected."
+        )
+}
+
+// Creates the global `stdin-name` argument, which can be given to any command.
+pub fn stdin_name_arg() -> Arg<'static, 'static> {
+    Arg::with_name("stdin-name")
+        .long("stdin-name")
+        .takes_value(true)
+        .global(true)
+        .help("Name for STDIN in error messages and --tag-source [default: STDIN]")
+        .long_help(
+            "The name STDIN is given in error messages and wherever a command records
+which input a value came from, like `dump --tag-source`, e.g.
+  curl -s $URL | ion dump --tag-source struct --stdin-name remote local.ion -
+Names STDIN whether it's read because no inputs were given or because
+`-` is one of them."
+        )
+}
+
+// Sets the name STDIN is given in messages. Only the first call has any effect.
+pub fn set_stdin_name(name: &str) {
+    let _ = STDIN_LABEL.set(name.to_owned());
+}
+
+// Returns the name an input is given in messages: its file name, or STDIN's name.
+pub fn display_name(input_name: &str) -> &str {
+    if input_name == STDIN_NAME {
+        STDIN_LABEL.get().map(String::as_str).unwrap_or(STDIN_DEFAULT_LABEL)
+    } else {
+        input_name
+    }
+}
+
+// Fails if STDIN has already been read, and otherwise notes that it's about to be. Every
+// command that reads STDIN calls this first.
+pub fn claim_stdin() -> Result<()> {
+    if STDIN_CLAIMED.swap(true, Ordering::SeqCst) {
+        bail!("STDIN ('-') can only be read once, but it's given more than once as an input.");
+    }
+    Ok(())
+}
+
+// Claims STDIN and copies it to a temporary file that will delete itself when it goes out of
+// scope. The copy can be examined, handed to ion-c, or opened with `IonInput::from_stdin_copy`.
+pub fn copy_stdin() -> Result<NamedTempFile> {
+    claim_stdin()?;
+    let temp_file = NamedTempFile::new()
+        .with_context(|| concat!(
+            "Failed to create a temporary file to store STDIN. ",
+            "Try passing the input's file name instead."
+        ))?;
+    let mut writer = BufWriter::new(temp_file);
+    io::copy(&mut io::stdin(), &mut writer)
+        .with_context(|| "Failed to copy STDIN to a temp file.")?;
+    writer.into_inner()
+        .with_context(|| "Failed to read from temp file containing STDIN data.")
+}
+
+// Creates the global `input-dir` argument, which can be given to any command that reads inputs.
+pub fn input_dir_arg() -> Arg<'static, 'static> {
+    Arg::with_name("input-dir")
+        .long("input-dir")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .global(true)
+        .help("Read every file in this directory and its subdirectories, after any other inputs")
+        .long_help(
+            "Reads the files in a directory and its subdirectories as inputs, after
+any that were named. Use it instead of letting the shell expand a glob,
+which fails when there are too many files for one command line. Files
+are read in the same order on every system: each directory's entries
+are sorted by name, and each subdirectory is read where its name falls.
+Symbolic links to directories aren't followed, and hidden files and
+those listed in .ionignore files are skipped; see --exclude. May be
+repeated to read several directories, one after another."
+        )
+}
+
+// Creates the global `glob` argument, which chooses the files --input-dir reads.
+pub fn glob_arg() -> Arg<'static, 'static> {
+    Arg::with_name("glob")
+        .long("glob")
+        .takes_value(true)
+        .global(true)
+        .help("With --input-dir, only read files whose paths within it match this pattern, e.g. '*.10n'")
+        .long_help(
+            "With --input-dir, only reads the files whose paths, relative to the
+directory, match this glob pattern. `*` matches across `/`, so '*.10n'
+matches .10n files at any depth, and '2021-*/*.ion' matches .ion files
+under directories whose names start with 2021-. Quote the pattern so
+that the shell doesn't expand it. [default: every file]"
+        )
+}
+
+// Creates the global `exclude` argument, which skips files and directories under --input-dir.
+pub fn exclude_arg() -> Arg<'static, 'static> {
+    Arg::with_name("exclude")
+        .long("exclude")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .global(true)
+        .help("With --input-dir, skip files and directories matching this pattern, e.g. '*.tmp'")
+        .long_help(
+            "With --input-dir, skips the files and directories that match this glob
+pattern; nothing under a skipped directory is read. A pattern without a
+`/` is matched against names, so 'tmp' skips every file or directory
+named tmp. A pattern with one is matched against paths relative to the
+directory, like 'staging/*.ion'. May be repeated.
+
+Patterns are also read from `.ionignore` files, one per line, ignoring
+blank lines and lines starting with `#`. They apply to the directory
+holding the file and everything beneath it, relative to that directory.
+Files and directories whose names start with `.` are skipped unless
+--include-hidden is given."
+        )
+}
+
+// Creates the global `include-hidden` argument.
+pub fn include_hidden_arg() -> Arg<'static, 'static> {
+    Arg::with_name("include-hidden")
+        .long("include-hidden")
+        .global(true)
+        .help("With --input-dir, also read files and directories whose names start with '.'")
+}
+
+// Chooses which of the files under the --input-dir directories are read.
+pub struct DirectoryFilter<'a> {
+    pub glob: Option<&'a str>,
+    pub excludes: Vec<&'a str>,
+    pub include_hidden: bool,
+}
+
+// A pattern from --exclude or an .ionignore file, and the directory its paths are relative to.
+struct Exclusion {
+    base: PathBuf,
+    pattern: Pattern,
+    // Patterns without a `/` are matched against names at any depth.
+    by_name: bool,
+}
+
+impl Exclusion {
+    fn new(base: &Path, text: &str) -> Result<Exclusion> {
+        let text = text.trim_end_matches('/');
+        let pattern = Pattern::new(text.trim_start_matches('/'))?;
+        Ok(Exclusion { base: base.to_owned(), pattern, by_name: !text.contains('/') })
+    }
+
+    fn excludes(&self, path: &Path) -> bool {
+        if self.by_name {
+            return path.file_name().is_some_and(|name| self.pattern.matches(&name.to_string_lossy()));
+        }
+        path.strip_prefix(&self.base).is_ok_and(|relative| self.pattern.matches_path(relative))
+    }
+}
+
+// Finds the files that --input-dir reads. Only the first call has any effect.
+pub fn set_input_dirs(directories: &[&str], filter: &DirectoryFilter) -> Result<()> {
+    let pattern = match filter.glob {
+        Some(text) => Some(Pattern::new(text).with_context(|| format!("Invalid --glob '{}'", text))?),
+        None => None,
+    };
+    let mut files = Vec::new();
+    for directory in directories {
+        let top = Path::new(directory);
+        let mut exclusions = Vec::new();
+        for text in &filter.excludes {
+            exclusions.push(Exclusion::new(top, text).with_context(|| format!("Invalid --exclude '{}'", text))?);
+        }
+        let start = files.len();
+        let mut walk = Walk { top, pattern: pattern.as_ref(), include_hidden: filter.include_hidden, exclusions, files };
+        walk.directory(top)?;
+        files = walk.files;
+        if files.len() == start {
+            match filter.glob {
+                Some(glob) => bail!("No files in '{}' match '{}'.", directory, glob),
+                None => bail!("There are no files to read in '{}'.", directory),
+            }
+        }
+    }
+    let _ = DIRECTORY_INPUTS.set(files);
+    Ok(())
+}
+
+// A search for the files to read under one --input-dir directory.
+struct Walk<'a> {
+    top: &'a Path,
+    pattern: Option<&'a Pattern>,
+    include_hidden: bool,
+    // The exclusions that apply to the directory being visited.
+    exclusions: Vec<Exclusion>,
+    files: Vec<String>,
+}
+
+impl Walk<'_> {
+    // Adds the files in `directory` and its subdirectories whose paths relative to the top
+    // directory match the pattern, visiting the entries of each directory in order of their names.
+    fn directory(&mut self, directory: &Path) -> Result<()> {
+        // The patterns in this directory's .ionignore only apply until we leave it.
+        let inherited = self.exclusions.len();
+        let ignore_file = directory.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            let text = fs::read_to_string(&ignore_file)
+                .with_context(|| format!("Could not read '{}'", ignore_file.display()))?;
+            for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                self.exclusions.push(Exclusion::new(directory, line)
+                    .with_context(|| format!("Invalid pattern '{}' in '{}'", line, ignore_file.display()))?);
+            }
+        }
+
+        let mut entries: Vec<(PathBuf, bool)> = Vec::new();
+        let listing = fs::read_dir(directory)
+            .with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+        for entry in listing {
+            let entry = entry.with_context(|| format!("Could not read directory '{}'", directory.display()))?;
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            entries.push((entry.path(), is_dir));
+        }
+        entries.sort();
+        for (path, is_dir) in entries {
+            let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            if name == IGNORE_FILE_NAME
+                || (name.starts_with('.') && !self.include_hidden)
+                || self.exclusions.iter().any(|exclusion| exclusion.excludes(&path)) {
+                continue;
+            }
+            if is_dir {
+                self.directory(&path)?;
+                continue;
+            }
+            let relative = path.strip_prefix(self.top).unwrap_or(&path);
+            if self.pattern.is_none_or(|pattern| pattern.matches_path(relative)) {
+                match path.to_str() {
+                    Some(name) => self.files.push(name.to_owned()),
+                    None => bail!("The path '{}' isn't valid UTF-8.", path.display()),
+                }
+            }
+        }
+        self.exclusions.truncate(inherited);
+        Ok(())
+    }
+}
+
+// Sets the policy for duplicate field names. Only the first call has any effect.
+pub fn set_duplicate_fields(policy: DuplicateFields) {
+    let _ = DUPLICATE_FIELDS.set(policy);
+}
+
+pub fn duplicate_fields() -> DuplicateFields {
+    *DUPLICATE_FIELDS.get().unwrap_or(&DuplicateFields::KeepAll)
+}
+
+// Sets how every input is divided into records. Only the first call has any effect.
+pub fn set_framing(framing: Framing) {
+    let _ = FRAMING.set(framing);
+}
+
+fn framing() -> &'static Framing {
+    FRAMING.get().unwrap_or(&Framing::None)
+}
+
+// Enables strict checks of text Ion inputs. Only the first call has any effect.
+pub fn set_strict_checks(checks: Option<StrictChecks>) {
+    let _ = STRICT_CHECKS.set(checks);
+}
+
+fn strict_checks() -> Option<StrictChecks> {
+    STRICT_CHECKS.get().copied().flatten()
+}
+
+// Sets whether text inputs that declare another Ion version are read as Ion 1.0. Only the first
+// call has any effect.
+pub fn set_force_ion_1_0(force: bool) {
+    let _ = FORCE_ION_1_0.set(force);
+}
+
+fn force_ion_1_0() -> bool {
+    *FORCE_ION_1_0.get().unwrap_or(&false)
+}
+
+// Sets the format used to read every input. Only the first call has any effect.
+pub fn set_input_format(format: &str) {
+    let format = match format {
+        "ion" => InputFormat::Ion,
+        "json" => InputFormat::Json,
+        _ => InputFormat::Auto,
+    };
+    let _ = INPUT_FORMAT.set(format);
+}
+
+fn input_format() -> InputFormat {
+    *INPUT_FORMAT.get().unwrap_or(&InputFormat::Auto)
+}
+
+// Whether an input file might need to be decompressed, converted from JSON, checked, have its
+// version markers checked or rewritten, or have its duplicate fields handled before its values
+// can be written. If not, it can be handed to ion-c as-is. STDIN can't be examined without
+// consuming it, so check a copy from `copy_stdin` instead.
+pub fn needs_decoding(path: &str) -> bool {
+    if input_format() == InputFormat::Json
+        || *framing() != Framing::None
+        || strict_checks().is_some()
+        || duplicate_fields() != DuplicateFields::KeepAll
+        || force_ion_1_0() {
+        return true;
+    }
+    // Let the command report problems with opening the file.
+    let mmap = match File::open(path).ok().and_then(|file| map(path, &file).ok()) {
+        Some(Some(mmap)) => mmap,
+        _ => return false,
+    };
+    let bytes = &mmap[..];
+    if is_version_marker(bytes) {
+        return unsupported_version_marker(bytes).is_some();
+    }
+    bytes.starts_with(&GZIP_MAGIC)
+        || bytes.starts_with(&ZSTD_MAGIC)
+        || (input_format() == InputFormat::Auto && looks_like_json(bytes))
+        || std::str::from_utf8(bytes).is_ok_and(|text| !other_version_markers(text).is_empty())
+}
+
+// A binary Ion reader over a byte array, which is how every input is ultimately read.
+pub type IonReader<'input> = Reader<BinaryIonCursor<io::Cursor<&'input [u8]>>>;
+
+// Creates the `input` argument shared by commands that read Ion streams.
+pub fn input_arg() -> Arg<'static, 'static> {
+    // Every argument that isn't an option or an option's value is an input file name. It's
+    // positional, so it can't also be given as a flag like `-i`.
+    Arg::with_name("input")
+        .index(1)
+        .multiple(true)
+        .help("Input file, or - for STDIN; inputs are read in the order given [default: STDIN]")
+}
+
+// Returns the input file names that were specified on the command line, in order. If there were
+// none, returns STDIN's name. STDIN is only read when there are no inputs or `-` is one of them,
+// in which case it's read at that point in the order.
+pub fn input_names<'a>(matches: &'a ArgMatches<'static>) -> Vec<&'a str> {
+    listed_inputs(matches).unwrap_or_else(|| vec![STDIN_NAME])
+}
+
+// Returns the input file names that were specified on the command line, followed by the files
+// found by --input-dir, or None if there weren't any.
+pub fn listed_inputs<'a>(matches: &'a ArgMatches<'static>) -> Option<Vec<&'a str>> {
+    let mut names: Vec<&str> = matches.values_of("input").into_iter().flatten().collect();
+    names.extend(DIRECTORY_INPUTS.get().into_iter().flatten().map(String::as_str));
+    if names.is_empty() {
+        None
+    } else {
+        Some(names)
+    }
+}
+
+// An Ion stream read from a file or STDIN. ion-rs can only read binary Ion, so text inputs are
+// transcoded to binary Ion (using ion-c) when they are opened; either way, the stream's bytes
+// are mmap()ed and read as a byte array. Compressed inputs are decompressed and JSON inputs are
+// converted to text Ion before that happens.
+pub struct IonInput {
+    name: String,
+    // mmap() cannot map an empty file, so empty inputs have no mapping.
+    mmap: Option<Mmap>,
+    // Whether the input was text Ion that had to be transcoded to binary.
+    transcoded: bool,
+}
+
+impl IonInput {
+    // Opens the named file, or STDIN if the name is "-".
+    pub fn open(name: &str) -> Result<IonInput> {
+        profile::time(Phase::Read, || {
+            if name == STDIN_NAME {
+                return IonInput::from_stdin();
+            }
+            let file = File::open(name).with_context(|| format!("Could not open '{}'", name))?;
+            IonInput::from_source(name, name, &file)
+        })
+    }
+
+    // We need to be able to mmap() our input and (if it's text) hand its path to ion-c, so we
+    // read STDIN from a copy.
+    fn from_stdin() -> Result<IonInput> {
+        IonInput::from_stdin_copy(&copy_stdin()?)
+    }
+
+    // Opens a copy of STDIN made by `copy_stdin`, naming it as STDIN.
+    pub fn from_stdin_copy(temp_file: &NamedTempFile) -> Result<IonInput> {
+        IonInput::from_source(display_name(STDIN_NAME), path_str(temp_file)?, temp_file.as_file())
+    }
+
+    // Opens a file or copy of STDIN, dividing it into records first if requested.
+    fn from_source(name: &str, path: &str, file: &File) -> Result<IonInput> {
+        if *framing() == Framing::None {
+            return IonInput::from_file(name, path, file);
+        }
+        let mmap = map(name, file)?;
+        let bytes = mmap.as_deref().unwrap_or(&[]);
+        IonInput::from_frames(name, split_records(framing(), name, bytes)?)
+    }
+
+    // Opens each record as an input of its own and joins their contents into one binary stream.
+    // Each binary stream begins with an IVM, which resets the symbol table, so they can simply
+    // be concatenated.
+    fn from_frames(name: &str, frames: Vec<Cow<[u8]>>) -> Result<IonInput> {
+        let temp_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to read the records in '{}'", name))?;
+        let mut writer = BufWriter::new(temp_file);
+        for (index, frame) in frames.into_iter().enumerate() {
+            let written = if frame.starts_with(&IVM) {
+                check_binary_version(&format!("{} (record {})", name, index + 1), &frame)?;
+                writer.write_all(&frame)
+            } else {
+                let record = IonInput::from_bytes(&format!("{} (record {})", name, index + 1), &frame)?;
+                writer.write_all(record.bytes())
+            };
+            written.with_context(|| format!("Failed to copy the records in '{}' to a temp file.", name))?;
+        }
+        let temp_file = writer.into_inner()
+            .with_context(|| format!("Failed to write the records in '{}' to a temp file.", name))?;
+        let mmap = map(name, temp_file.as_file())?;
+        // Byte offsets within the joined records don't correspond to offsets in the input.
+        Ok(IonInput { name: name.to_owned(), mmap, transcoded: true })
+    }
+
+    // Creates an input from bytes that are already in memory, like the contents of a blob.
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<IonInput> {
+        let mut temp_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to store '{}'", name))?;
+        temp_file.write_all(bytes)
+            .with_context(|| format!("Failed to write '{}' to a temp file.", name))?;
+        let path = path_str(&temp_file)?;
+        IonInput::from_file(name, path, temp_file.as_file())
+    }
+
+    // Creates an input from a text or binary Ion document in memory that isn't one of the inputs,
+    // like one embedded in a value or decrypted from one. It's always read as Ion, whatever
+    // --input-format and --strict say, since those describe the inputs.
+    pub fn from_ion_bytes(name: &str, bytes: &[u8]) -> Result<IonInput> {
+        let mut temp_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to store '{}'", name))?;
+        temp_file.write_all(bytes)
+            .with_context(|| format!("Failed to write '{}' to a temp file.", name))?;
+        IonInput::from_ion_file(name, path_str(&temp_file)?, temp_file.as_file())
+    }
+
+    // Opens an Ion file that the CLI wrote itself, like a partition spilled to disk. As with
+    // `from_ion_bytes`, the options describing the inputs don't apply to it.
+    pub fn open_ion(path: &str) -> Result<IonInput> {
+        let file = File::open(path).with_context(|| format!("Could not open '{}'", path))?;
+        IonInput::from_ion_file(path, path, &file)
+    }
+
+    fn from_ion_file(name: &str, path: &str, file: &File) -> Result<IonInput> {
+        let mmap = map(name, file)?;
+        match &mmap {
+            Some(bytes) if !bytes.starts_with(&IVM) => IonInput::from_text(name, path),
+            _ => Ok(IonInput { name: name.to_owned(), mmap, transcoded: false }),
+        }
+    }
+
+    fn from_file(name: &str, path: &str, file: &File) -> Result<IonInput> {
+        let mmap = map(name, file)?;
+        let bytes = match &mmap {
+            Some(bytes) => &bytes[..],
+            // An empty stream is equally valid as text or binary.
+            None => return Ok(IonInput { name: name.to_owned(), mmap, transcoded: false }),
+        };
+        if is_version_marker(bytes) {
+            check_binary_version(name, bytes)?;
+            return Ok(IonInput { name: name.to_owned(), mmap, transcoded: false });
+        }
+        if bytes.starts_with(&GZIP_MAGIC) {
+            return IonInput::from_compressed(name, MultiGzDecoder::new(bytes));
+        }
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            let mut decoder = zstd::stream::read::Decoder::with_dictionary(bytes, dictionary().unwrap_or(&[]))
+                .with_context(|| format!("Failed to start decompressing '{}'", name))?;
+            // Allow the large windows that `beta compress --long` may use.
+            decoder.window_log_max(MAX_WINDOW_LOG)
+                .with_context(|| format!("Failed to start decompressing '{}'", name))?;
+            return IonInput::from_compressed(name, decoder);
+        }
+        let json_text = match input_format() {
+            InputFormat::Ion => None,
+            InputFormat::Json => Some(json_to_text_ion(name, bytes)
+                .with_context(|| format!("Input file '{}' is not valid JSON", name))?),
+            InputFormat::Auto if looks_like_json(bytes) => json_to_text_ion(name, bytes).ok(),
+            InputFormat::Auto => None,
+        };
+        if let Some(text_file) = json_text {
+            return IonInput::from_json(name, text_file);
+        }
+        if let Some(checks) = strict_checks() {
+            check_strictly(name, bytes, checks)?;
+        }
+        let forced_file = check_text_version(name, bytes)?;
+        let path = match &forced_file {
+            Some(forced_file) => path_str(forced_file)?,
+            None => path,
+        };
+        // Otherwise, this is presumably text Ion.
+        IonInput::from_text(name, path)
+    }
+
+    // Asks ion-c to transcode a text Ion file to binary Ion in a temporary file. The file is
+    // deleted when `binary_file` is dropped, but the mapping remains valid until it is dropped
+    // too.
+    fn from_text(name: &str, path: &str) -> Result<IonInput> {
+        let binary_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to transcode '{}'", name))?;
+        ion_c::transcode(&[path], "binary", Some(path_str(&binary_file)?))
+            .with_context(|| format!("Input file '{}' does not appear to be text Ion, binary Ion, or JSON.", name))?;
+        let mmap = map(name, binary_file.as_file())?;
+        match &mmap {
+            Some(bytes) if bytes.starts_with(&IVM) => {}
+            _ => bail!("Input file '{}' does not appear to be text Ion, binary Ion, or JSON.", name),
+        }
+        Ok(IonInput { name: name.to_owned(), mmap, transcoded: true })
+    }
+
+    // Decompresses a gzipped or zstd-compressed input to a temporary file and opens that instead.
+    // Whatever format it contains is detected as usual. Decompression runs on a thread of its
+    // own while this one writes what it has decompressed so far, and only a bounded number of
+    // chunks can be waiting, so memory use doesn't grow with the input.
+    fn from_compressed(name: &str, decoder: impl Read + Send) -> Result<IonInput> {
+        let temp_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to decompress '{}'", name))?;
+        let mut writer = BufWriter::new(temp_file);
+        let (sender, receiver) = mpsc::sync_channel(DECOMPRESSED_CHUNKS_AHEAD);
+        thread::scope(|scope| -> Result<()> {
+            let decompressor = scope.spawn(move || decompress(decoder, sender));
+            // If writing fails, dropping the receiver stops the decompressor.
+            for chunk in receiver {
+                writer.write_all(&chunk)
+                    .with_context(|| format!("Failed to write the decompressed contents of '{}'", name))?;
+            }
+            match decompressor.join() {
+                Ok(result) => result.with_context(|| format!("Failed to decompress '{}'", name)),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        })?;
+        let temp_file = writer.into_inner()
+            .with_context(|| format!("Failed to write the decompressed contents of '{}'", name))?;
+        IonInput::from_file(name, path_str(&temp_file)?, temp_file.as_file())
+    }
+
+    // Opens the text Ion file that `json_to_text_ion` wrote for an input.
+    fn from_json(name: &str, temp_file: NamedTempFile) -> Result<IonInput> {
+        let path = path_str(&temp_file)?;
+        // An empty document is equally valid as JSON or Ion, and is handled as binary Ion.
+        let is_empty = temp_file.as_file().metadata()
+            .with_context(|| format!("Failed to read the converted contents of '{}'", name))?
+            .len() == 0;
+        if is_empty {
+            return IonInput::from_file(name, path, temp_file.as_file());
+        }
+        let binary_file = NamedTempFile::new()
+            .with_context(|| format!("Failed to create a temporary file to transcode '{}'", name))?;
+        ion_c::transcode(&[path], "binary", Some(path_str(&binary_file)?))
+            .with_context(|| format!("Could not transcode the JSON in '{}' to binary Ion.", name))?;
+        let mmap = map(name, binary_file.as_file())?;
+        match &mmap {
+            Some(bytes) if bytes.starts_with(&IVM) => {}
+            _ => bail!("Could not transcode the JSON in '{}' to binary Ion.", name),
+        }
+        Ok(IonInput { name: name.to_owned(), mmap, transcoded: true })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Whether the input was text Ion. If so, byte offsets within `bytes()` don't correspond to
+    // offsets in the original input.
+    pub fn was_transcoded(&self) -> bool {
+        self.transcoded
+    }
+
+    // The input's contents as binary Ion.
+    pub fn bytes(&self) -> &[u8] {
+        match &self.mmap {
+            Some(mmap) => &mmap[..],
+            None => &[],
+        }
+    }
+
+    pub fn reader(&self) -> IonReader<'_> {
+        reader_for(self.bytes())
+    }
+
+    // Reads every top-level value in the input.
+    pub fn read_all(&self) -> Result<Vec<Value>> {
+        let mut reader = self.reader();
+        let mut values = Vec::new();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}'", self.name))?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+// Reads everything `decoder` decompresses and sends it in chunks, stopping early if the receiver
+// has gone away.
+fn decompress(mut decoder: impl Read, sender: SyncSender<Vec<u8>>) -> io::Result<()> {
+    loop {
+        let mut chunk = Vec::with_capacity(DECOMPRESSED_CHUNK_SIZE);
+        if (&mut decoder).take(DECOMPRESSED_CHUNK_SIZE as u64).read_to_end(&mut chunk)? == 0 {
+            return Ok(());
+        }
+        if sender.send(chunk).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+// Fails with a message naming the version and where it was declared if a binary input has a
+// version marker for a version of Ion other than 1.0.
+fn check_binary_version(name: &str, bytes: &[u8]) -> Result<()> {
+    if let Some((offset, major, minor)) = unsupported_version_marker(bytes) {
+        let hex: Vec<String> = bytes[offset..offset + IVM.len()].iter().map(|byte| format!("{:02x}", byte)).collect();
+        bail!(
+            "Input file '{}' has a binary version marker for Ion {}.{} ({}) at offset {}, but this CLI only reads Ion 1.0. Binary Ion {}.{} is encoded differently, so --force-version can't be used with it; convert it to Ion 1.0 with a tool that supports Ion {}.{} first.",
+            name, major, minor, hex.join(" "), offset, major, minor, major, minor
+        );
+    }
+    Ok(())
+}
+
+// Fails with a message naming the version and where it was declared if a text input has a
+// version marker like `$ion_1_1` for a version of Ion other than 1.0. With --force-version 1.0,
+// returns a copy of the input with those markers replaced by `$ion_1_0` instead.
+fn check_text_version(name: &str, bytes: &[u8]) -> Result<Option<NamedTempFile>> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        _ => return Ok(None),
+    };
+    let markers = other_version_markers(text);
+    let (offset, major, minor) = match markers.first() {
+        Some(marker) => *marker,
+        None => return Ok(None),
+    };
+    if !force_ion_1_0() {
+        let (line, column) = line_and_column(text, offset);
+        bail!(
+            "Input file '{}' declares Ion {}.{} with `$ion_{}_{}` at line {} column {} (offset {}), but this CLI only reads Ion 1.0. If the data doesn't use any Ion {}.{} features, `--force-version 1.0` reads it as Ion 1.0.",
+            name, major, minor, major, minor, line, column, offset, major, minor
+        );
+    }
+    let mut forced = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (offset, major, minor) in markers {
+        forced.push_str(&text[copied..offset]);
+        forced.push_str("$ion_1_0");
+        copied = offset + format!("$ion_{}_{}", major, minor).len();
+    }
+    forced.push_str(&text[copied..]);
+    let mut forced_file = NamedTempFile::new()
+        .with_context(|| format!("Failed to create a temporary file to read '{}' as Ion 1.0", name))?;
+    forced_file.write_all(forced.as_bytes())
+        .with_context(|| format!("Failed to write '{}' to a temp file.", name))?;
+    Ok(Some(forced_file))
+}
+
+// Returns the offset and version of each version marker in a text input, like `$ion_1_1`, for a
+// version of Ion other than 1.0.
+fn other_version_markers(text: &str) -> Vec<(usize, u32, u32)> {
+    if !text.contains("$ion_") {
+        return Vec::new();
+    }
+    // If the input can't be scanned, ion-c will explain why when it's transcoded.
+    version_markers(text)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, major, minor)| (*major, *minor) != (1, 0))
+        .collect()
+}
+
+// Fails with a report of every problem found if a text Ion input doesn't pass the strict checks.
+fn check_strictly(name: &str, bytes: &[u8], checks: StrictChecks) -> Result<()> {
+    let text = std::str::from_utf8(bytes)
+        .with_context(|| format!("Input file '{}' is not valid UTF-8 text", name))?;
+    let diagnostics = check(text, checks);
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    let report: Vec<String> = diagnostics.iter().map(|diagnostic| render(text, name, diagnostic)).collect();
+    bail!(
+        "{}\n\nInput file '{}' failed strict checks with {} problem(s).",
+        report.join("\n\n"),
+        name,
+        diagnostics.len()
+    )
+}
+
+// Text Ion is a superset of JSON, so only inputs that begin like a JSON container are candidates
+// for being read as JSON.
+fn looks_like_json(bytes: &[u8]) -> bool {
+    matches!(bytes.iter().find(|byte| !byte.is_ascii_whitespace()), Some(b'{') | Some(b'['))
+}
+
+// Converts a sequence of whitespace-separated JSON values, like a document or JSON Lines, to text
+// Ion in a temporary file. Each value is written as soon as it's read, so only one is held in
+// memory at a time.
+fn json_to_text_ion(name: &str, bytes: &[u8]) -> Result<NamedTempFile> {
+    let temp_file = NamedTempFile::new()
+        .with_context(|| format!("Failed to create a temporary file to convert '{}'", name))?;
+    let mut writer = BufWriter::new(temp_file);
+    let mut formatter = TextFormatter::new();
+    let mut text = String::new();
+    for json in Deserializer::from_slice(bytes).into_iter::<JsonValue>() {
+        text.clear();
+        formatter.format(&from_json(json?, Dialect::Plain, NumbersAs::Auto)?, &mut text)?;
+        text.push('\n');
+        writer.write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write the converted contents of '{}'", name))?;
+    }
+    writer.into_inner()
+        .with_context(|| format!("Failed to write the converted contents of '{}'", name))
+}
+
+// Creates a reader over a byte array containing binary Ion.
+pub fn reader_for(bytes: &[u8]) -> IonReader<'_> {
+    Reader::new(BinaryIonCursor::new(io::Cursor::new(bytes)))
+}
+
+// Reads a key (for signing or encryption) from a file containing its bytes in base64.
+pub fn read_key_file(file_name: &str) -> Result<Vec<u8>> {
+    let text = fs::read_to_string(file_name)
+        .with_context(|| format!("Could not read key file '{}'", file_name))?;
+    base64::decode(text.trim())
+        .with_context(|| format!("Key file '{}' does not contain base64 text", file_name))
+}
+
+fn map(name: &str, file: &File) -> Result<Option<Mmap>> {
+    let length = file.metadata()
+        .with_context(|| format!("Could not read the metadata of '{}'", name))?
+        .len();
+    if length == 0 {
+        return Ok(None);
+    }
+    // mmap involves operating system interactions that inherently place its usage outside of Rust's
+    // safety guarantees. If the file is unexpectedly truncated while it's being read, for example,
+    // problems could arise.
+    let mmap = unsafe {
+        MmapOptions::new().map(file)
+            .with_context(|| format!("Could not mmap '{}'", name))?
+    };
+    Ok(Some(mmap))
+}
+
+pub fn path_str(temp_file: &NamedTempFile) -> Result<&str> {
+    temp_file.path()
+        .to_str()
+        .with_context(|| format!("Temporary file path {:?} is not valid UTF-8", temp_file.path()))
+}
@@ -0,0 +1,56 @@
+use anyhow::{bail, Context, Result};
+use libc::c_char;
+use libc::c_int;
+use std::ffi::CString;
+use std::ptr;
+
+// ion_c_cli_main is a C function that lives in the ion-c CLI, to which ion-cli is
+// statically linked.
+extern "C" {
+    fn ion_c_cli_main(argc: c_int, argv: *const *const c_char) -> c_int;
+}
+
+// Runs the ion-c CLI with the given arguments, failing if it exits with a non-zero status. ion-c
+// reports the details of any failure on STDERR.
+pub fn run_ion_c_cli(args: &[&str]) -> Result<()> {
+    // Convert the length-prefixed Rust str arguments to null-terminated C strings
+    let argv_as_c_str = args
+        .iter()
+        .map(|arg| CString::new(*arg).with_context(|| format!("Argument {:?} contains a NUL byte", arg)))
+        .collect::<Result<Vec<CString>>>()?;
+
+    // Convert the C strings to char * pointers. Note: it's important that we collect()
+    // the values below into a separate vector from the values above; it guarantees that
+    // the memory being pointed to will still be valid by the time the ion_c_cli accesses it.
+    let mut argv_as_char_star = argv_as_c_str
+        .iter()
+        .map(|arg| arg.as_ptr())
+        .collect::<Vec<*const c_char>>();
+
+    // The number of arguments as a C int
+    let argc = argv_as_char_star.len() as c_int;
+
+    // Programs sometimes rely on argv being null-terminated, so we'll push a null onto the array.
+    argv_as_char_star.push(ptr::null());
+
+    let argv = argv_as_char_star.as_ptr();
+
+    let status = unsafe { ion_c_cli_main(argc, argv) };
+    if status != 0 {
+        bail!("ion-c failed with exit status {}.", status);
+    }
+    Ok(())
+}
+
+// Uses the ion-c CLI's `process` command to re-encode the given input files in the requested
+// format ("binary", "text", or "pretty"). If no output file is specified, ion-c will write
+// to STDOUT.
+pub fn transcode(input_files: &[&str], format: &str, output_file: Option<&str>) -> Result<()> {
+    let mut args: Vec<&str> = vec!["ion", "process", "-f", format];
+    if let Some(output_file) = output_file {
+        args.push("-o");
+        args.push(output_file);
+    }
+    args.extend_from_slice(input_files);
+    run_ion_c_cli(&args).with_context(|| format!("Could not transcode {} to {}", input_files.join(", "), format))
+}
@@ -0,0 +1,487 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::BigDecimal;
+use clap::Arg;
+use ion_rs::IonType;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::value::{Data, Symbol, Value};
+
+// Creates the `dialect` argument shared by the JSON conversion commands.
+pub fn dialect_arg() -> Arg<'static, 'static> {
+    Arg::with_name("dialect")
+        .long("dialect")
+        .short("d")
+        .takes_value(true)
+        .default_value("plain")
+        .possible_values(&["plain", "dynamodb"])
+        .help("The flavor of JSON to read or write")
+        .long_help(
+            "The flavor of JSON to read or write.
+  plain     ordinary JSON. Ion types without a JSON equivalent are written
+            as strings (timestamps, symbols) or base64 strings (lobs), and
+            annotations are dropped.
+  dynamodb  DynamoDB's attribute value format, as found in table exports,
+            e.g. {\"id\": {\"N\": \"17\"}, \"name\": {\"S\": \"Zoe\"}}. Top-level
+            structs are written as items; N is read as an Ion int or decimal,
+            and B as a blob."
+        )
+}
+
+// Creates the `big-numbers` argument used by `to json`.
+pub fn big_numbers_arg() -> Arg<'static, 'static> {
+    Arg::with_name("big-numbers")
+        .long("big-numbers")
+        .takes_value(true)
+        .default_value("number")
+        .possible_values(&["number", "string", "object"])
+        .help("How to write numbers that a double-precision float can't hold exactly")
+        .long_help(
+            "Controls how ints beyond ±2^53 and decimals that don't survive a round
+trip through a double-precision float are written. Many JSON readers,
+including JavaScript's, parse every number as a double and silently
+round these.
+  number  writes them as JSON numbers with all of their digits
+  string  writes them as strings, e.g. \"12345678901234567890\"
+  object  writes them as {\"$int\": \"...\"} or {\"$decimal\": \"...\"},
+          which `from json --restore-big-numbers` converts back
+Other numbers are always written as JSON numbers."
+        )
+}
+
+// How `to json` writes numbers that would lose precision as a double.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BigNumbers {
+    Number,
+    String,
+    Object,
+}
+
+impl BigNumbers {
+    // Parses the value of a `--big-numbers` argument.
+    pub fn from_arg(arg: &str) -> BigNumbers {
+        match arg {
+            "string" => BigNumbers::String,
+            "object" => BigNumbers::Object,
+            _ => BigNumbers::Number,
+        }
+    }
+}
+
+// The field names of the objects written by `--big-numbers object`.
+const BIG_INT_FIELD: &str = "$int";
+const BIG_DECIMAL_FIELD: &str = "$decimal";
+
+// The largest magnitude at which every integer can be represented by an f64.
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+// Replaces the ints and decimals within `value` that can't be represented exactly as an f64 with
+// strings or wrapper structs, so that they're written to JSON without a number's precision loss.
+pub fn protect_big_numbers(value: &mut Value, style: BigNumbers) {
+    if style == BigNumbers::Number {
+        return;
+    }
+    let (field, text) = match &mut value.data {
+        Data::Integer(i) if i.unsigned_abs() > MAX_SAFE_INTEGER => (BIG_INT_FIELD, i.to_string()),
+        Data::BigInteger(i) => (BIG_INT_FIELD, i.to_string()),
+        Data::Decimal(d) if !fits_in_f64(d) => (BIG_DECIMAL_FIELD, d.to_string()),
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                protect_big_numbers(child, style);
+            }
+            return;
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                protect_big_numbers(child, style);
+            }
+            return;
+        }
+        _ => return,
+    };
+    value.data = match style {
+        BigNumbers::Object => Data::Struct(vec![(Symbol::from(field), Value::new(Data::String(text)))]),
+        _ => Data::String(text),
+    };
+}
+
+// Reverses `--big-numbers object`, replacing each {$int: "..."} or {$decimal: "..."} struct
+// within `value` with the number it holds.
+pub fn restore_big_numbers(value: &mut Value) -> Result<()> {
+    let restored = match &value.data {
+        Data::Struct(fields) if fields.len() == 1 => match (&fields[0].0, &fields[0].1.data) {
+            (name, Data::String(text)) if name == BIG_INT_FIELD || name == BIG_DECIMAL_FIELD => {
+                let invalid = || format!("Invalid number in {{\"{}\": \"{}\"}}", name.text().unwrap_or_default(), text);
+                let data = if name == BIG_INT_FIELD {
+                    Data::integer(BigInt::from_str(text).with_context(invalid)?)
+                } else {
+                    Data::Decimal(BigDecimal::from_str(text).with_context(invalid)?)
+                };
+                Some(data)
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(data) = restored {
+        value.data = data;
+        return Ok(());
+    }
+    match &mut value.data {
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                restore_big_numbers(child)?;
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                restore_big_numbers(child)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Whether a decimal has the same value after a round trip through an f64.
+fn fits_in_f64(d: &BigDecimal) -> bool {
+    let text = d.to_string();
+    match f64::from_str(&text) {
+        Ok(f) if f.is_finite() => BigDecimal::from_str(&f.to_string()).is_ok_and(|round_trip| round_trip == *d),
+        _ => false,
+    }
+}
+
+// How JSON numbers other than integers are converted to Ion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumbersAs {
+    // The way Ion text reads them: numbers with exponents become floats, others become decimals.
+    Auto,
+    Decimal,
+    Float,
+}
+
+impl NumbersAs {
+    // Parses the value of a `--numbers-as` argument.
+    pub fn from_arg(arg: &str) -> NumbersAs {
+        match arg {
+            "decimal" => NumbersAs::Decimal,
+            "float" => NumbersAs::Float,
+            _ => NumbersAs::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Plain,
+    DynamoDb,
+}
+
+impl Dialect {
+    // Parses the value of a `--dialect` argument.
+    pub fn from_arg(arg: &str) -> Dialect {
+        match arg {
+            "dynamodb" => Dialect::DynamoDb,
+            _ => Dialect::Plain,
+        }
+    }
+}
+
+// Converts a top-level Ion value to JSON.
+pub fn to_json(value: &Value, dialect: Dialect) -> Result<JsonValue> {
+    match (dialect, &value.data) {
+        (Dialect::Plain, _) => to_plain_json(value),
+        // A DynamoDB item is a map of attribute names to attribute values, without the "M" wrapper.
+        (Dialect::DynamoDb, Data::Struct(fields)) => {
+            let mut item = Map::new();
+            for (name, field_value) in fields {
+                item.insert(symbol_text(name)?.to_owned(), to_attribute_value(field_value)?);
+            }
+            Ok(JsonValue::Object(item))
+        }
+        (Dialect::DynamoDb, _) => to_attribute_value(value),
+    }
+}
+
+// Converts a top-level JSON value to Ion.
+pub fn from_json(json: JsonValue, dialect: Dialect, numbers: NumbersAs) -> Result<Value> {
+    match (dialect, json) {
+        (Dialect::Plain, json) => from_plain_json(json, numbers),
+        (Dialect::DynamoDb, JsonValue::Object(mut item)) => {
+            // Table exports wrap each item in {"Item": {...}}.
+            if item.len() == 1 {
+                if let Some(JsonValue::Object(inner)) = item.remove("Item") {
+                    item = inner;
+                }
+            }
+            let fields = item
+                .into_iter()
+                .map(|(name, attribute)| {
+                    let value = from_attribute_value(attribute, numbers)
+                        .with_context(|| format!("Invalid attribute value for '{}'", name))?;
+                    Ok((Symbol::from(name), value))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::new(Data::Struct(fields)))
+        }
+        (Dialect::DynamoDb, other) => bail!("Expected a DynamoDB item (a JSON object), found {}", other),
+    }
+}
+
+fn to_plain_json(value: &Value) -> Result<JsonValue> {
+    let json = match &value.data {
+        Data::Null(_) => JsonValue::Null,
+        Data::Boolean(b) => JsonValue::Bool(*b),
+        Data::Integer(i) => JsonValue::Number(Number::from(*i)),
+        Data::BigInteger(i) => JsonValue::Number(
+            Number::from_str(&i.to_string()).with_context(|| format!("Cannot write integer {} as JSON", i))?,
+        ),
+        // JSON can't represent NaN or infinity.
+        Data::Float(f) => Number::from_f64(*f).map_or(JsonValue::Null, JsonValue::Number),
+        Data::Decimal(d) => JsonValue::Number(decimal_number(d)?),
+        Data::Timestamp(t) => JsonValue::String(t.instant().to_rfc3339()),
+        Data::Symbol(s) => JsonValue::String(symbol_text(s)?.to_owned()),
+        Data::String(s) => JsonValue::String(s.clone()),
+        Data::Clob(bytes) | Data::Blob(bytes) => JsonValue::String(base64::encode(bytes)),
+        Data::List(values) | Data::SExpression(values) => {
+            JsonValue::Array(values.iter().map(to_plain_json).collect::<Result<_>>()?)
+        }
+        Data::Struct(fields) => {
+            let mut object = Map::new();
+            for (name, field_value) in fields {
+                object.insert(symbol_text(name)?.to_owned(), to_plain_json(field_value)?);
+            }
+            JsonValue::Object(object)
+        }
+    };
+    Ok(json)
+}
+
+fn from_plain_json(json: JsonValue, numbers: NumbersAs) -> Result<Value> {
+    let data = match json {
+        JsonValue::Null => Data::Null(IonType::Null),
+        JsonValue::Bool(b) => Data::Boolean(b),
+        JsonValue::Number(n) => number_data(&n.to_string(), numbers)?,
+        JsonValue::String(s) => Data::String(s),
+        JsonValue::Array(values) => {
+            Data::List(values.into_iter().map(|value| from_plain_json(value, numbers)).collect::<Result<_>>()?)
+        }
+        JsonValue::Object(object) => Data::Struct(
+            object
+                .into_iter()
+                .map(|(name, value)| Ok((Symbol::from(name), from_plain_json(value, numbers)?)))
+                .collect::<Result<_>>()?,
+        ),
+    };
+    Ok(Value::new(data))
+}
+
+fn to_attribute_value(value: &Value) -> Result<JsonValue> {
+    let (type_name, json) = match &value.data {
+        Data::Null(_) => ("NULL", JsonValue::Bool(true)),
+        Data::Boolean(b) => ("BOOL", JsonValue::Bool(*b)),
+        Data::Integer(i) => ("N", JsonValue::String(i.to_string())),
+        Data::BigInteger(i) => ("N", JsonValue::String(i.to_string())),
+        Data::Float(f) if f.is_finite() => ("N", JsonValue::String(f.to_string())),
+        Data::Float(f) => bail!("DynamoDB numbers cannot represent {}", f),
+        Data::Decimal(d) => ("N", JsonValue::String(d.to_string())),
+        Data::Timestamp(t) => ("S", JsonValue::String(t.instant().to_rfc3339())),
+        Data::Symbol(s) => ("S", JsonValue::String(symbol_text(s)?.to_owned())),
+        Data::String(s) => ("S", JsonValue::String(s.clone())),
+        Data::Clob(bytes) | Data::Blob(bytes) => ("B", JsonValue::String(base64::encode(bytes))),
+        Data::List(values) | Data::SExpression(values) => {
+            ("L", JsonValue::Array(values.iter().map(to_attribute_value).collect::<Result<_>>()?))
+        }
+        Data::Struct(fields) => {
+            let mut map = Map::new();
+            for (name, field_value) in fields {
+                map.insert(symbol_text(name)?.to_owned(), to_attribute_value(field_value)?);
+            }
+            ("M", JsonValue::Object(map))
+        }
+    };
+    let mut attribute = Map::new();
+    attribute.insert(type_name.to_owned(), json);
+    Ok(JsonValue::Object(attribute))
+}
+
+fn from_attribute_value(json: JsonValue, numbers: NumbersAs) -> Result<Value> {
+    let mut attribute = match json {
+        JsonValue::Object(attribute) if attribute.len() == 1 => attribute,
+        other => bail!("Expected an attribute value like {{\"S\": \"...\"}}, found {}", other),
+    };
+    // The length was checked above.
+    let type_name = attribute.keys().next().unwrap().clone();
+    let json = attribute.remove(&type_name).unwrap();
+    let data = match (type_name.as_str(), json) {
+        ("NULL", _) => Data::Null(IonType::Null),
+        ("BOOL", JsonValue::Bool(b)) => Data::Boolean(b),
+        ("S", JsonValue::String(s)) => Data::String(s),
+        // DynamoDB numbers are decimal, so they're only read as floats if that's requested.
+        ("N", JsonValue::String(n)) if numbers == NumbersAs::Auto => number_data(&n, NumbersAs::Decimal)?,
+        ("N", JsonValue::String(n)) => number_data(&n, numbers)?,
+        ("B", JsonValue::String(b)) => Data::Blob(base64::decode(&b).with_context(|| "Invalid base64 in B")?),
+        ("L", JsonValue::Array(values)) => {
+            Data::List(values.into_iter().map(|value| from_attribute_value(value, numbers)).collect::<Result<_>>()?)
+        }
+        ("M", JsonValue::Object(map)) => Data::Struct(
+            map.into_iter()
+                .map(|(name, value)| Ok((Symbol::from(name), from_attribute_value(value, numbers)?)))
+                .collect::<Result<_>>()?,
+        ),
+        // Sets become lists of their members.
+        ("SS", JsonValue::Array(values)) | ("NS", JsonValue::Array(values)) | ("BS", JsonValue::Array(values)) => {
+            let member_type = &type_name[..1];
+            let members = values
+                .into_iter()
+                .map(|member| {
+                    let mut member_attribute = Map::new();
+                    member_attribute.insert(member_type.to_owned(), member);
+                    from_attribute_value(JsonValue::Object(member_attribute), numbers)
+                })
+                .collect::<Result<_>>()?;
+            Data::List(members)
+        }
+        (type_name, json) => bail!("Unsupported attribute value {{\"{}\": {}}}", type_name, json),
+    };
+    Ok(Value::new(data))
+}
+
+// Converts the text of a JSON or DynamoDB number to an Ion int, of any size, if it's an integer.
+// Other numbers become floats or decimals as `numbers` specifies; with `Auto`, numbers with an
+// exponent become floats, as in Ion text.
+fn number_data(text: &str, numbers: NumbersAs) -> Result<Data> {
+    let has_exponent = text.contains(&['e', 'E'][..]);
+    if !has_exponent && !text.contains('.') {
+        if let Ok(i) = BigInt::from_str(text) {
+            return Ok(Data::integer(i));
+        }
+    }
+    if numbers == NumbersAs::Float || (numbers == NumbersAs::Auto && has_exponent) {
+        let f = f64::from_str(text).with_context(|| format!("Invalid number '{}'", text))?;
+        return Ok(Data::Float(f));
+    }
+    let d = BigDecimal::from_str(text).with_context(|| format!("Invalid number '{}'", text))?;
+    Ok(Data::Decimal(d))
+}
+
+fn decimal_number(d: &BigDecimal) -> Result<Number> {
+    Number::from_str(&d.to_string()).with_context(|| format!("Cannot write decimal {} as JSON", d))
+}
+
+fn symbol_text(symbol: &Symbol) -> Result<&str> {
+    match (symbol.text(), symbol.sid()) {
+        (Some(text), _) => Ok(text),
+        (None, Some(sid)) => bail!("Could not resolve text for symbol ID ${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn decimal(text: &str) -> Data {
+        Data::Decimal(BigDecimal::from_str(text).unwrap())
+    }
+
+    #[test]
+    fn integers_of_any_size_are_ints() {
+        assert_eq!(number_data("42", NumbersAs::Auto).unwrap(), Data::Integer(42));
+        assert_eq!(number_data("-0", NumbersAs::Float).unwrap(), Data::Integer(0));
+        assert_eq!(number_data("-9223372036854775808", NumbersAs::Auto).unwrap(), Data::Integer(i64::MIN));
+        let big = BigInt::from_str("9223372036854775808").unwrap();
+        assert_eq!(number_data("9223372036854775808", NumbersAs::Decimal).unwrap(), Data::BigInteger(big));
+    }
+
+    #[test]
+    fn auto_reads_numbers_the_way_ion_text_does() {
+        assert_eq!(number_data("1.5", NumbersAs::Auto).unwrap(), decimal("1.5"));
+        assert_eq!(number_data("1e3", NumbersAs::Auto).unwrap(), Data::Float(1000.0));
+        assert_eq!(number_data("2.5E-1", NumbersAs::Auto).unwrap(), Data::Float(0.25));
+    }
+
+    #[test]
+    fn decimals_keep_every_digit() {
+        match number_data("12345678901234567890.000000000000000001", NumbersAs::Auto).unwrap() {
+            Data::Decimal(d) => assert_eq!(d.to_string(), "12345678901234567890.000000000000000001"),
+            other => panic!("expected a decimal, found {:?}", other),
+        }
+        assert_eq!(number_data("1e3", NumbersAs::Decimal).unwrap(), decimal("1000"));
+        assert_eq!(number_data("0.1", NumbersAs::Float).unwrap(), Data::Float(0.1));
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        for text in ["abc", "1.2.3", "1e", "--1", "0x10"].iter() {
+            assert!(number_data(text, NumbersAs::Auto).is_err(), "{}", text);
+            assert!(number_data(text, NumbersAs::Float).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn reads_dynamodb_items() {
+        let item = json!({"Item": {
+            "id": {"N": "1.50"},
+            "name": {"S": "widget"},
+            "tags": {"SS": ["a", "b"]},
+            "gone": {"NULL": true},
+            "data": {"B": "AQI="},
+        }});
+        let value = from_json(item, Dialect::DynamoDb, NumbersAs::Auto).unwrap();
+        // DynamoDB numbers are decimal, even with --numbers-as auto.
+        assert_eq!(value.get("id").unwrap().data, decimal("1.50"));
+        assert_eq!(value.get("name").unwrap().data, Data::String("widget".to_owned()));
+        let tags = vec![Value::new(Data::String("a".to_owned())), Value::new(Data::String("b".to_owned()))];
+        assert_eq!(value.get("tags").unwrap().data, Data::List(tags));
+        assert_eq!(value.get("gone").unwrap().data, Data::Null(IonType::Null));
+        assert_eq!(value.get("data").unwrap().data, Data::Blob(vec![1, 2]));
+    }
+
+    #[test]
+    fn dynamodb_numbers_are_floats_only_if_requested() {
+        let item = json!({"n": {"N": "1e3"}});
+        let value = from_json(item.clone(), Dialect::DynamoDb, NumbersAs::Auto).unwrap();
+        assert_eq!(value.get("n").unwrap().data, decimal("1000"));
+        let value = from_json(item, Dialect::DynamoDb, NumbersAs::Float).unwrap();
+        assert_eq!(value.get("n").unwrap().data, Data::Float(1000.0));
+    }
+
+    #[test]
+    fn rejects_malformed_dynamodb_items() {
+        let items = [
+            json!([1, 2]),
+            json!({"a": {"S": "x", "N": "1"}}),
+            json!({"a": {"X": "1"}}),
+            json!({"a": {"S": 1}}),
+            json!({"a": {"N": "one"}}),
+            json!({"a": {"B": "not base64!"}}),
+        ];
+        for item in items.iter() {
+            assert!(from_json(item.clone(), Dialect::DynamoDb, NumbersAs::Auto).is_err(), "{}", item);
+        }
+    }
+
+    #[test]
+    fn writes_dynamodb_items() {
+        let value = Value::new(Data::Struct(vec![
+            (Symbol::from("id"), Value::new(Data::Integer(7))),
+            (Symbol::from("price"), Value::new(decimal("1.50"))),
+            (Symbol::from("tags"), Value::new(Data::List(vec![Value::new(Data::Symbol(Symbol::from("a")))]))),
+        ]));
+        let expected = json!({"id": {"N": "7"}, "price": {"N": "1.50"}, "tags": {"L": [{"S": "a"}]}});
+        assert_eq!(to_json(&value, Dialect::DynamoDb).unwrap(), expected);
+    }
+
+    #[test]
+    fn dynamodb_cant_hold_non_finite_floats() {
+        for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+            assert!(to_json(&Value::new(Data::Float(*float)), Dialect::DynamoDb).is_err());
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::{bail, Result};
+
+use crate::path::Path;
+use crate::value::{TextFormatter, UnknownSymbols, Value};
+
+// Extracts the key that identifies a value, for commands that partition, group, or join values.
+// Keys are compared as compact text Ion, so equal keys match regardless of the input's format or
+// symbol tables. A symbol with unknown text is represented by its symbol ID, like `$14`, which
+// text Ion keeps distinct from any symbol with text: one whose text is `$14` is written `'$14'`,
+// and one whose text is `$unknown_14` is written as it is.
+pub struct KeyExtractor {
+    path: Path,
+    formatter: TextFormatter,
+}
+
+impl KeyExtractor {
+    pub fn new(path: Path) -> KeyExtractor {
+        let mut formatter = TextFormatter::new();
+        formatter.set_unknown_symbols(UnknownSymbols::PreserveSids);
+        KeyExtractor { path, formatter }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Returns the text of the value the path selects within `value`, or `None` if it selects
+    // nothing. A path that selects more than one value is an error.
+    pub fn key_of(&mut self, value: &Value) -> Result<Option<String>> {
+        match self.path.select(value).as_slice() {
+            [] => Ok(None),
+            [key] => {
+                let mut text = String::new();
+                self.formatter.format(key, &mut text)?;
+                Ok(Some(text))
+            }
+            _ => bail!("Key path {} selected more than one value", self.path),
+        }
+    }
+}
+
+// The 64-bit FNV-1a hash. Unlike the standard library's hashers, its output is guaranteed not
+// to change between releases, so it can be used to assign keys to files.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(PRIME))
+}
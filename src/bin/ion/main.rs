@@ -1,8 +1,44 @@
+mod binary;
+mod canonical;
 mod commands;
+mod compression;
+mod encryption;
+mod framing;
+mod input;
+mod ion_c;
+mod json;
+mod key;
+mod nested;
+mod output;
+mod path;
+mod profile;
+mod schema;
+mod signature;
+mod size;
+mod text;
+mod timestamp;
+mod transform;
+mod validation;
+mod value;
 
-use anyhow::Result;
-use crate::commands::{built_in_commands, runner_for_built_in_command};
-use clap::{crate_authors, crate_version, App, AppSettings};
+use anyhow::{bail, Result};
+use crate::commands::{built_in_commands, runner_for_built_in_command, ExitStatus};
+use crate::compression::{dict_arg, set_dictionary};
+use crate::framing::Framing;
+use crate::input::{
+    duplicate_fields_arg, embedded_arg, embedded_end_arg, exclude_arg, force_version_arg, frame_arg, glob_arg,
+    include_hidden_arg, input_dir_arg, input_format_arg, reject_duplicate_fields_arg, set_duplicate_fields,
+    set_force_ion_1_0, set_framing, set_input_dirs, set_input_format, set_stdin_name, set_strict_checks,
+    stdin_name_arg, strict_arg, DirectoryFilter,
+};
+use crate::output::{deterministic_arg, set_deterministic, set_verify_round_trip, verify_round_trip_arg};
+use crate::profile::{profile_arg, profiling, report, set_profile};
+use crate::size::{max_memory_arg, set_max_memory};
+use crate::text::StrictChecks;
+use crate::value::DuplicateFields;
+use clap::{crate_authors, crate_version, App, AppSettings, ArgMatches};
+use std::process;
+use std::time::Instant;
 
 const PROGRAM_NAME: &str = "ion";
 
@@ -11,19 +47,100 @@ fn main() -> Result<()> {
         .version(crate_version!())
         .author(crate_authors!())
         .setting(AppSettings::SubcommandRequiredElseHelp)
-        .setting(AppSettings::TrailingVarArg);
+        .setting(AppSettings::TrailingVarArg)
+        .arg(input_format_arg())
+        .arg(stdin_name_arg())
+        .arg(input_dir_arg())
+        .arg(glob_arg())
+        .arg(exclude_arg())
+        .arg(include_hidden_arg())
+        .arg(frame_arg())
+        .arg(embedded_arg())
+        .arg(embedded_end_arg())
+        .arg(strict_arg())
+        .arg(force_version_arg())
+        .arg(reject_duplicate_fields_arg())
+        .arg(duplicate_fields_arg())
+        .arg(verify_round_trip_arg())
+        .arg(deterministic_arg())
+        .arg(max_memory_arg())
+        .arg(dict_arg())
+        .arg(profile_arg());
 
     for command in built_in_commands() {
         app = app.subcommand(command);
     }
 
     let args = app.get_matches();
+    let levels = command_levels(&args);
+    // Global arguments can appear at any level of subcommand. The innermost occurrence wins.
+    if let Some(input_format) = levels.iter().rev().find_map(|level| level.value_of("input-format")) {
+        set_input_format(input_format);
+    }
+    if let Some(name) = levels.iter().rev().find_map(|level| level.value_of("stdin-name")) {
+        set_stdin_name(name);
+    }
+    let filter = DirectoryFilter {
+        glob: levels.iter().rev().find_map(|level| level.value_of("glob")),
+        excludes: levels.iter().rev().find_map(|level| level.values_of("exclude")).into_iter().flatten().collect(),
+        include_hidden: levels.iter().any(|level| level.is_present("include-hidden")),
+    };
+    match levels.iter().rev().find_map(|level| level.values_of("input-dir")) {
+        Some(directories) => set_input_dirs(&directories.collect::<Vec<_>>(), &filter)?,
+        None if filter.glob.is_some() || !filter.excludes.is_empty() => {
+            bail!("--glob and --exclude require --input-dir.")
+        }
+        None => {}
+    }
+    if let Some(start) = levels.iter().rev().find_map(|level| level.value_of("embedded")) {
+        if start.is_empty() {
+            bail!("--embedded needs the text that precedes each value.");
+        }
+        // An empty end marker is the same as none: each value runs to the end of its line.
+        let end = levels.iter().rev().find_map(|level| level.value_of("embedded-end")).filter(|end| !end.is_empty());
+        set_framing(Framing::Embedded { start: start.to_owned(), end: end.map(str::to_owned) });
+    } else if let Some(framing) = levels.iter().rev().find_map(|level| level.value_of("frame")) {
+        set_framing(Framing::from_arg(framing));
+    }
+    if let Some(policy) = levels.iter().rev().find_map(|level| level.value_of("duplicate-fields")) {
+        set_duplicate_fields(DuplicateFields::from_arg(policy));
+    }
+    let reject_duplicate_fields = levels.iter().any(|level| level.is_present("reject-duplicate-fields"));
+    if reject_duplicate_fields || levels.iter().any(|level| level.is_present("strict")) {
+        set_strict_checks(Some(StrictChecks { duplicate_fields: reject_duplicate_fields }));
+    }
+    set_force_ion_1_0(levels.iter().rev().find_map(|level| level.value_of("force-version")) == Some("1.0"));
+    set_verify_round_trip(levels.iter().any(|level| level.is_present("verify-round-trip")));
+    set_deterministic(levels.iter().any(|level| level.is_present("deterministic")));
+    if let Some(max_memory) = levels.iter().rev().find_map(|level| level.value_of("max-memory")) {
+        set_max_memory(max_memory)?;
+    }
+    if let Some(file_name) = levels.iter().rev().find_map(|level| level.value_of("dict")) {
+        set_dictionary(file_name)?;
+    }
+    set_profile(levels.iter().any(|level| level.is_present("profile")));
     let (command_name, command_args) = args.subcommand();
 
     if let Some(runner) = runner_for_built_in_command(command_name) {
+        let start = Instant::now();
         // If a runner is registered for the given command name, command_args is guaranteed to
         // be defined.
-        runner(command_name, command_args.unwrap())?;
+        let result = runner(command_name, command_args.unwrap());
+        if profiling() {
+            let names: Vec<&str> = levels.iter().filter_map(|level| level.subcommand_name()).collect();
+            report(&names.join(" "), start.elapsed());
+        }
+        if let Err(error) = result {
+            match error.downcast::<ExitStatus>() {
+                Ok(ExitStatus { code, error }) => {
+                    if let Some(error) = error {
+                        eprintln!("Error: {:?}", error);
+                    }
+                    process::exit(code);
+                }
+                Err(error) => return Err(error),
+            }
+        }
     } else {
         let message = format!(
             "The requested command ('{}') is not supported and clap did not generate an error message.",
@@ -33,3 +150,14 @@ fn main() -> Result<()> {
     }
     Ok(())
 }
+
+// Returns the matches for the program and each level of subcommand, outermost first.
+fn command_levels<'a>(args: &'a ArgMatches<'static>) -> Vec<&'a ArgMatches<'static>> {
+    let mut levels = vec![args];
+    let mut matches = args;
+    while let (_, Some(subcommand_matches)) = matches.subcommand() {
+        levels.push(subcommand_matches);
+        matches = subcommand_matches;
+    }
+    levels
+}
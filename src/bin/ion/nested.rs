@@ -0,0 +1,70 @@
+use std::mem;
+
+use anyhow::Result;
+
+use crate::input::{reader_for, IonInput, IVM};
+use crate::value::{Data, Symbol, Value};
+
+// Annotation added to the list of values decoded from a nested Ion document.
+pub const NESTED_DOCUMENT_ANNOTATION: &str = "nested_ion";
+
+// Replaces each blob within `value` that begins with an Ion version marker, and each string
+// that looks like a text Ion container, with a list of the values in that nested document,
+// annotated with `nested_ion`. Nested documents are themselves searched for nested documents.
+// Blobs and strings that cannot be decoded are left as they are.
+pub fn decode_nested(value: &mut Value) {
+    if let Some(nested_values) = nested_values(value) {
+        let mut annotations = mem::take(&mut value.annotations);
+        annotations.push(Symbol::from(NESTED_DOCUMENT_ANNOTATION));
+        *value = Value { annotations, data: Data::List(nested_values) };
+    }
+    match &mut value.data {
+        Data::List(values) | Data::SExpression(values) => {
+            for child in values.iter_mut() {
+                decode_nested(child);
+            }
+        }
+        Data::Struct(fields) => {
+            for (_, child) in fields.iter_mut() {
+                decode_nested(child);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn nested_values(value: &Value) -> Option<Vec<Value>> {
+    match &value.data {
+        Data::Blob(bytes) if bytes.starts_with(&IVM) => read_document(bytes).ok(),
+        Data::String(text) if looks_like_text_ion(text) => read_document(text.as_bytes()).ok(),
+        _ => None,
+    }
+}
+
+// Reads all of the values in a text or binary Ion document that's already in memory, like a
+// nested document, a decrypted value, or a state file. The document isn't one of the inputs, so
+// --input-format, --strict, and --duplicate-fields don't apply to it.
+pub fn read_document(bytes: &[u8]) -> Result<Vec<Value>> {
+    // Binary Ion can be read where it is.
+    let input = if bytes.starts_with(&IVM) {
+        None
+    } else {
+        Some(IonInput::from_ion_bytes("nested text Ion", bytes)?)
+    };
+    let mut reader = match &input {
+        Some(input) => input.reader(),
+        None => reader_for(bytes),
+    };
+    let mut values = Vec::new();
+    while reader.next()?.is_some() {
+        values.push(Value::read_all_fields(&mut reader)?);
+    }
+    Ok(values)
+}
+
+// Almost any string is technically valid text Ion (`hello` is a symbol), so we only attempt to
+// decode strings that begin with a version marker or a container.
+fn looks_like_text_ion(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with("$ion_1_0") || text.starts_with('{') || text.starts_with('[')
+}
@@ -0,0 +1,491 @@
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use clap::{Arg, ArgMatches};
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::canonical::canonical_bytes;
+use crate::framing::write_frames;
+use crate::input::{path_str, reader_for, IonInput};
+use crate::ion_c;
+use crate::profile::{self, profiling, Phase, TimedWriter};
+use crate::value::{FloatStyle, SymbolMode, TextFormatter, UnknownSymbols, Value};
+
+// Whether every output is re-read once it's finished and checked against the values written to
+// it. This applies to every command, so it's set once by `main`.
+static VERIFY_ROUND_TRIP: OnceLock<bool> = OnceLock::new();
+
+// Creates the global `verify-round-trip` argument, which can be given to any command.
+pub fn verify_round_trip_arg() -> Arg<'static, 'static> {
+    Arg::with_name("verify-round-trip")
+        .long("verify-round-trip")
+        .global(true)
+        .help("Re-read each Ion output and fail if it doesn't hold the values that were written")
+        .long_help(
+            "After an Ion output is finished, reads it back and compares each value
+with the value that was written, ignoring the order of struct fields and
+any differences in encoding. If a value differs, or the number of values
+does, the command fails naming the first difference. Output for STDOUT
+is held back until it has been verified, and an output file is
+written as <file>.partial and only renamed once it has been verified,
+so a failed check never leaves output behind. When `dump` hands its
+inputs to ion-c as they are, its output is compared with the values in
+the inputs instead.
+
+Values are compared by their canonical form, so symbols whose text is
+unknown can't be verified. Outputs that aren't Ion streams, like JSON or
+CSV, are not checked."
+        )
+}
+
+// Enables round-trip verification of every output. Only the first call has any effect.
+pub fn set_verify_round_trip(verify: bool) {
+    let _ = VERIFY_ROUND_TRIP.set(verify);
+}
+
+pub fn verify_round_trip() -> bool {
+    *VERIFY_ROUND_TRIP.get().unwrap_or(&false)
+}
+
+// Whether commands must produce the same output every time they're given the same input and
+// arguments. This applies to every command, so it's set once by `main`.
+static DETERMINISTIC: OnceLock<bool> = OnceLock::new();
+
+// Creates the global `deterministic` argument, which can be given to any command.
+pub fn deterministic_arg() -> Arg<'static, 'static> {
+    Arg::with_name("deterministic")
+        .long("deterministic")
+        .global(true)
+        .help("Fail rather than write output that could differ between runs with the same input")
+        .long_help(
+            "Guarantees that the same input and arguments produce byte-for-byte the
+same output on every run and platform, or fails before writing anything.
+
+Output is always written the same way: values are written in the order
+they're produced, struct fields keep their order, and binary Ion is
+encoded by ion-c from text with no shared symbol table imports and a
+local symbol table listing symbols in the order they're first used.
+Output never depends on hash table ordering, the host, or the locale.
+
+Only a few commands add something of their own that varies:
+  beta sample          requires --seed
+  beta wrap            with --container struct, timestamps each batch
+                       with SOURCE_DATE_EPOCH, which must be set
+  beta encrypt-fields  fails, since each value needs a random nonce"
+        )
+}
+
+// Requires deterministic output. Only the first call has any effect.
+pub fn set_deterministic(deterministic: bool) {
+    let _ = DETERMINISTIC.set(deterministic);
+}
+
+pub fn deterministic() -> bool {
+    *DETERMINISTIC.get().unwrap_or(&false)
+}
+
+// Creates the `format` argument shared by commands that write Ion streams.
+pub fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .long("format")
+        .short("f")
+        .takes_value(true)
+        .default_value("pretty")
+        .possible_values(&["binary", "text", "pretty"])
+        .help("Output format")
+}
+
+// Creates the `output` argument shared by commands that write Ion streams.
+pub fn output_arg() -> Arg<'static, 'static> {
+    Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .takes_value(true)
+        .help("Output file [default: STDOUT]")
+}
+
+// Creates the `unknown-symbols` argument shared by commands that write Ion streams.
+pub fn unknown_symbols_arg() -> Arg<'static, 'static> {
+    Arg::with_name("unknown-symbols")
+        .long("unknown-symbols")
+        .takes_value(true)
+        .default_value("error")
+        .possible_values(&["error", "preserve-sids", "placeholder"])
+        .help("How to write symbols whose text is unknown")
+        .long_help(
+            "Controls what happens when the input refers to a symbol ID whose text
+is unknown, as when a shared symbol table is unavailable.
+  error          stops with an error naming the symbol ID
+  preserve-sids  writes the symbol ID (e.g. `$14`); requires `--format text`
+  placeholder    writes a symbol with the text `$unknown_14`"
+        )
+}
+
+// Writes are gathered into buffers this large before they're passed to the file or STDOUT.
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+// Opens `output_file` for writing, or STDOUT if no file was given. Every command writes its output
+// through one of these. STDOUT is locked once here, rather than by each write, and holds the lock
+// until the writer is dropped.
+pub fn output_writer(output_file: Option<&str>) -> Result<BufWriter<Box<dyn Write>>> {
+    let sink: Box<dyn Write> = match output_file {
+        Some(file_name) => Box::new(File::create(file_name)
+            .with_context(|| format!("Could not open '{}'", file_name))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    Ok(buffered(sink))
+}
+
+fn buffered(sink: Box<dyn Write>) -> BufWriter<Box<dyn Write>> {
+    let sink: Box<dyn Write> = if profiling() { Box::new(TimedWriter::new(sink)) } else { sink };
+    BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, sink)
+}
+
+// Transcodes finished text to the requested format, which counts as encoding when profiling.
+fn transcode(input_paths: &[&str], format: &str, output_file: Option<&str>) -> Result<()> {
+    profile::time(Phase::Encode, || ion_c::transcode(input_paths, format, output_file))
+}
+
+// A destination for a stream of Ion values. ion-rs can only write text Ion, so values are always
+// written as text first. If another format was requested, the text is written to a temporary
+// file that ion-c transcodes to the requested format when the output is finished.
+pub struct IonOutput {
+    format: String,
+    output_file: Option<String>,
+    writer: BufWriter<Box<dyn Write>>,
+    // Only used when the requested format is not "text", or for verified text for STDOUT.
+    temp_file: Option<NamedTempFile>,
+    // Where verified text for an output file is written until it has been verified.
+    staging: Option<StagingFile>,
+    formatter: TextFormatter,
+    // Reusable buffer for formatting each value
+    text_buffer: String,
+    // A digest of each value written, if the output will be verified when it's finished.
+    digests: Option<Vec<Vec<u8>>>,
+    // Whether each top-level value is written as a length-prefixed record.
+    framed: bool,
+    // Whether each record is compressed on its own.
+    compress_records: bool,
+}
+
+impl IonOutput {
+    // Creates an output using the `format` and `output` arguments from the command line.
+    pub fn from_matches(matches: &ArgMatches<'static>) -> Result<IonOutput> {
+        // --format has a default value, so we can unwrap this safely.
+        let format = matches.value_of("format").unwrap();
+        IonOutput::new(format, matches.value_of("output"))
+    }
+
+    // Creates an output that will write the given format to `output_file`, or to STDOUT if no
+    // file was specified.
+    pub fn new(format: &str, output_file: Option<&str>) -> Result<IonOutput> {
+        let verify = verify_round_trip();
+        let mut temp_file = None;
+        let mut staging = None;
+        // STDOUT can't be read back, so verified text for STDOUT is held in a temporary file too.
+        let writer = if format != "text" || (verify && output_file.is_none()) {
+            let file = NamedTempFile::new()
+                .with_context(|| "Failed to create a temporary file for the output.")?;
+            let sink = file.reopen()
+                .with_context(|| "Failed to open the temporary output file.")?;
+            temp_file = Some(file);
+            buffered(Box::new(sink))
+        } else if verify {
+            let file = StagingFile::for_output(output_file)?;
+            let sink = file.open()?;
+            staging = Some(file);
+            buffered(Box::new(sink))
+        } else {
+            output_writer(output_file)?
+        };
+        Ok(IonOutput {
+            format: format.to_owned(),
+            output_file: output_file.map(|name| name.to_owned()),
+            writer,
+            temp_file,
+            staging,
+            formatter: TextFormatter::new(),
+            text_buffer: String::new(),
+            digests: if verify { Some(Vec::new()) } else { None },
+            framed: false,
+            compress_records: false,
+        })
+    }
+
+    // Controls how symbols are written. Only text output can represent symbol IDs and comments;
+    // the other formats are transcoded from text and would lose them.
+    pub fn set_symbol_mode(&mut self, symbol_mode: SymbolMode) {
+        self.formatter.set_symbol_mode(symbol_mode);
+    }
+
+    pub fn set_unknown_symbols(&mut self, unknown_symbols: UnknownSymbols) {
+        self.formatter.set_unknown_symbols(unknown_symbols);
+    }
+
+    // Like symbol modes, float styles and digit comments only survive in text output.
+    pub fn set_float_style(&mut self, float_style: FloatStyle) {
+        self.formatter.set_float_style(float_style);
+    }
+
+    pub fn set_digit_separator(&mut self, digit_separator: Option<char>) {
+        self.formatter.set_digit_separator(digit_separator);
+    }
+
+    // Writes each top-level value as a length-prefixed record holding a binary Ion stream of its
+    // own. Only binary output can be framed.
+    pub fn set_framed(&mut self, framed: bool) {
+        self.framed = framed;
+    }
+
+    // Compresses each framed record with zstd, using the --dict dictionary if there is one.
+    pub fn set_compress_records(&mut self, compress_records: bool) {
+        self.compress_records = compress_records;
+    }
+
+    pub fn write_value(&mut self, value: &Value) -> Result<()> {
+        self.record(value)?;
+        self.text_buffer.clear();
+        profile::time(Phase::Encode, || self.formatter.format(value, &mut self.text_buffer))?;
+        writeln!(self.writer, "{}", self.text_buffer)
+            .with_context(|| "Failed to write to the output.")?;
+        Ok(())
+    }
+
+    // Writes a value with comments before it and, optionally, after it on the same line. Like
+    // symbol IDs, comments only survive in text output.
+    pub fn write_value_with_comments(&mut self, value: &Value, leading: &[String], trailing: Option<&str>) -> Result<()> {
+        for comment in leading {
+            self.write_line(comment)?;
+        }
+        self.record(value)?;
+        self.text_buffer.clear();
+        profile::time(Phase::Encode, || self.formatter.format(value, &mut self.text_buffer))?;
+        if let Some(comment) = trailing {
+            self.text_buffer.push(' ');
+            self.text_buffer.push_str(comment);
+        }
+        writeln!(self.writer, "{}", self.text_buffer)
+            .with_context(|| "Failed to write to the output.")?;
+        Ok(())
+    }
+
+    // Writes a line of text, like a comment or a separator, between values.
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        writeln!(self.writer, "{}", line).with_context(|| "Failed to write to the output.")?;
+        Ok(())
+    }
+
+    // Keeps a digest of a value's canonical form if the output will be verified.
+    fn record(&mut self, value: &Value) -> Result<()> {
+        if let Some(digests) = &mut self.digests {
+            digests.push(digest(value)?);
+        }
+        Ok(())
+    }
+
+    // Flushes the output, transcoding it to the requested format if necessary.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush().with_context(|| "Failed to write to the output.")?;
+        let digests = self.digests.take();
+        if digests.is_none() && !self.framed {
+            if let Some(temp_file) = &self.temp_file {
+                transcode(&[path_str(temp_file)?], &self.format, self.output_file.as_deref())?;
+            }
+            return Ok(());
+        }
+
+        // Verified output is checked, and framed output divided into records, once it's finished
+        // in a staging file. Only then is it moved to its destination.
+        let output_file = self.output_file.as_deref();
+        let finished = match (self.staging.take(), self.temp_file.take()) {
+            // Verified text for an output file was written straight to a staging file.
+            (Some(staging), _) => staging,
+            // As was verified text for STDOUT, to a temporary file.
+            (None, Some(temp_file)) if self.format == "text" => StagingFile::Temporary(temp_file),
+            (None, Some(temp_file)) => {
+                // Framed output is staged again as it's divided into records.
+                let staging = StagingFile::for_output(if self.framed { None } else { output_file })?;
+                transcode(&[path_str(&temp_file)?], &self.format, Some(staging.path()?))?;
+                staging
+            }
+            // Output that isn't text is always written to a temporary file first.
+            (None, None) => unreachable!(),
+        };
+        let display_name = output_file.unwrap_or("STDOUT");
+        if let Some(digests) = &digests {
+            check_round_trip(finished.path()?, display_name, digests)?;
+        }
+        if !self.framed {
+            return finished.finish(output_file);
+        }
+
+        let binary = fs::read(finished.path()?)
+            .with_context(|| format!("Could not reopen the finished output for '{}'", display_name))?;
+        let framed = StagingFile::for_output(output_file)?;
+        let mut writer = buffered(Box::new(framed.open()?));
+        write_frames(&binary, &mut writer, self.compress_records)?;
+        writer.flush().with_context(|| "Failed to write to the output.")?;
+        drop(writer);
+        framed.finish(output_file)
+    }
+}
+
+// Runs the ion-c CLI to write `output_file`, or STDOUT, from input files that it can read as they
+// are, as `dump` does. `args` are ion-c's arguments other than the output and inputs. With
+// --verify-round-trip, ion-c writes to a staging file that is checked against the values in the
+// inputs before it's moved to its destination.
+pub fn run_ion_c(args: &[&str], output_file: Option<&str>, input_paths: &[&str]) -> Result<()> {
+    let mut args = args.to_vec();
+    if !verify_round_trip() {
+        if let Some(output_file) = output_file {
+            args.push("-o");
+            args.push(output_file);
+        }
+        args.extend_from_slice(input_paths);
+        return ion_c::run_ion_c_cli(&args);
+    }
+
+    let staging = StagingFile::for_output(output_file)?;
+    args.push("-o");
+    args.push(staging.path()?);
+    args.extend_from_slice(input_paths);
+    ion_c::run_ion_c_cli(&args)?;
+
+    let mut digests = Vec::new();
+    for path in input_paths {
+        let input = IonInput::open(path)?;
+        let mut reader = input.reader();
+        while reader.next()?.is_some() {
+            let value = Value::read(&mut reader)
+                .with_context(|| format!("Could not read a value from '{}' to verify the output", input.name()))?;
+            digests.push(digest(&value)?);
+        }
+    }
+    check_round_trip(staging.path()?, output_file.unwrap_or("STDOUT"), &digests)?;
+    staging.finish(output_file)
+}
+
+// A file that an output is finished in before it's moved to its destination, so that the
+// destination never holds output that failed verification.
+enum StagingFile {
+    // A file beside the output file, so that it can be renamed to it. It's removed if it's
+    // dropped before then.
+    Beside(PathBuf),
+    // A temporary file, for STDOUT, which it's copied to.
+    Temporary(NamedTempFile),
+}
+
+impl StagingFile {
+    // Creates an empty staging file for `output_file`, or for STDOUT if there isn't one. It's
+    // created like the output file itself would be, so it has the same permissions.
+    fn for_output(output_file: Option<&str>) -> Result<StagingFile> {
+        match output_file {
+            Some(output_file) => {
+                let mut path = OsString::from(output_file);
+                path.push(".partial");
+                let path = PathBuf::from(path);
+                File::create(&path).with_context(|| format!("Could not create '{}'", path.display()))?;
+                Ok(StagingFile::Beside(path))
+            }
+            None => Ok(StagingFile::Temporary(
+                NamedTempFile::new().with_context(|| "Failed to create a temporary file for the output.")?,
+            )),
+        }
+    }
+
+    fn path(&self) -> Result<&str> {
+        match self {
+            StagingFile::Beside(path) => path
+                .to_str()
+                .with_context(|| format!("Output path {:?} is not valid UTF-8", path)),
+            StagingFile::Temporary(temp_file) => path_str(temp_file),
+        }
+    }
+
+    // Opens the staging file to be written from the start.
+    fn open(&self) -> Result<File> {
+        File::create(self.path()?).with_context(|| "Failed to open the staging file for the output.")
+    }
+
+    // Moves the finished output to `output_file`, or copies it to STDOUT.
+    fn finish(self, output_file: Option<&str>) -> Result<()> {
+        match (&self, output_file) {
+            (StagingFile::Beside(path), Some(output_file)) => fs::rename(path, output_file)
+                .with_context(|| format!("Could not move '{}' to '{}'", path.display(), output_file)),
+            _ => {
+                let mut finished = File::open(self.path()?)
+                    .with_context(|| "Could not reopen the finished output.")?;
+                let mut writer = output_writer(output_file)?;
+                io::copy(&mut finished, &mut writer).with_context(|| "Failed to write to the output.")?;
+                writer.flush().with_context(|| "Failed to write to the output.")
+            }
+        }
+    }
+}
+
+impl Drop for StagingFile {
+    fn drop(&mut self) {
+        // Once it's been renamed, there's nothing left to remove.
+        if let StagingFile::Beside(path) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// A digest of a value's canonical form, by which values written to an output are compared with
+// the values read back from it.
+fn digest(value: &Value) -> Result<Vec<u8>> {
+    let bytes = canonical_bytes(std::slice::from_ref(value))
+        .with_context(|| "Could not prepare a value for --verify-round-trip")?;
+    Ok(Sha256::digest(&bytes).to_vec())
+}
+
+// Reads a finished output back and fails if it doesn't hold values with the given digests, in
+// order. The output is transcoded to binary first so that it's read the same way whatever the
+// global input settings are.
+fn check_round_trip(path: &str, display_name: &str, digests: &[Vec<u8>]) -> Result<()> {
+    let binary_file = NamedTempFile::new()
+        .with_context(|| "Failed to create a temporary file to verify the output.")?;
+    ion_c::transcode(&[path], "binary", Some(path_str(&binary_file)?))
+        .with_context(|| format!("Could not read back '{}' to verify it", display_name))?;
+    let bytes = fs::read(binary_file.path())
+        .with_context(|| format!("Could not read back '{}' to verify it", display_name))?;
+    let mut reader = reader_for(&bytes);
+    let mut index = 0;
+    while reader.next()?.is_some() {
+        let value = Value::read(&mut reader)
+            .with_context(|| format!("Could not read back value {} of '{}' to verify it", index + 1, display_name))?;
+        let expected = match digests.get(index) {
+            Some(expected) => expected,
+            None => bail!(
+                "Round-trip verification of '{}' failed: {} values were written, but more were read back.",
+                display_name,
+                digests.len()
+            ),
+        };
+        if &digest(&value)? != expected {
+            bail!(
+                "Round-trip verification of '{}' failed: value {} read back differs from the value written.",
+                display_name,
+                index + 1
+            );
+        }
+        index += 1;
+    }
+    if index < digests.len() {
+        bail!(
+            "Round-trip verification of '{}' failed: {} values were written, but only {} were read back.",
+            display_name,
+            digests.len(),
+            index
+        );
+    }
+    Ok(())
+}
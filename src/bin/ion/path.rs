@@ -0,0 +1,323 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use ion_rs::IonType;
+
+use crate::input::IonReader;
+use crate::value::{Data, Symbol, Value};
+
+// A path identifies zero or more values nested within a top-level value. Paths are written as
+// s-expressions of steps, e.g. `(config db host)` or `(records 0 id)`:
+//   * A symbol (optionally 'quoted' or "double-quoted") selects struct fields with that name.
+//   * An unsigned integer selects the list or s-expression element at that index.
+//   * `*` selects every child of a container.
+// The parentheses may be omitted for convenience. The empty path `()` selects the top-level
+// value itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Field(String),
+    Index(usize),
+    Wildcard,
+}
+
+impl Path {
+    // Returns the path of the containers holding the values this path selects, along with the
+    // final step. Returns `None` for the empty path.
+    pub fn split_last(&self) -> Option<(Path, &Step)> {
+        let (last, parent) = self.steps.split_last()?;
+        Some((Path { steps: parent.to_vec() }, last))
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // Returns every value within `value` that the path selects.
+    pub fn select<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        let mut selected = vec![value];
+        for step in &self.steps {
+            selected = selected
+                .into_iter()
+                .flat_map(|value| step.select(value))
+                .collect();
+        }
+        selected
+    }
+
+    // Calls `f` on every value within `value` that the path selects, allowing it to be modified.
+    pub fn for_each_mut<F>(&self, value: &mut Value, f: &mut F) -> Result<()>
+        where F: FnMut(&mut Value) -> Result<()> {
+        for_each_mut(&self.steps, value, f)
+    }
+}
+
+fn for_each_mut<F>(steps: &[Step], value: &mut Value, f: &mut F) -> Result<()>
+    where F: FnMut(&mut Value) -> Result<()> {
+    let (step, remaining_steps) = match steps.split_first() {
+        Some(split) => split,
+        None => return f(value),
+    };
+    match &mut value.data {
+        Data::List(values) | Data::SExpression(values) => {
+            for (index, child) in values.iter_mut().enumerate() {
+                if step.matches_index(index) {
+                    for_each_mut(remaining_steps, child, f)?;
+                }
+            }
+        }
+        Data::Struct(fields) => {
+            for (name, child) in fields.iter_mut() {
+                if step.matches_field(name) {
+                    for_each_mut(remaining_steps, child, f)?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Reads the value on which the reader is currently parked, like `Value::read`, but only as much of
+// it as `paths` can select from. Struct fields that no path leads into are left out, and list and
+// s-expression elements that no path leads into are replaced with `null` so the others keep their
+// indexes. Each path selects the same values from the result as it would from the whole value.
+// In binary Ion, the reader skips the parts that are left out using their length prefixes
+// without decoding them, so commands that only look at a few paths don't pay for the rest.
+pub fn read_projection(reader: &mut IonReader, paths: &[&Path]) -> Result<Value> {
+    let steps: Vec<&[Step]> = paths.iter().map(|path| path.steps.as_slice()).collect();
+    read_projected(reader, &steps)
+}
+
+fn read_projected(reader: &mut IonReader, paths: &[&[Step]]) -> Result<Value> {
+    // A path that ends here selects the whole value.
+    if paths.iter().any(|steps| steps.is_empty()) {
+        return Value::read(reader);
+    }
+    let ion_type = reader.ion_type().expect("read_projection() called when reader was exhausted");
+    let is_container = matches!(ion_type, IonType::List | IonType::SExpression | IonType::Struct);
+    // Paths can't select anything from within scalars or null containers.
+    if !is_container || reader.is_null() {
+        return Ok(Value::new(Data::Null(IonType::Null)));
+    }
+    let annotations = reader
+        .annotation_ids()
+        .iter()
+        .map(|sid| Symbol::from_sid(reader, *sid))
+        .collect();
+    let mut children = Vec::new();
+    let mut fields = Vec::new();
+    reader.step_in()?;
+    let mut index = 0;
+    while reader.next()?.is_some() {
+        if ion_type == IonType::Struct {
+            let field_id = reader.field_id().expect("Struct field has no field ID.");
+            let name = Symbol::from_sid(reader, field_id);
+            let remaining: Vec<&[Step]> = paths
+                .iter()
+                .filter(|steps| steps[0].matches_field(&name))
+                .map(|steps| &steps[1..])
+                .collect();
+            if !remaining.is_empty() {
+                fields.push((name, read_projected(reader, &remaining)?));
+            }
+        } else {
+            let remaining: Vec<&[Step]> = paths
+                .iter()
+                .filter(|steps| steps[0].matches_index(index))
+                .map(|steps| &steps[1..])
+                .collect();
+            children.push(if remaining.is_empty() {
+                Value::new(Data::Null(IonType::Null))
+            } else {
+                read_projected(reader, &remaining)?
+            });
+            index += 1;
+        }
+    }
+    reader.step_out()?;
+    let data = match ion_type {
+        IonType::List => Data::List(children),
+        IonType::SExpression => Data::SExpression(children),
+        _ => Data::Struct(fields),
+    };
+    Ok(Value { annotations, data })
+}
+
+impl Step {
+    // Returns the children of `value` that this step selects.
+    pub fn select<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        match &value.data {
+            Data::List(values) | Data::SExpression(values) => values
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.matches_index(*index))
+                .map(|(_, child)| child)
+                .collect(),
+            Data::Struct(fields) => fields
+                .iter()
+                .filter(|(name, _)| self.matches_field(name))
+                .map(|(_, child)| child)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn matches_index(&self, index: usize) -> bool {
+        match self {
+            Step::Index(i) => *i == index,
+            Step::Wildcard => true,
+            Step::Field(_) => false,
+        }
+    }
+
+    pub fn matches_field(&self, field_name: &Symbol) -> bool {
+        match self {
+            Step::Field(name) => field_name == name.as_str(),
+            Step::Wildcard => true,
+            Step::Index(_) => false,
+        }
+    }
+}
+
+impl From<Vec<Step>> for Path {
+    fn from(steps: Vec<Step>) -> Path {
+        Path { steps }
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Path> {
+        let mut text = text.trim();
+        if text.starts_with('(') {
+            if !text.ends_with(')') {
+                bail!("Path '{}' is missing its closing parenthesis.", text);
+            }
+            text = &text[1..text.len() - 1];
+        }
+
+        let mut steps = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+            if c == '\'' || c == '"' {
+                // A quoted field name, which may contain whitespace or look like a number.
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => name.push(escaped),
+                            None => bail!("Path '{}' ends with an incomplete escape.", text),
+                        },
+                        Some(q) if q == c => break,
+                        Some(other) => name.push(other),
+                        None => bail!("Path '{}' has an unterminated quoted field name.", text),
+                    }
+                }
+                steps.push(Step::Field(name));
+                continue;
+            }
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            let step = if token == "*" {
+                Step::Wildcard
+            } else if let Ok(index) = usize::from_str(&token) {
+                Step::Index(index)
+            } else {
+                Step::Field(token)
+            };
+            steps.push(step);
+        }
+        Ok(Path { steps })
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            match step {
+                Step::Field(name) => write!(f, "'{}'", name.replace('\\', "\\\\").replace('\'', "\\'"))?,
+                Step::Index(i) => write!(f, "{}", i)?,
+                Step::Wildcard => write!(f, "*")?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(text: &str) -> Vec<Step> {
+        Path::from_str(text).unwrap().steps().to_vec()
+    }
+
+    fn field(name: &str) -> Step {
+        Step::Field(name.to_owned())
+    }
+
+    #[test]
+    fn parses_fields_indexes_and_wildcards() {
+        assert_eq!(steps("(config db host)"), [field("config"), field("db"), field("host")]);
+        assert_eq!(steps("(records 0 id)"), [field("records"), Step::Index(0), field("id")]);
+        assert_eq!(steps("(items * sku)"), [field("items"), Step::Wildcard, field("sku")]);
+    }
+
+    #[test]
+    fn parentheses_and_extra_whitespace_are_optional() {
+        assert_eq!(steps("records 0 id"), steps("(records 0 id)"));
+        assert_eq!(steps("  ( a \t  b )  "), [field("a"), field("b")]);
+    }
+
+    #[test]
+    fn empty_path_selects_the_value_itself() {
+        assert!(steps("()").is_empty());
+        assert!(steps("").is_empty());
+        assert!(steps("   ").is_empty());
+    }
+
+    #[test]
+    fn quoted_names_may_look_like_numbers_or_hold_spaces() {
+        assert_eq!(steps("('0' \"two words\" '*')"), [field("0"), field("two words"), field("*")]);
+        assert_eq!(steps(r"('it\'s' 'back\\slash')"), [field("it's"), field("back\\slash")]);
+        assert_eq!(steps("('')"), [field("")]);
+    }
+
+    #[test]
+    fn numbers_that_arent_indexes_are_field_names() {
+        assert_eq!(steps("(-1)"), [field("-1")]);
+        assert_eq!(steps("(1.5)"), [field("1.5")]);
+        assert_eq!(steps("(18446744073709551616)"), [field("18446744073709551616")]);
+        assert_eq!(steps("(007)"), [Step::Index(7)]);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(Path::from_str("(a b").is_err());
+        assert!(Path::from_str("('abc)").is_err());
+        assert!(Path::from_str("(a \"b c)").is_err());
+        assert!(Path::from_str(r"'abc\").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use clap::Arg;
+
+// Whether the time spent in each phase of a command is reported when it finishes. This applies to
+// every command, so it's set once by `main`.
+static PROFILE: OnceLock<bool> = OnceLock::new();
+
+// Creates the global `profile` argument, which can be given to any command.
+pub fn profile_arg() -> Arg<'static, 'static> {
+    Arg::with_name("profile")
+        .long("profile")
+        .global(true)
+        .help("Report the time spent reading, decoding, transforming, encoding, and writing")
+        .long_help(
+            "When the command finishes, writes a table to STDERR of how much of its
+time was spent in each phase of its work:
+  read       opening inputs, including decompressing them and
+             transcoding text Ion to binary
+  decode     reading values from binary Ion. Memory-mapped inputs are
+             read from disk as they're decoded, so cold reads of large
+             files are counted here.
+  transform  changing values, as with dump's transform options or
+             pipeline stages
+  encode     writing values as Ion text, JSON, or other formats, and
+             transcoding Ion text to the requested format
+  write      handing output to the output file or STDOUT
+  other      everything else, like parsing arguments. `dump` without any
+             options that change values hands the whole job to ion-c,
+             so its time is all counted here.
+Only time on the main thread is counted, and each moment is counted in
+the innermost phase, so the phases add up to the total."
+        )
+}
+
+// Enables profiling. Only the first call has any effect.
+pub fn set_profile(profile: bool) {
+    let _ = PROFILE.set(profile);
+}
+
+pub fn profiling() -> bool {
+    *PROFILE.get().unwrap_or(&false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Read,
+    Decode,
+    Transform,
+    Encode,
+    Write,
+}
+
+const PHASES: [Phase; 5] = [Phase::Read, Phase::Decode, Phase::Transform, Phase::Encode, Phase::Write];
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Read => "read",
+            Phase::Decode => "decode",
+            Phase::Transform => "transform",
+            Phase::Encode => "encode",
+            Phase::Write => "write",
+        }
+    }
+}
+
+// The time attributed to each phase so far, the phases currently running (innermost last), and
+// when time was last attributed.
+struct Profile {
+    totals: [Duration; 5],
+    running: Vec<Phase>,
+    since: Instant,
+}
+
+impl Profile {
+    // Attributes the time since the last call to the innermost running phase.
+    fn advance(&mut self) {
+        let now = Instant::now();
+        if let Some(phase) = self.running.last() {
+            self.totals[*phase as usize] += now - self.since;
+        }
+        self.since = now;
+    }
+}
+
+thread_local! {
+    static PROFILE_DATA: RefCell<Profile> = RefCell::new(Profile {
+        totals: [Duration::ZERO; 5],
+        running: Vec::new(),
+        since: Instant::now(),
+    });
+}
+
+// Runs `f`, counting the time it takes toward `phase` if profiling is enabled. Phases can be
+// nested; time spent in an inner phase isn't also counted toward the outer one.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !profiling() {
+        return f();
+    }
+    PROFILE_DATA.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        profile.advance();
+        profile.running.push(phase);
+    });
+    let result = f();
+    PROFILE_DATA.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        profile.advance();
+        profile.running.pop();
+    });
+    result
+}
+
+// Writes the time spent in each phase to STDERR, out of `total`, the command's running time.
+pub fn report(command: &str, total: Duration) {
+    let totals = PROFILE_DATA.with(|profile| profile.borrow().totals);
+    let counted: Duration = totals.iter().sum();
+    let other = total.saturating_sub(counted);
+    let percent = |duration: Duration| {
+        if total.is_zero() { 0.0 } else { 100.0 * duration.as_secs_f64() / total.as_secs_f64() }
+    };
+    let mut rows: Vec<(&str, Duration)> = PHASES.iter().map(|phase| (phase.name(), totals[*phase as usize])).collect();
+    rows.push(("other", other));
+
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    let _ = writeln!(stderr, "Profile of `ion {}`: {:.3}s", command, total.as_secs_f64());
+    for (name, duration) in rows {
+        let _ = writeln!(stderr, "  {:<10} {:>10.3}s {:>6.1}%", name, duration.as_secs_f64(), percent(duration));
+    }
+    let io_time = totals[Phase::Read as usize] + totals[Phase::Write as usize];
+    let cpu_time = totals[Phase::Decode as usize] + totals[Phase::Transform as usize] + totals[Phase::Encode as usize];
+    let bound = if io_time > cpu_time { "I/O" } else { "CPU" };
+    let _ = writeln!(stderr, "Mostly {} bound.", bound);
+}
+
+// A writer that counts the time spent in each write and flush toward the write phase.
+pub struct TimedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> TimedWriter<W> {
+    pub fn new(inner: W) -> TimedWriter<W> {
+        TimedWriter { inner }
+    }
+}
+
+impl<W: Write> Write for TimedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        time(Phase::Write, || self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        time(Phase::Write, || self.inner.flush())
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::path::{Path as FilePath, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Arg;
+
+use crate::input::IonInput;
+use crate::value::{Data, TextFormatter, UnknownSymbols, Value};
+
+// Reads Ion Schema Language (ISL) documents well enough to describe them: their imports, the
+// types they define, and the types those definitions refer to. Schemas are not checked for
+// validity, and constraints are kept as the Ion values they're written as.
+
+// Creates the `authority` argument shared by the `schema` commands.
+pub fn authority_arg() -> Arg<'static, 'static> {
+    Arg::with_name("authority")
+        .long("authority")
+        .short("A")
+        .takes_value(true)
+        .help("Directory that schema IDs are resolved against [default: the schema's directory]")
+}
+
+// Finds schemas by ID. An ID is a path relative to the authority's directory.
+pub struct Authority {
+    root: PathBuf,
+    schemas: HashMap<String, Schema>,
+}
+
+impl Authority {
+    pub fn new(root: PathBuf) -> Authority {
+        Authority { root, schemas: HashMap::new() }
+    }
+
+    // Creates an authority from the `authority` argument. Without it, schemas are found
+    // relative to the directory containing `schema_file`.
+    pub fn for_schema_file(authority: Option<&str>, schema_file: &str) -> Authority {
+        let root = match authority {
+            Some(directory) => PathBuf::from(directory),
+            None => FilePath::new(schema_file)
+                .parent()
+                .map(|parent| parent.to_owned())
+                .unwrap_or_default(),
+        };
+        Authority::new(root)
+    }
+
+    // The ID of a schema file within this authority.
+    pub fn id_of(&self, schema_file: &str) -> Result<String> {
+        let path = FilePath::new(schema_file);
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => Ok(relative.to_string_lossy().replace('\\', "/")),
+            Err(_) => bail!("'{}' is not within the authority directory '{}'.", schema_file, self.root.display()),
+        }
+    }
+
+    // Reads the schema with the given ID, if it hasn't been read already.
+    pub fn load(&mut self, id: &str) -> Result<&Schema> {
+        if !self.schemas.contains_key(id) {
+            let path = self.root.join(id);
+            let file_name = path.to_str()
+                .with_context(|| format!("Schema path {:?} is not valid UTF-8", path))?;
+            let values = IonInput::open(file_name)
+                .and_then(|input| input.read_all())
+                .with_context(|| format!("Could not read schema '{}'", id))?;
+            let schema = Schema::from_values(id, values)?;
+            self.schemas.insert(id.to_owned(), schema);
+        }
+        Ok(&self.schemas[id])
+    }
+
+    // Adds a schema that isn't in a file, like one written on the command line, under `id`.
+    pub fn add(&mut self, id: &str, values: Vec<Value>) -> Result<()> {
+        let schema = Schema::from_values(id, values)?;
+        self.schemas.insert(id.to_owned(), schema);
+        Ok(())
+    }
+
+    // Finds the definition that `name` refers to within the schema `id`: one of its own types,
+    // a type it imports by name or alias, or a type in a schema it imports entirely. Returns
+    // the ID of the schema defining the type and the type's name there, or None for built-in
+    // types and names that can't be resolved.
+    pub fn resolve(&mut self, id: &str, name: &str) -> Result<Option<(String, String)>> {
+        let schema = self.load(id)?;
+        if schema.type_named(name).is_some() {
+            return Ok(Some((id.to_owned(), name.to_owned())));
+        }
+        let imports = schema.imports.clone();
+        for import in &imports {
+            match (&import.type_name, &import.alias) {
+                (Some(type_name), Some(alias)) if alias == name => {
+                    return Ok(Some((import.id.clone(), type_name.clone())));
+                }
+                (Some(type_name), None) if type_name == name => {
+                    return Ok(Some((import.id.clone(), type_name.clone())));
+                }
+                (None, _) if self.load(&import.id)?.type_named(name).is_some() => {
+                    return Ok(Some((import.id.clone(), name.to_owned())));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+// An ISL document.
+pub struct Schema {
+    // Every top-level value, in order, including the header and footer.
+    pub values: Vec<Value>,
+    pub imports: Vec<Import>,
+    pub types: Vec<TypeDefinition>,
+}
+
+// An import from a schema's header: either a whole schema, or one of its types, optionally
+// under another name.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub id: String,
+    pub type_name: Option<String>,
+    pub alias: Option<String>,
+}
+
+// A named type defined at the top level of a schema.
+pub struct TypeDefinition {
+    pub name: String,
+    // The type's struct, including its `name` field.
+    pub definition: Value,
+}
+
+// A reference to a type from within a type definition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeReference {
+    // A type named by a symbol, which may be defined in the schema, imported, or built in.
+    Named(String),
+    // A type named along with the schema that defines it, like `{id: "units.isl", type: meters}`.
+    Imported { id: String, type_name: String },
+}
+
+impl Schema {
+    pub fn from_values(id: &str, values: Vec<Value>) -> Result<Schema> {
+        let mut imports = Vec::new();
+        let mut types = Vec::new();
+        for value in &values {
+            if has_annotation(value, "schema_header") {
+                if let Some(Data::List(entries)) = value.get("imports").map(|imports| &imports.data) {
+                    for entry in entries {
+                        imports.push(parse_import(id, entry)?);
+                    }
+                }
+            } else if has_annotation(value, "type") {
+                let name = match value.get("name").and_then(|name| name.as_text()) {
+                    Some(name) => name.to_owned(),
+                    None => bail!("Schema '{}' has a top-level type without a name.", id),
+                };
+                types.push(TypeDefinition { name, definition: value.clone() });
+            }
+        }
+        Ok(Schema { values, imports, types })
+    }
+
+    pub fn type_named(&self, name: &str) -> Option<&TypeDefinition> {
+        self.types.iter().find(|definition| definition.name == name)
+    }
+}
+
+fn parse_import(id: &str, entry: &Value) -> Result<Import> {
+    let text = |field: &str| entry.get(field).and_then(|value| value.as_text()).map(|text| text.to_owned());
+    match text("id") {
+        Some(import_id) => Ok(Import { id: import_id, type_name: text("type"), alias: text("as") }),
+        None => bail!("Schema '{}' has an import without an `id`.", id),
+    }
+}
+
+pub fn has_annotation(value: &Value, annotation: &str) -> bool {
+    value.annotations.iter().any(|a| a == annotation)
+}
+
+// The constraints whose values are a type reference, or a list of them.
+pub const REFERENCE_CONSTRAINTS: &[&str] = &["type", "element", "not"];
+pub const REFERENCE_LIST_CONSTRAINTS: &[&str] = &["one_of", "any_of", "all_of", "ordered_elements"];
+
+// The types that every schema can refer to without defining or importing them. Each of the Ion
+// types also has a `$`-prefixed form, like `$int`, that includes null values.
+const BUILT_IN_TYPES: &[&str] = &[
+    "any", "nothing", "document", "lob", "number", "text", "blob", "bool", "clob", "decimal",
+    "float", "int", "list", "sexp", "string", "struct", "symbol", "timestamp",
+];
+
+pub fn is_built_in(name: &str) -> bool {
+    BUILT_IN_TYPES.contains(&name) || name == "$any" || name == "$null"
+        || name.strip_prefix('$').is_some_and(|name| BUILT_IN_TYPES.contains(&name))
+}
+
+// Returns every type that a type definition (or inline type) refers to, in the order they
+// first appear. The constraints of inline types are searched too.
+pub fn references(definition: &Value) -> Vec<TypeReference> {
+    let mut found = Vec::new();
+    add_constraint_references(definition, &mut found);
+    found
+}
+
+fn add_constraint_references(definition: &Value, found: &mut Vec<TypeReference>) {
+    let fields = match &definition.data {
+        Data::Struct(fields) => fields,
+        _ => return,
+    };
+    for (name, value) in fields {
+        match name.text() {
+            Some(name) if REFERENCE_CONSTRAINTS.contains(&name) => add_reference(value, found),
+            Some(name) if REFERENCE_LIST_CONSTRAINTS.contains(&name) => {
+                if let Data::List(elements) = &value.data {
+                    for element in elements {
+                        add_reference(element, found);
+                    }
+                }
+            }
+            Some("fields") => {
+                if let Data::Struct(fields) = &value.data {
+                    for (_, field_type) in fields {
+                        add_reference(field_type, found);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_reference(value: &Value, found: &mut Vec<TypeReference>) {
+    match as_reference(value) {
+        Some(reference) => {
+            if !found.contains(&reference) {
+                found.push(reference);
+            }
+        }
+        // Otherwise, this is an inline type, whose constraints may refer to other types.
+        None => add_constraint_references(value, found),
+    }
+}
+
+// Calls `rewrite` with every type reference in a type definition (or inline type), including
+// those within inline types, so that it can replace them.
+pub fn rewrite_references(definition: &mut Value, rewrite: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+    let fields = match &mut definition.data {
+        Data::Struct(fields) => fields,
+        _ => return Ok(()),
+    };
+    for (name, value) in fields.iter_mut() {
+        match name.text() {
+            Some(name) if REFERENCE_CONSTRAINTS.contains(&name) => rewrite_reference(value, rewrite)?,
+            Some(name) if REFERENCE_LIST_CONSTRAINTS.contains(&name) => {
+                if let Data::List(elements) = &mut value.data {
+                    for element in elements.iter_mut() {
+                        rewrite_reference(element, rewrite)?;
+                    }
+                }
+            }
+            Some("fields") => {
+                if let Data::Struct(fields) = &mut value.data {
+                    for (_, field_type) in fields.iter_mut() {
+                        rewrite_reference(field_type, rewrite)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn rewrite_reference(value: &mut Value, rewrite: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+    match as_reference(value) {
+        Some(_) => rewrite(value),
+        None => rewrite_references(value, rewrite),
+    }
+}
+
+// Returns the type that `value` names if it's a type reference rather than an inline type.
+pub fn as_reference(value: &Value) -> Option<TypeReference> {
+    match &value.data {
+        Data::Symbol(symbol) => symbol.text().map(|name| TypeReference::Named(name.to_owned())),
+        Data::Struct(_) => {
+            let id = value.get("id")?.as_text()?;
+            let type_name = value.get("type")?.as_text()?;
+            Some(TypeReference::Imported { id: id.to_owned(), type_name: type_name.to_owned() })
+        }
+        _ => None,
+    }
+}
+
+// Writes a value as compact text Ion, for display.
+pub fn ion_text(value: &Value) -> String {
+    let mut formatter = TextFormatter::new();
+    formatter.set_unknown_symbols(UnknownSymbols::Placeholder);
+    let mut text = String::new();
+    // Unknown symbols are written as placeholders, so formatting can't fail.
+    let _ = formatter.format(value, &mut text);
+    text
+}
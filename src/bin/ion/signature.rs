@@ -0,0 +1,62 @@
+use std::convert::TryFrom;
+
+use anyhow::{bail, Result};
+use ed25519_dalek::{PublicKey, SecretKey, Signature};
+
+use crate::input::read_key_file;
+use crate::value::{Data, Symbol, Value};
+
+// The annotation on a signature, whether it's embedded at the end of the signed stream or
+// written to a separate file.
+pub const SIGNATURE_ANNOTATION: &str = "ion_signature";
+
+// The only supported signature algorithm.
+pub const ALGORITHM: &str = "ed25519";
+
+// Reads a secret key from a file containing its 32 bytes in base64.
+pub fn read_secret_key(file_name: &str) -> Result<SecretKey> {
+    let bytes = read_key_file(file_name)?;
+    SecretKey::from_bytes(&bytes)
+        .map_err(|error| anyhow::anyhow!("'{}' is not a valid Ed25519 secret key: {}", file_name, error))
+}
+
+// Reads a public key from a file containing its 32 bytes in base64.
+pub fn read_public_key(file_name: &str) -> Result<PublicKey> {
+    let bytes = read_key_file(file_name)?;
+    PublicKey::from_bytes(&bytes)
+        .map_err(|error| anyhow::anyhow!("'{}' is not a valid Ed25519 public key: {}", file_name, error))
+}
+
+// Creates the value that represents a signature:
+//   ion_signature::{algorithm: ed25519, public_key: {{...}}, signature: {{...}}}
+pub fn signature_value(signature: &Signature, public_key: &PublicKey) -> Value {
+    let mut value = Value::new(Data::Struct(vec![
+        (Symbol::from("algorithm"), Value::new(Data::Symbol(Symbol::from(ALGORITHM)))),
+        (Symbol::from("public_key"), Value::new(Data::Blob(public_key.as_bytes().to_vec()))),
+        (Symbol::from("signature"), Value::new(Data::Blob(signature.to_bytes().to_vec()))),
+    ]));
+    value.annotations.push(Symbol::from(SIGNATURE_ANNOTATION));
+    value
+}
+
+// Tests whether `value` is annotated as a signature.
+pub fn is_signature(value: &Value) -> bool {
+    value.annotations.iter().any(|annotation| annotation == SIGNATURE_ANNOTATION)
+}
+
+// Extracts the signature from a value created by `signature_value`.
+pub fn parse_signature_value(value: &Value) -> Result<Signature> {
+    if !is_signature(value) {
+        bail!("Expected a value annotated with '{}'", SIGNATURE_ANNOTATION);
+    }
+    match value.get("algorithm").and_then(|algorithm| algorithm.as_text()) {
+        Some(ALGORITHM) => {}
+        Some(other) => bail!("Unsupported signature algorithm '{}'", other),
+        None => bail!("The signature does not name its algorithm"),
+    }
+    let bytes = match value.get("signature").and_then(|signature| signature.as_lob()) {
+        Some(bytes) => bytes,
+        None => bail!("The signature does not have a 'signature' blob"),
+    };
+    Signature::try_from(bytes).map_err(|_| anyhow::anyhow!("The signature blob is malformed"))
+}
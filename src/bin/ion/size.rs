@@ -0,0 +1,98 @@
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use clap::Arg;
+
+use crate::value::{TextFormatter, UnknownSymbols, Value};
+
+// The --max-memory limit, as a number of bytes and as it was written on the command line.
+static MAX_MEMORY: OnceLock<(usize, String)> = OnceLock::new();
+
+// Creates the global `max-memory` argument, which can be given to any command.
+pub fn max_memory_arg() -> Arg<'static, 'static> {
+    Arg::with_name("max-memory")
+        .long("max-memory")
+        .takes_value(true)
+        .global(true)
+        .help("Approximate memory that commands may use to hold values. Accepts K, M, and G suffixes.")
+        .long_help(
+            "Approximate amount of memory that commands may use to hold values.
+Accepts K, M, and G suffixes, e.g. 512M.
+
+Inputs are never read into memory: STDIN, compressed inputs, and inputs
+converted from text Ion or JSON are written to temporary files and
+read from there. Commands that have to hold values do the following
+when they would exceed the limit:
+
+  beta join     joins in partitions on disk, as if --memory-limit
+                had been given the same size
+  beta agg      fails, suggesting fewer --group-by paths or a --window
+  beta sample   fails when the --count values selected don't fit
+
+Sizes are estimated from the length of each value's text, so the
+process itself may use somewhat more. Without --max-memory, there is
+no limit."
+        )
+}
+
+// Sets the limit given by --max-memory. Only the first call has any effect.
+pub fn set_max_memory(text: &str) -> Result<()> {
+    let bytes = parse_size(text).with_context(|| "Invalid --max-memory")?;
+    let _ = MAX_MEMORY.set((bytes, text.to_owned()));
+    Ok(())
+}
+
+// The most memory, in bytes, that commands should use to hold values, if there's a limit.
+pub fn max_memory() -> Option<usize> {
+    MAX_MEMORY.get().map(|(bytes, _)| *bytes)
+}
+
+// Fails if `estimated_size` bytes exceed --max-memory. `what` describes what is being held, and
+// is only called if the limit is exceeded; `advice` suggests how to hold less.
+pub fn check_max_memory<F>(estimated_size: usize, what: F, advice: &str) -> Result<()>
+where
+    F: FnOnce() -> String,
+{
+    match MAX_MEMORY.get() {
+        Some((bytes, text)) if estimated_size > *bytes => {
+            bail!("{} needs more memory than --max-memory {} allows; {}.", what(), text, advice)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Estimates the memory a value uses by the length of its compact text, which is a rough but
+// cheap proxy.
+pub struct SizeEstimator {
+    formatter: TextFormatter,
+    text: String,
+}
+
+impl SizeEstimator {
+    pub fn new() -> SizeEstimator {
+        let mut formatter = TextFormatter::new();
+        formatter.set_unknown_symbols(UnknownSymbols::Placeholder);
+        SizeEstimator { formatter, text: String::new() }
+    }
+
+    pub fn size_of(&mut self, value: &Value) -> Result<usize> {
+        self.text.clear();
+        self.formatter.format(value, &mut self.text)?;
+        Ok(self.text.len())
+    }
+}
+
+// Parses a size in bytes like `512`, `64K`, or `2G`. Suffixes are powers of 1024.
+pub fn parse_size(text: &str) -> Result<usize> {
+    let text = text.trim();
+    let (digits, multiplier) = match text.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&text[..text.len() - 1], 1 << 10),
+        Some('M') => (&text[..text.len() - 1], 1 << 20),
+        Some('G') => (&text[..text.len() - 1], 1 << 30),
+        _ => (text, 1),
+    };
+    let count: usize = digits.parse()
+        .with_context(|| format!("Invalid size '{}'", text))?;
+    count.checked_mul(multiplier)
+        .with_context(|| format!("Size '{}' is too large", text))
+}
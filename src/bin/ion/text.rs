@@ -0,0 +1,624 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Error, Result};
+
+use crate::path::Step;
+
+// The byte range of a value in a text Ion document, along with enough of its structure to find
+// the values nested within it. A value's range includes its field name and annotations.
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub field_name: Option<String>,
+    children: Vec<Span>,
+}
+
+impl Span {
+    // Follows the children of this value that contain `offset`, returning the path that was
+    // followed and the innermost value found.
+    pub fn descend(&self, offset: usize) -> (Vec<Step>, &Span) {
+        let mut steps = Vec::new();
+        let mut span = self;
+        while let Some((index, child)) = span.children
+            .iter()
+            .enumerate()
+            .find(|(_, child)| child.start <= offset && offset < child.end) {
+            steps.push(match &child.field_name {
+                Some(name) => Step::Field(name.clone()),
+                None => Step::Index(index),
+            });
+            span = child;
+        }
+        (steps, span)
+    }
+}
+
+// Converts a 1-based line and column (counted in characters) to a byte offset in `text`.
+pub fn offset_of(text: &str, line: usize, column: usize) -> Result<usize> {
+    let line_text = text.split('\n')
+        .nth(line.wrapping_sub(1))
+        .ok_or_else(|| anyhow!("The input does not have a line {}.", line))?;
+    let line_start = line_text.as_ptr() as usize - text.as_ptr() as usize;
+    match line_text.char_indices().nth(column.wrapping_sub(1)) {
+        Some((index, _)) => Ok(line_start + index),
+        None => bail!("Line {} does not have a column {}.", line, column),
+    }
+}
+
+// Converts a byte offset in `text` to a 1-based line and column.
+pub fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+// Scans the top-level values of `text` until it finds the one containing `offset`. Returns its
+// index and span. Values after it are never looked at, so they don't need to be valid Ion.
+pub fn find_top_level_value(text: &str, offset: usize) -> Result<(usize, Span)> {
+    let mut scanner = Scanner::new(text, None);
+    let mut index = 0;
+    loop {
+        scanner.skip_whitespace()?;
+        if scanner.position > offset {
+            // We passed the offset without finding a value; it's in whitespace or a comment.
+            break;
+        }
+        let span = scanner.value(false)?;
+        if offset < span.end {
+            return Ok((index, span));
+        }
+        index += 1;
+    }
+    let (line, column) = line_and_column(text, offset);
+    bail!("Line {} column {} is not within a value.", line, column)
+}
+
+// Finds the top-level version markers in `text`, like `$ion_1_0`, returning each one's byte offset
+// and its major and minor versions.
+pub fn version_markers(text: &str) -> Result<Vec<(usize, u32, u32)>> {
+    let mut scanner = Scanner::new(text, None);
+    let mut markers = Vec::new();
+    loop {
+        scanner.skip_whitespace()?;
+        if scanner.position >= text.len() {
+            break;
+        }
+        let span = scanner.value(false)?;
+        let version = text[span.start..span.end]
+            .strip_prefix("$ion_")
+            .and_then(|version| version.split_once('_'))
+            .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+        if let Some((major, minor)) = version {
+            markers.push((span.start, major, minor));
+        }
+    }
+    Ok(markers)
+}
+
+// The comments that belong to a top-level value.
+#[derive(Default)]
+pub struct ValueComments {
+    // The comments before the value (since the previous value) and inside of it, in order.
+    pub leading: Vec<String>,
+    // A comment that follows the value on the same line.
+    pub trailing: Option<String>,
+}
+
+// Finds the comments in `text` and assigns them to its top-level user values, in order. Returns
+// the comments for each value and those after the last value. Version markers and symbol tables
+// aren't user values, so the comments around them go to the next value.
+pub fn comments(text: &str) -> Result<(Vec<ValueComments>, Vec<String>)> {
+    let mut scanner = Scanner::new(text, None);
+    let mut spans = Vec::new();
+    loop {
+        scanner.skip_whitespace()?;
+        if scanner.position >= text.len() {
+            break;
+        }
+        let span = scanner.value(false)?;
+        let source = &text[span.start..span.end];
+        if source != "$ion_1_0" && !source.starts_with("$ion_symbol_table") {
+            spans.push(span);
+        }
+    }
+
+    let mut values: Vec<ValueComments> = spans.iter().map(|_| ValueComments::default()).collect();
+    let mut remaining = Vec::new();
+    let mut index = 0;
+    for (start, end) in scanner.comments {
+        let comment = text[start..end].to_owned();
+        // Comments after the end of a value belong to the next one, unless they're on its line.
+        while index < spans.len() && spans[index].end <= start {
+            let between = &text[spans[index].end..start];
+            if values[index].trailing.is_none() && !between.contains('\n') {
+                break;
+            }
+            index += 1;
+        }
+        match spans.get(index) {
+            Some(span) if span.end <= start => values[index].trailing = Some(comment),
+            Some(_) => values[index].leading.push(comment),
+            None => remaining.push(comment),
+        }
+    }
+    Ok((values, remaining))
+}
+
+// A problem found by `check`, covering `length` bytes starting at `offset`.
+pub struct Diagnostic {
+    pub offset: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+// The optional strict checks.
+#[derive(Debug, Clone, Copy)]
+pub struct StrictChecks {
+    pub duplicate_fields: bool,
+}
+
+// Scans all of `text` and reports unknown escape sequences, non-canonical numbers, duplicate
+// field names (if requested), and the first syntax error, if any. Syntax errors stop the scan.
+pub fn check(text: &str, checks: StrictChecks) -> Vec<Diagnostic> {
+    let mut scanner = Scanner::new(text, Some(checks));
+    loop {
+        let result = scanner.skip_whitespace().and_then(|_| {
+            if scanner.position < text.len() {
+                scanner.value(false).map(|_| ())
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(error) = result {
+            let offset = scanner.position.min(text.len());
+            scanner.report(offset, 1, error.root_cause().to_string());
+            break;
+        }
+        if scanner.position >= text.len() {
+            break;
+        }
+    }
+    let mut diagnostics = scanner.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.offset);
+    diagnostics
+}
+
+// Formats a diagnostic with its location and the line it's on, with carets under the problem:
+//   Unknown escape sequence '\q'
+//    --> config.ion:3:8
+//     |
+//   3 | {a: "x\q"}
+//     |       ^^
+pub fn render(text: &str, name: &str, diagnostic: &Diagnostic) -> String {
+    let (line, column) = line_and_column(text, diagnostic.offset);
+    let line_start = text[..diagnostic.offset].rfind('\n').map_or(0, |index| index + 1);
+    let line_text = text[line_start..].split('\n').next().unwrap_or("").trim_end_matches('\r');
+    // Keep any tabs before the problem so that the carets line up with it.
+    let indentation: String = line_text
+        .chars()
+        .take(column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end = (diagnostic.offset + diagnostic.length).min(line_start + line_text.len());
+    let width = text.get(diagnostic.offset..end).map_or(1, |problem| problem.chars().count()).max(1);
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{}\n{} --> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+        diagnostic.message,
+        gutter, name, line, column,
+        gutter,
+        line, line_text,
+        gutter, indentation, "^".repeat(width)
+    )
+}
+
+// The characters that make up s-expression operators like `+` or `<=`.
+const OPERATOR_CHARACTERS: &[u8] = b"!#%&*+-./;<=>?@^`|~";
+
+struct Scanner<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+    // Strict checks are only made if this is set.
+    checks: Option<StrictChecks>,
+    diagnostics: Vec<Diagnostic>,
+    // The byte ranges of the comments that have been skipped, in order.
+    comments: Vec<(usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str, checks: Option<StrictChecks>) -> Scanner<'a> {
+        Scanner { text, bytes: text.as_bytes(), position: 0, checks, diagnostics: Vec::new(), comments: Vec::new() }
+    }
+
+    fn report(&mut self, offset: usize, length: usize, message: String) {
+        self.diagnostics.push(Diagnostic { offset, length, message });
+    }
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn peek_at(&self, distance: usize) -> Option<u8> {
+        self.bytes.get(self.position + distance).copied()
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.bytes.get(self.position..).is_some_and(|rest| rest.starts_with(prefix.as_bytes()))
+    }
+
+    // The location is added as context, so the message itself is the error's root cause.
+    fn error(&self, message: &str) -> Error {
+        let (line, column) = line_and_column(self.text, self.position.min(self.text.len()));
+        anyhow!("{}", message).context(format!("Invalid text Ion at line {} column {}", line, column))
+    }
+
+    // Skips whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+                Some(b'/') if self.peek_at(1) == Some(b'/') => {
+                    let start = self.position;
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.position += 1;
+                    }
+                    self.record_comment(start);
+                }
+                Some(b'/') if self.peek_at(1) == Some(b'*') => {
+                    let start = self.position;
+                    match self.text[self.position + 2..].find("*/") {
+                        Some(index) => self.position += index + 4,
+                        None => return Err(self.error("Unterminated block comment")),
+                    }
+                    self.record_comment(start);
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // Comments can be skipped more than once when the scanner looks ahead for `::`, so each is
+    // only recorded the first time.
+    fn record_comment(&mut self, start: usize) {
+        if self.comments.last().is_none_or(|(last_start, _)| *last_start < start) {
+            self.comments.push((start, self.position));
+        }
+    }
+
+    // Scans a value, including any annotations. Operators are only values in s-expressions.
+    fn value(&mut self, in_s_expression: bool) -> Result<Span> {
+        let start = self.position;
+        let mut children = Vec::new();
+        loop {
+            let is_symbol = match self.peek() {
+                None => return Err(self.error("Expected a value")),
+                Some(b'{') if self.peek_at(1) == Some(b'{') => {
+                    self.lob()?;
+                    false
+                }
+                Some(b'{') => {
+                    children = self.structure()?;
+                    false
+                }
+                Some(b'[') => {
+                    children = self.sequence(b']', Some(b','), false)?;
+                    false
+                }
+                Some(b'(') => {
+                    children = self.sequence(b')', None, true)?;
+                    false
+                }
+                Some(b'"') => {
+                    self.quoted(b'"')?;
+                    false
+                }
+                Some(b'\'') if self.starts_with("'''") => {
+                    self.long_strings()?;
+                    false
+                }
+                Some(b'\'') => {
+                    self.quoted(b'\'')?;
+                    true
+                }
+                Some(_) => self.token(in_s_expression)?,
+            };
+            // A symbol followed by `::` is an annotation on the value that follows it.
+            if is_symbol {
+                let end = self.position;
+                self.skip_whitespace()?;
+                if self.starts_with("::") {
+                    self.position += 2;
+                    self.skip_whitespace()?;
+                    continue;
+                }
+                self.position = end;
+            }
+            return Ok(Span { start, end: self.position, field_name: None, children });
+        }
+    }
+
+    // Scans a number, timestamp, keyword, identifier, or operator. Returns whether it was a
+    // symbol that could be an annotation.
+    fn token(&mut self, in_s_expression: bool) -> Result<bool> {
+        let first = self.peek().unwrap();
+        let signed_number = (first == b'-' || first == b'+')
+            && (self.peek_at(1).is_some_and(|byte| byte.is_ascii_digit()) || self.text[self.position + 1..].starts_with("inf"));
+        if first.is_ascii_digit() || signed_number {
+            let start = self.position;
+            self.position += 1;
+            while self.peek().is_some_and(|byte| byte.is_ascii_alphanumeric() || b".-+:_".contains(&byte)) {
+                self.position += 1;
+            }
+            if self.checks.is_some() {
+                self.check_number(start);
+            }
+            return Ok(false);
+        }
+        if first.is_ascii_alphabetic() || first == b'_' || first == b'$' {
+            let start = self.position;
+            self.identifier();
+            // Typed nulls, like `null.struct`.
+            if &self.text[start..self.position] == "null" && self.peek() == Some(b'.') {
+                self.position += 1;
+                self.identifier();
+                return Ok(false);
+            }
+            return Ok(true);
+        }
+        if in_s_expression && OPERATOR_CHARACTERS.contains(&first) {
+            while self.peek().is_some_and(|byte| OPERATOR_CHARACTERS.contains(&byte)) {
+                self.position += 1;
+            }
+            return Ok(false);
+        }
+        Err(self.error(&format!("Unexpected character '{}'", self.text[self.position..].chars().next().unwrap())))
+    }
+
+    fn identifier(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$') {
+            self.position += 1;
+        }
+    }
+
+    // Scans a string or quoted symbol, returning its text with escapes removed.
+    fn quoted(&mut self, delimiter: u8) -> Result<String> {
+        self.position += 1;
+        let start = self.position;
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.escape(),
+                Some(byte) if byte == delimiter => break,
+                Some(_) => self.position += 1,
+                None => return Err(self.error("Unterminated quoted text")),
+            }
+        }
+        let text = unescape(&self.text[start..self.position]);
+        self.position += 1;
+        Ok(text)
+    }
+
+    // Scans one or more adjacent long strings, which together form a single string value.
+    fn long_strings(&mut self) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            self.position += 3;
+            let start = self.position;
+            loop {
+                if self.starts_with("'''") {
+                    break;
+                }
+                match self.peek() {
+                    Some(b'\\') => self.escape(),
+                    Some(_) => self.position += 1,
+                    None => return Err(self.error("Unterminated long string")),
+                }
+            }
+            text.push_str(&unescape(&self.text[start..self.position]));
+            self.position += 3;
+            let end = self.position;
+            self.skip_whitespace()?;
+            if !self.starts_with("'''") {
+                self.position = end;
+                return Ok(text);
+            }
+        }
+    }
+
+    // Scans a blob or clob. Clobs may contain strings, which may contain `}}`.
+    fn lob(&mut self) -> Result<()> {
+        self.position += 2;
+        loop {
+            match self.peek() {
+                Some(b'}') if self.peek_at(1) == Some(b'}') => {
+                    self.position += 2;
+                    return Ok(());
+                }
+                Some(b'"') => {
+                    self.quoted(b'"')?;
+                }
+                Some(b'\'') if self.starts_with("'''") => {
+                    self.long_strings()?;
+                }
+                Some(_) => self.position += 1,
+                None => return Err(self.error("Unterminated blob or clob")),
+            }
+        }
+    }
+
+    // Skips an escape sequence in quoted text, reporting it if strict checks are enabled and it
+    // isn't one that Ion defines.
+    fn escape(&mut self) {
+        let start = self.position;
+        let escaped = match self.text.get(start + 1..).and_then(|rest| rest.chars().next()) {
+            Some(escaped) => escaped,
+            None => {
+                self.position += 1;
+                return;
+            }
+        };
+        self.position += 1 + escaped.len_utf8();
+        let hex_digits = match escaped {
+            'a' | 'b' | 't' | 'n' | 'f' | 'r' | 'v' | '?' | '0' | '\'' | '"' | '/' | '\\' | '\n' => return,
+            '\r' => {
+                if self.peek() == Some(b'\n') {
+                    self.position += 1;
+                }
+                return;
+            }
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            _ => {
+                if self.checks.is_some() {
+                    let message = format!("Unknown escape sequence '\\{}'", escaped);
+                    self.report(start, self.position - start, message);
+                }
+                return;
+            }
+        };
+        let digits = self.bytes[self.position..].iter().take(hex_digits).take_while(|byte| byte.is_ascii_hexdigit()).count();
+        self.position += digits;
+        if digits < hex_digits && self.checks.is_some() {
+            let message = format!("Escape sequence '\\{}' needs {} hex digits", escaped, hex_digits);
+            self.report(start, self.position - start, message);
+        }
+    }
+
+    // Reports the ways in which the number (or timestamp) that begins at `start` and ends at the
+    // current position isn't written the way Ion writes it.
+    fn check_number(&mut self, start: usize) {
+        let token = &self.text[start..self.position];
+        let unsigned = token.strip_prefix(&['-', '+'][..]).unwrap_or(token);
+        let is_timestamp = unsigned.len() >= 5
+            && unsigned[..4].bytes().all(|byte| byte.is_ascii_digit())
+            && matches!(unsigned.as_bytes()[4], b'-' | b'T');
+        if is_timestamp || unsigned == "inf" {
+            return;
+        }
+        let mut problems = Vec::new();
+        if token.starts_with('+') {
+            problems.push("Numbers can't begin with '+'".to_owned());
+        }
+        if token == "-0" {
+            problems.push("Write the integer -0 as 0".to_owned());
+        }
+        let is_radix = unsigned.len() > 1 && unsigned.starts_with('0') && matches!(unsigned.as_bytes()[1], b'x' | b'X' | b'b' | b'B');
+        if is_radix {
+            if unsigned.as_bytes()[1].is_ascii_uppercase() {
+                problems.push(format!("Write the radix prefix '{}' in lowercase", &unsigned[..2]));
+            }
+        } else {
+            let digits = unsigned.split(|c: char| !c.is_ascii_digit() && c != '_').next().unwrap_or("");
+            if digits.len() > 1 && digits.starts_with('0') {
+                problems.push("Numbers can't have leading zeros".to_owned());
+            }
+            if let Some(index) = unsigned.find(&['e', 'E', 'd', 'D'][..]) {
+                let marker = &unsigned[index..index + 1];
+                if marker.chars().all(|c| c.is_ascii_uppercase()) {
+                    problems.push(format!("Write the exponent marker '{}' in lowercase", marker));
+                }
+                let exponent = &unsigned[index + 1..];
+                if exponent.starts_with('+') {
+                    problems.push("Omit the '+' from exponents".to_owned());
+                }
+                let exponent_digits = exponent.trim_start_matches(&['-', '+'][..]);
+                if exponent_digits.len() > 1 && exponent_digits.starts_with('0') {
+                    problems.push("Exponents can't have leading zeros".to_owned());
+                }
+            }
+        }
+        for problem in problems {
+            self.report(start, token.len(), format!("{}: '{}'", problem, token));
+        }
+    }
+
+    // Scans a list or s-expression, returning the spans of its elements.
+    fn sequence(&mut self, closing: u8, separator: Option<u8>, in_s_expression: bool) -> Result<Vec<Span>> {
+        self.position += 1;
+        let mut children = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(closing) {
+                self.position += 1;
+                return Ok(children);
+            }
+            children.push(self.value(in_s_expression)?);
+            if let Some(separator) = separator {
+                self.skip_whitespace()?;
+                match self.peek() {
+                    Some(byte) if byte == separator => self.position += 1,
+                    Some(byte) if byte == closing => {}
+                    _ => return Err(self.error(&format!("Expected '{}' or '{}'", separator as char, closing as char))),
+                }
+            }
+        }
+    }
+
+    // Scans a struct, returning the spans of its fields.
+    fn structure(&mut self) -> Result<Vec<Span>> {
+        self.position += 1;
+        let mut fields = Vec::new();
+        let mut names = HashSet::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(b'}') {
+                self.position += 1;
+                return Ok(fields);
+            }
+            let start = self.position;
+            let name = match self.peek() {
+                Some(b'"') => self.quoted(b'"')?,
+                Some(b'\'') if self.starts_with("'''") => self.long_strings()?,
+                Some(b'\'') => self.quoted(b'\'')?,
+                Some(byte) if byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$' => {
+                    self.identifier();
+                    self.text[start..self.position].to_owned()
+                }
+                _ => return Err(self.error("Expected a field name")),
+            };
+            let check_duplicates = self.checks.is_some_and(|checks| checks.duplicate_fields);
+            if check_duplicates && !names.insert(name.clone()) {
+                self.report(start, self.position - start, format!("Duplicate field name '{}'", name));
+            }
+            self.skip_whitespace()?;
+            if self.peek() != Some(b':') || self.starts_with("::") {
+                return Err(self.error("Expected ':' after a field name"));
+            }
+            self.position += 1;
+            self.skip_whitespace()?;
+            let mut field = self.value(false)?;
+            field.start = start;
+            field.field_name = Some(name);
+            fields.push(field);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {}
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+// Removes the common escapes from quoted text. This is only used for field names in paths, so
+// it doesn't need to handle every escape Ion supports.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('0') => unescaped.push('\0'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,373 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bigdecimal::num_bigint::BigUint;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use crate::binary::{read_var_int, read_var_uint};
+use crate::value::ConversionError;
+
+// Parses a text Ion timestamp like `2021-06T` or `2021-06-01T12:30:00.250-07:00` and returns the
+// period it denotes: the instant it starts at and the instant just after it ends. A timestamp's
+// period is one unit of its precision long, so `2021-06T` is all of June and `12:30:00.250` is
+// a millisecond. Timestamps without a time are in UTC, as are those with the unknown offset
+// `-00:00`. Fractions finer than a nanosecond, which chrono can't represent, last a nanosecond.
+pub fn parse_period(text: &str) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let timestamp = Timestamp::from_str(text.trim())?;
+    let start = timestamp.instant();
+    let first_of_month = |year: i32, month: u32| {
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .and_then(|date_time| start.offset().from_local_datetime(&date_time).single())
+            .ok_or_else(|| anyhow!("'{}' is not a valid Ion timestamp.", text))
+    };
+    // Ion timestamps' years are at most 9999, so the next year is always a valid date.
+    let end = match timestamp.precision {
+        Precision::Year => first_of_month(start.year() + 1, 1)?,
+        Precision::Month if start.month() == 12 => first_of_month(start.year() + 1, 1)?,
+        Precision::Month => first_of_month(start.year(), start.month() + 1)?,
+        Precision::Day => start + Duration::days(1),
+        Precision::Minute => start + Duration::minutes(1),
+        Precision::Second => {
+            let digits = timestamp.fraction.len().min(NANOSECOND_DIGITS) as u32;
+            start + Duration::nanoseconds(10i64.pow(NANOSECOND_DIGITS as u32 - digits))
+        }
+    };
+    Ok((start, end))
+}
+
+// The most digits of fractional seconds a timestamp may have. Ion sets no limit, but a damaged
+// stream could otherwise ask for an enormous string of zeros.
+const MAX_FRACTION_DIGITS: usize = 4096;
+const NANOSECOND_DIGITS: usize = 9;
+// Offsets are less than a day either way.
+const MAX_OFFSET_MINUTES: usize = 24 * 60 - 1;
+
+// How much of a timestamp was written. Ion timestamps end at one of these fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    Year,
+    Month,
+    Day,
+    Minute,
+    Second,
+}
+
+// A timestamp as Ion models it. Besides the instant, this keeps the precision it was written
+// with, every digit of its fractional seconds, and whether its offset is known, none of which a
+// chrono `DateTime` can hold: `2021T`, `2021-01-01T00:00Z` and `2021-01-01T00:00-00:00` are the
+// same `DateTime`, but they're different Ion values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timestamp {
+    // The date and time at `offset`. Fields beyond the precision are at their minimum, and the
+    // nanoseconds are the first nine digits of `fraction`.
+    local: NaiveDateTime,
+    precision: Precision,
+    // The digits of the fractional seconds, e.g. "250" for `12:30:00.250`. Only timestamps with
+    // second precision have them.
+    fraction: String,
+    // Minutes east of UTC, or `None` for the unknown offset `-00:00`. Timestamps without a time
+    // always have an unknown offset.
+    offset: Option<i32>,
+}
+
+impl Timestamp {
+    // A timestamp with second precision, and as many digits of fractional seconds as the
+    // nanoseconds need, for an instant like the current time.
+    pub fn from_datetime(datetime: DateTime<FixedOffset>) -> Timestamp {
+        let nanosecond = datetime.nanosecond() % 1_000_000_000;
+        let fraction = format!("{:09}", nanosecond).trim_end_matches('0').to_owned();
+        Timestamp {
+            local: datetime.naive_local(),
+            precision: Precision::Second,
+            fraction,
+            offset: Some(datetime.offset().local_minus_utc() / 60),
+        }
+    }
+
+    // Decodes the body of a binary Ion timestamp: its offset, then its fields in UTC as far as its
+    // precision, then its fractional seconds as a decimal.
+    pub fn decode(bytes: &[u8]) -> Result<Timestamp> {
+        let invalid = || anyhow!("Invalid binary timestamp {:02x?}.", bytes);
+        let (offset, negative, mut position) = read_var_int(bytes).ok_or_else(invalid)?;
+        if offset > MAX_OFFSET_MINUTES {
+            return Err(invalid());
+        }
+        let offset = match (offset as i32, negative) {
+            (0, true) => None,
+            (minutes, true) => Some(-minutes),
+            (minutes, false) => Some(minutes),
+        };
+
+        // Year, month, day, hour, minute, and second. The hour and minute come together.
+        let mut fields = Vec::with_capacity(6);
+        while fields.len() < 6 && position < bytes.len() {
+            let (field, length) = read_var_uint(&bytes[position..]).ok_or_else(invalid)?;
+            fields.push(u32::try_from(field).map_err(|_| invalid())?);
+            position += length;
+        }
+        let precision = match fields.len() {
+            1 => Precision::Year,
+            2 => Precision::Month,
+            3 => Precision::Day,
+            5 => Precision::Minute,
+            6 => Precision::Second,
+            _ => return Err(invalid()),
+        };
+        let fraction = match bytes.get(position..).filter(|rest| !rest.is_empty()) {
+            Some(rest) if precision == Precision::Second => fraction_digits(rest)?,
+            Some(_) => return Err(invalid()),
+            None => String::new(),
+        };
+
+        let field = |index: usize, default: u32| fields.get(index).copied().unwrap_or(default);
+        let nanosecond = format!("{:0<9}", &fraction[..fraction.len().min(NANOSECOND_DIGITS)]);
+        let utc = NaiveDate::from_ymd_opt(field(0, 1) as i32, field(1, 1), field(2, 1))
+            .and_then(|date| {
+                date.and_hms_nano_opt(field(3, 0), field(4, 0), field(5, 0), nanosecond.parse().ok()?)
+            })
+            .filter(|utc| (1..=9999).contains(&utc.year()))
+            .ok_or_else(invalid)?;
+        // Only timestamps with a time are stored in UTC.
+        let (local, offset) = if precision >= Precision::Minute {
+            (utc + Duration::minutes(offset.unwrap_or(0) as i64), offset)
+        } else {
+            (utc, None)
+        };
+        Ok(Timestamp { local, precision, fraction, offset })
+    }
+
+    // The instant the timestamp begins at. An unknown offset is taken to be UTC.
+    pub fn instant(&self) -> DateTime<FixedOffset> {
+        let offset = FixedOffset::east_opt(self.offset.unwrap_or(0) * 60).expect("Offsets are less than a day.");
+        offset.from_local_datetime(&self.local).unwrap()
+    }
+
+    // The offset as ISL writes it, e.g. `+00:00`, `-08:00`, or `-00:00` if it's unknown.
+    pub fn offset_text(&self) -> String {
+        match self.offset {
+            None => "-00:00".to_owned(),
+            Some(minutes) => {
+                let sign = if minutes < 0 { '-' } else { '+' };
+                format!("{}{:02}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+            }
+        }
+    }
+}
+
+// Reads the decimal fractional seconds at the end of a binary timestamp, a VarInt exponent and an
+// Int coefficient, and returns their digits, e.g. "050" for 50d-3.
+fn fraction_digits(bytes: &[u8]) -> Result<String> {
+    let invalid = || anyhow!("Invalid fractional seconds {:02x?} in a binary timestamp.", bytes);
+    let (exponent, negative_exponent, length) = read_var_int(bytes).ok_or_else(invalid)?;
+    let coefficient = &bytes[length..];
+    let (sign, magnitude) = match coefficient.split_first() {
+        Some((first, rest)) => (first & 0x80 != 0, [&[first & 0x7F], rest].concat()),
+        None => (false, Vec::new()),
+    };
+    let magnitude = BigUint::from_bytes_be(&magnitude);
+    let zero = magnitude == BigUint::from(0u8);
+    if sign && !zero {
+        return Err(invalid());
+    }
+    // A fraction with no digits after the point, like 0d0, has to be zero.
+    if !negative_exponent || exponent == 0 {
+        return if zero { Ok(String::new()) } else { Err(invalid()) };
+    }
+    let digits = if zero { String::new() } else { magnitude.to_string() };
+    // The fraction must be less than one.
+    if digits.len() > exponent {
+        return Err(invalid());
+    }
+    // Such a timestamp is valid Ion, but more precise than we're prepared to hold.
+    if exponent > MAX_FRACTION_DIGITS {
+        return Err(ConversionError(format!(
+            "A timestamp has {} digits of fractional seconds; at most {} are supported.",
+            exponent, MAX_FRACTION_DIGITS
+        )).into());
+    }
+    Ok(format!("{:0>width$}", digits, width = exponent))
+}
+
+// Parses a text Ion timestamp, keeping its precision, fractional digits, and offset.
+impl FromStr for Timestamp {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Timestamp> {
+        let invalid = || anyhow!("'{}' is not a valid Ion timestamp.", text);
+        let number = |field: &str, length: usize| match field.len() == length && field.chars().all(|c| c.is_ascii_digit()) {
+            true => field.parse::<u32>().map_err(|_| invalid()),
+            false => Err(invalid()),
+        };
+        let (date, time) = text.split_once('T').unwrap_or((text, ""));
+        let date_fields: Vec<&str> = date.split('-').collect();
+        let (year, month, day, precision) = match date_fields.as_slice() {
+            [year] if text.ends_with('T') => (number(year, 4)?, 1, 1, Precision::Year),
+            [year, month] if text.ends_with('T') => (number(year, 4)?, number(month, 2)?, 1, Precision::Month),
+            [year, month, day] => (number(year, 4)?, number(month, 2)?, number(day, 2)?, Precision::Day),
+            _ => return Err(invalid()),
+        };
+        let date = NaiveDate::from_ymd_opt(year as i32, month, day).filter(|_| year > 0).ok_or_else(invalid)?;
+        if time.is_empty() {
+            let local = date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+            return Ok(Timestamp { local, precision, fraction: String::new(), offset: None });
+        }
+
+        // The time always ends with an offset: `Z` or `+hh:mm`/`-hh:mm`.
+        let (clock, offset) = match time.strip_suffix('Z') {
+            Some(clock) => (clock, Some(0)),
+            None => {
+                let index = time.rfind(&['+', '-'][..]).ok_or_else(invalid)?;
+                let (clock, offset) = time.split_at(index);
+                let (hours, minutes) = offset[1..].split_once(':').ok_or_else(invalid)?;
+                let minutes = (number(hours, 2)? * 60 + number(minutes, 2)?) as i32;
+                match (offset.starts_with('-'), minutes) {
+                    (true, 0) => (clock, None),
+                    (true, minutes) => (clock, Some(-minutes)),
+                    (false, minutes) => (clock, Some(minutes)),
+                }
+            }
+        };
+        let clock_fields: Vec<&str> = clock.split(':').collect();
+        let (hour, minute, second, fraction, precision) = match clock_fields.as_slice() {
+            [hour, minute] => (number(hour, 2)?, number(minute, 2)?, 0, "", Precision::Minute),
+            [hour, minute, second] => {
+                let (whole, fraction) = second.split_once('.').unwrap_or((second, ""));
+                if !fraction.chars().all(|c| c.is_ascii_digit()) || (second.contains('.') && fraction.is_empty()) {
+                    return Err(invalid());
+                }
+                (number(hour, 2)?, number(minute, 2)?, number(whole, 2)?, fraction, Precision::Second)
+            }
+            _ => return Err(invalid()),
+        };
+        if offset.is_some_and(|minutes: i32| minutes.unsigned_abs() as usize > MAX_OFFSET_MINUTES) {
+            return Err(invalid());
+        }
+        let nanosecond = format!("{:0<9}", &fraction[..fraction.len().min(NANOSECOND_DIGITS)]);
+        let local = date
+            .and_hms_nano_opt(hour, minute, second, nanosecond.parse().map_err(|_| invalid())?)
+            .ok_or_else(invalid)?;
+        Ok(Timestamp { local, precision, fraction: fraction.to_owned(), offset })
+    }
+}
+
+// Formats the timestamp as text Ion, to exactly its precision.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = &self.local;
+        match self.precision {
+            Precision::Year => return write!(f, "{:04}T", local.year()),
+            Precision::Month => return write!(f, "{:04}-{:02}T", local.year(), local.month()),
+            Precision::Day => return write!(f, "{}", local.format("%Y-%m-%dT")),
+            Precision::Minute => write!(f, "{}", local.format("%Y-%m-%dT%H:%M"))?,
+            Precision::Second => write!(f, "{}", local.format("%Y-%m-%dT%H:%M:%S"))?,
+        }
+        if !self.fraction.is_empty() {
+            write!(f, ".{}", self.fraction)?;
+        }
+        match self.offset {
+            Some(0) => write!(f, "Z"),
+            _ => write!(f, "{}", self.offset_text()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instant(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    fn period(text: &str) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+        parse_period(text).unwrap()
+    }
+
+    #[test]
+    fn dates_are_periods_in_utc() {
+        assert_eq!(period("2021T"), (instant("2021-01-01T00:00:00Z"), instant("2022-01-01T00:00:00Z")));
+        assert_eq!(period("2021-06T"), (instant("2021-06-01T00:00:00Z"), instant("2021-07-01T00:00:00Z")));
+        assert_eq!(period("2021-12T"), (instant("2021-12-01T00:00:00Z"), instant("2022-01-01T00:00:00Z")));
+        assert_eq!(period("2021-06-15"), (instant("2021-06-15T00:00:00Z"), instant("2021-06-16T00:00:00Z")));
+        assert_eq!(period(" 2021-06-15T "), period("2021-06-15"));
+    }
+
+    #[test]
+    fn leap_days_are_only_valid_in_leap_years() {
+        assert_eq!(period("2020-02-29"), (instant("2020-02-29T00:00:00Z"), instant("2020-03-01T00:00:00Z")));
+        assert!(parse_period("2021-02-29").is_err());
+        assert!(parse_period("1900-02-29").is_err());
+        assert!(parse_period("2000-02-29").is_ok());
+    }
+
+    #[test]
+    fn times_last_one_unit_of_their_precision() {
+        assert_eq!(period("2021-06-01T12:30Z"), (instant("2021-06-01T12:30:00Z"), instant("2021-06-01T12:31:00Z")));
+        assert_eq!(period("2021-06-01T12:30:59Z"), (instant("2021-06-01T12:30:59Z"), instant("2021-06-01T12:31:00Z")));
+        assert_eq!(
+            period("2021-06-01T12:30:00.250-07:00"),
+            (instant("2021-06-01T19:30:00.250Z"), instant("2021-06-01T19:30:00.251Z"))
+        );
+        assert_eq!(
+            period("2021-06-01T23:59:59.999999999Z"),
+            (instant("2021-06-01T23:59:59.999999999Z"), instant("2021-06-02T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn trailing_zeros_in_the_fraction_are_precision() {
+        let (start, end) = period("2021-06-01T12:30:00.0Z");
+        assert_eq!(end - start, Duration::milliseconds(100));
+        let (start, end) = period("2021-06-01T12:30:00.000000000Z");
+        assert_eq!(end - start, Duration::nanoseconds(1));
+        let (start, end) = period("2021-06-01T12:30:00.1234567890Z");
+        assert_eq!((start, end - start), (instant("2021-06-01T12:30:00.123456789Z"), Duration::nanoseconds(1)));
+    }
+
+    #[test]
+    fn the_last_year_ends_after_9999() {
+        assert!(parse_period("9999T").is_ok());
+        assert!(parse_period("9999-12T").is_ok());
+        assert_eq!(period("9999-12-31T23:59Z").1, period("9999T").1);
+    }
+
+    #[test]
+    fn offsets_move_the_instant() {
+        assert_eq!(period("2021-06-01T00:00+05:30").0, instant("2021-05-31T18:30:00Z"));
+        assert_eq!(period("2021-06-01T00:00-00:00"), period("2021-06-01T00:00Z"));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        let invalid = [
+            "",
+            "T",
+            "20x1T",
+            "2021-1a-01",
+            "2021-13T",
+            "2021-06-31",
+            "2021-06-01T12:30",
+            "2021-06-01T12Z",
+            "2021-06-01T24:00Z",
+            "2021-06-01T12:60Z",
+            "2021-06-01T12:30:00.Z1",
+            "2021-06-01T12:30+24:00",
+            "2021-06-01T12:30+05",
+            "2021-06T12:30Z",
+            // Years and months need a `T`, and each field has a fixed number of digits.
+            "2021",
+            "2021-06",
+            "21-06-01",
+            "2021-6-1",
+            "0000T",
+            "10000T",
+            "99999999999-01-01",
+        ];
+        for text in invalid.iter() {
+            assert!(parse_period(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+use flate2::read::GzDecoder;
+
+use crate::nested::{read_document, NESTED_DOCUMENT_ANNOTATION};
+use crate::path::Path;
+use crate::value::{Data, Symbol, Value};
+
+// A sequence of decoding steps applied to the values at a path. Transforms are written as
+// `path : step,step,...`, e.g. `(payload) : base64,gunzip,ion`.
+#[derive(Debug, Clone)]
+pub struct Transform {
+    path: Path,
+    steps: Vec<TransformStep>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformStep {
+    // Decodes base64 text (a string, symbol, or lob) to a blob.
+    Base64,
+    // Decompresses gzipped bytes (a lob) to a blob.
+    Gunzip,
+    // Decodes UTF-8 bytes (a lob) to a string.
+    Utf8,
+    // Decodes a text or binary Ion document (a lob or string) to a `nested_ion::[...]` list.
+    Ion,
+}
+
+impl Transform {
+    // Applies each of the transform's steps, in order, to every value selected by its path.
+    // Null values are left as they are.
+    pub fn apply(&self, value: &mut Value) -> Result<()> {
+        let path = &self.path;
+        path.for_each_mut(value, &mut |selected| {
+            for step in &self.steps {
+                step.apply(selected)
+                    .with_context(|| format!("Transform step '{}' failed at path {}", step, path))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl TransformStep {
+    fn apply(&self, value: &mut Value) -> Result<()> {
+        if value.is_null() {
+            return Ok(());
+        }
+        let data = match (self, &value.data) {
+            (TransformStep::Base64, Data::String(_)) | (TransformStep::Base64, Data::Symbol(_)) => {
+                // Symbols without known text can't be decoded.
+                match value.as_text() {
+                    Some(text) => Data::Blob(decode_base64(text.as_bytes())?),
+                    None => bail!("Cannot apply 'base64' to a symbol with unknown text"),
+                }
+            }
+            (TransformStep::Base64, Data::Blob(bytes)) | (TransformStep::Base64, Data::Clob(bytes)) => {
+                Data::Blob(decode_base64(bytes)?)
+            }
+            (TransformStep::Gunzip, Data::Blob(bytes)) | (TransformStep::Gunzip, Data::Clob(bytes)) => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(&bytes[..])
+                    .read_to_end(&mut decompressed)
+                    .with_context(|| "Could not decompress gzipped data")?;
+                Data::Blob(decompressed)
+            }
+            (TransformStep::Utf8, Data::Blob(bytes)) | (TransformStep::Utf8, Data::Clob(bytes)) => {
+                Data::String(String::from_utf8(bytes.clone())
+                    .with_context(|| "Data is not valid UTF-8")?)
+            }
+            (TransformStep::Ion, Data::Blob(bytes)) | (TransformStep::Ion, Data::Clob(bytes)) => {
+                Data::List(read_document(bytes)?)
+            }
+            (TransformStep::Ion, Data::String(text)) => {
+                Data::List(read_document(text.as_bytes())?)
+            }
+            _ => bail!("Cannot apply '{}' to a value of type {:?}", self, value.ion_type()),
+        };
+        if *self == TransformStep::Ion {
+            value.annotations.push(Symbol::from(NESTED_DOCUMENT_ANNOTATION));
+        }
+        // Annotations on the original value are preserved.
+        value.data = data;
+        Ok(())
+    }
+}
+
+fn decode_base64(text: &[u8]) -> Result<Vec<u8>> {
+    // Base64 text frequently has line breaks or other padding whitespace in it.
+    let text: Vec<u8> = text.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    base64::decode(&text).with_context(|| "Could not decode base64 text")
+}
+
+impl FromStr for Transform {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Transform> {
+        // Step names never contain a colon, but quoted field names in the path might.
+        let split = match text.rfind(':') {
+            Some(index) => index,
+            None => bail!("Transform '{}' must have the form 'path : step,step,...'", text),
+        };
+        let path = Path::from_str(&text[..split])?;
+        let steps = text[split + 1..]
+            .split(',')
+            .map(TransformStep::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Transform { path, steps })
+    }
+}
+
+impl FromStr for TransformStep {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<TransformStep> {
+        let step = match text.trim() {
+            "base64" => TransformStep::Base64,
+            "gunzip" => TransformStep::Gunzip,
+            "utf8" => TransformStep::Utf8,
+            "ion" => TransformStep::Ion,
+            other => bail!("Unknown transform step '{}'. Valid steps are base64, gunzip, utf8, and ion.", other),
+        };
+        Ok(step)
+    }
+}
+
+impl fmt::Display for TransformStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TransformStep::Base64 => "base64",
+            TransformStep::Gunzip => "gunzip",
+            TransformStep::Utf8 => "utf8",
+            TransformStep::Ion => "ion",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,783 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use anyhow::{bail, Context, Result};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use ion_rs::IonType;
+use regex::RegexBuilder;
+
+use crate::path::{Path, Step};
+use crate::schema::{as_reference, has_annotation, ion_text, is_built_in, Authority, TypeReference};
+use crate::value::{Data, Symbol, Value};
+
+// Checks values against the types defined in ISL 1.0 schemas. Most constraints are supported;
+// a schema that uses one that isn't, like `timestamp_precision`, is rejected rather than
+// partially checked.
+
+// A way in which a value doesn't match a type.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    // Where the offending value is within the value that was validated.
+    pub path: Path,
+    pub message: String,
+    // The constraint that found it, like `fields`, if it was found by one.
+    pub constraint: Option<String>,
+    // Whether the violation has been added to the trace.
+    traced: bool,
+}
+
+impl Violation {
+    pub fn new(path: Path, message: String) -> Violation {
+        Violation { path, message, constraint: None, traced: false }
+    }
+}
+
+// A record of each type and constraint checked, in order, for explaining why a value does or
+// doesn't match a type. Lines are indented by how deeply they're nested.
+#[derive(Default)]
+struct Trace {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+pub struct Validator {
+    authority: Authority,
+    // Type definitions that have already been looked up, by schema ID and type name.
+    definitions: HashMap<(String, String), Value>,
+    // Only kept while explaining a value.
+    trace: Option<Trace>,
+}
+
+impl Validator {
+    pub fn new(authority: Authority) -> Validator {
+        Validator { authority, definitions: HashMap::new(), trace: None }
+    }
+
+    // Checks a value against the type named `type_name` in the schema `schema_id`, which may be
+    // one of the schema's own types, one it imports, or a built-in type. Returns every violation
+    // found; fails if the schema can't be read or uses constraints that aren't supported.
+    pub fn validate(&mut self, value: &Value, schema_id: &str, type_name: &str) -> Result<Vec<Violation>> {
+        let reference = Value::new(Data::Symbol(Symbol::from(type_name)));
+        let mut violations = Vec::new();
+        self.check_type(schema_id, &reference, value, &mut Vec::new(), &mut violations)?;
+        Ok(violations)
+    }
+
+    // Whether `name` is a built-in type or one that the schema `schema_id` defines or imports.
+    pub fn has_type(&mut self, schema_id: &str, name: &str) -> Result<bool> {
+        Ok(is_built_in(name) || self.authority.resolve(schema_id, name)?.is_some())
+    }
+
+    // Checks a value like `validate`, also returning a trace of every type and constraint that
+    // was checked, in the order they were checked, with whether each passed, which violations
+    // each found, and where checking stopped early.
+    pub fn explain(&mut self, value: &Value, schema_id: &str, type_name: &str) -> Result<(Vec<Violation>, Vec<String>)> {
+        self.trace = Some(Trace::default());
+        let violations = self.validate(value, schema_id, type_name);
+        let trace = self.trace.take().unwrap_or_default();
+        Ok((violations?, trace.lines))
+    }
+
+    // Adds a line to the trace, if there is one, and indents the lines after it until the
+    // matching `trace_leave`. Returns the line's index so that its outcome can be added to it.
+    fn trace_enter(&mut self, line: impl FnOnce() -> String) -> usize {
+        match &mut self.trace {
+            Some(trace) => {
+                trace.lines.push(format!("{}{}", "  ".repeat(trace.depth), line()));
+                trace.depth += 1;
+                trace.lines.len() - 1
+            }
+            None => 0,
+        }
+    }
+
+    fn trace_note(&mut self, line: impl FnOnce() -> String) {
+        if let Some(trace) = &mut self.trace {
+            trace.lines.push(format!("{}{}", "  ".repeat(trace.depth), line()));
+        }
+    }
+
+    // Ends the lines begun by `trace_enter`, adding the violations found since `before` that
+    // nothing nested already traced, and marking the entered line with whether any were found.
+    fn trace_leave(&mut self, line: usize, violations: &mut [Violation], before: usize) {
+        let trace = match &mut self.trace {
+            Some(trace) => trace,
+            None => return,
+        };
+        let indent = "  ".repeat(trace.depth);
+        for violation in violations[before..].iter_mut().filter(|violation| !violation.traced) {
+            trace.lines.push(format!("{}violation at {}: {}", indent, violation.path, violation.message));
+            violation.traced = true;
+        }
+        trace.depth -= 1;
+        trace.lines[line].push_str(if violations.len() > before { " -> failed" } else { " -> ok" });
+    }
+
+    // Checks a value against a type reference or inline type that appears in schema `schema_id`.
+    fn check_type(
+        &mut self,
+        schema_id: &str,
+        reference: &Value,
+        value: &Value,
+        path: &mut Vec<Step>,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let line = self.trace_enter(|| {
+            if path.is_empty() {
+                format!("type {}", brief(reference))
+            } else {
+                format!("type {} at {}", brief(reference), Path::from(path.to_vec()))
+            }
+        });
+        let before = violations.len();
+        let result = self.check_reference(schema_id, reference, value, path, violations);
+        self.trace_leave(line, violations, before);
+        result
+    }
+
+    fn check_reference(
+        &mut self,
+        schema_id: &str,
+        reference: &Value,
+        value: &Value,
+        path: &mut Vec<Step>,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        if value.is_null() && has_annotation(reference, "nullable") {
+            self.trace_note(|| "the value is null and the type is nullable, so its constraints aren't checked".to_owned());
+            return Ok(());
+        }
+        match as_reference(reference) {
+            Some(TypeReference::Named(name)) if is_built_in(&name) => {
+                if !matches_built_in(&name, value)? {
+                    violation(violations, path, format!("expected {}, found {}", name, describe(value)));
+                }
+                Ok(())
+            }
+            Some(TypeReference::Named(name)) => {
+                let (id, name) = self.authority.resolve(schema_id, &name)?
+                    .with_context(|| format!("Schema '{}' refers to type '{}', which it doesn't define or import.", schema_id, name))?;
+                let definition = self.definition(&id, &name)?;
+                self.check_constraints(&id, &definition, value, path, violations)
+            }
+            Some(TypeReference::Imported { id, type_name }) => {
+                let (id, name) = self.authority.resolve(&id, &type_name)?
+                    .with_context(|| format!("Schema '{}' doesn't define or import type '{}'.", id, type_name))?;
+                let definition = self.definition(&id, &name)?;
+                self.check_constraints(&id, &definition, value, path, violations)
+            }
+            None => self.check_constraints(schema_id, reference, value, path, violations),
+        }
+    }
+
+    // Whether a value matches a type without any violations.
+    fn is_valid(&mut self, schema_id: &str, reference: &Value, value: &Value) -> Result<bool> {
+        let mut violations = Vec::new();
+        self.check_type(schema_id, reference, value, &mut Vec::new(), &mut violations)?;
+        Ok(violations.is_empty())
+    }
+
+    fn definition(&mut self, id: &str, name: &str) -> Result<Value> {
+        let key = (id.to_owned(), name.to_owned());
+        if let Some(definition) = self.definitions.get(&key) {
+            return Ok(definition.clone());
+        }
+        let definition = match self.authority.load(id)?.type_named(name) {
+            Some(definition) => definition.definition.clone(),
+            None => bail!("Schema '{}' doesn't define type '{}'.", id, name),
+        };
+        self.definitions.insert(key, definition.clone());
+        Ok(definition)
+    }
+
+    // Checks a value against each constraint of a type definition or inline type.
+    fn check_constraints(
+        &mut self,
+        schema_id: &str,
+        definition: &Value,
+        value: &Value,
+        path: &mut Vec<Step>,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let constraints = match &definition.data {
+            Data::Struct(constraints) => constraints,
+            _ => bail!("Schema '{}' has a type that isn't a struct: {}", schema_id, ion_text(definition)),
+        };
+        // A type without a `type` constraint only matches values that aren't null.
+        if definition.get("type").is_none() && value.is_null() {
+            violation(violations, path, format!("expected a value, found {}", describe(value)));
+            self.trace_note(|| "the type has no `type` constraint, so it doesn't match nulls; its constraints aren't checked".to_owned());
+            return Ok(());
+        }
+        for (name, constraint) in constraints {
+            let name = name.text().unwrap_or_default();
+            // `occurs` is read by the `fields` or `ordered_elements` constraint that holds the type.
+            if name == "name" || name == "occurs" {
+                continue;
+            }
+            let line = self.trace_enter(|| format!("{}: {}", name, brief(constraint)));
+            let before = violations.len();
+            match name {
+                "type" => self.check_type(schema_id, constraint, value, path, violations)?,
+                "fields" => self.check_fields(schema_id, definition, constraint, value, path, violations)?,
+                // Checked along with `fields`.
+                "content" => {
+                    if constraint.as_text() != Some("closed") || definition.get("fields").is_none() {
+                        bail!("Schema '{}' has a `content` constraint other than `content: closed` with `fields`.", schema_id);
+                    }
+                }
+                "element" => self.check_elements(schema_id, constraint, value, path, violations)?,
+                "ordered_elements" => self.check_ordered_elements(schema_id, constraint, value, path, violations)?,
+                "any_of" | "one_of" => {
+                    let mut matched = 0;
+                    for alternative in type_list(schema_id, name, constraint)? {
+                        if self.is_valid(schema_id, alternative, value)? {
+                            matched += 1;
+                        }
+                    }
+                    if name == "any_of" && matched == 0 {
+                        violation(violations, path, "matches none of the types in any_of".to_owned());
+                    } else if name == "one_of" && matched != 1 {
+                        violation(violations, path, format!("matches {} of the types in one_of, not exactly one", matched));
+                    }
+                }
+                "all_of" => {
+                    for each in type_list(schema_id, name, constraint)? {
+                        self.check_type(schema_id, each, value, path, violations)?;
+                    }
+                }
+                "not" => {
+                    if self.is_valid(schema_id, constraint, value)? {
+                        violation(violations, path, format!("matches {}, which `not` excludes", ion_text(constraint)));
+                    }
+                }
+                "valid_values" => {
+                    let valid = match &constraint.data {
+                        Data::List(candidates) if !has_annotation(constraint, "range") => {
+                            let mut valid = false;
+                            for candidate in candidates {
+                                valid |= is_valid_value(schema_id, candidate, value)?;
+                            }
+                            valid
+                        }
+                        _ => is_valid_value(schema_id, constraint, value)?,
+                    };
+                    if !valid {
+                        violation(violations, path, format!("{} is not one of the valid_values {}", ion_text(value), ion_text(constraint)));
+                    }
+                }
+                "annotations" => check_annotations(schema_id, constraint, value, path, violations)?,
+                "regex" => check_regex(schema_id, constraint, value, path, violations)?,
+                "contains" => {
+                    let expected = match &constraint.data {
+                        Data::List(expected) => expected,
+                        _ => bail!("Schema '{}' has a `contains` constraint that isn't a list.", schema_id),
+                    };
+                    match children(value) {
+                        Some(children) => {
+                            for wanted in expected {
+                                if !children.iter().any(|(_, child)| child.data == wanted.data) {
+                                    violation(violations, path, format!("doesn't contain {}", ion_text(wanted)));
+                                }
+                            }
+                        }
+                        None => violation(violations, path, format!("expected a container for `contains`, found {}", describe(value))),
+                    }
+                }
+                "timestamp_offset" => {
+                    let offsets = match &constraint.data {
+                        Data::List(offsets) => offsets.iter().filter_map(Value::as_text).collect::<Vec<_>>(),
+                        _ => bail!("Schema '{}' has a `timestamp_offset` constraint that isn't a list.", schema_id),
+                    };
+                    match &value.data {
+                        Data::Timestamp(timestamp) => {
+                            let offset = timestamp.offset_text();
+                            if !offsets.contains(&offset.as_str()) {
+                                violation(violations, path, format!("has offset {}, which isn't one of {}", offset, ion_text(constraint)));
+                            }
+                        }
+                        _ => violation(violations, path, format!("expected a timestamp, found {}", describe(value))),
+                    }
+                }
+                "container_length" => {
+                    let length = children(value).map(|children| children.len());
+                    check_length(schema_id, name, constraint, length, "a container", value, path, violations)?;
+                }
+                "codepoint_length" => {
+                    let length = value.as_text().map(|text| text.chars().count());
+                    check_length(schema_id, name, constraint, length, "text", value, path, violations)?;
+                }
+                "utf8_byte_length" => {
+                    let length = value.as_text().map(str::len);
+                    check_length(schema_id, name, constraint, length, "text", value, path, violations)?;
+                }
+                "byte_length" => {
+                    let length = value.as_lob().map(<[u8]>::len);
+                    check_length(schema_id, name, constraint, length, "a lob", value, path, violations)?;
+                }
+                "precision" | "scale" => {
+                    let measured = match &value.data {
+                        Data::Decimal(decimal) => {
+                            let (digits, scale) = decimal.as_bigint_and_exponent();
+                            if name == "scale" {
+                                usize::try_from(scale).ok()
+                            } else {
+                                Some(digits.to_string().trim_start_matches('-').len())
+                            }
+                        }
+                        _ => None,
+                    };
+                    check_length(schema_id, name, constraint, measured, "a decimal", value, path, violations)?;
+                }
+                _ => bail!("Schema '{}' uses the `{}` constraint, which isn't supported.", schema_id, name),
+            }
+            // Violations found by a constraint of a nested type already name that constraint.
+            for violation in violations[before..].iter_mut().filter(|violation| violation.constraint.is_none()) {
+                violation.constraint = Some(name.to_owned());
+            }
+            self.trace_leave(line, violations, before);
+        }
+        Ok(())
+    }
+
+    fn check_fields(
+        &mut self,
+        schema_id: &str,
+        definition: &Value,
+        constraint: &Value,
+        value: &Value,
+        path: &mut Vec<Step>,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let declared = match &constraint.data {
+            Data::Struct(declared) => declared,
+            _ => bail!("Schema '{}' has a `fields` constraint that isn't a struct.", schema_id),
+        };
+        let actual = match &value.data {
+            Data::Struct(actual) => actual,
+            _ => {
+                violation(violations, path, format!("expected a struct, found {}", describe(value)));
+                return Ok(());
+            }
+        };
+        for (field_name, field_type) in declared {
+            let field_name = field_name.text().unwrap_or_default();
+            let occurrences: Vec<&Value> = actual
+                .iter()
+                .filter(|(name, _)| name == field_name)
+                .map(|(_, value)| value)
+                .collect();
+            let (min, max) = occurs(schema_id, field_type, (0, 1))?;
+            if occurrences.len() < min {
+                violation(violations, path, format!("missing required field '{}'", field_name));
+            } else if occurrences.len() > max {
+                violation(violations, path, format!("has {} '{}' fields; at most {} are allowed", occurrences.len(), field_name, max));
+            }
+            path.push(Step::Field(field_name.to_owned()));
+            for occurrence in occurrences {
+                self.check_type(schema_id, field_type, occurrence, path, violations)?;
+            }
+            path.pop();
+        }
+        if definition.get("content").is_some() {
+            for (name, _) in actual {
+                if !declared.iter().any(|(declared_name, _)| declared_name == name) {
+                    let name = name.text().unwrap_or("$0");
+                    violation(violations, path, format!("has field '{}', which a closed type doesn't declare", name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_elements(
+        &mut self,
+        schema_id: &str,
+        constraint: &Value,
+        value: &Value,
+        path: &mut Vec<Step>,
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let children = match children(value) {
+            Some(children) => children,
+            None => {
+                violation(violations, path, format!("expected a container, found {}", describe(value)));
+                return Ok(());
+            }
+        };
+        for (step, child) in children {
+            path.push(step);
+            self.check_type(schema_id, constraint, child, path, violations)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn check_ordered_elements(
+        &mut self,
+        schema_id: &str,
+        constraint: &Value,
+        value: &Value,
+        path: &[Step],
+        violations: &mut Vec<Violation>,
+    ) -> Result<()> {
+        let elements = match &value.data {
+            Data::List(elements) | Data::SExpression(elements) => elements,
+            _ => {
+                violation(violations, path, format!("expected a list or sexp, found {}", describe(value)));
+                return Ok(());
+            }
+        };
+        let mut items = Vec::new();
+        for item in type_list(schema_id, "ordered_elements", constraint)? {
+            let (min, max) = occurs(schema_id, item, (1, 1))?;
+            items.push((item, min, max));
+        }
+        let mut matcher = OrderedMatcher { items: &items, elements, matches: HashMap::new(), failures: HashSet::new() };
+        if !matcher.matches(self, schema_id, 0, 0, 0)? {
+            violation(violations, path, format!("its {} elements don't match the sequence of types in ordered_elements", elements.len()));
+        }
+        Ok(())
+    }
+}
+
+// Matches a sequence of elements against `ordered_elements`, where each type may match a range
+// of consecutive elements. Tries each possible split, remembering which have already failed.
+struct OrderedMatcher<'a> {
+    // Each type, with the fewest and most elements it may match.
+    items: &'a [(&'a Value, usize, usize)],
+    elements: &'a [Value],
+    // Whether each element matches each type, by element and type index.
+    matches: HashMap<(usize, usize), bool>,
+    // Positions, by type index, element index, and elements matched so far, known not to match.
+    failures: HashSet<(usize, usize, usize)>,
+}
+
+impl<'a> OrderedMatcher<'a> {
+    fn matches(&mut self, validator: &mut Validator, schema_id: &str, item: usize, element: usize, count: usize) -> Result<bool> {
+        if item == self.items.len() {
+            return Ok(element == self.elements.len());
+        }
+        if self.failures.contains(&(item, element, count)) {
+            validator.trace_note(|| {
+                format!("the elements from {} on already failed to match from {}, so they aren't checked again", element, brief(self.items[item].0))
+            });
+            return Ok(false);
+        }
+        let (item_type, min, max) = self.items[item];
+        if count >= min && self.matches(validator, schema_id, item + 1, element, 0)? {
+            return Ok(true);
+        }
+        if element < self.elements.len() && count < max {
+            let element_matches = match self.matches.get(&(element, item)) {
+                Some(matches) => {
+                    validator.trace_note(|| {
+                        let outcome = if *matches { "matches" } else { "doesn't match" };
+                        format!("element {} {} {}, as already checked", element, outcome, brief(item_type))
+                    });
+                    *matches
+                }
+                None => {
+                    let matches = validator.is_valid(schema_id, item_type, &self.elements[element])?;
+                    self.matches.insert((element, item), matches);
+                    matches
+                }
+            };
+            if element_matches && self.matches(validator, schema_id, item, element + 1, count + 1)? {
+                return Ok(true);
+            }
+        }
+        self.failures.insert((item, element, count));
+        Ok(false)
+    }
+}
+
+fn violation(violations: &mut Vec<Violation>, path: &[Step], message: String) {
+    violations.push(Violation::new(Path::from(path.to_vec()), message));
+}
+
+// A value's text, shortened to fit on a line of a trace.
+fn brief(value: &Value) -> String {
+    let text = ion_text(value);
+    if text.chars().count() <= 60 {
+        return text;
+    }
+    let mut brief: String = text.chars().take(57).collect();
+    brief.push_str("...");
+    brief
+}
+
+// Whether a value matches one of the built-in types. Types whose names begin with `$`, like
+// `$int`, also match nulls of that type.
+fn matches_built_in(name: &str, value: &Value) -> Result<bool> {
+    let (base, allows_null) = match name.strip_prefix('$') {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+    if value.is_null() && !allows_null {
+        return Ok(false);
+    }
+    let ion_type = value.ion_type();
+    let matches = match base {
+        "any" => true,
+        "nothing" => false,
+        "null" => value.data == Data::Null(IonType::Null),
+        "number" => matches!(ion_type, IonType::Integer | IonType::Float | IonType::Decimal),
+        "text" => matches!(ion_type, IonType::String | IonType::Symbol),
+        "lob" => matches!(ion_type, IonType::Blob | IonType::Clob),
+        "document" => bail!("The built-in type `document` isn't supported."),
+        _ => type_name(ion_type) == base,
+    };
+    Ok(matches)
+}
+
+// The ISL name of an Ion type.
+pub fn type_name(ion_type: IonType) -> &'static str {
+    match ion_type {
+        IonType::Null => "null",
+        IonType::Boolean => "bool",
+        IonType::Integer => "int",
+        IonType::Float => "float",
+        IonType::Decimal => "decimal",
+        IonType::Timestamp => "timestamp",
+        IonType::Symbol => "symbol",
+        IonType::String => "string",
+        IonType::Clob => "clob",
+        IonType::Blob => "blob",
+        IonType::List => "list",
+        IonType::SExpression => "sexp",
+        IonType::Struct => "struct",
+    }
+}
+
+// Describes a value's type for a violation, like `a string` or `null.int`.
+fn describe(value: &Value) -> String {
+    match value.ion_type() {
+        ion_type if value.is_null() => format!("null.{}", type_name(ion_type)),
+        IonType::Integer => "an int".to_owned(),
+        ion_type => format!("a {}", type_name(ion_type)),
+    }
+}
+
+// The children of a container, with the step that selects each of them.
+fn children(value: &Value) -> Option<Vec<(Step, &Value)>> {
+    match &value.data {
+        Data::List(elements) | Data::SExpression(elements) => {
+            Some(elements.iter().enumerate().map(|(index, element)| (Step::Index(index), element)).collect())
+        }
+        Data::Struct(fields) => Some(
+            fields
+                .iter()
+                .map(|(name, value)| (Step::Field(name.text().unwrap_or("$0").to_owned()), value))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn type_list<'v>(schema_id: &str, constraint_name: &str, constraint: &'v Value) -> Result<&'v [Value]> {
+    match &constraint.data {
+        Data::List(types) => Ok(types),
+        _ => bail!("Schema '{}' has an `{}` constraint that isn't a list of types.", schema_id, constraint_name),
+    }
+}
+
+// Reads the `occurs` of a field or ordered element's type: `optional`, `required`, a number,
+// or a range. Returns the fewest and most occurrences allowed.
+pub fn occurs(schema_id: &str, item_type: &Value, default: (usize, usize)) -> Result<(usize, usize)> {
+    let occurs = match item_type.get("occurs") {
+        Some(occurs) => occurs,
+        None => return Ok(default),
+    };
+    let invalid = || format!("Schema '{}' has an invalid `occurs`: {}", schema_id, ion_text(occurs));
+    match &occurs.data {
+        Data::Symbol(symbol) if symbol == "optional" => Ok((0, 1)),
+        Data::Symbol(symbol) if symbol == "required" => Ok((1, 1)),
+        Data::Integer(count) => {
+            let count = usize::try_from(*count).ok().with_context(invalid)?;
+            Ok((count, count))
+        }
+        Data::List(_) if has_annotation(occurs, "range") => {
+            let range = Range::from_value(schema_id, occurs)?;
+            let bound = |bound: &Bound, unbounded: usize, adjust: i64| match bound {
+                Bound::Unbounded => Some(unbounded),
+                Bound::Inclusive(value) => integer(value).and_then(|n| usize::try_from(n).ok()),
+                Bound::Exclusive(value) => integer(value).and_then(|n| usize::try_from(n + adjust).ok()),
+            };
+            let min = bound(&range.lower, 0, 1).with_context(invalid)?;
+            let max = bound(&range.upper, usize::MAX, -1).with_context(invalid)?;
+            Ok((min, max))
+        }
+        _ => bail!(invalid()),
+    }
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match &value.data {
+        Data::Integer(n) => Some(*n),
+        _ => None,
+    }
+}
+
+// Checks a measurement, like a string's length, against a constraint that's either an exact
+// number or a range. `measured` is `None` if the value is the wrong type to be measured.
+#[allow(clippy::too_many_arguments)]
+fn check_length(
+    schema_id: &str,
+    name: &str,
+    constraint: &Value,
+    measured: Option<usize>,
+    expected: &str,
+    value: &Value,
+    path: &[Step],
+    violations: &mut Vec<Violation>,
+) -> Result<()> {
+    let measured = match measured {
+        Some(measured) if !value.is_null() => measured,
+        _ => {
+            violation(violations, path, format!("expected {} for `{}`, found {}", expected, name, describe(value)));
+            return Ok(());
+        }
+    };
+    let allowed = match &constraint.data {
+        Data::Integer(exact) => usize::try_from(*exact) == Ok(measured),
+        Data::List(_) if has_annotation(constraint, "range") => {
+            Range::from_value(schema_id, constraint)?.contains(&Value::new(Data::Integer(measured as i64)))
+        }
+        _ => bail!("Schema '{}' has an invalid `{}`: {}", schema_id, name, ion_text(constraint)),
+    };
+    if !allowed {
+        violation(violations, path, format!("has {} {}, but {} is required", name, measured, ion_text(constraint)));
+    }
+    Ok(())
+}
+
+// Whether a value is one of `valid_values`: either equal to it, ignoring annotations, or within
+// it if it's a range.
+fn is_valid_value(schema_id: &str, candidate: &Value, value: &Value) -> Result<bool> {
+    if has_annotation(candidate, "range") {
+        return Ok(Range::from_value(schema_id, candidate)?.contains(value));
+    }
+    Ok(candidate.data == value.data)
+}
+
+fn check_annotations(schema_id: &str, constraint: &Value, value: &Value, path: &[Step], violations: &mut Vec<Violation>) -> Result<()> {
+    let listed = match &constraint.data {
+        Data::List(listed) => listed,
+        _ => bail!("Schema '{}' has an `annotations` constraint that isn't a list.", schema_id),
+    };
+    let all_required = has_annotation(constraint, "required");
+    let mut positions = Vec::new();
+    for annotation in listed {
+        let text = annotation.as_text().unwrap_or_default();
+        let required = has_annotation(annotation, "required") || (all_required && !has_annotation(annotation, "optional"));
+        match value.annotations.iter().position(|a| a == text) {
+            Some(position) => positions.push(position),
+            None if required => violation(violations, path, format!("missing required annotation '{}'", text)),
+            None => {}
+        }
+    }
+    if has_annotation(constraint, "ordered") && positions.windows(2).any(|pair| pair[0] > pair[1]) {
+        violation(violations, path, format!("annotations aren't in the order {}", ion_text(constraint)));
+    }
+    if has_annotation(constraint, "closed") {
+        for annotation in &value.annotations {
+            let text = annotation.text().unwrap_or("$0");
+            if !listed.iter().any(|listed| listed.as_text() == Some(text)) {
+                violation(violations, path, format!("has annotation '{}', which isn't allowed", text));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_regex(schema_id: &str, constraint: &Value, value: &Value, path: &[Step], violations: &mut Vec<Violation>) -> Result<()> {
+    let pattern = match &constraint.data {
+        Data::String(pattern) => pattern,
+        _ => bail!("Schema '{}' has a `regex` constraint that isn't a string.", schema_id),
+    };
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(has_annotation(constraint, "i"))
+        .multi_line(has_annotation(constraint, "m"))
+        .build()
+        .with_context(|| format!("Schema '{}' has an invalid regex: {}", schema_id, pattern))?;
+    match value.as_text() {
+        Some(text) if !value.is_null() => {
+            if !regex.is_match(text) {
+                violation(violations, path, format!("{} doesn't match the regex {}", ion_text(value), ion_text(constraint)));
+            }
+        }
+        _ => violation(violations, path, format!("expected text for `regex`, found {}", describe(value))),
+    }
+    Ok(())
+}
+
+pub enum Bound {
+    Unbounded,
+    Inclusive(Value),
+    Exclusive(Value),
+}
+
+// An ISL range, like `range::[1, max]` or `range::[exclusive::0.0, 1.0]`, of numbers or
+// timestamps.
+pub struct Range {
+    pub lower: Bound,
+    pub upper: Bound,
+}
+
+impl Range {
+    pub fn from_value(schema_id: &str, range: &Value) -> Result<Range> {
+        let bounds = match &range.data {
+            Data::List(bounds) if bounds.len() == 2 => bounds,
+            _ => bail!("Schema '{}' has an invalid range: {}", schema_id, ion_text(range)),
+        };
+        let bound = |value: &Value, unbounded: &str| match value.as_text() {
+            Some(text) if text == unbounded => Bound::Unbounded,
+            _ if has_annotation(value, "exclusive") => Bound::Exclusive(Value::new(value.data.clone())),
+            _ => Bound::Inclusive(Value::new(value.data.clone())),
+        };
+        Ok(Range { lower: bound(&bounds[0], "min"), upper: bound(&bounds[1], "max") })
+    }
+
+    // Whether a value is within the range. Values that can't be compared with the bounds, like
+    // strings, never are.
+    fn contains(&self, value: &Value) -> bool {
+        let above = match &self.lower {
+            Bound::Unbounded => true,
+            Bound::Inclusive(lower) => matches!(compare(value, lower), Some(Ordering::Greater | Ordering::Equal)),
+            Bound::Exclusive(lower) => compare(value, lower) == Some(Ordering::Greater),
+        };
+        let below = match &self.upper {
+            Bound::Unbounded => true,
+            Bound::Inclusive(upper) => matches!(compare(value, upper), Some(Ordering::Less | Ordering::Equal)),
+            Bound::Exclusive(upper) => compare(value, upper) == Some(Ordering::Less),
+        };
+        above && below && !value.is_null()
+    }
+}
+
+// Compares two numbers, or two timestamps, by their value.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (&left.data, &right.data) {
+        (Data::Timestamp(left), Data::Timestamp(right)) => Some(left.instant().cmp(&right.instant())),
+        (Data::Float(_), _) | (_, Data::Float(_)) => as_f64(left)?.partial_cmp(&as_f64(right)?),
+        _ => Some(as_decimal(left)?.cmp(&as_decimal(right)?)),
+    }
+}
+
+fn as_decimal(value: &Value) -> Option<BigDecimal> {
+    match &value.data {
+        Data::Integer(n) => Some(BigDecimal::from(*n)),
+        Data::BigInteger(n) => Some(BigDecimal::new(n.clone(), 0)),
+        Data::Decimal(decimal) => Some(decimal.clone()),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match &value.data {
+        Data::Float(f) => Some(*f),
+        _ => as_decimal(value)?.to_f64(),
+    }
+}
@@ -0,0 +1,786 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::from_utf8_unchecked;
+
+use anyhow::{bail, Result};
+use bigdecimal::num_bigint::{BigInt, Sign};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use ion_rs::IonType;
+use ion_rs::result::IonResult;
+use ion_rs::text::writer::TextWriter;
+
+use crate::binary::NEGATIVE_INT_TYPE_CODE;
+use crate::input::{duplicate_fields, IonReader};
+use crate::profile::{self, Phase};
+use crate::timestamp::Timestamp;
+
+// An owned, in-memory Ion value. Commands that only need to stream over their input should use
+// the reader directly; this is for commands that need to examine or rearrange whole values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub annotations: Vec<Symbol>,
+    pub data: Data,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Data {
+    Null(IonType),
+    Boolean(bool),
+    Integer(i64),
+    // Integers that don't fit in an i64. Use `Data::integer` to pick between the two.
+    BigInteger(BigInt),
+    Float(f64),
+    Decimal(BigDecimal),
+    Timestamp(Timestamp),
+    Symbol(Symbol),
+    String(String),
+    Clob(Vec<u8>),
+    Blob(Vec<u8>),
+    List(Vec<Value>),
+    SExpression(Vec<Value>),
+    // Struct fields are kept in their original order. Ion allows repeated field names.
+    Struct(Vec<(Symbol, Value)>),
+}
+
+impl Data {
+    // An integer, which is only a BigInteger if it needs to be, so each integer has one
+    // representation.
+    pub fn integer(value: BigInt) -> Data {
+        match value.to_i64() {
+            Some(value) => Data::Integer(value),
+            None => Data::BigInteger(value),
+        }
+    }
+}
+
+// A symbol value, field name, or annotation. Symbols read from binary Ion remember the symbol ID
+// they were encoded with; if the ID isn't in the symbol table, the symbol has no text.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    text: Option<String>,
+    sid: Option<usize>,
+}
+
+impl Symbol {
+    pub fn from_sid(reader: &IonReader, sid: usize) -> Symbol {
+        let text = reader.symbol_table().text_for(sid).map(|text| text.to_string());
+        Symbol { text, sid: Some(sid) }
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn sid(&self) -> Option<usize> {
+        self.sid
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Symbol {
+        Symbol { text: Some(text.to_owned()), sid: None }
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(text: String) -> Symbol {
+        Symbol { text: Some(text), sid: None }
+    }
+}
+
+// Symbol IDs are an artifact of the encoding, so symbols with text are equal if their text is.
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        match (&self.text, &other.text) {
+            (Some(text), Some(other_text)) => text == other_text,
+            (None, None) => self.sid == other.sid,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.text() == Some(other)
+    }
+}
+
+impl Value {
+    pub fn new(data: Data) -> Value {
+        Value { annotations: Vec::new(), data }
+    }
+
+    // Reads the value on which the reader is currently parked, stepping into it if it is a
+    // container.
+    pub fn read(reader: &mut IonReader) -> Result<Value> {
+        profile::time(Phase::Decode, || Value::decode(reader, duplicate_fields()))
+    }
+
+    // Reads a value like `read`, but keeps every field of its structs whatever --duplicate-fields
+    // says, for documents that aren't inputs, like one the CLI wrote itself.
+    pub fn read_all_fields(reader: &mut IonReader) -> Result<Value> {
+        profile::time(Phase::Decode, || Value::decode(reader, DuplicateFields::KeepAll))
+    }
+
+    fn decode(reader: &mut IonReader, duplicate_fields: DuplicateFields) -> Result<Value> {
+        let annotations = reader
+            .annotation_ids()
+            .iter()
+            .map(|sid| Symbol::from_sid(reader, *sid))
+            .collect();
+        let ion_type = reader.ion_type().expect("Value::read() called when reader was exhausted");
+        if reader.is_null() {
+            return Ok(Value { annotations, data: Data::Null(ion_type) });
+        }
+        let data = match ion_type {
+            IonType::Null => Data::Null(IonType::Null),
+            IonType::Boolean => Data::Boolean(reader.read_bool()?.unwrap()),
+            IonType::Integer => read_integer(reader)?,
+            IonType::Float => Data::Float(reader.read_f64()?.unwrap()),
+            IonType::Decimal => Data::Decimal(reader.read_big_decimal()?.unwrap()),
+            IonType::Timestamp => Data::Timestamp(read_timestamp(reader)?),
+            IonType::Symbol => {
+                let sid = reader.read_symbol_id()?.unwrap();
+                Data::Symbol(Symbol::from_sid(reader, sid))
+            }
+            IonType::String => Data::String(reader.read_string()?.unwrap()),
+            IonType::Clob => Data::Clob(reader.read_clob_bytes()?.unwrap()),
+            IonType::Blob => Data::Blob(reader.read_blob_bytes()?.unwrap()),
+            IonType::List => Data::List(read_sequence(reader, duplicate_fields)?),
+            IonType::SExpression => Data::SExpression(read_sequence(reader, duplicate_fields)?),
+            IonType::Struct => {
+                let mut fields = Vec::new();
+                reader.step_in()?;
+                while reader.next()?.is_some() {
+                    let field_id = reader.field_id().expect("Struct field has no field ID.");
+                    let name = Symbol::from_sid(reader, field_id);
+                    fields.push((name, Value::decode(reader, duplicate_fields)?));
+                }
+                reader.step_out()?;
+                Data::Struct(apply_duplicate_fields(fields, duplicate_fields)?)
+            }
+        };
+        Ok(Value { annotations, data })
+    }
+
+    pub fn ion_type(&self) -> IonType {
+        match &self.data {
+            Data::Null(ion_type) => *ion_type,
+            Data::Boolean(_) => IonType::Boolean,
+            Data::Integer(_) | Data::BigInteger(_) => IonType::Integer,
+            Data::Float(_) => IonType::Float,
+            Data::Decimal(_) => IonType::Decimal,
+            Data::Timestamp(_) => IonType::Timestamp,
+            Data::Symbol(_) => IonType::Symbol,
+            Data::String(_) => IonType::String,
+            Data::Clob(_) => IonType::Clob,
+            Data::Blob(_) => IonType::Blob,
+            Data::List(_) => IonType::List,
+            Data::SExpression(_) => IonType::SExpression,
+            Data::Struct(_) => IonType::Struct,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.data, Data::Null(_))
+    }
+
+    // Removes the fields whose values are null from this value and every struct nested in it.
+    pub fn drop_null_fields(&mut self) {
+        match &mut self.data {
+            Data::List(values) | Data::SExpression(values) => {
+                for value in values.iter_mut() {
+                    value.drop_null_fields();
+                }
+            }
+            Data::Struct(fields) => {
+                fields.retain(|(_, value)| !value.is_null());
+                for (_, value) in fields.iter_mut() {
+                    value.drop_null_fields();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Returns the value of the first field with the given name if this is a struct.
+    pub fn get(&self, field_name: &str) -> Option<&Value> {
+        match &self.data {
+            Data::Struct(fields) => fields
+                .iter()
+                .find(|(name, _)| name == field_name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    // Returns the text of a string or symbol.
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.data {
+            Data::String(text) => Some(text),
+            Data::Symbol(symbol) => symbol.text(),
+            _ => None,
+        }
+    }
+
+    // Returns the bytes of a blob or clob.
+    pub fn as_lob(&self) -> Option<&[u8]> {
+        match &self.data {
+            Data::Blob(bytes) | Data::Clob(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+// An error reading a value that's encoded correctly, but can't be held in a Value or breaks the
+// policy it's being read with, like --duplicate-fields error. Commands that look for damaged
+// data, like repair, can look for this in an error's causes to tell the two kinds apart.
+#[derive(Debug)]
+pub struct ConversionError(pub String);
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+// What to do with structs that have more than one field with the same name when they're read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateFields {
+    // Fail with an error naming the field.
+    Error,
+    // Keep only the first field with each name.
+    First,
+    // Keep only the last field with each name.
+    Last,
+    // Keep every field, as Ion allows.
+    KeepAll,
+}
+
+impl DuplicateFields {
+    // Parses the value of a `--duplicate-fields` argument.
+    pub fn from_arg(arg: &str) -> DuplicateFields {
+        match arg {
+            "error" => DuplicateFields::Error,
+            "first" => DuplicateFields::First,
+            "last" => DuplicateFields::Last,
+            _ => DuplicateFields::KeepAll,
+        }
+    }
+}
+
+fn apply_duplicate_fields(fields: Vec<(Symbol, Value)>, policy: DuplicateFields) -> Result<Vec<(Symbol, Value)>> {
+    if policy == DuplicateFields::KeepAll {
+        return Ok(fields);
+    }
+    // Symbols without known text are compared by their symbol ID.
+    let key = |name: &Symbol| match (name.text(), name.sid()) {
+        (Some(text), _) => text.to_owned(),
+        (None, Some(sid)) => format!("${}", sid),
+        (None, None) => unreachable!("Symbols always have text or a symbol ID."),
+    };
+    // The index of the field with each name that will be kept.
+    let mut kept: HashMap<String, usize> = HashMap::with_capacity(fields.len());
+    for (index, (name, _)) in fields.iter().enumerate() {
+        match (kept.entry(key(name)), policy) {
+            (Entry::Vacant(entry), _) => {
+                entry.insert(index);
+            }
+            (Entry::Occupied(entry), DuplicateFields::Error) => {
+                return Err(ConversionError(format!(
+                    "Found a struct with more than one field named '{}'.",
+                    entry.key()
+                )).into())
+            }
+            (Entry::Occupied(mut entry), DuplicateFields::Last) => {
+                entry.insert(index);
+            }
+            (Entry::Occupied(_), _) => {}
+        }
+    }
+    if kept.len() == fields.len() {
+        return Ok(fields);
+    }
+    Ok(fields
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (name, _))| kept.get(&key(name)) == Some(index))
+        .map(|(_, field)| field)
+        .collect())
+}
+
+// Reads an integer from its encoding, since `read_i64` can't read integers wider than 64 bits.
+// The body is the magnitude, and the type code gives the sign.
+fn read_integer(reader: &IonReader) -> Result<Data> {
+    let (descriptor, magnitude) = match (reader.raw_header_bytes(), reader.raw_value_bytes()) {
+        (Some([descriptor, ..]), Some(magnitude)) => (*descriptor, magnitude),
+        _ => bail!("Could not find the encoding of an integer."),
+    };
+    let sign = if descriptor >> 4 == NEGATIVE_INT_TYPE_CODE { Sign::Minus } else { Sign::Plus };
+    Ok(Data::integer(BigInt::from_bytes_be(sign, magnitude)))
+}
+
+// Reads a timestamp from its encoding, since `read_datetime` loses its precision and offset.
+fn read_timestamp(reader: &IonReader) -> Result<Timestamp> {
+    match reader.raw_value_bytes() {
+        Some(body) => Timestamp::decode(body),
+        None => bail!("Could not find the encoding of a timestamp."),
+    }
+}
+
+fn read_sequence(reader: &mut IonReader, duplicate_fields: DuplicateFields) -> Result<Vec<Value>> {
+    let mut values = Vec::new();
+    reader.step_in()?;
+    while reader.next()?.is_some() {
+        values.push(Value::decode(reader, duplicate_fields)?);
+    }
+    reader.step_out()?;
+    Ok(values)
+}
+
+// The most spare allocations of each kind that a ValuePool keeps.
+const MAX_POOLED: usize = 1024;
+
+// Spare strings and vectors taken from values that are no longer needed. Reading values through a
+// pool reuses them rather than allocating new ones for every value, which is most of the cost of
+// converting streams of small values.
+#[derive(Default)]
+pub struct ValuePool {
+    strings: Vec<String>,
+    byte_vectors: Vec<Vec<u8>>,
+    sequences: Vec<Vec<Value>>,
+    field_lists: Vec<Vec<(Symbol, Value)>>,
+    annotation_lists: Vec<Vec<Symbol>>,
+}
+
+impl ValuePool {
+    pub fn new() -> ValuePool {
+        ValuePool::default()
+    }
+
+    // Like `Value::read`, but the value's strings and vectors come from the pool when it has them.
+    pub fn read(&mut self, reader: &mut IonReader) -> Result<Value> {
+        profile::time(Phase::Decode, || self.decode(reader))
+    }
+
+    fn decode(&mut self, reader: &mut IonReader) -> Result<Value> {
+        let mut annotations = self.annotation_lists.pop().unwrap_or_default();
+        for sid in reader.annotation_ids() {
+            annotations.push(self.symbol(reader, *sid));
+        }
+        let ion_type = reader.ion_type().expect("ValuePool::read() called when reader was exhausted");
+        if reader.is_null() {
+            return Ok(Value { annotations, data: Data::Null(ion_type) });
+        }
+        let data = match ion_type {
+            IonType::Symbol => {
+                let sid = reader.read_symbol_id()?.unwrap();
+                Data::Symbol(self.symbol(reader, sid))
+            }
+            IonType::String => {
+                let mut text = self.strings.pop().unwrap_or_default();
+                reader.string_ref_map(|value| text.push_str(value))?.unwrap();
+                Data::String(text)
+            }
+            IonType::Clob | IonType::Blob => {
+                let mut bytes = self.byte_vectors.pop().unwrap_or_default();
+                if ion_type == IonType::Clob {
+                    reader.clob_ref_map(|value| bytes.extend_from_slice(value))?.unwrap();
+                    Data::Clob(bytes)
+                } else {
+                    reader.blob_ref_map(|value| bytes.extend_from_slice(value))?.unwrap();
+                    Data::Blob(bytes)
+                }
+            }
+            IonType::List | IonType::SExpression => {
+                let mut values = self.sequences.pop().unwrap_or_default();
+                reader.step_in()?;
+                while reader.next()?.is_some() {
+                    values.push(self.decode(reader)?);
+                }
+                reader.step_out()?;
+                if ion_type == IonType::List { Data::List(values) } else { Data::SExpression(values) }
+            }
+            IonType::Struct => {
+                let mut fields = self.field_lists.pop().unwrap_or_default();
+                reader.step_in()?;
+                while reader.next()?.is_some() {
+                    let field_id = reader.field_id().expect("Struct field has no field ID.");
+                    let name = self.symbol(reader, field_id);
+                    fields.push((name, self.decode(reader)?));
+                }
+                reader.step_out()?;
+                Data::Struct(apply_duplicate_fields(fields, duplicate_fields())?)
+            }
+            IonType::Null => Data::Null(IonType::Null),
+            IonType::Boolean => Data::Boolean(reader.read_bool()?.unwrap()),
+            IonType::Integer => read_integer(reader)?,
+            IonType::Float => Data::Float(reader.read_f64()?.unwrap()),
+            IonType::Decimal => Data::Decimal(reader.read_big_decimal()?.unwrap()),
+            IonType::Timestamp => Data::Timestamp(read_timestamp(reader)?),
+        };
+        Ok(Value { annotations, data })
+    }
+
+    fn symbol(&mut self, reader: &IonReader, sid: usize) -> Symbol {
+        let text = reader.symbol_table().text_for(sid).map(|text| {
+            let mut buffer = self.strings.pop().unwrap_or_default();
+            buffer.push_str(text);
+            buffer
+        });
+        Symbol { text, sid: Some(sid) }
+    }
+
+    // Takes back a value's strings and vectors, emptied, to reuse for the values read after it.
+    pub fn recycle(&mut self, value: Value) {
+        let mut annotations = value.annotations;
+        for symbol in annotations.drain(..) {
+            self.recycle_symbol(symbol);
+        }
+        keep(&mut self.annotation_lists, annotations);
+        match value.data {
+            Data::String(text) => self.recycle_string(text),
+            Data::Symbol(symbol) => self.recycle_symbol(symbol),
+            Data::Clob(mut bytes) | Data::Blob(mut bytes) => {
+                bytes.clear();
+                keep(&mut self.byte_vectors, bytes);
+            }
+            Data::List(mut values) | Data::SExpression(mut values) => {
+                for child in values.drain(..) {
+                    self.recycle(child);
+                }
+                keep(&mut self.sequences, values);
+            }
+            Data::Struct(mut fields) => {
+                for (name, child) in fields.drain(..) {
+                    self.recycle_symbol(name);
+                    self.recycle(child);
+                }
+                keep(&mut self.field_lists, fields);
+            }
+            _ => {}
+        }
+    }
+
+    fn recycle_symbol(&mut self, symbol: Symbol) {
+        if let Some(text) = symbol.text {
+            self.recycle_string(text);
+        }
+    }
+
+    fn recycle_string(&mut self, mut text: String) {
+        text.clear();
+        keep(&mut self.strings, text);
+    }
+}
+
+// Adds an emptied allocation to a pool unless the pool is already full.
+fn keep<T>(pool: &mut Vec<T>, spare: T) {
+    if pool.len() < MAX_POOLED {
+        pool.push(spare);
+    }
+}
+
+// How the TextFormatter writes symbols (including field names and annotations).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolMode {
+    // Write each symbol's text.
+    Text,
+    // Write each symbol's ID (e.g. `$14`) if it has one, and its text otherwise.
+    Sids,
+    // Write each symbol's text followed by a comment containing its ID, if it has one.
+    Verbose,
+}
+
+// What the TextFormatter does with symbols whose text is unknown, which happens when a stream
+// refers to a shared symbol table that isn't available.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnknownSymbols {
+    // Fail with an error naming the symbol ID.
+    Error,
+    // Write the symbol ID, e.g. `$14`.
+    PreserveSids,
+    // Write a symbol with placeholder text, e.g. `'$unknown_14'`.
+    Placeholder,
+}
+
+impl UnknownSymbols {
+    // Parses the value of an `--unknown-symbols` argument.
+    pub fn from_arg(arg: &str) -> UnknownSymbols {
+        match arg {
+            "preserve-sids" => UnknownSymbols::PreserveSids,
+            "placeholder" => UnknownSymbols::Placeholder,
+            _ => UnknownSymbols::Error,
+        }
+    }
+}
+
+// How the TextFormatter writes finite floats. Non-finite floats are always written as `nan`,
+// `+inf`, or `-inf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatStyle {
+    // Let the TextWriter decide.
+    Default,
+    // The fewest digits that read back as the same float, e.g. `1e-1`.
+    Shortest,
+    // Every digit of the float's exact binary value, e.g.
+    // `1.000000000000000055511151231257827021181583404541015625e-1`.
+    Precise,
+    // The shortest digits with an exponent that is a multiple of 3, e.g. `12.5e3`.
+    Engineering,
+}
+
+impl FloatStyle {
+    // Parses the value of a `--float-style` argument.
+    pub fn from_arg(arg: &str) -> FloatStyle {
+        match arg {
+            "shortest" => FloatStyle::Shortest,
+            "precise" => FloatStyle::Precise,
+            "engineering" => FloatStyle::Engineering,
+            _ => FloatStyle::Default,
+        }
+    }
+}
+
+const TEXT_WRITER_INITIAL_BUFFER_SIZE: usize = 128;
+
+// Formats `Value`s as text Ion. Scalars (including field names and annotations, which are
+// formatted as symbols) are encoded by ion-rs's TextWriter; this type is only responsible for
+// the delimiters that surround them.
+pub struct TextFormatter {
+    writer: TextWriter<Vec<u8>>,
+    symbol_mode: SymbolMode,
+    unknown_symbols: UnknownSymbols,
+    float_style: FloatStyle,
+    // If set, large integers and decimals are followed by a comment showing them with their
+    // digits grouped in thousands, e.g. `1234567 /* 1,234,567 */`.
+    digit_separator: Option<char>,
+    // If set, decimals are written as their coefficient and exponent, e.g. `150d-2`. A decimal's
+    // usual text has no exponent, so `1d3` would be written as `1000`, which is a different value.
+    exact_decimals: bool,
+}
+
+impl Default for TextFormatter {
+    fn default() -> Self {
+        TextFormatter::new()
+    }
+}
+
+impl TextFormatter {
+    pub fn new() -> TextFormatter {
+        TextFormatter {
+            writer: TextWriter::new(Vec::with_capacity(TEXT_WRITER_INITIAL_BUFFER_SIZE)),
+            symbol_mode: SymbolMode::Text,
+            unknown_symbols: UnknownSymbols::Error,
+            float_style: FloatStyle::Default,
+            digit_separator: None,
+            exact_decimals: false,
+        }
+    }
+
+    pub fn set_symbol_mode(&mut self, symbol_mode: SymbolMode) {
+        self.symbol_mode = symbol_mode;
+    }
+
+    pub fn set_unknown_symbols(&mut self, unknown_symbols: UnknownSymbols) {
+        self.unknown_symbols = unknown_symbols;
+    }
+
+    pub fn set_float_style(&mut self, float_style: FloatStyle) {
+        self.float_style = float_style;
+    }
+
+    pub fn set_digit_separator(&mut self, digit_separator: Option<char>) {
+        self.digit_separator = digit_separator;
+    }
+
+    pub fn set_exact_decimals(&mut self, exact_decimals: bool) {
+        self.exact_decimals = exact_decimals;
+    }
+
+    // Appends the text Ion representation of `value` to `buffer`.
+    pub fn format(&mut self, value: &Value, buffer: &mut String) -> Result<()> {
+        for annotation in &value.annotations {
+            self.symbol(buffer, annotation)?;
+            buffer.push_str("::");
+        }
+        match &value.data {
+            Data::Null(ion_type) => self.scalar(buffer, |w| w.write_null(*ion_type))?,
+            Data::Boolean(b) => self.scalar(buffer, |w| w.write_bool(*b))?,
+            Data::Integer(i) => {
+                self.scalar(buffer, |w| w.write_i64(*i))?;
+                self.digit_comment(buffer, &i.to_string());
+            }
+            // The TextWriter can only write integers that fit in an i64.
+            Data::BigInteger(i) => {
+                let text = i.to_string();
+                buffer.push_str(&text);
+                self.digit_comment(buffer, &text);
+            }
+            Data::Float(f) => match self.float_style {
+                FloatStyle::Default => self.scalar(buffer, |w| w.write_f64(*f))?,
+                _ if !f.is_finite() => self.scalar(buffer, |w| w.write_f64(*f))?,
+                FloatStyle::Shortest => buffer.push_str(&format!("{:e}", f)),
+                FloatStyle::Precise => buffer.push_str(&precise_float(*f)),
+                FloatStyle::Engineering => buffer.push_str(&engineering_float(*f)),
+            },
+            Data::Decimal(d) if self.exact_decimals => {
+                let (coefficient, scale) = d.as_bigint_and_exponent();
+                buffer.push_str(&format!("{}d{}", coefficient, -scale));
+            }
+            Data::Decimal(d) => {
+                self.scalar(buffer, |w| w.write_big_decimal(d))?;
+                self.digit_comment(buffer, &d.to_string());
+            }
+            // The TextWriter can only write chrono's DateTimes, which lose the precision and offset.
+            Data::Timestamp(t) => buffer.push_str(&t.to_string()),
+            Data::Symbol(s) => self.symbol(buffer, s)?,
+            Data::String(s) => self.scalar(buffer, |w| w.write_string(s))?,
+            Data::Clob(c) => self.scalar(buffer, |w| w.write_clob(c))?,
+            Data::Blob(b) => self.scalar(buffer, |w| w.write_blob(b))?,
+            Data::List(values) => self.sequence(buffer, "[", ", ", "]", values)?,
+            Data::SExpression(values) => self.sequence(buffer, "(", " ", ")", values)?,
+            Data::Struct(fields) => {
+                buffer.push('{');
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        buffer.push_str(", ");
+                    }
+                    self.symbol(buffer, name)?;
+                    buffer.push_str(": ");
+                    self.format(value, buffer)?;
+                }
+                buffer.push('}');
+            }
+        }
+        Ok(())
+    }
+
+    fn sequence(&mut self,
+                buffer: &mut String,
+                start: &str,
+                delimiter: &str,
+                end: &str,
+                values: &[Value]) -> Result<()> {
+        buffer.push_str(start);
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                buffer.push_str(delimiter);
+            }
+            self.format(value, buffer)?;
+        }
+        buffer.push_str(end);
+        Ok(())
+    }
+
+    fn symbol(&mut self, buffer: &mut String, symbol: &Symbol) -> Result<()> {
+        match (symbol.text(), symbol.sid(), self.symbol_mode) {
+            (_, Some(sid), SymbolMode::Sids) => buffer.push_str(&format!("${}", sid)),
+            (Some(text), Some(sid), SymbolMode::Verbose) => {
+                self.symbol_text(buffer, text)?;
+                buffer.push_str(&format!(" /* ${} */", sid));
+            }
+            (Some(text), _, _) => self.symbol_text(buffer, text)?,
+            (None, Some(sid), _) => match self.unknown_symbols {
+                UnknownSymbols::Error => bail!("Could not resolve text for symbol ID ${}", sid),
+                UnknownSymbols::PreserveSids => buffer.push_str(&format!("${}", sid)),
+                UnknownSymbols::Placeholder => {
+                    let placeholder = format!("$unknown_{}", sid);
+                    self.scalar(buffer, |w| w.write_symbol(&placeholder))?;
+                }
+            },
+            (None, None, _) => unreachable!("Symbols always have text or a symbol ID."),
+        }
+        Ok(())
+    }
+
+    // Text that looks like a symbol ID, like `$14`, is quoted, or it would be read back as the
+    // symbol with that ID. This also keeps it distinct from an unknown symbol written as its ID.
+    fn symbol_text(&mut self, buffer: &mut String, text: &str) -> Result<()> {
+        if text.len() > 1 && text.starts_with('$') && text[1..].bytes().all(|byte| byte.is_ascii_digit()) {
+            buffer.push_str(&format!("'{}'", text));
+            return Ok(());
+        }
+        self.scalar(buffer, |w| w.write_symbol(text))?;
+        Ok(())
+    }
+
+    // If a digit separator is set and `number` (as written by Rust) has more than three digits
+    // before its decimal point, appends a comment with its digits grouped in thousands.
+    fn digit_comment(&self, buffer: &mut String, number: &str) {
+        let separator = match self.digit_separator {
+            Some(separator) => separator,
+            None => return,
+        };
+        if number.contains(&['e', 'E'][..]) {
+            return;
+        }
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number),
+        };
+        let (integer, fraction) = unsigned.split_at(unsigned.find('.').unwrap_or(unsigned.len()));
+        if integer.len() <= 3 {
+            return;
+        }
+        let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        buffer.push_str(&format!(" /* {}{}{} */", sign, grouped, fraction));
+    }
+
+    // Uses the TextWriter to encode a single scalar, then appends the result to `buffer`.
+    fn scalar<F>(&mut self, buffer: &mut String, write: F) -> IonResult<()>
+        where F: FnOnce(&mut TextWriter<Vec<u8>>) -> IonResult<()> {
+        write(&mut self.writer)?;
+        // This is writing to a Vec, so flush() will always succeed.
+        let _ = self.writer.flush();
+        // The writer produces valid UTF-8, so there's no need to re-validate it.
+        let text = unsafe { from_utf8_unchecked(self.writer.output().as_slice()) };
+        buffer.push_str(text.trim());
+        // Clear the writer's output Vec. We encode each scalar independently of one another.
+        self.writer.output_mut().clear();
+        Ok(())
+    }
+}
+
+// Writes every digit of a finite float's exact value. A double's exact decimal expansion has at
+// most 767 significant digits, so formatting with that many and trimming the zeros is exact.
+fn precise_float(f: f64) -> String {
+    let text = format!("{:.766e}", f);
+    // `{:e}` always includes an exponent, so we can unwrap this safely.
+    let (mantissa, exponent) = text.split_once('e').unwrap();
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    format!("{}e{}", mantissa, exponent)
+}
+
+// Writes a finite float's shortest digits with an exponent that is a multiple of 3.
+fn engineering_float(f: f64) -> String {
+    let text = format!("{:e}", f);
+    // `{:e}` always includes an exponent, so we can unwrap these safely.
+    let (mantissa, exponent) = text.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let mut digits = mantissa.replace('.', "");
+    let engineering_exponent = exponent.div_euclid(3) * 3;
+    let integer_length = 1 + (exponent - engineering_exponent) as usize;
+    while digits.len() < integer_length {
+        digits.push('0');
+    }
+    let (integer, fraction) = digits.split_at(integer_length);
+    if fraction.is_empty() {
+        format!("{}{}e{}", sign, integer, engineering_exponent)
+    } else {
+        format!("{}{}.{}e{}", sign, integer, fraction, engineering_exponent)
+    }
+}
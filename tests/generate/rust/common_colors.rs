@@ -0,0 +1,4 @@
+// Generated by `ion beta generate` from schema 'common/colors.isl'. Don't edit it by hand; change the
+// schema and generate it again.
+
+pub type Color = String;
@@ -0,0 +1,5 @@
+// Generated by `ion beta generate` from schema 'shapes.isl' and the schemas it refers to. Don't edit it by hand; change the
+// schema and generate it again.
+
+pub mod shapes;
+pub mod common_colors;
@@ -0,0 +1,431 @@
+// Generated by `ion beta generate` from schema 'shapes.isl'. Don't edit it by hand; change the
+// schema and generate it again.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn builder() -> PointBuilder {
+        PointBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PointBuilder {
+    x: Option<i64>,
+    y: Option<i64>,
+}
+
+impl PointBuilder {
+    pub fn x(mut self, value: impl Into<i64>) -> Self {
+        self.x = Some(value.into());
+        self
+    }
+
+    pub fn y(mut self, value: impl Into<i64>) -> Self {
+        self.y = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Point, Vec<String>> {
+        let mut missing = Vec::new();
+        if self.x.is_none() {
+            missing.push("missing required field 'x'".to_owned());
+        }
+        if self.y.is_none() {
+            missing.push("missing required field 'y'".to_owned());
+        }
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let value = Point {
+            x: self.x.unwrap(),
+            y: self.y.unwrap(),
+        };
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+impl Point {
+    // Checks the constraints that the type can't express, returning a message for each
+    // value that doesn't meet them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Shape {
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+impl Shape {
+    // Validates the struct or union the value holds, if it holds one.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        match self {
+            Shape::Circle(value) => value.validate(),
+            Shape::Polygon(value) => value.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<super::common_colors::Color>,
+}
+
+impl Circle {
+    pub fn builder() -> CircleBuilder {
+        CircleBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CircleBuilder {
+    center: Option<Point>,
+    radius: Option<f64>,
+    color: Option<super::common_colors::Color>,
+}
+
+impl CircleBuilder {
+    pub fn center(mut self, value: impl Into<Point>) -> Self {
+        self.center = Some(value.into());
+        self
+    }
+
+    pub fn radius(mut self, value: impl Into<f64>) -> Self {
+        self.radius = Some(value.into());
+        self
+    }
+
+    pub fn color(mut self, value: impl Into<super::common_colors::Color>) -> Self {
+        self.color = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Circle, Vec<String>> {
+        let mut missing = Vec::new();
+        if self.center.is_none() {
+            missing.push("missing required field 'center'".to_owned());
+        }
+        if self.radius.is_none() {
+            missing.push("missing required field 'radius'".to_owned());
+        }
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let value = Circle {
+            center: self.center.unwrap(),
+            radius: self.radius.unwrap(),
+            color: self.color,
+        };
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+impl Circle {
+    // Checks the constraints that the type can't express, returning a message for each
+    // value that doesn't meet them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        {
+            let value = &self.center;
+            let label = "center";
+            if let Err(nested) = value.validate() {
+                errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+            }
+        }
+        if let Some(value) = &self.color {
+            let label = "color";
+            if !regex::Regex::new("^#[0-9a-f]{6}$").expect("The schema's regex is valid.").is_match(value) {
+                errors.push(format!("{}: {:?} doesn't meet {}", label, value, "regex: \"^#[0-9a-f]{6}$\""));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Polygon {
+    pub points: Vec<Point>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<PolygonStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holes: Option<Vec<Polygon>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_bytes")]
+    pub thumbnail: Option<Vec<u8>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Polygon {
+    pub fn builder() -> PolygonBuilder {
+        PolygonBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PolygonBuilder {
+    points: Option<Vec<Point>>,
+    label: Option<String>,
+    style: Option<PolygonStyle>,
+    holes: Option<Vec<Polygon>>,
+    thumbnail: Option<Vec<u8>>,
+    tags: Vec<String>,
+}
+
+impl PolygonBuilder {
+    pub fn points(mut self, value: impl Into<Vec<Point>>) -> Self {
+        self.points = Some(value.into());
+        self
+    }
+
+    pub fn label(mut self, value: impl Into<String>) -> Self {
+        self.label = Some(value.into());
+        self
+    }
+
+    pub fn style(mut self, value: impl Into<PolygonStyle>) -> Self {
+        self.style = Some(value.into());
+        self
+    }
+
+    pub fn holes(mut self, value: impl Into<Vec<Polygon>>) -> Self {
+        self.holes = Some(value.into());
+        self
+    }
+
+    pub fn thumbnail(mut self, value: impl Into<Vec<u8>>) -> Self {
+        self.thumbnail = Some(value.into());
+        self
+    }
+
+    pub fn tags(mut self, values: Vec<String>) -> Self {
+        self.tags = values;
+        self
+    }
+
+    pub fn build(self) -> Result<Polygon, Vec<String>> {
+        let mut missing = Vec::new();
+        if self.points.is_none() {
+            missing.push("missing required field 'points'".to_owned());
+        }
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let value = Polygon {
+            points: self.points.unwrap(),
+            label: self.label,
+            style: self.style,
+            holes: self.holes,
+            thumbnail: self.thumbnail,
+            tags: self.tags,
+        };
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+impl Polygon {
+    // Checks the constraints that the type can't express, returning a message for each
+    // value that doesn't meet them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        {
+            let value = &self.points;
+            let label = "points";
+            for (index, value) in value.iter().enumerate() {
+                let label = format!("{}[{}]", label, index);
+                if let Err(nested) = value.validate() {
+                    errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+                }
+            }
+        }
+        if let Some(value) = &self.label {
+            let label = "label";
+            if !regex::Regex::new("^[a-z]+$").expect("The schema's regex is valid.").is_match(value) {
+                errors.push(format!("{}: {:?} doesn't meet {}", label, value, "regex: \"^[a-z]+$\""));
+            }
+            if value.chars().count() < 8 || value.chars().count() > 8 {
+                errors.push(format!("{}: {:?} doesn't meet {}", label, value, "codepoint_length: 8"));
+            }
+        }
+        if let Some(value) = &self.style {
+            let label = "style";
+            if let Err(nested) = value.validate() {
+                errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+            }
+        }
+        if let Some(value) = &self.holes {
+            let label = "holes";
+            for (index, value) in value.iter().enumerate() {
+                let label = format!("{}[{}]", label, index);
+                if let Err(nested) = value.validate() {
+                    errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PolygonStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dashed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<ion_rs::Decimal>,
+}
+
+impl PolygonStyle {
+    pub fn builder() -> PolygonStyleBuilder {
+        PolygonStyleBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PolygonStyleBuilder {
+    dashed: Option<bool>,
+    width: Option<ion_rs::Decimal>,
+}
+
+impl PolygonStyleBuilder {
+    pub fn dashed(mut self, value: impl Into<bool>) -> Self {
+        self.dashed = Some(value.into());
+        self
+    }
+
+    pub fn width(mut self, value: impl Into<ion_rs::Decimal>) -> Self {
+        self.width = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> Result<PolygonStyle, Vec<String>> {
+        let value = PolygonStyle {
+            dashed: self.dashed,
+            width: self.width,
+        };
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+impl PolygonStyle {
+    // Checks the constraints that the type can't express, returning a message for each
+    // value that doesn't meet them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Drawing {
+    pub shape: Shape,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<ion_rs::Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Box<Drawing>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<Option<String>>,
+}
+
+impl Drawing {
+    pub fn builder() -> DrawingBuilder {
+        DrawingBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DrawingBuilder {
+    shape: Option<Shape>,
+    created: Option<ion_rs::Timestamp>,
+    previous: Option<Box<Drawing>>,
+    notes: Option<Option<String>>,
+}
+
+impl DrawingBuilder {
+    pub fn shape(mut self, value: impl Into<Shape>) -> Self {
+        self.shape = Some(value.into());
+        self
+    }
+
+    pub fn created(mut self, value: impl Into<ion_rs::Timestamp>) -> Self {
+        self.created = Some(value.into());
+        self
+    }
+
+    pub fn previous(mut self, value: impl Into<Box<Drawing>>) -> Self {
+        self.previous = Some(value.into());
+        self
+    }
+
+    pub fn notes(mut self, value: impl Into<Option<String>>) -> Self {
+        self.notes = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Drawing, Vec<String>> {
+        let mut missing = Vec::new();
+        if self.shape.is_none() {
+            missing.push("missing required field 'shape'".to_owned());
+        }
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let value = Drawing {
+            shape: self.shape.unwrap(),
+            created: self.created,
+            previous: self.previous,
+            notes: self.notes,
+        };
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+impl Drawing {
+    // Checks the constraints that the type can't express, returning a message for each
+    // value that doesn't meet them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        {
+            let value = &self.shape;
+            let label = "shape";
+            if let Err(nested) = value.validate() {
+                errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+            }
+        }
+        if let Some(value) = &self.previous {
+            let label = "previous";
+            if let Err(nested) = value.validate() {
+                errors.extend(nested.into_iter().map(|error| format!("{}.{}", label, error)));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
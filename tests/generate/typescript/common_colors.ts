@@ -0,0 +1,15 @@
+// Generated by `ion beta generate` from schema 'common/colors.isl'. Don't edit it by hand; change the
+// schema and generate it again.
+
+import * as ion from "ion-js";
+import * as codec from "./ion_codec";
+
+export type Color = string;
+
+export function decodeColor(value: ion.dom.Value | null): Color {
+  return codec.decodeString(value);
+}
+
+export function encodeColor(writer: ion.Writer, value: Color): void {
+  codec.encodeString(writer, value);
+}
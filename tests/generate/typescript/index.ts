@@ -0,0 +1,5 @@
+// Generated by `ion beta generate` from schema 'shapes.isl' and the schemas it refers to. Don't edit it by hand; change the
+// schema and generate it again.
+
+export * as shapes from "./shapes";
+export * as common_colors from "./common_colors";
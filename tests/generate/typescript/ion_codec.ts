@@ -0,0 +1,106 @@
+// Generated by `ion beta generate` for the modules beside it. Don't edit it by hand; change the
+// schema and generate it again.
+
+import * as ion from "ion-js";
+
+export type Decode<T> = (value: ion.dom.Value | null) => T;
+export type Encode<T> = (writer: ion.Writer, value: T) => void;
+
+export function expect(value: ion.dom.Value | null, types: ion.IonType[], expected: string): ion.dom.Value {
+  if (value === null || value.isNull() || !types.includes(value.getType())) {
+    const found = value === null ? "nothing" : ion.dumpText(value);
+    throw new Error(`Expected ${expected}, but found ${found}.`);
+  }
+  return value;
+}
+
+export function field(struct: ion.dom.Value, name: string): ion.dom.Value | null {
+  const values = struct.getAll(name) ?? [];
+  if (values.length > 1) {
+    throw new Error(`Expected one field named '${name}', but found ${values.length}.`);
+  }
+  return values.length === 0 ? null : values[0];
+}
+
+export function decodeRequired<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T {
+  const value = field(struct, name);
+  if (value === null) {
+    throw new Error(`Missing required field '${name}'.`);
+  }
+  return decode(value);
+}
+
+export function decodeOptional<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T | undefined {
+  const value = field(struct, name);
+  return value === null ? undefined : decode(value);
+}
+
+export function decodeRepeated<T>(struct: ion.dom.Value, name: string, decode: Decode<T>): T[] {
+  return (struct.getAll(name) ?? []).map(decode);
+}
+
+export function decodeNullable<T>(value: ion.dom.Value | null, decode: Decode<T>): T | null {
+  return value !== null && value.isNull() ? null : decode(value);
+}
+
+export function decodeList<T>(value: ion.dom.Value | null, decode: Decode<T>): T[] {
+  return expect(value, [ion.IonTypes.LIST, ion.IonTypes.SEXP], "a list").elements().map(decode);
+}
+
+// Decodes a union as the first alternative that the value matches.
+export function decodeUnion<T>(value: ion.dom.Value | null, name: string, alternatives: Decode<T>[]): T {
+  for (const alternative of alternatives) {
+    try {
+      return alternative(value);
+    } catch {
+      // Try the next alternative.
+    }
+  }
+  const found = value === null ? "nothing" : ion.dumpText(value);
+  throw new Error(`Expected a ${name}, but found ${found}.`);
+}
+
+export const decodeBool: Decode<boolean> = (value) => expect(value, [ion.IonTypes.BOOL], "a bool").booleanValue()!;
+export const decodeInt: Decode<number> = (value) => expect(value, [ion.IonTypes.INT], "an int").numberValue()!;
+export const decodeFloat: Decode<number> = (value) => expect(value, [ion.IonTypes.FLOAT], "a float").numberValue()!;
+export const decodeDecimal: Decode<ion.Decimal> = (value) =>
+  expect(value, [ion.IonTypes.DECIMAL], "a decimal").decimalValue()!;
+export const decodeTimestamp: Decode<ion.Timestamp> = (value) =>
+  expect(value, [ion.IonTypes.TIMESTAMP], "a timestamp").timestampValue()!;
+export const decodeString: Decode<string> = (value) => expect(value, [ion.IonTypes.STRING], "a string").stringValue()!;
+export const decodeSymbol: Decode<string> = (value) => expect(value, [ion.IonTypes.SYMBOL], "a symbol").stringValue()!;
+export const decodeBlob: Decode<Uint8Array> = (value) => expect(value, [ion.IonTypes.BLOB], "a blob").uInt8ArrayValue()!;
+export const decodeClob: Decode<Uint8Array> = (value) => expect(value, [ion.IonTypes.CLOB], "a clob").uInt8ArrayValue()!;
+export const decodeAny: Decode<ion.dom.Value> = (value) => {
+  if (value === null) {
+    throw new Error("Expected a value, but found nothing.");
+  }
+  return value;
+};
+
+export function encodeNullable<T>(writer: ion.Writer, value: T | null, encode: Encode<T>): void {
+  if (value === null) {
+    writer.writeNull(ion.IonTypes.NULL);
+  } else {
+    encode(writer, value);
+  }
+}
+
+export function encodeList<T>(writer: ion.Writer, values: T[], encode: Encode<T>): void {
+  writer.stepIn(ion.IonTypes.LIST);
+  for (const value of values) {
+    encode(writer, value);
+  }
+  writer.stepOut();
+}
+
+export const encodeBool: Encode<boolean> = (writer, value) => writer.writeBoolean(value);
+export const encodeInt: Encode<number> = (writer, value) => writer.writeInt(value);
+export const encodeFloat: Encode<number> = (writer, value) => writer.writeFloat64(value);
+export const encodeDecimal: Encode<ion.Decimal> = (writer, value) => writer.writeDecimal(value);
+export const encodeTimestamp: Encode<ion.Timestamp> = (writer, value) => writer.writeTimestamp(value);
+export const encodeString: Encode<string> = (writer, value) => writer.writeString(value);
+export const encodeSymbol: Encode<string> = (writer, value) => writer.writeSymbol(value);
+export const encodeBlob: Encode<Uint8Array> = (writer, value) => writer.writeBlob(value);
+export const encodeClob: Encode<Uint8Array> = (writer, value) => writer.writeClob(value);
+export const encodeAny: Encode<ion.dom.Value> = (writer, value) => value.writeTo(writer);
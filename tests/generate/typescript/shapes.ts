@@ -0,0 +1,188 @@
+// Generated by `ion beta generate` from schema 'shapes.isl'. Don't edit it by hand; change the
+// schema and generate it again.
+
+import * as ion from "ion-js";
+import * as codec from "./ion_codec";
+import * as common_colors from "./common_colors";
+
+export interface Point {
+  x: number;
+  y: number;
+}
+
+export function decodePoint(value: ion.dom.Value | null): Point {
+  const struct = codec.expect(value, [ion.IonTypes.STRUCT], "a Point");
+  return {
+    x: codec.decodeRequired(struct, "x", codec.decodeInt),
+    y: codec.decodeRequired(struct, "y", codec.decodeInt),
+  };
+}
+
+export function encodePoint(writer: ion.Writer, value: Point): void {
+  writer.stepIn(ion.IonTypes.STRUCT);
+  writer.writeFieldName("x");
+  codec.encodeInt(writer, value.x);
+  writer.writeFieldName("y");
+  codec.encodeInt(writer, value.y);
+  writer.stepOut();
+}
+
+export type Shape =
+  | { kind: "Circle"; value: Circle }
+  | { kind: "Polygon"; value: Polygon };
+
+export function decodeShape(value: ion.dom.Value | null): Shape {
+  return codec.decodeUnion<Shape>(value, "Shape", [
+    (value) => ({ kind: "Circle", value: decodeCircle(value) }),
+    (value) => ({ kind: "Polygon", value: decodePolygon(value) }),
+  ]);
+}
+
+export function encodeShape(writer: ion.Writer, value: Shape): void {
+  switch (value.kind) {
+    case "Circle":
+      encodeCircle(writer, value.value);
+      break;
+    case "Polygon":
+      encodePolygon(writer, value.value);
+      break;
+  }
+}
+
+export interface Circle {
+  center: Point;
+  radius: number;
+  color?: common_colors.Color;
+}
+
+export function decodeCircle(value: ion.dom.Value | null): Circle {
+  const struct = codec.expect(value, [ion.IonTypes.STRUCT], "a Circle");
+  return {
+    center: codec.decodeRequired(struct, "center", decodePoint),
+    radius: codec.decodeRequired(struct, "radius", codec.decodeFloat),
+    color: codec.decodeOptional(struct, "color", common_colors.decodeColor),
+  };
+}
+
+export function encodeCircle(writer: ion.Writer, value: Circle): void {
+  writer.stepIn(ion.IonTypes.STRUCT);
+  writer.writeFieldName("center");
+  encodePoint(writer, value.center);
+  writer.writeFieldName("radius");
+  codec.encodeFloat(writer, value.radius);
+  if (value.color !== undefined) {
+    writer.writeFieldName("color");
+    common_colors.encodeColor(writer, value.color);
+  }
+  writer.stepOut();
+}
+
+export interface Polygon {
+  points: Point[];
+  label?: string;
+  style?: PolygonStyle;
+  holes?: Polygon[];
+  thumbnail?: Uint8Array;
+  tags: string[];
+}
+
+export function decodePolygon(value: ion.dom.Value | null): Polygon {
+  const struct = codec.expect(value, [ion.IonTypes.STRUCT], "a Polygon");
+  return {
+    points: codec.decodeRequired(struct, "points", (value: ion.dom.Value | null) => codec.decodeList(value, decodePoint)),
+    label: codec.decodeOptional(struct, "label", codec.decodeString),
+    style: codec.decodeOptional(struct, "style", decodePolygonStyle),
+    holes: codec.decodeOptional(struct, "holes", (value: ion.dom.Value | null) => codec.decodeList(value, decodePolygon)),
+    thumbnail: codec.decodeOptional(struct, "thumbnail", codec.decodeBlob),
+    tags: codec.decodeRepeated(struct, "tags", codec.decodeSymbol),
+  };
+}
+
+export function encodePolygon(writer: ion.Writer, value: Polygon): void {
+  writer.stepIn(ion.IonTypes.STRUCT);
+  writer.writeFieldName("points");
+  (writer: ion.Writer, values: Point[]) => codec.encodeList(writer, values, encodePoint)(writer, value.points);
+  if (value.label !== undefined) {
+    writer.writeFieldName("label");
+    codec.encodeString(writer, value.label);
+  }
+  if (value.style !== undefined) {
+    writer.writeFieldName("style");
+    encodePolygonStyle(writer, value.style);
+  }
+  if (value.holes !== undefined) {
+    writer.writeFieldName("holes");
+    (writer: ion.Writer, values: Polygon[]) => codec.encodeList(writer, values, encodePolygon)(writer, value.holes);
+  }
+  if (value.thumbnail !== undefined) {
+    writer.writeFieldName("thumbnail");
+    codec.encodeBlob(writer, value.thumbnail);
+  }
+  for (const element of value.tags) {
+    writer.writeFieldName("tags");
+    codec.encodeSymbol(writer, element);
+  }
+  writer.stepOut();
+}
+
+export interface PolygonStyle {
+  dashed?: boolean;
+  width?: ion.Decimal;
+}
+
+export function decodePolygonStyle(value: ion.dom.Value | null): PolygonStyle {
+  const struct = codec.expect(value, [ion.IonTypes.STRUCT], "a PolygonStyle");
+  return {
+    dashed: codec.decodeOptional(struct, "dashed", codec.decodeBool),
+    width: codec.decodeOptional(struct, "width", codec.decodeDecimal),
+  };
+}
+
+export function encodePolygonStyle(writer: ion.Writer, value: PolygonStyle): void {
+  writer.stepIn(ion.IonTypes.STRUCT);
+  if (value.dashed !== undefined) {
+    writer.writeFieldName("dashed");
+    codec.encodeBool(writer, value.dashed);
+  }
+  if (value.width !== undefined) {
+    writer.writeFieldName("width");
+    codec.encodeDecimal(writer, value.width);
+  }
+  writer.stepOut();
+}
+
+export interface Drawing {
+  shape: Shape;
+  created?: ion.Timestamp;
+  previous?: Drawing;
+  notes?: string | null;
+}
+
+export function decodeDrawing(value: ion.dom.Value | null): Drawing {
+  const struct = codec.expect(value, [ion.IonTypes.STRUCT], "a Drawing");
+  return {
+    shape: codec.decodeRequired(struct, "shape", decodeShape),
+    created: codec.decodeOptional(struct, "created", codec.decodeTimestamp),
+    previous: codec.decodeOptional(struct, "previous", decodeDrawing),
+    notes: codec.decodeOptional(struct, "notes", (value: ion.dom.Value | null) => codec.decodeNullable(value, codec.decodeString)),
+  };
+}
+
+export function encodeDrawing(writer: ion.Writer, value: Drawing): void {
+  writer.stepIn(ion.IonTypes.STRUCT);
+  writer.writeFieldName("shape");
+  encodeShape(writer, value.shape);
+  if (value.created !== undefined) {
+    writer.writeFieldName("created");
+    codec.encodeTimestamp(writer, value.created);
+  }
+  if (value.previous !== undefined) {
+    writer.writeFieldName("previous");
+    encodeDrawing(writer, value.previous);
+  }
+  if (value.notes !== undefined) {
+    writer.writeFieldName("notes");
+    (writer: ion.Writer, value: string | null) => codec.encodeNullable(writer, value, codec.encodeString)(writer, value.notes);
+  }
+  writer.stepOut();
+}